@@ -9,6 +9,8 @@ layout(input_attachment_index = 1, set = 0, binding = 1) uniform subpassInputMS
 layout(set = 0, binding = 2) uniform sampler2D hdr_fragments;
 layout(set = 0, binding = 3) uniform sampler2D average_lumiosity;
 layout(set = 0, binding = 4) uniform sampler2D dir_depth;
+//The half sized, blurred color for the depth of field
+layout(set = 0, binding = 5) uniform sampler2D dof_blur;
 
 //Get the uvs
 layout(location = 0) in vec2 inter_coord;
@@ -26,6 +28,10 @@ layout(set = 1, binding = 0) uniform hdr_settings{
   float use_auto_exposure;
   int sampling_rate;
   int show_mode;
+  //distance to the camera which is sharp
+  float focus_distance;
+  //0.0 if the depth of field is turned off
+  float aperture;
 }u_hdr_settings;
 
 ///Will hold the average lumiosity of this frame
@@ -42,6 +48,18 @@ float linear_depth(float depth){
   return z;
 }
 
+//Returns how much of the blurred color is used for this pixel, 0.0 is sharp 1.0 fully blurred
+float circle_of_confusion(){
+  if (u_hdr_settings.aperture <= 0.0){
+    return 0.0;
+  }
+  //linear_depth is relative to the far plane
+  float view_distance = linear_depth(subpassLoad(depths_input, 0).x) * u_hdr_settings.far;
+  view_distance = max(view_distance, u_hdr_settings.near);
+  float coc = u_hdr_settings.aperture * abs(view_distance - u_hdr_settings.focus_distance) / view_distance;
+  return clamp(coc, 0.0, 1.0);
+}
+
 void main()
 {
   //MainDepth
@@ -104,8 +122,20 @@ void main()
   }
 
 
+  //CircleOfConfusion
+  if (u_hdr_settings.show_mode == 5) {
+    FragColor = vec4(vec3(circle_of_confusion()), 1.0);
+    return;
+  }
+
   //Add the blur to the image
   vec3 hdrColor = texture(color_input, inter_coord).rgb;
+
+  //blend to the blurred image outside of the focus
+  float coc = circle_of_confusion();
+  if (coc > 0.0){
+    hdrColor = mix(hdrColor, texture(dof_blur, inter_coord).rgb, coc);
+  }
   vec3 bloomColor = texture(hdr_fragments, inter_coord).rgb;
 
 
@@ -0,0 +1,11 @@
+#version 450
+
+//Describes the image based lighting of the scene
+layout(set = 4, binding = 0) uniform EnvironmentInfo {
+  float intensity;
+  //mip level of the prefiltered map at roughness 1.0
+  float max_lod;
+  uint has_environment;
+} u_environment;
+
+void main(){}
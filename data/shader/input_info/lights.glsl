@@ -22,10 +22,16 @@ layout(set = 3, binding = 0) readonly buffer ClusterBuffer {
 
 struct PointLight
 {
+  //one region and matrix per cube face in the order +x, -x, +y, -y, +z, -z
+  vec4 shadow_region[6];
+  mat4 light_space[6];
   vec3 color;
   vec3 location;
   float intensity;
   float radius;
+  float poisson_spread;
+  uint pcf_samples;
+  uint has_shadow;
 };
 
 layout(set = 3, binding = 1) readonly buffer point_lights{
@@ -42,6 +48,7 @@ struct DirectionalLight
   float intensity;
   float poisson_spread;
   uint pcf_samples;
+  uint has_shadow;
 };
 
 layout(set = 3, binding = 2) readonly buffer directional_lights{
@@ -50,6 +57,8 @@ layout(set = 3, binding = 2) readonly buffer directional_lights{
 //==============================================================================
 struct SpotLight
 {
+  vec4 shadow_region;
+  mat4 light_space;
   vec3 color;
   vec3 direction;
   vec3 location;
@@ -59,6 +68,9 @@ struct SpotLight
   float outer_radius;
   float inner_radius;
 
+  float poisson_spread;
+  uint pcf_samples;
+  uint has_shadow;
 };
 
 layout(set = 3, binding = 3) readonly buffer spot_lights{
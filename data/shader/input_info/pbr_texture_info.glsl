@@ -10,6 +10,7 @@ layout(set = 2, binding = 0) uniform TextureUsageInfo {
   uint b_roughness;
   uint b_occlusion;
   uint b_emissive;
+  uint b_height;
   uint b_is_masked;
 } u_tex_usage_info;
 
@@ -22,6 +23,8 @@ layout(set = 2, binding = 1) uniform TextureFactors {
   float roughness_factor;
   float occlusion_factor;
   float alpha_cutoff;
+  float height_scale;
+  uint height_steps;
 } u_tex_fac;
 
 void main(){}
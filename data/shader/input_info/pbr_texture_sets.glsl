@@ -7,6 +7,7 @@ layout(set = 1, binding = 1) uniform sampler2D t_Normal;
 layout(set = 1, binding = 2) uniform sampler2D t_Metall_Rough;
 layout(set = 1, binding = 3) uniform sampler2D t_Occlusion;
 layout(set = 1, binding = 4) uniform sampler2D t_Emissive;
+layout(set = 1, binding = 5) uniform sampler2D t_Height;
 
 
 void main(){}
@@ -0,0 +1,15 @@
+#version 450
+
+//Describes the reflection probes which influence the current object, the nearest first
+layout(set = 5, binding = 0) uniform ProbeInfo {
+  //xyz = location of the probe
+  vec4 location[2];
+  //xyz = min of the influence volume in world space, w = blend distance
+  vec4 influence_min[2];
+  //xyz = max of the influence volume in world space
+  vec4 influence_max[2];
+  //how many of the probes are valid
+  uint count;
+} u_probes;
+
+void main(){}
@@ -0,0 +1,60 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec2 tex_coord;
+layout(location = 2) in vec3 normal;
+layout(location = 3) in vec4 tangent;
+layout(location = 4) in vec4 color;
+//The model matrix of this instance, one column per attribute
+layout(location = 5) in vec4 model_0;
+layout(location = 6) in vec4 model_1;
+layout(location = 7) in vec4 model_2;
+layout(location = 8) in vec4 model_3;
+
+
+layout(location = 0) out vec3 v_normal;
+layout(location = 1) out vec3 FragmentPosition;
+layout(location = 2) out vec2 v_TexCoord;
+layout(location = 3) out vec3 v_position;
+layout(location = 4) out vec3 out_view_pos;
+layout(location = 5) out mat3 v_TBN;
+
+
+//Global uniforms, the model matrix is unused since it comes from the instance
+layout(set = 0, binding = 0) uniform Data {
+  vec3 camera_position;
+  mat4 model;
+  mat4 view;
+  mat4 proj;
+  float near;
+  float far;
+} u_main;
+
+
+void main() {
+
+  mat4 model = mat4(model_0, model_1, model_2, model_3);
+  vec4 pos = model * vec4(position, 1.0);
+
+  //Normals have to use the inverse transpose, otherwise non-uniform scale bends them
+  mat3 normal_matrix = transpose(inverse(mat3(model)));
+
+  vec3 T = normalize(vec3(model * vec4(tangent.xyz, 0.0)));
+  vec3 N = normalize(normal_matrix * normal);
+  // re-orthogonalize T with respect to N
+  T = normalize(T - dot(T, N) * N);
+  // then retrieve perpendicular vector B with the cross product of T and N
+  vec3 B = normalize(cross(N, T));// * tangent.w);
+
+  v_TBN = mat3(T, B, N);
+
+
+
+  FragmentPosition = vec3(pos);
+  v_position = pos.xyz;
+  v_TexCoord = tex_coord;
+  v_normal = normalize(N);
+  out_view_pos = (u_main.view * pos).xyz;
+  //The proj has been manipulated like here: https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/
+  gl_Position = u_main.proj * u_main.view * pos;
+}
@@ -32,6 +32,7 @@ layout(set = 1, binding = 1) uniform sampler2D t_Normal;
 layout(set = 1, binding = 2) uniform sampler2D t_Metall_Rough;
 layout(set = 1, binding = 3) uniform sampler2D t_Occlusion;
 layout(set = 1, binding = 4) uniform sampler2D t_Emissive;
+layout(set = 1, binding = 5) uniform sampler2D t_Height;
 //TEXTURE_USAGE
 //Texture usage infos (!= 1 is "not used" for now)
 layout(set = 2, binding = 0) uniform TextureUsageInfo {
@@ -41,6 +42,7 @@ layout(set = 2, binding = 0) uniform TextureUsageInfo {
   uint b_roughness;
   uint b_occlusion;
   uint b_emissive;
+  uint b_height;
   uint b_is_masked;
 } u_tex_usage_info;
 
@@ -55,6 +57,8 @@ layout(set = 2, binding = 1) uniform TextureFactors {
   float roughness_factor;
   float occlusion_factor;
   float alpha_cutoff;
+  float height_scale;
+  uint height_steps;
 } u_tex_fac;
 
 
@@ -80,10 +84,16 @@ layout(set = 3, binding = 0) readonly buffer ClusterBuffer {
 
 struct PointLight
 {
+  //one region and matrix per cube face in the order +x, -x, +y, -y, +z, -z
+  vec4 shadow_region[6];
+  mat4 light_space[6];
   vec3 color;
   vec3 location;
   float intensity;
   float radius;
+  float poisson_spread;
+  uint pcf_samples;
+  uint has_shadow;
 };
 
 layout(set = 3, binding = 1) readonly buffer point_lights{
@@ -100,6 +110,7 @@ struct DirectionalLight
   float intensity;
   float poisson_spread;
   uint pcf_samples;
+  uint has_shadow;
 };
 
 layout(set = 3, binding = 2) readonly buffer directional_lights{
@@ -108,6 +119,8 @@ layout(set = 3, binding = 2) readonly buffer directional_lights{
 //==============================================================================
 struct SpotLight
 {
+  vec4 shadow_region;
+  mat4 light_space;
   vec3 color;
   vec3 direction;
   vec3 location;
@@ -117,6 +130,9 @@ struct SpotLight
   float outer_radius;
   float inner_radius;
 
+  float poisson_spread;
+  uint pcf_samples;
+  uint has_shadow;
 };
 
 layout(set = 3, binding = 3) readonly buffer spot_lights{
@@ -133,6 +149,41 @@ layout(set = 3, binding = 4) uniform LightCount{
 //==============================================================================
 //The shadow maps.
 layout(set = 3, binding = 5) uniform sampler2D t_DirectionalShadows;
+//The atlas of the point and spot lights
+layout(set = 3, binding = 6) uniform sampler2D t_LocalShadows;
+
+//ENVIRONMENT
+//==============================================================================
+layout(set = 4, binding = 0) uniform EnvironmentInfo {
+  float intensity;
+  //mip level of the prefiltered map at roughness 1.0
+  float max_lod;
+  uint has_environment;
+} u_environment;
+
+layout(set = 4, binding = 1) uniform samplerCube t_Irradiance;
+layout(set = 4, binding = 2) uniform samplerCube t_Prefiltered;
+layout(set = 4, binding = 3) uniform sampler2D t_BrdfLut;
+
+//REFLECTION PROBES
+//==============================================================================
+//The probes which influence this object, the nearest first
+layout(set = 5, binding = 0) uniform ProbeInfo {
+  //xyz = location of the probe
+  vec4 location[2];
+  //xyz = min of the influence volume in world space, w = blend distance
+  vec4 influence_min[2];
+  //xyz = max of the influence volume in world space
+  vec4 influence_max[2];
+  //how many of the probes are valid
+  uint count;
+} u_probes;
+
+//each probe has a sharp cube map and a small, blurred one for rough surfaces
+layout(set = 5, binding = 1) uniform samplerCube t_ProbeSharp0;
+layout(set = 5, binding = 2) uniform samplerCube t_ProbeRough0;
+layout(set = 5, binding = 3) uniform samplerCube t_ProbeSharp1;
+layout(set = 5, binding = 4) uniform samplerCube t_ProbeRough1;
 
 //==============================================================================
 ///outgoing final color
@@ -189,6 +240,12 @@ vec3 fresnelSchlick(float cosTheta, vec3 F0)
     return F0 + (1.0 - F0) * pow(1.0 - cosTheta, 5.0);
 }
 
+//Like fresnelSchlick, but rough surfaces reflect less at grazing angles
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 F0, float roughness)
+{
+    return F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(1.0 - cosTheta, 5.0);
+}
+
 vec3 srgb_to_linear(vec3 c) {
     return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
 }
@@ -296,6 +353,18 @@ float pcfShadow(vec4 P, vec2 offset, sampler2D sm, vec4 region, int pcf, float s
 	return shadowFactor / count;
 }
 
+//Returns the cube face a direction points to, in the order +x, -x, +y, -y, +z, -z
+uint cubeFace(vec3 dir){
+  vec3 abs_dir = abs(dir);
+  if (abs_dir.x >= abs_dir.y && abs_dir.x >= abs_dir.z){
+    return dir.x > 0.0 ? 0 : 1;
+  }
+  if (abs_dir.y >= abs_dir.z){
+    return dir.y > 0.0 ? 2 : 3;
+  }
+  return dir.z > 0.0 ? 4 : 5;
+}
+
 //Calculates a point ligh -----------------------------------------------------
 vec3 calcPointLight(PointLight light, vec3 F0)
 {
@@ -308,6 +377,22 @@ vec3 calcPointLight(PointLight light, vec3 F0)
   //float attenuation = 1.0 / (distance * distance);
   vec3 radiance = light.color * light.intensity * falloff;
 
+  //each cube face has its own region on the atlas
+  if (light.has_shadow == 1){
+    uint face = cubeFace(FragmentPosition - light.location);
+    vec4 FragPosLightSpace = biasMat * light.light_space[face] * vec4(FragmentPosition, 1.0);
+
+    float shadow = pcfShadow(
+      FragPosLightSpace / FragPosLightSpace.w,
+      vec2(0,0),
+      t_LocalShadows,
+      light.shadow_region[face],
+      int(light.pcf_samples),
+      light.poisson_spread
+    );
+    radiance = shadow * radiance;
+  }
+
   // Cook-Torrance BRDF
   float NDF = DistributionGGX(H);
   float G   = GeometrySmith(surf_normal, V, L, roughness);
@@ -351,28 +436,30 @@ vec3 calcDirectionalLight(DirectionalLight light, vec3 F0)
   //find cascade
 
   //now compare the current depth to find the correct cascade, we want to use the nearest one
-  uint cascadeIndex = 0;
-  //TODO fix that
-	for(uint i = 0; i < 3; ++i) {
-		if(in_view_pos.z < light.shadow_depths[i]) {
-			cascadeIndex = i + 1;
-		}
-	}
-
-  vec4 shadow_region = light.shadow_region[cascadeIndex];
-  mat4 light_space = light.light_space[cascadeIndex];
-
-  vec4 FragPosLightSpace = biasMat * light_space * vec4(FragmentPosition, 1.0);
-
-  float shadow = pcfShadow(
-    FragPosLightSpace / FragPosLightSpace.w,
-    vec2(0,0),
-    t_DirectionalShadows,
-    shadow_region,
-    int(light.pcf_samples),
-    light.poisson_spread
-  );
-  radiance = shadow * radiance;
+  if (light.has_shadow == 1){
+    uint cascadeIndex = 0;
+    //TODO fix that
+  	for(uint i = 0; i < 3; ++i) {
+  		if(in_view_pos.z < light.shadow_depths[i]) {
+  			cascadeIndex = i + 1;
+  		}
+  	}
+
+    vec4 shadow_region = light.shadow_region[cascadeIndex];
+    mat4 light_space = light.light_space[cascadeIndex];
+
+    vec4 FragPosLightSpace = biasMat * light_space * vec4(FragmentPosition, 1.0);
+
+    float shadow = pcfShadow(
+      FragPosLightSpace / FragPosLightSpace.w,
+      vec2(0,0),
+      t_DirectionalShadows,
+      shadow_region,
+      int(light.pcf_samples),
+      light.poisson_spread
+    );
+    radiance = shadow * radiance;
+  }
 
   // Cook-Torrance BRDF
   float NDF = DistributionGGX(H);
@@ -421,6 +508,20 @@ vec3 calcSpotLight(SpotLight light, vec3 F0)
   float falloff = calcFalloff(distance, light.radius);
   vec3 radiance = light.color * light.intensity * falloff;
 
+  if (light.has_shadow == 1){
+    vec4 FragPosLightSpace = biasMat * light.light_space * vec4(FragmentPosition, 1.0);
+
+    float shadow = pcfShadow(
+      FragPosLightSpace / FragPosLightSpace.w,
+      vec2(0,0),
+      t_LocalShadows,
+      light.shadow_region,
+      int(light.pcf_samples),
+      light.poisson_spread
+    );
+    radiance = shadow * radiance;
+  }
+
   // Cook-Torrance BRDF
   float NDF = DistributionGGX(H);
   float G   = GeometrySmith(surf_normal, V, L, roughness);
@@ -437,6 +538,61 @@ vec3 calcSpotLight(SpotLight light, vec3 F0)
   return ((kD * albedo.xyz / PI + specular) * radiance * NdotL) * spot_intensity;  // note that we already multiplied the BRDF by the Fresnel (kS) so we won't multiply by kS again
 }
 
+//The probes are captured from the scene mirrored on y, see ReflectionProbe::get_face_views()
+vec3 probeDirection(vec3 dir){
+  return vec3(dir.x, -dir.y, dir.z);
+}
+
+//Returns 1.0 within the influence volume of a probe, fading to 0.0 at its edges
+float probeWeight(uint idx){
+  vec3 inf_min = u_probes.influence_min[idx].xyz;
+  vec3 inf_max = u_probes.influence_max[idx].xyz;
+  float blend_distance = u_probes.influence_min[idx].w;
+
+  //distance to the nearest side of the volume, negative outside of it
+  vec3 to_side = min(FragmentPosition - inf_min, inf_max - FragmentPosition);
+  float side_distance = min(to_side.x, min(to_side.y, to_side.z));
+  if (side_distance < 0.0){
+    return 0.0;
+  }
+  if (blend_distance <= 0.0){
+    return 1.0;
+  }
+  return clamp(side_distance / blend_distance, 0.0, 1.0);
+}
+
+//Intersects the reflection ray with the influence volume and returns the direction from the probe
+// to the hit point. Otherwise reflections would look like they are infinitely far away.
+vec3 boxProjection(vec3 R, uint idx){
+  vec3 first = (u_probes.influence_max[idx].xyz - FragmentPosition) / R;
+  vec3 second = (u_probes.influence_min[idx].xyz - FragmentPosition) / R;
+  vec3 furthest = max(first, second);
+  float hit_distance = min(furthest.x, min(furthest.y, furthest.z));
+
+  vec3 hit = FragmentPosition + R * hit_distance;
+  return hit - u_probes.location[idx].xyz;
+}
+
+//Samples a probe, `blur` blends from the sharp to the blurred cube map. Uses lod 0 since the
+// probes are sampled in non uniform control flow and don't have mip maps.
+vec3 sampleProbe(uint idx, vec3 dir, float blur){
+  vec3 probe_dir = probeDirection(dir);
+  if (idx == 0){
+    return mix(textureLod(t_ProbeSharp0, probe_dir, 0.0).rgb, textureLod(t_ProbeRough0, probe_dir, 0.0).rgb, blur);
+  }
+  return mix(textureLod(t_ProbeSharp1, probe_dir, 0.0).rgb, textureLod(t_ProbeRough1, probe_dir, 0.0).rgb, blur);
+}
+
+//Analytic fit of the brdf lookup table, used if there is no environment
+//Source: https://www.unrealengine.com/en-US/blog/physically-based-shading-on-mobile
+vec2 envBrdfApprox(float NdotV, float roughness){
+  const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+  const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+  vec4 r = roughness * c0 + c1;
+  float a004 = min(r.x * r.x, exp2(-9.28 * NdotV)) * r.x + r.y;
+  return vec2(-1.04, 1.04) * a004 + r.zw;
+}
+
 bool isInClusters(){
   if (
     FragmentPosition.x < indice_buffer.min_extend.x ||
@@ -453,14 +609,57 @@ bool isInClusters(){
   return true;
 }
 
+//PARALLAX OCCLUSION MAPPING
+//==============================================================================
+//Steps through the height map along the view ray (in tangent space) and returns the texture
+// coordinate where the ray enters the surface. Flat view angles use more steps.
+vec2 parallaxOcclusion(vec2 uv, vec3 view_tangent){
+  float max_steps = float(max(u_tex_fac.height_steps, 1u));
+  float num_layers = mix(max_steps, max(max_steps / 4.0, 1.0), abs(view_tangent.z));
+  float layer_depth = 1.0 / num_layers;
+  vec2 delta = (view_tangent.xy / max(view_tangent.z, 0.05)) * u_tex_fac.height_scale / num_layers;
+
+  //the gradients of the original coordinate keep the mip level stable within the loop
+  vec2 uv_dx = dFdx(uv);
+  vec2 uv_dy = dFdy(uv);
+
+  //the height map stores heights, the ray walks down in depth
+  vec2 current_uv = uv;
+  float current_layer = 0.0;
+  float current_depth = 1.0 - textureGrad(t_Height, current_uv, uv_dx, uv_dy).r;
+  for (int i = 0; i < int(num_layers) && current_layer < current_depth; i++){
+    current_uv -= delta;
+    current_depth = 1.0 - textureGrad(t_Height, current_uv, uv_dx, uv_dy).r;
+    current_layer += layer_depth;
+  }
+
+  //interpolate between the layers before and after the hit
+  vec2 previous_uv = current_uv + delta;
+  float after = current_depth - current_layer;
+  float before = (1.0 - textureGrad(t_Height, previous_uv, uv_dx, uv_dy).r) - current_layer + layer_depth;
+  float denominator = after - before;
+  if (abs(denominator) < 0.0001){
+    return current_uv;
+  }
+  return mix(current_uv, previous_uv, after / denominator);
+}
+
 // ----------------------------------------------------------------------------
 void main()
 {
+  //everything is sampled where the view ray hits the height map, camera_position is the eye of
+  // the current pass (the camera or the face of a reflection probe)
+  vec2 tex_coord = v_TexCoord;
+  if (u_tex_usage_info.b_height == 1){
+    vec3 view_tangent = normalize(transpose(v_TBN) * (u_main.camera_position - FragmentPosition));
+    tex_coord = parallaxOcclusion(v_TexCoord, view_tangent);
+  }
+
   if (u_tex_usage_info.b_albedo != 1) {
     albedo = u_tex_fac.albedo_factor;
   }else{
     //convert from srgb (lazy)
-    albedo = texture(t_Albedo, v_TexCoord);// * u_tex_fac.albedo_factor;
+    albedo = texture(t_Albedo, tex_coord);// * u_tex_fac.albedo_factor;
     //before we do anything expensive, theck if that material is masked, if so,
     //return if the current albedo alpha value is below the alpha_cutoff
     if(u_tex_usage_info.b_is_masked != 0){
@@ -480,14 +679,14 @@ void main()
   if (u_tex_usage_info.b_metal != 1) {
     metallic = u_tex_fac.metal_factor;
   }else{
-    metallic = texture(t_Metall_Rough, v_TexCoord).b * u_tex_fac.metal_factor;
+    metallic = texture(t_Metall_Rough, tex_coord).b * u_tex_fac.metal_factor;
   }
 
   //Set roughness color
   if (u_tex_usage_info.b_roughness != 1) {
     roughness = u_tex_fac.roughness_factor;
   }else{
-    roughness = texture(t_Metall_Rough, v_TexCoord).g * u_tex_fac.roughness_factor;
+    roughness = texture(t_Metall_Rough, tex_coord).g * u_tex_fac.roughness_factor;
   }
 
   //Set ao color
@@ -495,7 +694,7 @@ void main()
   if (u_tex_usage_info.b_occlusion != 1) {
     ao = u_tex_fac.occlusion_factor;
   }else{
-    ao = texture(t_Occlusion, v_TexCoord).r * u_tex_fac.occlusion_factor;
+    ao = texture(t_Occlusion, tex_coord).r * u_tex_fac.occlusion_factor;
   }
 
   //Set emessive color
@@ -503,7 +702,7 @@ void main()
   if (u_tex_usage_info.b_emissive != 1) {
     emissive = vec3(u_tex_fac.emissive_factor * u_tex_fac.max_emission);
   }else{
-    emissive = texture(t_Emissive, v_TexCoord).rgb * u_tex_fac.emissive_factor * u_tex_fac.max_emission;
+    emissive = texture(t_Emissive, tex_coord).rgb * u_tex_fac.emissive_factor * u_tex_fac.max_emission;
   }
 
   //TODO implemetn emmessive
@@ -512,7 +711,7 @@ void main()
     //from three-rs
     surf_normal = v_normal; //use the vertex normal
   }else {
-    vec3 surf_normal_tex = texture(t_Normal, v_TexCoord).rgb;
+    vec3 surf_normal_tex = texture(t_Normal, tex_coord).rgb;
     surf_normal = normalize(v_TBN * ((surf_normal_tex * 2.0 - 1.0) * vec3(u_tex_fac.normal_factor, u_tex_fac.normal_factor, 1.0)));
   }
 
@@ -574,10 +773,46 @@ void main()
   for(int i = 0; i < u_light_count.directionals; i++){
     Lo += calcDirectionalLight(u_dir_light.d_light[i], F0);
   }
-  //TODO
-  // ambient lighting (note that the next IBL tutorial will replace
-  // this ambient lighting with environment lighting).
-  vec3 ambient = vec3(0.03) * albedo.xyz * ao;
+  // ambient lighting, blended from the reflection probes and the environment maps
+  float NdotV = max(dot(surf_normal, V), 0.0);
+  vec3 R = reflect(-V, surf_normal);
+  vec3 F = fresnelSchlickRoughness(NdotV, F0, roughness);
+  vec3 kD = (vec3(1.0) - F) * (1.0 - metallic);
+
+  //the nearest probe wins where both overlap, the environment gets what is left
+  float probe_weight[2] = float[](0.0, 0.0);
+  float weight_sum = 0.0;
+  for (uint i = 0; i < u_probes.count && i < 2; i++){
+    probe_weight[i] = min(probeWeight(i), 1.0 - weight_sum);
+    weight_sum += probe_weight[i];
+  }
+
+  vec3 irradiance = vec3(0.0);
+  vec3 prefiltered = vec3(0.0);
+  vec2 brdf = envBrdfApprox(NdotV, roughness);
+  if (u_environment.has_environment == 1){
+    float env_weight = (1.0 - weight_sum) * u_environment.intensity;
+    irradiance = texture(t_Irradiance, surf_normal).rgb * env_weight;
+    //the prefiltered map gets blurrier with each mip level
+    prefiltered = textureLod(t_Prefiltered, R, roughness * u_environment.max_lod).rgb * env_weight;
+    brdf = texture(t_BrdfLut, vec2(NdotV, roughness)).rg;
+  }
+
+  //the blurred cube map of a probe is used as irradiance and for rough surfaces
+  float blur = smoothstep(0.0, 0.7, roughness);
+  for (uint i = 0; i < 2; i++){
+    if (probe_weight[i] <= 0.0){
+      continue;
+    }
+    irradiance += sampleProbe(i, surf_normal, 1.0) * probe_weight[i];
+    prefiltered += sampleProbe(i, boxProjection(R, i), blur) * probe_weight[i];
+  }
+
+  vec3 specular = prefiltered * (F * brdf.x + brdf.y);
+  vec3 ambient = (kD * irradiance * albedo.xyz + specular) * ao;
+  if (u_environment.has_environment != 1){
+    ambient += vec3(0.03) * albedo.xyz * ao * (1.0 - weight_sum);
+  }
 
   vec3 color = ambient + Lo + emissive;
 
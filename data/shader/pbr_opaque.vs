@@ -30,13 +30,11 @@ void main() {
 
   vec4 pos = u_main.model * vec4(position, 1.0);
 
-  //mat3 normal_matrix = transpose(inverse(mat3(u_main.model)));
-
-  //vec3 T = normalize(normal_matrix * tangent.xyz);
-  //vec3 N = normalize(normal_matrix * normal);
+  //Normals have to use the inverse transpose, otherwise non-uniform scale bends them
+  mat3 normal_matrix = transpose(inverse(mat3(u_main.model)));
 
   vec3 T = normalize(vec3(u_main.model * vec4(tangent.xyz, 0.0)));
-  vec3 N = normalize(vec3(u_main.model * vec4(normal, 0.0)));
+  vec3 N = normalize(normal_matrix * normal);
   // re-orthogonalize T with respect to N
   T = normalize(T - dot(T, N) * N);
   // then retrieve perpendicular vector B with the cross product of T and N
@@ -0,0 +1,29 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec2 tex_coord;
+layout(location = 2) in vec3 normal;
+layout(location = 3) in vec4 tangent;
+layout(location = 4) in vec4 color;
+//The model matrix of this instance, one column per attribute
+layout(location = 5) in vec4 model_0;
+layout(location = 6) in vec4 model_1;
+layout(location = 7) in vec4 model_2;
+layout(location = 8) in vec4 model_3;
+
+layout(location = 1) out vec2 uv;
+
+
+//Global uniforms, the model matrix is unused since it comes from the instance
+layout(set = 0, binding = 0) uniform LightData {
+  mat4 model;
+  mat4 viewproj;
+} u_light_main;
+
+void main(){
+  mat4 model = mat4(model_0, model_1, model_2, model_3);
+  //The proj has been manipulated like here: https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/
+  vec4 ndc_Pos = u_light_main.viewproj * model * vec4(position, 1.0);
+  uv = tex_coord;
+  gl_Position = ndc_Pos;
+}
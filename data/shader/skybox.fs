@@ -0,0 +1,22 @@
+#version 450
+
+layout(location = 0) in vec2 v_pos;
+
+layout(set = 0, binding = 0) uniform SkyInfo {
+  mat4 inverse_view_projection;
+  vec3 camera_position;
+  float intensity;
+} u_sky;
+
+layout(set = 0, binding = 1) uniform samplerCube t_Sky;
+
+layout(location = 0) out vec4 f_color;
+
+void main()
+{
+  //project the fragment back onto the far plane to get the view direction
+  vec4 far_point = u_sky.inverse_view_projection * vec4(v_pos, 1.0, 1.0);
+  vec3 direction = normalize(far_point.xyz / far_point.w - u_sky.camera_position);
+
+  f_color = vec4(texture(t_Sky, direction).rgb * u_sky.intensity, 1.0);
+}
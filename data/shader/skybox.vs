@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 tex_coord;
+
+layout(location = 0) out vec2 v_pos;
+
+void main()
+{
+    v_pos = position;
+    //Always on the far plane, only fragments which are not covered by any mesh pass the depth test
+    gl_Position = vec4(position.x, position.y, 1.0, 1.0);
+}
@@ -0,0 +1,113 @@
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use image;
+
+///Describes why an asset could not be loaded or created
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssetError {
+    ///A file could not be read, holds the path and the reason
+    Io{ path: String, reason: String },
+    ///The data could be read, but is broken or malformed, holds the path (or a description of the
+    /// data) and the reason
+    Decode{ path: String, reason: String },
+    ///The asset uses something the engine doesn't support (yet), holds a description
+    Unsupported(String),
+    ///Creating or uploading a gpu resource like an image, a buffer or a descriptor set failed
+    GpuUpload(String),
+    ///A manager already holds a resource with this name
+    AlreadyExists(String),
+    ///A resource is not in its manager, holds a description of it
+    NotFound(String),
+    ///The importer panicked while loading the file at `path`, holds the panic message if it had one
+    Panicked{ path: String, reason: String },
+}
+
+impl AssetError{
+    ///Creates an `Io` error for the file at `path`
+    pub fn io(path: &str, error: io::Error) -> Self{
+        AssetError::Io{ path: String::from(path), reason: error.to_string() }
+    }
+
+    ///Creates a `Decode` error for the file (or data) at `path`
+    pub fn decode(path: &str, reason: &str) -> Self{
+        AssetError::Decode{ path: String::from(path), reason: String::from(reason) }
+    }
+
+    ///Creates a `Panicked` error for the file at `path` from the payload `catch_unwind` returned
+    pub fn from_panic(path: &str, payload: Box<Any + Send>) -> Self{
+        let reason = if let Some(message) = payload.downcast_ref::<&str>(){
+            String::from(*message)
+        }else if let Some(message) = payload.downcast_ref::<String>(){
+            message.clone()
+        }else{
+            String::from("unknown reason")
+        };
+        AssetError::Panicked{ path: String::from(path), reason: reason }
+    }
+
+    ///Sorts an error of the image crate into io, decode and unsupported errors
+    pub fn from_image_error(path: &str, error: image::ImageError) -> Self{
+        match error{
+            image::ImageError::IoError(io_error) => AssetError::io(path, io_error),
+            image::ImageError::UnsupportedError(reason) => AssetError::Unsupported(
+                format!("{}: {}", path, reason)
+            ),
+            other => AssetError::decode(path, &other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for AssetError{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        match *self{
+            AssetError::Io{ref path, ref reason} => write!(f, "could not read {}: {}", path, reason),
+            AssetError::Decode{ref path, ref reason} => write!(f, "could not decode {}: {}", path, reason),
+            AssetError::Unsupported(ref what) => write!(f, "unsupported: {}", what),
+            AssetError::GpuUpload(ref what) => write!(f, "gpu upload failed: {}", what),
+            AssetError::AlreadyExists(ref name) => write!(f, "{} already exists", name),
+            AssetError::NotFound(ref what) => write!(f, "could not find {}", what),
+            AssetError::Panicked{ref path, ref reason} => write!(f, "the importer panicked while loading {}: {}", path, reason),
+        }
+    }
+}
+
+impl Error for AssetError{
+    fn description(&self) -> &str{
+        match *self{
+            AssetError::Io{..} => "could not read an asset file",
+            AssetError::Decode{..} => "could not decode an asset",
+            AssetError::Unsupported(_) => "the asset uses an unsupported feature",
+            AssetError::GpuUpload(_) => "could not upload an asset to the gpu",
+            AssetError::AlreadyExists(_) => "the asset already exists",
+            AssetError::NotFound(_) => "the asset could not be found",
+            AssetError::Panicked{..} => "the importer panicked",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic;
+
+    #[test]
+    fn panic_message_is_kept(){
+        let payload = panic::catch_unwind(|| panic!("broken accessor")).unwrap_err();
+        assert_eq!(
+            AssetError::from_panic("scene.gltf", payload),
+            AssetError::Panicked{ path: String::from("scene.gltf"), reason: String::from("broken accessor") }
+        );
+    }
+
+    #[test]
+    fn formatted_panic_message_is_kept(){
+        let payload = panic::catch_unwind(|| panic!("index {} out of range", 3)).unwrap_err();
+        match AssetError::from_panic("scene.gltf", payload){
+            AssetError::Panicked{ reason, .. } => assert_eq!(reason, "index 3 out of range"),
+            other => panic!("expected a panic error, got {:?}", other),
+        }
+    }
+}
@@ -2,6 +2,7 @@
 use winit;
 use vulkano;
 use core::render_settings;
+use physics::PhysicsSettings;
 
 ///Describes how the engine should handle debuging messages and vulkan settings
 #[derive(Clone, PartialEq)]
@@ -65,6 +66,17 @@ pub struct EngineSettings {
     ///The camera settings
     pub camera: CameraSettings,
 
+    ///If true the engine won't create a window, a vulkan device or the renderer. Only the asset
+    /// manager and the scene tree are updated. Meshes are kept on the cpu only.
+    pub headless: bool,
+
+    ///Settings of the physics simulation
+    pub physics: PhysicsSettings,
+
+    ///If `Some(interval)` the asset manager checks every `interval` seconds if the files of the
+    /// loaded textures and glTF scenes changed and reloads them. glTF materials are not reloaded.
+    pub hot_reload: Option<f32>,
+
 }
 
 impl EngineSettings{
@@ -118,7 +130,13 @@ impl EngineSettings{
             camera: CameraSettings{
                 far_plane: 100.0,
                 near_plane: 1.0,
-            }
+            },
+
+            headless: false,
+
+            physics: PhysicsSettings::default(),
+
+            hot_reload: None,
         }
     }
 
@@ -130,11 +148,23 @@ impl EngineSettings{
         self
     }
 
+    ///Adds custom physics settings to self
+    pub fn with_physics_settings(mut self, settings: PhysicsSettings) -> Self{
+        self.physics = settings;
+        self
+    }
+
     ///Returns the current render settings
     pub fn get_render_settings(&self) -> &render_settings::RenderSettings {
         &self.render_settings
     }
 
+    ///Adds custom physics settings to self
+    pub fn with_physics_settings(mut self, settings: PhysicsSettings) -> Self{
+        self.physics = settings;
+        self
+    }
+
     ///Returns the current render settings, but mutable
     pub fn get_render_settings_mut(&mut self) -> &mut render_settings::RenderSettings {
         &mut self.render_settings
@@ -218,6 +248,28 @@ impl EngineSettings{
         self
     }
 
+    ///Starts the engine without a window, input thread and renderer. Can be used to run
+    /// gameplay and scene code on machines without a gpu.
+    #[inline]
+    pub fn in_headless_mode(mut self) -> Self{
+        self.headless = true;
+        self
+    }
+
+    ///Reloads changed textures and glTF scenes, the files are checked every `interval` seconds.
+    /// Only the geometry of glTF scenes is reloaded, not their materials.
+    #[inline]
+    pub fn with_hot_reload(mut self, interval: f32) -> Self{
+        self.hot_reload = Some(interval);
+        self
+    }
+
+    ///Returns true if the engine runs without a renderer
+    #[inline]
+    pub fn is_headless(&self) -> bool{
+        self.headless
+    }
+
     ///Returns true if vulkan should be silent
     #[inline]
     pub fn vulkan_silence(&self) -> bool{
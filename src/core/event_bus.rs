@@ -0,0 +1,343 @@
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Weak, Mutex};
+use std::path::PathBuf;
+use std::panic;
+
+use winit;
+
+use core::asset_error::AssetError;
+
+///Every type which is `Clone + Send + Sync` can be published as event
+pub trait Event: Any + Clone + Send + Sync {}
+
+impl<T: Any + Clone + Send + Sync> Event for T{}
+
+///A key was pressed or released
+#[derive(Clone, Debug)]
+pub struct KeyEvent {
+    pub key: winit::VirtualKeyCode,
+    pub is_pressed: bool,
+}
+
+///A mouse button was pressed or released
+#[derive(Clone, Debug)]
+pub struct MouseButtonEvent {
+    pub button: winit::MouseButton,
+    pub is_pressed: bool,
+}
+
+///The cursor moved within the window, the position is in pixel from the upper left corner
+#[derive(Clone, Debug)]
+pub struct CursorMovedEvent {
+    pub position: (f64, f64),
+}
+
+///The mouse wheel or the touchpad was scrolled
+#[derive(Clone, Debug)]
+pub struct MouseWheelEvent {
+    pub delta: winit::MouseScrollDelta,
+}
+
+///The window was resized to `width` x `height` pixel
+#[derive(Clone, Debug)]
+pub struct WindowResizedEvent {
+    pub width: u32,
+    pub height: u32,
+}
+
+///The window gained or lost the focus
+#[derive(Clone, Debug)]
+pub struct WindowFocusEvent {
+    pub is_focused: bool,
+}
+
+///The window should be closed
+#[derive(Clone, Debug)]
+pub struct WindowClosedEvent;
+
+///A file was dropped onto the window
+#[derive(Clone, Debug)]
+pub struct FileDroppedEvent {
+    pub path: PathBuf,
+}
+
+///An asynchronous import finished, the result holds the name of the scene in the scene manager
+#[derive(Clone, Debug)]
+pub struct AssetLoadedEvent {
+    ///The name the scene was requested with
+    pub name: String,
+    pub path: String,
+    pub result: Result<String, AssetError>,
+}
+
+///A scene was added to the scene manager under `name`
+#[derive(Clone, Debug)]
+pub struct SceneAddedEvent {
+    pub name: String,
+}
+
+///The scene `name` was removed from the scene manager
+#[derive(Clone, Debug)]
+pub struct SceneRemovedEvent {
+    pub name: String,
+}
+
+///The renderer recreated the swapchain, usually because the window was resized
+#[derive(Clone, Debug)]
+pub struct SwapchainRecreatedEvent {
+    pub dimensions: [u32; 2],
+}
+
+///The renderer presented a frame
+#[derive(Clone, Debug)]
+pub struct FrameFinishedEvent {
+    ///Counts the presented frames, starting at 0
+    pub frame: u64,
+    ///Seconds the cpu spent on the frame, the gpu might still be working on it
+    pub cpu_time: f32,
+}
+
+///How many events a reader created by `EventBus::reader()` keeps
+pub const DEFAULT_READER_CAPACITY: usize = 1024;
+
+///Identifies a callback subscription, can be used to unsubscribe
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SubscriptionId {
+    type_id: TypeId,
+    id: usize,
+}
+
+///The events a reader did not read yet
+struct ReaderBuffer<E> {
+    events: VecDeque<E>,
+    capacity: usize,
+    dropped: usize,
+}
+
+///Receives every event of type `E` which was published since it was created. Call `read()` once
+/// per frame to get all new events.
+///NOTE: A reader buffers at most its capacity of events, if it is full the oldest event is
+/// dropped for every new one.
+pub struct EventReader<E> {
+    buffer: Arc<Mutex<ReaderBuffer<E>>>,
+}
+
+impl<E: Event> EventReader<E>{
+    ///Returns all events since the last call, the oldest first
+    pub fn read(&self) -> Vec<E>{
+        let mut buffer_lck = self.buffer.lock().expect("failed to lock event reader");
+        buffer_lck.dropped = 0;
+        buffer_lck.events.drain(..).collect()
+    }
+
+    ///Returns how many events were dropped since the last `read()` because the reader was full
+    pub fn get_dropped(&self) -> usize{
+        self.buffer.lock().expect("failed to lock event reader").dropped
+    }
+}
+
+///The subscribers of one event type
+struct EventChannel<E> {
+    callbacks: Vec<(usize, Arc<Fn(&E) + Send + Sync>)>,
+    readers: Vec<Weak<Mutex<ReaderBuffer<E>>>>,
+}
+
+///Lets the bus handle channels without knowing their event type
+trait AnyChannel: Send {
+    fn remove_callback(&mut self, id: usize) -> bool;
+    fn as_any_mut(&mut self) -> &mut Any;
+}
+
+impl<E: Event> AnyChannel for EventChannel<E>{
+    fn remove_callback(&mut self, id: usize) -> bool{
+        let old_len = self.callbacks.len();
+        self.callbacks.retain(|&(callback_id, _)| callback_id != id);
+        self.callbacks.len() != old_len
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any{
+        self
+    }
+}
+
+struct BusState {
+    channels: BTreeMap<TypeId, Box<AnyChannel>>,
+    next_id: usize,
+}
+
+impl BusState{
+    ///Returns the channel of `E`, creates it if needed
+    fn get_channel<E: Event>(&mut self) -> &mut EventChannel<E>{
+        self.channels.entry(TypeId::of::<E>())
+        .or_insert_with(|| Box::new(EventChannel::<E>{
+            callbacks: Vec::new(),
+            readers: Vec::new(),
+        }) as Box<AnyChannel>)
+        .as_any_mut()
+        .downcast_mut::<EventChannel<E>>()
+        .expect("event channel has the wrong type")
+    }
+}
+
+///A typed publish / subscribe bus. Can be cloned and used from any thread, all clones share
+/// their subscribers.
+#[derive(Clone)]
+pub struct EventBus {
+    state: Arc<Mutex<BusState>>,
+}
+
+impl EventBus{
+    ///Creates a bus without subscribers
+    pub fn new() -> Self{
+        EventBus{
+            state: Arc::new(Mutex::new(BusState{
+                channels: BTreeMap::new(),
+                next_id: 0,
+            })),
+        }
+    }
+
+    ///Calls `callback` for every published event of type `E`. The callback runs on the thread
+    /// which publishes the event, for instance the input thread, so keep it short. Scene and
+    /// asset events are published while the asset manager is locked, don't lock it in a callback,
+    /// use a reader or the command queue instead.
+    pub fn subscribe<E, F>(&self, callback: F) -> SubscriptionId
+    where E: Event, F: Fn(&E) + Send + Sync + 'static
+    {
+        let callback: Arc<Fn(&E) + Send + Sync> = Arc::new(callback);
+        let mut state_lck = self.state.lock().expect("failed to lock event bus");
+        let id = state_lck.next_id;
+        state_lck.next_id += 1;
+        state_lck.get_channel::<E>().callbacks.push((id, callback));
+
+        SubscriptionId{
+            type_id: TypeId::of::<E>(),
+            id: id,
+        }
+    }
+
+    ///Removes a callback, returns false if it was already removed
+    pub fn unsubscribe(&self, subscription: SubscriptionId) -> bool{
+        let mut state_lck = self.state.lock().expect("failed to lock event bus");
+        match state_lck.channels.get_mut(&subscription.type_id){
+            Some(channel) => channel.remove_callback(subscription.id),
+            None => false,
+        }
+    }
+
+    ///Creates a reader which receives every event of type `E` published from now on. It keeps
+    /// the last `DEFAULT_READER_CAPACITY` events.
+    pub fn reader<E: Event>(&self) -> EventReader<E>{
+        self.reader_with_capacity(DEFAULT_READER_CAPACITY)
+    }
+
+    ///Like `reader()` but keeps the last `capacity` events, at least one.
+    pub fn reader_with_capacity<E: Event>(&self, capacity: usize) -> EventReader<E>{
+        let buffer = Arc::new(Mutex::new(ReaderBuffer{
+            events: VecDeque::new(),
+            capacity: capacity.max(1),
+            dropped: 0,
+        }));
+        let mut state_lck = self.state.lock().expect("failed to lock event bus");
+        state_lck.get_channel::<E>().readers.push(Arc::downgrade(&buffer));
+        EventReader{
+            buffer: buffer,
+        }
+    }
+
+    ///Sends `event` to all readers and calls all callbacks of its type. A panicking callback
+    /// is removed.
+    pub fn publish<E: Event>(&self, event: E){
+        let callbacks = {
+            let mut state_lck = self.state.lock().expect("failed to lock event bus");
+            if !state_lck.channels.contains_key(&TypeId::of::<E>()){
+                return;
+            }
+            let channel = state_lck.get_channel::<E>();
+            //readers which were dropped are removed
+            channel.readers.retain(|reader| match reader.upgrade(){
+                Some(buffer) => {
+                    let mut buffer_lck = buffer.lock().expect("failed to lock event reader");
+                    if buffer_lck.events.len() >= buffer_lck.capacity{
+                        buffer_lck.events.pop_front();
+                        buffer_lck.dropped += 1;
+                    }
+                    buffer_lck.events.push_back(event.clone());
+                    true
+                },
+                None => false,
+            });
+            channel.callbacks.clone()
+        };
+
+        //The lock is released, so callbacks can publish and subscribe themselves
+        for (id, callback) in callbacks.into_iter(){
+            let result = panic::catch_unwind(panic::AssertUnwindSafe(||{
+                callback(&event)
+            }));
+
+            if result.is_err(){
+                println!("WARNING: EVENT_BUS: an event callback panicked, it is removed", );
+                self.unsubscribe(SubscriptionId{ type_id: TypeId::of::<E>(), id: id });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestEvent(u32);
+
+    #[test]
+    fn reader_gets_events_in_order(){
+        let bus = EventBus::new();
+        let reader = bus.reader::<TestEvent>();
+        bus.publish(TestEvent(1));
+        bus.publish(TestEvent(2));
+        assert_eq!(reader.read(), vec![TestEvent(1), TestEvent(2)]);
+        assert!(reader.read().is_empty());
+    }
+
+    #[test]
+    fn full_reader_drops_the_oldest_events(){
+        let bus = EventBus::new();
+        let reader = bus.reader_with_capacity::<TestEvent>(2);
+        for i in 0..5{
+            bus.publish(TestEvent(i));
+        }
+        assert_eq!(reader.get_dropped(), 3);
+        assert_eq!(reader.read(), vec![TestEvent(3), TestEvent(4)]);
+        assert_eq!(reader.get_dropped(), 0);
+    }
+
+    #[test]
+    fn dropped_reader_is_removed(){
+        let bus = EventBus::new();
+        let reader = bus.reader::<TestEvent>();
+        drop(reader);
+        bus.publish(TestEvent(0));
+
+        let mut state_lck = bus.state.lock().expect("failed to lock event bus");
+        assert!(state_lck.get_channel::<TestEvent>().readers.is_empty());
+    }
+
+    #[test]
+    fn callbacks_are_called_until_unsubscribed(){
+        let bus = EventBus::new();
+        let sum = Arc::new(Mutex::new(0));
+        let callback_sum = sum.clone();
+        let id = bus.subscribe(move |event: &TestEvent| {
+            *callback_sum.lock().expect("failed to lock sum") += event.0;
+        });
+
+        bus.publish(TestEvent(2));
+        bus.publish(TestEvent(3));
+        assert!(bus.unsubscribe(id));
+        bus.publish(TestEvent(4));
+        assert_eq!(*sum.lock().expect("failed to lock sum"), 5);
+    }
+}
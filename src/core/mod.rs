@@ -12,6 +12,10 @@ pub mod resource_management;
 pub mod resources;
 ///A new tree system which will replace the `simple_scene_system`.
 pub mod next_tree;
+///The error type of everything which loads or creates assets
+pub mod asset_error;
+///A typed publish / subscribe bus for input, window, asset and renderer events
+pub mod event_bus;
 
 //use std::sync::{Arc, Mutex};
 use cgmath::*;
@@ -2,14 +2,24 @@ use cgmath::*;
 use collision::*;
 use jakar_tree::node::Attribute;
 use super::jobs::SceneJobs;
+use super::transform::NodeTransform;
+use super::components::{Component, ComponentStore};
 use super::*;
+use physics::rigid_body::RigidBody;
 use std::f32;
+use std::collections::BTreeSet;
+
+///The layer every node is in if not set otherwise
+pub const LAYER_DEFAULT: u32 = 1;
+///A layer mask which contains every layer
+pub const LAYER_ALL: u32 = ::std::u32::MAX;
 
 ///A node can have this attributes
 #[derive(Clone)]
 pub struct NodeAttributes {
-    ///Transform of this node in local space
-    pub transform: Decomposed<Vector3<f32>, Quaternion<f32>>,
+    ///Transform of this node in world space. Moving a node with a job moves its children along,
+    /// use `NodeTransform::relative_to()` to get the transform relative to the parent.
+    pub transform: NodeTransform,
     ///The bounds of this node, takes the `content` bound as well as the max and min values of
     ///all its children into consideration.
     /// This is in worldspace.
@@ -32,19 +42,28 @@ pub struct NodeAttributes {
     /// This indicates the max draw distance for this object. However, while sorting the
     /// actuall value can dynamicly be tweaked by a bias. It is usually set while importing the mesh.
     pub max_draw_distance: f32,
+    ///Some if this node should be simulated by the physics system.
+    pub rigid_body: Option<RigidBody>,
+    ///Gameplay data of this node, at most one component per type.
+    pub components: ComponentStore,
+    ///User defined tags, unlike the node name they don't change when a model is re-imported.
+    pub tags: BTreeSet<String>,
+    ///Bitmask of the layers this node is in. Cameras only render nodes which share at least one
+    /// layer with their render layers.
+    pub layers: u32,
 }
 
 ///A custom implementation
 impl NodeAttributes{
     /// Returns a reference to the transform component. Can be used to determin 3d relation between
     /// nodes.
-    pub fn get_transform(&self) -> &Decomposed<Vector3<f32>, Quaternion<f32>>{
+    pub fn get_transform(&self) -> &NodeTransform{
         &self.transform
     }
 
     ///Returns the model matrix of this node
     pub fn get_matrix(&self) -> Matrix4<f32>{
-        Matrix4::from(self.transform)
+        self.transform.get_matrix()
     }
 
     /// Returns bound information of this node (**NOT THE MESH BOUND**)
@@ -57,6 +76,52 @@ impl NodeAttributes{
         &self.value_bound
     }
 
+    ///Adds a component to this node, returns the old one if the node already had a component of
+    /// this type.
+    pub fn add_component<T: Component>(&mut self, component: T) -> Option<T>{
+        self.components.add(component)
+    }
+
+    ///Returns the component of type `T` if this node has one
+    pub fn get_component<T: Component>(&self) -> Option<&T>{
+        self.components.get::<T>()
+    }
+
+    ///Returns the component of type `T` mutable if this node has one
+    pub fn get_component_mut<T: Component>(&mut self) -> Option<&mut T>{
+        self.components.get_mut::<T>()
+    }
+
+    ///Removes the component of type `T` from this node and returns it
+    pub fn remove_component<T: Component>(&mut self) -> Option<T>{
+        self.components.remove::<T>()
+    }
+
+    ///Returns true if this node has a component of type `T`
+    pub fn has_component<T: Component>(&self) -> bool{
+        self.components.has::<T>()
+    }
+
+    ///Adds a `tag` to this node
+    pub fn add_tag(&mut self, tag: &str){
+        self.tags.insert(String::from(tag));
+    }
+
+    ///Removes the `tag` from this node, returns true if the node had this tag
+    pub fn remove_tag(&mut self, tag: &str) -> bool{
+        self.tags.remove(tag)
+    }
+
+    ///Returns true if this node has the `tag`
+    pub fn has_tag(&self, tag: &str) -> bool{
+        self.tags.contains(tag)
+    }
+
+    ///Returns true if this node is in at least one of the layers of `layer_mask`
+    pub fn is_in_layer(&self, layer_mask: u32) -> bool{
+        self.layers & layer_mask != 0
+    }
+
 }
 
 
@@ -73,20 +138,24 @@ impl Attribute<SceneJobs> for NodeAttributes{
     /// - is_transparent: false
     /// - hide_in_game: false
     /// - max_draw_distance: 100.0
+    /// - rigid_body: None
+    /// - components: none
+    /// - tags: none
+    /// - layers: LAYER_DEFAULT
     fn default() -> Self{
         NodeAttributes{
-            transform: Decomposed{
-                    scale: 1.0,
-                    rot: Quaternion::from(Euler::new(Deg(0.0), Deg(0.0), Deg(0.0))),
-                    disp: Vector3::new(0.0, 0.0, 0.0),
-            },
+            transform: NodeTransform::one(),
             bound: Aabb3::new(Point3::new(-0.5, -0.5, -0.5), Point3::new(0.5, 0.5, 0.5)),
             value_bound: Aabb3::new(Point3::new(-0.5, -0.5, -0.5), Point3::new(0.5, 0.5, 0.5)),
             cast_shadow: true,
             is_transparent: false,
             hide_in_game: false,
             is_emessive: false,
-            max_draw_distance: 100.0
+            max_draw_distance: 100.0,
+            rigid_body: None,
+            components: ComponentStore::new(),
+            tags: BTreeSet::new(),
+            layers: LAYER_DEFAULT,
         }
     }
 
@@ -139,9 +208,44 @@ impl Attribute<SceneJobs> for NodeAttributes{
                 //All other children should be rotated the same
                 SceneJobs::RotateAroundPoint(rotation, point)
             }
+            &SceneJobs::RotateQAroundPoint(quad, point) => {
+                self.transform.rot = quad * self.transform.rot;
+                self.transform.disp = point + quad.rotate_vector(self.transform.disp - point);
+                SceneJobs::RotateQAroundPoint(quad, point)
+            }
             &SceneJobs::Scale(s) => {
-                self.transform.scale *= s.x;
-                SceneJobs::Scale(s)
+                self.transform.scale = self.transform.scale.mul_element_wise(s);
+                //the children have to be scaled along our axes
+                SceneJobs::ScaleAroundPoint(s, self.transform.disp, self.transform.rot)
+            },
+            &SceneJobs::ScaleAroundPoint(s, point, axes) => {
+                //Scale the offset to the point in the space of the scaling node
+                let local_offset = axes.invert().rotate_vector(self.transform.disp - point);
+                self.transform.disp = point + axes.rotate_vector(local_offset.mul_element_wise(s));
+                //a rotated child is stretched along the axes of the scaling node
+                self.transform.scale_along(s, axes);
+                SceneJobs::ScaleAroundPoint(s, point, axes)
+            },
+            &SceneJobs::SetLocation(location) => {
+                let delta = location - self.transform.disp;
+                self.transform.disp = location;
+                SceneJobs::Move(delta)
+            },
+            &SceneJobs::SetRotation(rotation) => {
+                let delta = rotation * self.transform.rot.invert();
+                self.transform.rot = rotation;
+                SceneJobs::RotateQAroundPoint(delta, self.transform.disp)
+            },
+            &SceneJobs::SetScale(scale) => {
+                //a zero scale can't be reverted, in this case only self is changed
+                let factor_of = |new: f32, old: f32| if old == 0.0 { 1.0 } else { new / old };
+                let factor = Vector3::new(
+                    factor_of(scale.x, self.transform.scale.x),
+                    factor_of(scale.y, self.transform.scale.y),
+                    factor_of(scale.z, self.transform.scale.z),
+                );
+                self.transform.scale = scale;
+                SceneJobs::ScaleAroundPoint(factor, self.transform.disp, self.transform.rot)
             },
         }
     }
@@ -167,7 +271,7 @@ impl Attribute<SceneJobs> for NodeAttributes{
         for _ in 0..lvl + 1{
             print!("\t");
         }
-        println!("\tscale: {}", self.transform.scale);
+        println!("\tscale: {:?}", self.transform.scale);
 
         //print bound
         for _ in 0..lvl + 1{
@@ -192,6 +296,28 @@ impl Attribute<SceneJobs> for NodeAttributes{
             print!("\t");
         }
         println!("\thide in game?: {}", self.hide_in_game);
+
+        //print rigid body flag
+        for _ in 0..lvl + 1{
+            print!("\t");
+        }
+        println!("\thas rigid body?: {}", self.rigid_body.is_some());
+
+        //print component count
+        for _ in 0..lvl + 1{
+            print!("\t");
+        }
+        println!("\tcomponents: {}", self.components.len());
+
+        //print tags and layers
+        for _ in 0..lvl + 1{
+            print!("\t");
+        }
+        println!("\ttags: {:?}", self.tags);
+        for _ in 0..lvl + 1{
+            print!("\t");
+        }
+        println!("\tlayers: {:#b}", self.layers);
     }
 
     ///Compares this node with a `comp` and returns true if all requierments are met,
@@ -289,6 +415,59 @@ impl Attribute<SceneJobs> for NodeAttributes{
             None => {},
         }
 
+        //rigid body
+        match comp.has_rigid_body{
+            Some(has_body) => {
+                if has_body != self.rigid_body.is_some(){
+                    return false;
+                }
+            },
+            None => {},
+        }
+
+        //components
+        match comp.with_components{
+            Some(ref types) => {
+                for type_id in types.iter(){
+                    if !self.components.has_type(type_id){
+                        return false;
+                    }
+                }
+            },
+            None => {},
+        }
+
+        match comp.without_components{
+            Some(ref types) => {
+                for type_id in types.iter(){
+                    if self.components.has_type(type_id){
+                        return false;
+                    }
+                }
+            },
+            None => {},
+        }
+
+        //tag
+        match comp.tag{
+            Some(ref tag) => {
+                if !self.tags.contains(tag){
+                    return false;
+                }
+            },
+            None => {},
+        }
+
+        //layer, the node has to be in at least one of the layers
+        match comp.layer{
+            Some(layer_mask) => {
+                if !self.is_in_layer(layer_mask){
+                    return false;
+                }
+            },
+            None => {},
+        }
+
         // Tests the screen volume this object has to the current camera
         match comp.distance_cull{
             Some((ref bias, ref vp_matrix)) => {
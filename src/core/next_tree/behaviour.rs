@@ -0,0 +1,111 @@
+use core::next_tree::attributes::NodeAttributes;
+use core::next_tree::jobs::SceneJobs;
+use core::resource_management::command_queue::CommandQueue;
+use input::keymap::KeyMap;
+
+///Everything a behaviour can access while one of its hooks is called
+pub struct BehaviourContext<'a> {
+    ///Name of the node the behaviour is attached to
+    pub node_name: &'a str,
+    ///The attributes of the node. NOTE: Changing the transform directly does not move the
+    /// children, use `add_job()` for that.
+    pub attributes: &'a mut NodeAttributes,
+    ///The input state of this frame
+    pub key_map: &'a KeyMap,
+    ///Can be used to spawn or despawn nodes, the commands are applied on the next update
+    pub commands: &'a CommandQueue,
+    jobs: Vec<SceneJobs>,
+}
+
+impl<'a> BehaviourContext<'a>{
+    ///Creates a context for the node `node_name`
+    pub fn new(
+        node_name: &'a str,
+        attributes: &'a mut NodeAttributes,
+        key_map: &'a KeyMap,
+        commands: &'a CommandQueue,
+    ) -> Self{
+        BehaviourContext{
+            node_name: node_name,
+            attributes: attributes,
+            key_map: key_map,
+            commands: commands,
+            jobs: Vec::new(),
+        }
+    }
+
+    ///Adds a job to the node, it is executed (and passed to the children) in this update.
+    pub fn add_job(&mut self, job: SceneJobs){
+        self.jobs.push(job);
+    }
+
+    ///Returns the jobs added by the hooks
+    pub fn into_jobs(self) -> Vec<SceneJobs>{
+        self.jobs
+    }
+}
+
+///Gameplay logic which is attached to a node. The hooks are called by the asset manager, a panic
+/// within a hook only disables this behaviour.
+pub trait Behaviour: Send {
+    ///Is called once before the first `on_update()`
+    fn on_start(&mut self, _context: &mut BehaviourContext){}
+    ///Is called on every update of the asset manager with the time since the last update in seconds
+    fn on_update(&mut self, context: &mut BehaviourContext, delta: f32);
+    ///Is called when the node is despawned or the behaviour is removed
+    fn on_destroy(&mut self, _context: &mut BehaviourContext){}
+}
+
+///A behaviour made of closures, for small logic which doesn't need its own type
+pub struct ClosureBehaviour {
+    on_start: Option<Box<FnMut(&mut BehaviourContext) + Send>>,
+    on_update: Box<FnMut(&mut BehaviourContext, f32) + Send>,
+    on_destroy: Option<Box<FnMut(&mut BehaviourContext) + Send>>,
+}
+
+impl ClosureBehaviour{
+    ///Creates a behaviour which calls `on_update` on every update
+    pub fn new<F>(on_update: F) -> Self
+    where F: FnMut(&mut BehaviourContext, f32) + Send + 'static
+    {
+        ClosureBehaviour{
+            on_start: None,
+            on_update: Box::new(on_update),
+            on_destroy: None,
+        }
+    }
+
+    ///Sets the closure which is called on start
+    pub fn with_start<F>(mut self, on_start: F) -> Self
+    where F: FnMut(&mut BehaviourContext) + Send + 'static
+    {
+        self.on_start = Some(Box::new(on_start));
+        self
+    }
+
+    ///Sets the closure which is called on destroy
+    pub fn with_destroy<F>(mut self, on_destroy: F) -> Self
+    where F: FnMut(&mut BehaviourContext) + Send + 'static
+    {
+        self.on_destroy = Some(Box::new(on_destroy));
+        self
+    }
+}
+
+impl Behaviour for ClosureBehaviour{
+    fn on_start(&mut self, context: &mut BehaviourContext){
+        if let Some(ref mut on_start) = self.on_start{
+            on_start(context);
+        }
+    }
+
+    fn on_update(&mut self, context: &mut BehaviourContext, delta: f32){
+        (self.on_update)(context, delta);
+    }
+
+    fn on_destroy(&mut self, context: &mut BehaviourContext){
+        if let Some(ref mut on_destroy) = self.on_destroy{
+            on_destroy(context);
+        }
+    }
+}
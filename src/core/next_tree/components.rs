@@ -0,0 +1,193 @@
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+
+///Any type which is `Clone + Send + Sync` can be stored as component on a node. The clone is
+/// needed since nodes are copied, for instance by `copy_all_nodes()`.
+pub trait Component: Any + Send + Sync {
+    ///Returns a boxed copy of this component
+    fn box_clone(&self) -> Box<Component>;
+    ///Returns self as `Any` to downcast it to its actual type
+    fn as_any(&self) -> &Any;
+    ///Returns self mutable as `Any` to downcast it to its actual type
+    fn as_any_mut(&mut self) -> &mut Any;
+    ///Returns the boxed self as `Any` to move it out as its actual type
+    fn into_any(self: Box<Self>) -> Box<Any>;
+}
+
+impl<T: Any + Clone + Send + Sync> Component for T{
+    fn box_clone(&self) -> Box<Component>{
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &Any{
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any{
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<Any>{
+        self
+    }
+}
+
+impl Clone for Box<Component>{
+    fn clone(&self) -> Self{
+        //NOTE: Box<Component> is a `Component` as well, without the deref this would call itself
+        (**self).box_clone()
+    }
+}
+
+///Stores at most one component per type. Can be used to attach gameplay data like health or the
+/// team of an object to a node.
+/// NOTE: Components are not written to scene files.
+#[derive(Clone)]
+pub struct ComponentStore {
+    components: BTreeMap<TypeId, Box<Component>>,
+}
+
+impl ComponentStore{
+    ///Creates a store without any component
+    pub fn new() -> Self{
+        ComponentStore{
+            components: BTreeMap::new(),
+        }
+    }
+
+    ///Adds `component`, returns the component of the same type which was stored before, if there
+    /// was one.
+    pub fn add<T: Component>(&mut self, component: T) -> Option<T>{
+        let old = self.remove::<T>();
+        self.components.insert(TypeId::of::<T>(), Box::new(component));
+        old
+    }
+
+    ///Returns the component of type `T` if there is one
+    pub fn get<T: Component>(&self) -> Option<&T>{
+        match self.components.get(&TypeId::of::<T>()){
+            Some(component) => (**component).as_any().downcast_ref::<T>(),
+            None => None,
+        }
+    }
+
+    ///Returns the component of type `T` mutable, if there is one
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T>{
+        match self.components.get_mut(&TypeId::of::<T>()){
+            Some(component) => (**component).as_any_mut().downcast_mut::<T>(),
+            None => None,
+        }
+    }
+
+    ///Removes the component of type `T` and returns it, if there was one
+    pub fn remove<T: Component>(&mut self) -> Option<T>{
+        match self.components.remove(&TypeId::of::<T>()){
+            Some(component) => component.into_any().downcast::<T>().ok().map(|component| *component),
+            None => None,
+        }
+    }
+
+    ///Returns true if there is a component of type `T`
+    pub fn has<T: Component>(&self) -> bool{
+        self.has_type(&TypeId::of::<T>())
+    }
+
+    ///Returns true if there is a component with this `type_id`
+    pub fn has_type(&self, type_id: &TypeId) -> bool{
+        self.components.contains_key(type_id)
+    }
+
+    ///Returns the number of stored components
+    pub fn len(&self) -> usize{
+        self.components.len()
+    }
+
+    ///Returns true if no component is stored
+    pub fn is_empty(&self) -> bool{
+        self.components.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::next_tree::{SceneTree, SceneComparer};
+    use core::next_tree::content::ContentType;
+    use core::next_tree::attributes::NodeAttributes;
+    use core::resources::empty::Empty;
+    use jakar_tree::tree::Tree;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Health(u32);
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Team(String);
+
+    #[test]
+    fn add_replaces_the_component_of_the_same_type(){
+        let mut store = ComponentStore::new();
+        assert_eq!(store.add(Health(10)), None);
+        assert_eq!(store.add(Team(String::from("red"))), None);
+        assert_eq!(store.add(Health(20)), Some(Health(10)));
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get::<Health>(), Some(&Health(20)));
+        assert_eq!(store.get::<Team>(), Some(&Team(String::from("red"))));
+    }
+
+    #[test]
+    fn get_mut_changes_the_stored_component(){
+        let mut store = ComponentStore::new();
+        assert!(store.get_mut::<Health>().is_none());
+
+        store.add(Health(10));
+        store.get_mut::<Health>().expect("health is missing").0 -= 3;
+        assert_eq!(store.get::<Health>(), Some(&Health(7)));
+    }
+
+    #[test]
+    fn remove_moves_the_component_out(){
+        let mut store = ComponentStore::new();
+        store.add(Health(10));
+        store.add(Team(String::from("blue")));
+
+        assert_eq!(store.remove::<Team>(), Some(Team(String::from("blue"))));
+        assert_eq!(store.remove::<Team>(), None);
+        assert!(!store.has::<Team>());
+        assert!(store.has::<Health>());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn copied_nodes_are_filtered_by_their_components(){
+        let mut tree = Tree::new(ContentType::Empty(Empty::new("root")), NodeAttributes::default());
+
+        let mut player = NodeAttributes::default();
+        player.add_component(Health(100));
+        player.add_component(Team(String::from("red")));
+        let player_name = tree.add_at_root(ContentType::Empty(Empty::new("player")), Some(player))
+        .expect("failed to add player");
+
+        let mut crate_attributes = NodeAttributes::default();
+        crate_attributes.add_component(Health(5));
+        let crate_name = tree.add_at_root(ContentType::Empty(Empty::new("crate")), Some(crate_attributes))
+        .expect("failed to add crate");
+
+        let names = |sorting: SceneComparer| -> Vec<String>{
+            tree.copy_all_nodes(&Some(sorting)).iter().map(|node| node.get_name().clone()).collect()
+        };
+
+        let mut with_health = names(SceneComparer::new().with_component::<Health>());
+        with_health.sort();
+        let mut expected = vec![player_name.clone(), crate_name.clone()];
+        expected.sort();
+        assert_eq!(with_health, expected);
+
+        assert_eq!(names(SceneComparer::new().with_component::<Health>().with_component::<Team>()), vec![player_name.clone()]);
+        assert_eq!(names(SceneComparer::new().with_component::<Health>().without_component::<Team>()), vec![crate_name.clone()]);
+
+        //the copies carry their own components
+        let copies = tree.copy_all_nodes(&Some(SceneComparer::new().with_component::<Team>()));
+        assert_eq!(copies[0].get_attrib().get_component::<Health>(), Some(&Health(100)));
+    }
+}
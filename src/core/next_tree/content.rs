@@ -4,6 +4,8 @@ use std::fmt;
 use core::resources::mesh;
 use core::resources::light;
 use core::resources::empty;
+use core::resources::prefab;
+use core::resources::reflection_probe;
 use core::resources::camera::{DefaultCamera, Camera};
 use core::ReturnBoundInfo;
 
@@ -36,6 +38,10 @@ pub enum ContentType {
     Empty(empty::Empty),
     /// a camera attached to the tree (TODO needs to be implemented correctly)
     Camera(DefaultCamera),
+    /// an instance of another scene from the scene manager, the scene is referenced, not copied
+    Prefab(prefab::Prefab),
+    /// captures the scene around it into a cube map used for reflections within its influence volume
+    ReflectionProbe(reflection_probe::ReflectionProbe),
 }
 
 impl fmt::Debug for ContentType {
@@ -49,6 +55,8 @@ impl fmt::Debug for ContentType {
                 ContentType::SpotLight(_) => "spot light",
                 ContentType::Empty(_) => "empty",
                 ContentType::Camera(_) => "camera",
+                ContentType::Prefab(_) => "prefab",
+                ContentType::ReflectionProbe(_) => "reflection probe",
             }
         };
 
@@ -86,6 +94,12 @@ impl ContentType{
                 //Always returns a 1x1x1 bound
                 Aabb3::new(Point3::new(-0.5, -0.5, -0.5), Point3::new(0.5, 0.5, 0.5))
             },
+            &ContentType::Prefab(ref prefab) => {
+                prefab.get_bound()
+            },
+            &ContentType::ReflectionProbe(ref probe) => {
+                probe.get_bound()
+            },
         }
     }
 
@@ -129,6 +143,22 @@ impl ContentType{
         }
     }
 
+    ///Returns the either a prefab or a None
+    pub fn as_prefab(&mut self) -> Option<&mut prefab::Prefab>{
+        match self{
+            &mut ContentType::Prefab(ref mut prefab) => return Some(prefab),
+            _ => None
+        }
+    }
+
+    ///Returns the either a reflection probe or a None
+    pub fn as_reflection_probe(&mut self) -> Option<&mut reflection_probe::ReflectionProbe>{
+        match self{
+            &mut ContentType::ReflectionProbe(ref mut probe) => return Some(probe),
+            _ => None
+        }
+    }
+
     ///Returns the either a camera or a None
     pub fn as_camera(&mut self) -> Option<&mut DefaultCamera>{
         match self{
@@ -169,6 +199,12 @@ impl jakar_tree::node::NodeContent for ContentType{
                 //c.name.clone() TODO add a camera name
                 String::from("Camera")
             },
+            &ContentType::Prefab(ref c) => {
+                c.name.clone()
+            },
+            &ContentType::ReflectionProbe(ref c) => {
+                c.name.clone()
+            },
         }
     }
 }
@@ -13,11 +13,18 @@ pub enum SceneJobs {
     ///Rotates the node around a given point.
     ///The first element is the rotation, the second element is the point.
     RotateAroundPoint(Vector3<f32>, Vector3<f32>),
-    ///Scales the object by this x,y and z values.
-    /// Currently only uniform scale (based on x) is suported
-    ///TODO: implement non uniform scale.
+    ///Rotates the node by the quaternion around a given point (first the rotation, then the point).
+    RotateQAroundPoint(Quaternion<f32>, Vector3<f32>),
+    ///Scales the object by this x,y and z values. The children are scaled as well and their
+    /// location is scaled relative to this node.
     Scale(Vector3<f32>),
-    /*TODO
-    add setter job to "set_location" etc.
-    */
+    ///Scales the node by the factor (first) along the axes of the rotation (third) relative to
+    /// the point (second). Is what `Scale` and `SetScale` pass to the children.
+    ScaleAroundPoint(Vector3<f32>, Vector3<f32>, Quaternion<f32>),
+    ///Sets the location of the node. The children are moved by the same amount.
+    SetLocation(Vector3<f32>),
+    ///Sets the rotation of the node. The children are rotated around the node by the same amount.
+    SetRotation(Quaternion<f32>),
+    ///Sets the scale of the node. The children are scaled by the same factor relative to the node.
+    SetScale(Vector3<f32>),
 }
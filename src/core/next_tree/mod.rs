@@ -3,6 +3,7 @@ use jakar_tree;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::collections::BTreeMap;
+use std::any::TypeId;
 
 use core::resources::*;
 use core::resources::camera::Camera;
@@ -18,6 +19,16 @@ use jakar_tree::node::Node;
 pub mod jobs;
 ///Contains several controller which can be used for nodes.
 pub mod node_controller;
+///Gameplay logic with start, update and destroy hooks which can be attached to nodes.
+pub mod behaviour;
+///Describes how a scene tree is written to and read from disk.
+pub mod scene_file;
+///The transform type of a node, supports non-uniform scale.
+pub mod transform;
+///Ray queries against the scene tree, for instance to pick objects with the mouse.
+pub mod raycast;
+///Typed gameplay data which can be attached to nodes.
+pub mod components;
 
 
 use cgmath::*;
@@ -36,7 +47,9 @@ pub struct ValueTypeBool {
     pub directional_light: bool,
     pub spot_light: bool,
     pub empty: bool,
-    pub camera: bool
+    pub camera: bool,
+    pub prefab: bool,
+    pub reflection_probe: bool,
 }
 
 impl ValueTypeBool {
@@ -49,7 +62,9 @@ impl ValueTypeBool {
             directional_light: true,
             spot_light: true,
             empty: true,
-            camera: true
+            camera: true,
+            prefab: true,
+            reflection_probe: true,
         }
     }
 
@@ -62,7 +77,9 @@ impl ValueTypeBool {
             directional_light: false,
             spot_light: false,
             empty: false,
-            camera: false
+            camera: false,
+            prefab: false,
+            reflection_probe: false,
         }
     }
 
@@ -89,6 +106,12 @@ impl ValueTypeBool {
         if (self.camera && !other.camera) || (!self.camera && other.camera){
             return false;
         }
+        if (self.prefab && !other.prefab) || (!self.prefab && other.prefab){
+            return false;
+        }
+        if (self.reflection_probe && !other.reflection_probe) || (!self.reflection_probe && other.reflection_probe){
+            return false;
+        }
         //everything self has is also contained in other therefore return true
         true
     }
@@ -127,6 +150,16 @@ impl ValueTypeBool {
         self.camera = true;
         self
     }
+
+    pub fn with_prefab(mut self) -> Self{
+        self.prefab = true;
+        self
+    }
+
+    pub fn with_reflection_probe(mut self) -> Self{
+        self.reflection_probe = true;
+        self
+    }
 }
 
 ///The comparer type used to comapre a SceneTree to attribtues.
@@ -134,7 +167,7 @@ impl ValueTypeBool {
 #[derive(Clone)]
 pub struct SceneComparer{
         ///Some if the transform component should be compared
-        pub transform: Option<Decomposed<Vector3<f32>, Quaternion<f32>>>,
+        pub transform: Option<transform::NodeTransform>,
         ///Some if the bound of the node should be in this bound
         pub bound: Option<Aabb3<f32>>,
         ///Some the node should be in this frustum
@@ -155,6 +188,16 @@ pub struct SceneComparer{
         /// If enabled it will not add any node where the screen coverage of the AABB is lower than
         /// the float which is supplied as the first argument
         pub distance_cull: Option<(f32, Matrix4<f32>)>,
+        ///Some if the node should (or should not) have a rigid body
+        pub has_rigid_body: Option<bool>,
+        ///Some if the node needs a component of each of this types
+        pub with_components: Option<Vec<TypeId>>,
+        ///Some if the node must not have a component of any of this types
+        pub without_components: Option<Vec<TypeId>>,
+        ///Some if the node needs this tag
+        pub tag: Option<String>,
+        ///Some if the node has to be in at least one of the layers of this mask
+        pub layer: Option<u32>,
 }
 
 impl SceneComparer{
@@ -171,11 +214,16 @@ impl SceneComparer{
             hide_in_game: None,
             is_emessive: None,
             distance_cull: None,
+            has_rigid_body: None,
+            with_components: None,
+            without_components: None,
+            tag: None,
+            layer: None,
         }
     }
 
     ///Adds a `Some(transform)` component to the comparer
-    pub fn with_transform(mut self, transform: Decomposed<Vector3<f32>, Quaternion<f32>>) -> Self{
+    pub fn with_transform(mut self, transform: transform::NodeTransform) -> Self{
         self.transform = Some(transform);
         self
     }
@@ -256,6 +304,50 @@ impl SceneComparer{
         self.distance_cull = Some((bias, view_projection_matrix));
         self
     }
+
+    ///Only nodes with a rigid body will pass
+    pub fn with_rigid_body(mut self) -> Self{
+        self.has_rigid_body = Some(true);
+        self
+    }
+
+    ///Only nodes without a rigid body will pass
+    pub fn without_rigid_body(mut self) -> Self{
+        self.has_rigid_body = Some(false);
+        self
+    }
+
+    ///Only nodes with a component of type `T` will pass, can be called several times to require
+    /// several components.
+    pub fn with_component<T: components::Component>(mut self) -> Self{
+        self.with_components.get_or_insert(Vec::new()).push(TypeId::of::<T>());
+        self
+    }
+
+    ///Only nodes without a component of type `T` will pass
+    pub fn without_component<T: components::Component>(mut self) -> Self{
+        self.without_components.get_or_insert(Vec::new()).push(TypeId::of::<T>());
+        self
+    }
+
+    ///Only nodes with this `tag` will pass
+    pub fn with_tag(mut self, tag: &str) -> Self{
+        self.tag = Some(String::from(tag));
+        self
+    }
+
+    ///Only nodes which are in at least one of the layers of `layer_mask` will pass
+    pub fn with_layer(mut self, layer_mask: u32) -> Self{
+        self.layer = Some(layer_mask);
+        self
+    }
+
+    ///Like `with_layer()`, but keeps only the layers which are in `layer_mask` and in the mask
+    /// which was set before, if there was one.
+    pub fn within_layers(mut self, layer_mask: u32) -> Self{
+        self.layer = Some(self.layer.map_or(layer_mask, |old_mask| old_mask & layer_mask));
+        self
+    }
 }
 
 
@@ -279,6 +371,153 @@ J: Clone, A: jakar_tree::node::Attribute<J> + Clone
     ///Rebuilds the bounds for the whole tree
     fn rebuild_bounds(&mut self);
 
+    ///Casts a ray from `origin` along `direction` and returns the closest node within `max_dist`
+    /// which fulfills the `SceneComparer`.
+    /// Children are skipped if the ray misses the `bound` of their parent. Meshes are tested
+    /// against their triangles. Other content like lights, probes, cameras or empties is only hit
+    /// if the comparer asks for its value type, it is tested against its `value_bound` then.
+    fn raycast(
+        &self,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+        max_dist: f32,
+        sorting: &Option<SceneComparer>
+    ) -> Option<raycast::RayHit>;
+
+    ///Finds a node by a path like `"level/house_01/door"` through the hierarchy and returns its
+    /// name. A path segment matches a child if it is the node name, the name of its value or one
+    /// of its tags. The node the search starts at is not part of the path.
+    fn find_by_path(&self, path: &str) -> Option<String>;
+
+    ///Removes the node `name` and its children from the tree and returns it. The node the search
+    /// starts at can't be removed.
+    fn remove_node(&mut self, name: &str) -> Option<jakar_tree::node::Node<T, J, A>>;
+
+}
+
+///Returns true if `node` fulfills the attribute as well as the value type requirements
+/// of `sorting`.
+fn node_matches_sorting(node: &JakarNode, sorting: &Option<SceneComparer>) -> bool{
+    let comparer = match sorting{
+        &Some(ref comparer) => comparer,
+        &None => return true,
+    };
+
+    if !node.get_attrib().compare(comparer){
+        return false;
+    }
+
+    match comparer.value_type{
+        Some(ref val_ty) => {
+            let mut tmp_bool = ValueTypeBool::none();
+            match node.get_value(){
+                content::ContentType::Renderable(_) => tmp_bool.render_able = true,
+                content::ContentType::Mesh(_) => tmp_bool.mesh = true,
+                content::ContentType::PointLight(_) => tmp_bool.point_light = true,
+                content::ContentType::DirectionalLight(_) => tmp_bool.directional_light = true,
+                content::ContentType::SpotLight(_) => tmp_bool.spot_light = true,
+                content::ContentType::Empty(_) => tmp_bool.empty = true,
+                content::ContentType::Camera(_) => tmp_bool.camera = true,
+                content::ContentType::Prefab(_) => tmp_bool.prefab = true,
+                content::ContentType::ReflectionProbe(_) => tmp_bool.reflection_probe = true,
+            }
+            tmp_bool.is_part_of(&val_ty)
+        },
+        None => true,
+    }
+}
+
+///Returns true if `segment` is the name, the value name or a tag of `node`
+fn node_matches_path_segment(node: &JakarNode, segment: &str) -> bool{
+    node.get_name() == segment
+    || node.get_value().get_name() == segment
+    || node.get_attrib().has_tag(segment)
+}
+
+///Recursive part of `find_by_path()`, the first child which matches the whole path is returned.
+fn find_path_in_children(node: &JakarNode, segments: &[&str]) -> Option<String>{
+    let (segment, rest) = match segments.split_first(){
+        Some(split) => split,
+        None => return None,
+    };
+
+    for (_, child) in node.get_children().iter(){
+        if !node_matches_path_segment(child, segment){
+            continue;
+        }
+        if rest.is_empty(){
+            return Some(child.get_name().clone());
+        }
+        if let Some(name) = find_path_in_children(child, rest){
+            return Some(name);
+        }
+    }
+    None
+}
+
+///Returns true if `sorting` selects nodes by their value type
+fn asks_for_value_type(sorting: &Option<SceneComparer>) -> bool{
+    match sorting{
+        &Some(ref comparer) => comparer.value_type.is_some(),
+        &None => false,
+    }
+}
+
+///Recursive part of `raycast()`, only returns hits which are closer than `max_dist`.
+fn raycast_node(
+    node: &JakarNode,
+    ray: &raycast::Ray,
+    max_dist: f32,
+    sorting: &Option<SceneComparer>
+) -> Option<raycast::RayHit>{
+    //Broad phase, if we miss the node bound we miss all children as well
+    if raycast::ray_aabb(ray, &node.get_attrib().bound, max_dist).is_none(){
+        return None;
+    }
+
+    let mut closest = None;
+    let mut closest_dist = max_dist;
+    for (_, child) in node.get_children().iter(){
+        if let Some(hit) = raycast_node(child, ray, closest_dist, sorting){
+            closest_dist = hit.distance;
+            closest = Some(hit);
+        }
+    }
+
+    if !node_matches_sorting(node, sorting){
+        return closest;
+    }
+
+    //Narrow phase for self
+    let bound_dist = match raycast::ray_aabb(ray, &node.get_attrib().value_bound, closest_dist){
+        Some(dist) => dist,
+        None => return closest,
+    };
+
+    let self_dist = match node.get_value(){
+        &content::ContentType::Mesh(ref mesh) => {
+            let mesh_lck = mesh.lock().expect("failed to lock mesh for raycast");
+            raycast::ray_mesh(
+                ray,
+                &mesh_lck.get_all_positions(),
+                &mesh_lck.get_indices(),
+                &node.get_attrib().transform,
+                closest_dist
+            )
+        },
+        //The bound of a light is its influence volume, so other content is only hit on request
+        _ if asks_for_value_type(sorting) => Some(bound_dist),
+        _ => None,
+    };
+
+    match self_dist{
+        Some(dist) => Some(raycast::RayHit{
+            node_name: node.get_name().clone(),
+            point: ray.get_point(dist),
+            distance: dist,
+        }),
+        None => closest,
+    }
 }
 
 impl SceneTree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>
@@ -292,38 +531,8 @@ impl SceneTree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes
         }
         //first of all test if self has the right attributes, if not we can already return the child
         // vector
-        match sorting{
-            &Some(ref comparer) => {
-                //early return if self doesnt match the sorting
-                if !self.get_attrib().compare(comparer){
-                    return return_vec;
-                }
-                //NOTE since the scene attributes don't know about the node value we havbe to compare
-                //them manually here. Not nice but it works.
-                //Check if the value type is in the scope we are searching for
-                match comparer.value_type{
-                    Some(ref val_ty) => {
-                        //value type, checks if the current value is within the parameters.
-                        let mut tmp_bool = ValueTypeBool::none();
-                        match self.get_value(){
-                            content::ContentType::Renderable(_) => tmp_bool.render_able = true,
-                            content::ContentType::Mesh(_) => tmp_bool.mesh = true,
-                            content::ContentType::PointLight(_) => tmp_bool.point_light = true,
-                            content::ContentType::DirectionalLight(_) => tmp_bool.directional_light = true,
-                            content::ContentType::SpotLight(_) => tmp_bool.spot_light = true,
-                            content::ContentType::Empty(_) => tmp_bool.empty = true,
-                            content::ContentType::Camera(_) => tmp_bool.camera = true,
-                        }
-
-                        if tmp_bool.is_part_of(&val_ty) == false{
-                            return return_vec; //We are not part of the sorting return early :/
-                        }
-                    },
-                    None => {}, //all right not checking
-                }
-
-            },
-            &None =>  {}, //all is nice, add
+        if !node_matches_sorting(self, sorting){
+            return return_vec;
         }
         //Passed the test, lets add our own name
         return_vec.push(self.get_name().clone());
@@ -338,37 +547,8 @@ impl SceneTree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes
         }
         //first of all test if self has the right attributes, if not we can already return the child
         // vector
-        match sorting{
-            &Some(ref comparer) => {
-                //early return if self doesnt match the sorting
-                if !self.get_attrib().compare(comparer){
-                    return return_vec;
-                }
-                //NOTE since the scene attributes don't know about the node value we havbe to compare
-                //them manually here. Not nice but it works.
-                //Check if the value type is in the scope we are searching for
-                match comparer.value_type{
-                    Some(ref val_ty) => {
-                        //value type, checks if the current value is within the parameters.
-                        let mut tmp_bool = ValueTypeBool::none();
-                        match self.get_value(){
-                            content::ContentType::Renderable(_) => tmp_bool.render_able = true,
-                            content::ContentType::Mesh(_) => tmp_bool.mesh = true,
-                            content::ContentType::PointLight(_) => tmp_bool.point_light = true,
-                            content::ContentType::DirectionalLight(_) => tmp_bool.directional_light = true,
-                            content::ContentType::SpotLight(_) => tmp_bool.spot_light = true,
-                            content::ContentType::Empty(_) => tmp_bool.empty = true,
-                            content::ContentType::Camera(_) => tmp_bool.camera = true,
-                        }
-
-                        if tmp_bool.is_part_of(&val_ty) == false{
-                            return return_vec; //We are not part of the sorting return early :/
-                        }
-                    },
-                    None => {}, //all right not checking
-                }
-            },
-            &None =>  {}, //all is nice, add the mesh
+        if !node_matches_sorting(self, sorting){
+            return return_vec;
         }
 
         //If self passed the ckeck for the attrributes, copy the current node and return
@@ -440,6 +620,40 @@ impl SceneTree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes
         self.get_attrib_mut().bound = Aabb3::new(mins, maxs);
     }
 
+    fn raycast(
+        &self,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+        max_dist: f32,
+        sorting: &Option<SceneComparer>
+    ) -> Option<raycast::RayHit>{
+        if direction.magnitude2() == 0.0{
+            return None;
+        }
+        let ray = raycast::Ray::new(origin, direction);
+        raycast_node(self, &ray, max_dist, sorting)
+    }
+
+    fn find_by_path(&self, path: &str) -> Option<String>{
+        let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+        find_path_in_children(self, &segments)
+    }
+
+    fn remove_node(&mut self, name: &str) -> Option<jakar_tree::node::Node<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>>{
+        let removed = self.get_children_mut().remove(name);
+        if removed.is_some(){
+            return removed;
+        }
+
+        for (_, child) in self.get_children_mut().iter_mut(){
+            let removed = child.remove_node(name);
+            if removed.is_some(){
+                return removed;
+            }
+        }
+        None
+    }
+
 
 }
 
@@ -462,6 +676,24 @@ impl SceneTree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes
     fn rebuild_bounds(&mut self){
         self.root_node.rebuild_bounds()
     }
+
+    fn raycast(
+        &self,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+        max_dist: f32,
+        sorting: &Option<SceneComparer>
+    ) -> Option<raycast::RayHit>{
+        self.root_node.raycast(origin, direction, max_dist, sorting)
+    }
+
+    fn find_by_path(&self, path: &str) -> Option<String>{
+        self.root_node.find_by_path(path)
+    }
+
+    fn remove_node(&mut self, name: &str) -> Option<jakar_tree::node::Node<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>>{
+        self.root_node.remove_node(name)
+    }
 }
 
 ///unwraps the vector into a vector of meshes
@@ -648,3 +880,150 @@ pub fn get_max_xy_len(points: &Vec<Point3<f32>>) -> f32{
     let dist_vec = max - min;
     return dist_vec.magnitude();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ReturnBoundInfo;
+    use jakar_tree::tree::Tree;
+
+    ///A point light at the origin whose influence volume contains the ray origin and a quad of
+    /// 2x2 units five units in front of it. Returns the tree and the node names of the light and
+    /// the mesh.
+    fn light_and_mesh_scene()
+     -> (Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>, String, String){
+        let mut tree = Tree::new(
+            content::ContentType::Empty(empty::Empty::new("root")),
+            attributes::NodeAttributes::default()
+        );
+
+        let mut point = light::LightPoint::new("point");
+        point.set_radius(10.0);
+        let light_name = tree.add_at_root(content::ContentType::PointLight(point), None).expect("failed to add light");
+
+        let mut quad = mesh::Mesh::new_cpu_only("quad");
+        let vertex = |x: f32, y: f32| mesh::Vertex::new([x, y, 5.0], [0.0; 2], [0.0, 0.0, -1.0], [0.0; 4], [1.0; 4]);
+        quad.set_cpu_vertices_and_indices(
+            vec![vertex(-1.0, -1.0), vertex(1.0, -1.0), vertex(1.0, 1.0), vertex(-1.0, 1.0)],
+            vec![0, 1, 2, 0, 2, 3]
+        );
+        quad.set_bound(Point3::new(-1.0, -1.0, 5.0), Point3::new(1.0, 1.0, 5.0));
+        let mesh_name = tree.add_at_root(content::ContentType::Mesh(Arc::new(Mutex::new(quad))), None)
+        .expect("failed to add mesh");
+
+        tree.rebuild_bounds();
+        (tree, light_name, mesh_name)
+    }
+
+    #[test]
+    fn raycast_only_hits_meshes_by_default(){
+        let (tree, _, mesh_name) = light_and_mesh_scene();
+        let hit = tree.raycast(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0), 100.0, &None)
+        .expect("the ray missed the mesh");
+
+        assert_eq!(hit.node_name, mesh_name);
+        assert!((hit.distance - 5.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn raycast_hits_lights_on_request(){
+        let (tree, light_name, _) = light_and_mesh_scene();
+        let sorting = Some(SceneComparer::new().with_value_type(ValueTypeBool::none().with_point_light()));
+        let hit = tree.raycast(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0), 100.0, &sorting)
+        .expect("the ray missed the light");
+
+        assert_eq!(hit.node_name, light_name);
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    ///The names of the nodes of `level_scene()`
+    struct LevelNames {
+        level: String,
+        house_a: String,
+        door_a: String,
+        house_b: String,
+        door_b: String,
+    }
+
+    ///A level with two houses which both have a door. The houses share the value name "house" and
+    /// are tagged "house_01" and "house_02", the second one is only in layer 2.
+    fn level_scene() -> (Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>, LevelNames){
+        let mut tree = Tree::new(
+            content::ContentType::Empty(empty::Empty::new("root")),
+            attributes::NodeAttributes::default()
+        );
+        let empty_content = |name: &str| content::ContentType::Empty(empty::Empty::new(name));
+
+        let level = tree.add_at_root(empty_content("level"), None).expect("failed to add level");
+
+        let mut house_a_attributes = attributes::NodeAttributes::default();
+        house_a_attributes.add_tag("house_01");
+        let house_a = tree.add(empty_content("house"), level.clone(), Some(house_a_attributes)).expect("failed to add house");
+        let door_a = tree.add(empty_content("door"), house_a.clone(), None).expect("failed to add door");
+
+        let mut house_b_attributes = attributes::NodeAttributes::default();
+        house_b_attributes.add_tag("house_02");
+        house_b_attributes.layers = 0b10;
+        let house_b = tree.add(empty_content("house"), level.clone(), Some(house_b_attributes)).expect("failed to add house");
+        let door_b = tree.add(empty_content("door"), house_b.clone(), None).expect("failed to add door");
+
+        (tree, LevelNames{ level: level, house_a: house_a, door_a: door_a, house_b: house_b, door_b: door_b })
+    }
+
+    #[test]
+    fn path_segments_match_node_names(){
+        let (tree, names) = level_scene();
+        let path = format!("{}/{}/{}", names.level, names.house_b, names.door_b);
+        assert_eq!(tree.find_by_path(&path), Some(names.door_b.clone()));
+    }
+
+    #[test]
+    fn path_segments_match_value_names_and_tags(){
+        let (tree, names) = level_scene();
+        assert_eq!(tree.find_by_path("level/house_01/door"), Some(names.door_a.clone()));
+        assert_eq!(tree.find_by_path("level/house_02/door"), Some(names.door_b.clone()));
+        //empty segments are ignored
+        assert_eq!(tree.find_by_path("/level//house_02/door/"), Some(names.door_b.clone()));
+    }
+
+    #[test]
+    fn unknown_paths_find_nothing(){
+        let (tree, _) = level_scene();
+        assert_eq!(tree.find_by_path("level/house_03/door"), None);
+        assert_eq!(tree.find_by_path("level/house_01/door/handle"), None);
+        assert_eq!(tree.find_by_path(""), None);
+        //the node the search starts at is not part of the path
+        assert_eq!(tree.find_by_path("root/level"), None);
+    }
+
+    #[test]
+    fn comparer_filters_by_tag(){
+        let (tree, names) = level_scene();
+        let tagged = tree.get_all_names(&Some(SceneComparer::new().with_tag("house_01")));
+        assert_eq!(tagged, vec![names.house_a.clone()]);
+        assert!(tree.get_all_names(&Some(SceneComparer::new().with_tag("garage"))).is_empty());
+    }
+
+    #[test]
+    fn comparer_filters_by_layer(){
+        let (tree, names) = level_scene();
+        let second_layer = tree.get_all_names(&Some(SceneComparer::new().with_layer(0b10)));
+        assert_eq!(second_layer, vec![names.house_b.clone()]);
+
+        let default_layer = tree.get_all_names(&Some(SceneComparer::new().with_layer(attributes::LAYER_DEFAULT)));
+        assert!(!default_layer.contains(&names.house_b));
+        assert!(default_layer.contains(&names.house_a));
+        assert_eq!(tree.get_all_names(&Some(SceneComparer::new().with_layer(attributes::LAYER_ALL))).len(), 6);
+    }
+
+    #[test]
+    fn within_layers_combines_the_masks(){
+        assert_eq!(SceneComparer::new().within_layers(0b110).layer, Some(0b110));
+        assert_eq!(SceneComparer::new().with_layer(0b011).within_layers(0b110).layer, Some(0b010));
+
+        //a node in the requested layer which is not rendered is left out
+        let (tree, _) = level_scene();
+        let hidden = SceneComparer::new().with_layer(0b10).within_layers(attributes::LAYER_DEFAULT);
+        assert!(tree.get_all_names(&Some(hidden)).is_empty());
+    }
+}
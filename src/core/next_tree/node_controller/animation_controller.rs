@@ -0,0 +1,587 @@
+use jakar_tree::node::{NodeController, Node};
+
+use core::next_tree::content::ContentType;
+use core::next_tree::jobs::SceneJobs;
+use core::next_tree::attributes::NodeAttributes;
+use core::next_tree::transform::NodeTransform;
+use core::resources::animation::{ChannelValues, Interpolation, find_keyframes, sample_vector, sample_rotation};
+use tools::math::time_tools::*;
+use cgmath::*;
+
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+///Animates one property of a node
+#[derive(Clone, Debug)]
+pub struct NodeTrack {
+    ///The keyframe times in seconds, sorted ascending
+    pub times: Vec<f32>,
+    ///The keyframe values, they are relative to the parent of the node like in glTF.
+    pub values: ChannelValues,
+    pub interpolation: Interpolation,
+}
+
+impl NodeTrack{
+    ///Returns the time of the last keyframe
+    pub fn get_duration(&self) -> f32{
+        match self.times.last(){
+            Some(time) => *time,
+            None => 0.0,
+        }
+    }
+
+    ///Overwrites the animated property of the parent relative `local` transform with the value
+    /// at `time`. Returns false if the track has no value at `time`.
+    fn apply(&self, time: f32, local: &mut NodeTransform) -> bool{
+        let (first, second, factor) = match find_keyframes(&self.times, time, self.interpolation){
+            Some(keys) => keys,
+            None => return false,
+        };
+
+        let sampled = match self.values{
+            ChannelValues::Translation(ref values) => {
+                sample_vector(values, first, second, factor).map(|v| local.disp = v)
+            },
+            ChannelValues::Rotation(ref values) => {
+                sample_rotation(values, first, second, factor).map(|r| local.rot = r)
+            },
+            ChannelValues::Scale(ref values) => {
+                sample_vector(values, first, second, factor).map(|s| local.scale = s)
+            },
+        };
+        sampled.is_some()
+    }
+
+    ///Returns the job which sets the animated property of the node to the one of `world`
+    fn get_job(&self, world: &NodeTransform) -> SceneJobs{
+        match self.values{
+            ChannelValues::Translation(_) => SceneJobs::SetLocation(world.disp),
+            ChannelValues::Rotation(_) => SceneJobs::SetRotation(world.rot),
+            ChannelValues::Scale(_) => SceneJobs::SetScale(world.scale),
+        }
+    }
+}
+
+///Describes what happens when the animation reaches its end
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaybackMode {
+    ///Starts again at the beginning
+    Loop,
+    ///Plays backwards till the beginning, then forward again
+    PingPong,
+    ///Stops at the last keyframe
+    Clamp,
+}
+
+///Is sent when the playback passes the time of an event
+#[derive(Clone, Debug)]
+pub struct AnimationEvent {
+    ///Name of the node the controller is attached to
+    pub node_name: String,
+    ///Name of the event as it was added to the controller
+    pub name: String,
+    ///Time of the event in the animation
+    pub time: f32,
+}
+
+///A part of the animation which was played during one update
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Segment {
+    from: f32,
+    to: f32,
+    ///False if `from` was already reached by the previous segment or update, so an event at a
+    /// border is only sent once.
+    includes_from: bool,
+}
+
+impl Segment{
+    fn new(from: f32, to: f32, includes_from: bool) -> Self{
+        Segment{
+            from: from,
+            to: to,
+            includes_from: includes_from,
+        }
+    }
+
+    ///Returns true if the playback passed `time` while playing this segment
+    fn contains(&self, time: f32) -> bool{
+        if time == self.from{
+            return self.includes_from;
+        }
+        if self.from <= self.to{
+            time > self.from && time <= self.to
+        }else{
+            time < self.from && time >= self.to
+        }
+    }
+}
+
+///The playback state which is shared between an `AnimationController` and its `AnimationHandle`s
+#[derive(Clone, Debug)]
+struct Playback {
+    mode: PlaybackMode,
+    ///Multiplies the time, 1.0 is normal speed
+    speed: f32,
+    time: f32,
+    ///1.0 while playing forward, -1.0 while playing backwards in ping pong mode
+    direction: f32,
+    duration: f32,
+    is_playing: bool,
+    ///Is set by `stop()` and `seek()` so that the new pose is applied even while paused
+    has_changed: bool,
+}
+
+impl Playback{
+    ///Advances the time by `delta` seconds, respecting the mode. Returns the played segments,
+    /// used to find the passed events.
+    fn advance(&mut self, delta: f32) -> Vec<Segment>{
+        let mut segments = Vec::new();
+        if !self.is_playing || self.duration <= 0.0{
+            return segments;
+        }
+
+        let start = self.time;
+        let target = self.time + delta * self.speed * self.direction;
+
+        match self.mode{
+            PlaybackMode::Clamp => {
+                let end = target.max(0.0).min(self.duration);
+                segments.push(Segment::new(start, end, false));
+                self.time = end;
+                if end == self.duration && target >= self.duration || end == 0.0 && target < 0.0{
+                    self.is_playing = false;
+                }
+            },
+            PlaybackMode::Loop => {
+                //the wrapped segment starts at another time than the first one ended, therefore
+                // it includes its start
+                if target > self.duration{
+                    segments.push(Segment::new(start, self.duration, false));
+                    self.time = target % self.duration;
+                    segments.push(Segment::new(0.0, self.time, true));
+                }else if target < 0.0{
+                    segments.push(Segment::new(start, 0.0, false));
+                    self.time = self.duration + (target % self.duration);
+                    segments.push(Segment::new(self.duration, self.time, true));
+                }else{
+                    segments.push(Segment::new(start, target, false));
+                    self.time = target;
+                }
+            },
+            PlaybackMode::PingPong => {
+                if target > self.duration{
+                    segments.push(Segment::new(start, self.duration, false));
+                    self.time = (2.0 * self.duration - target).max(0.0);
+                    segments.push(Segment::new(self.duration, self.time, false));
+                    self.direction = -self.direction;
+                }else if target < 0.0{
+                    segments.push(Segment::new(start, 0.0, false));
+                    self.time = (-target).min(self.duration);
+                    segments.push(Segment::new(0.0, self.time, false));
+                    self.direction = -self.direction;
+                }else{
+                    segments.push(Segment::new(start, target, false));
+                    self.time = target;
+                }
+            },
+        }
+
+        segments
+    }
+}
+
+///Controls the playback of an `AnimationController` after it was attached to a node. Get it
+/// with `get_handle()` before calling `set_controller()`, it can be cloned and used from any thread.
+#[derive(Clone)]
+pub struct AnimationHandle {
+    playback: Arc<Mutex<Playback>>,
+}
+
+impl AnimationHandle{
+    ///Resumes the playback. A clamped animation which reached its end starts again.
+    pub fn play(&self){
+        let mut playback = self.playback.lock().expect("failed to lock animation playback");
+        if !playback.is_playing && playback.mode == PlaybackMode::Clamp{
+            let forward = playback.speed * playback.direction >= 0.0;
+            if forward && playback.time >= playback.duration{
+                playback.time = 0.0;
+            }else if !forward && playback.time <= 0.0{
+                playback.time = playback.duration;
+            }
+        }
+        playback.is_playing = true;
+    }
+
+    ///Pauses the playback at the current time
+    pub fn pause(&self){
+        let mut playback = self.playback.lock().expect("failed to lock animation playback");
+        playback.is_playing = false;
+    }
+
+    ///Stops the playback and resets the node to the start of the animation
+    pub fn stop(&self){
+        let mut playback = self.playback.lock().expect("failed to lock animation playback");
+        playback.is_playing = false;
+        playback.time = 0.0;
+        playback.direction = 1.0;
+        playback.has_changed = true;
+    }
+
+    ///Jumps to `time`, clamped to the duration. The events between the old and the new time
+    /// are not sent.
+    pub fn seek(&self, time: f32){
+        let mut playback = self.playback.lock().expect("failed to lock animation playback");
+        playback.time = time.max(0.0).min(playback.duration);
+        playback.has_changed = true;
+    }
+
+    ///Sets the playback speed, 1.0 is normal speed. Negative values play backwards.
+    pub fn set_speed(&self, speed: f32){
+        let mut playback = self.playback.lock().expect("failed to lock animation playback");
+        playback.speed = speed;
+    }
+
+    ///Sets the playback mode
+    pub fn set_mode(&self, mode: PlaybackMode){
+        let mut playback = self.playback.lock().expect("failed to lock animation playback");
+        playback.mode = mode;
+    }
+
+    ///Returns true while the animation is playing
+    pub fn is_playing(&self) -> bool{
+        self.playback.lock().expect("failed to lock animation playback").is_playing
+    }
+
+    ///Returns the current time in the animation
+    pub fn get_time(&self) -> f32{
+        self.playback.lock().expect("failed to lock animation playback").time
+    }
+}
+
+///Plays keyframe tracks on the node it is attached to. The tracks are relative to the parent of
+/// the node. Since the tree stores world space transforms, the controller finds the current parent
+/// transform from the world transform of the node and the local transform it set last, so the
+/// node follows a moving parent. Each update the sampled values are converted to world space and
+/// added as `SceneJobs::SetLocation`, `SetRotation` and `SetScale` to the node, therefore the
+/// children follow the node. The gltf importer attaches one to every animated node which is no joint.
+pub struct AnimationController {
+    tracks: Vec<NodeTrack>,
+    ///The transform of the node relative to its parent as set by the last update, `None` till the
+    /// first update if no local transform was given.
+    local: Option<NodeTransform>,
+    playback: Arc<Mutex<Playback>>,
+    ///(time, name) of every event
+    events: Vec<(f32, String)>,
+    event_sender: Option<Sender<AnimationEvent>>,
+    last_update: Instant,
+}
+
+impl AnimationController{
+    ///Creates a controller which loops `tracks` with normal speed.
+    pub fn new(tracks: Vec<NodeTrack>) -> Self{
+        let duration = tracks.iter().fold(0.0, |max: f32, track| max.max(track.get_duration()));
+        AnimationController{
+            tracks: tracks,
+            local: None,
+            playback: Arc::new(Mutex::new(Playback{
+                mode: PlaybackMode::Loop,
+                speed: 1.0,
+                time: 0.0,
+                direction: 1.0,
+                duration: duration,
+                is_playing: true,
+                has_changed: false,
+            })),
+            events: Vec::new(),
+            event_sender: None,
+            last_update: Instant::now(),
+        }
+    }
+
+    ///Sets the transform of the node relative to its parent when the controller is attached. The
+    /// properties which are not animated keep this value. Without it the parent is assumed to be at
+    /// the origin on the first update.
+    pub fn with_local_transform(mut self, local: NodeTransform) -> Self{
+        self.local = Some(local);
+        self
+    }
+
+    ///Sets the playback mode
+    pub fn with_mode(self, mode: PlaybackMode) -> Self{
+        self.get_handle().set_mode(mode);
+        self
+    }
+
+    ///Sets the playback speed, 1.0 is normal speed.
+    pub fn with_speed(self, speed: f32) -> Self{
+        self.get_handle().set_speed(speed);
+        self
+    }
+
+    ///Adds an event called `name` which is sent whenever the playback passes `time`. An event at
+    /// the start of a looping animation is sent when the playback wraps around.
+    pub fn with_event(mut self, time: f32, name: &str) -> Self{
+        self.events.push((time, String::from(name)));
+        self
+    }
+
+    ///Adds an event at the time of the keyframe with `index` in the first track.
+    pub fn with_keyframe_event(self, index: usize, name: &str) -> Self{
+        let time = match self.tracks.get(0).and_then(|track| track.times.get(index)){
+            Some(time) => *time,
+            None => {
+                println!("WARNING: ANIMATION_CONTROLLER: there is no keyframe {} for event {}", index, name);
+                return self;
+            }
+        };
+        self.with_event(time, name)
+    }
+
+    ///Sets the sender to which all events are sent. Since the controller is owned by the node
+    /// after `set_controller()`, create the channel before and keep the receiver.
+    pub fn with_event_sender(mut self, sender: Sender<AnimationEvent>) -> Self{
+        self.event_sender = Some(sender);
+        self
+    }
+
+    ///Returns a handle which plays, pauses, stops and seeks this controller, also after it was
+    /// moved into a node by `set_controller()`.
+    pub fn get_handle(&self) -> AnimationHandle{
+        AnimationHandle{
+            playback: self.playback.clone(),
+        }
+    }
+
+    ///Returns the length of the animation in seconds
+    pub fn get_duration(&self) -> f32{
+        self.playback.lock().expect("failed to lock animation playback").duration
+    }
+
+    ///Advances the time by `delta` seconds. Returns the played segments and the new time, or
+    /// `None` if the pose does not change.
+    fn advance(&mut self, delta: f32) -> Option<(Vec<Segment>, f32)>{
+        let mut playback = self.playback.lock().expect("failed to lock animation playback");
+        if !playback.is_playing && !playback.has_changed{
+            return None;
+        }
+        playback.has_changed = false;
+
+        let segments = playback.advance(delta);
+        Some((segments, playback.time))
+    }
+
+    ///Sends every event which lies in one of the played segments
+    fn send_events(&self, segments: &[Segment], node_name: &str){
+        let sender = match self.event_sender{
+            Some(ref sender) => sender,
+            None => return,
+        };
+
+        for segment in segments.iter(){
+            for &(time, ref name) in self.events.iter(){
+                if segment.contains(time){
+                    let _ = sender.send(AnimationEvent{
+                        node_name: String::from(node_name),
+                        name: name.clone(),
+                        time: time,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl NodeController<ContentType,SceneJobs,NodeAttributes> for AnimationController
+{
+    fn update(&mut self, node: &mut Node<ContentType,SceneJobs,NodeAttributes>){
+        let delta = dur_as_f32(self.last_update.elapsed());
+        self.last_update = Instant::now();
+
+        let (segments, time) = match self.advance(delta){
+            Some(played) => played,
+            None => return,
+        };
+        let node_name = node.get_name().clone();
+        self.send_events(&segments, &node_name);
+
+        let world = node.get_attrib().transform;
+        let old_local = match self.local{
+            Some(local) => local,
+            None => world,
+        };
+        let parent = world.parent_transform(&old_local);
+
+        let mut local = old_local;
+        let mut animated = Vec::new();
+        for track in self.tracks.iter(){
+            if track.apply(time, &mut local){
+                animated.push(track);
+            }
+        }
+
+        let new_world = parent.concat(&local);
+        for track in animated.into_iter(){
+            node.add_job(track.get_job(&new_world));
+        }
+        self.local = Some(local);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    fn controller(mode: PlaybackMode) -> AnimationController{
+        let track = NodeTrack{
+            times: vec![0.0, 1.0],
+            values: ChannelValues::Translation(vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)]),
+            interpolation: Interpolation::Linear,
+        };
+        AnimationController::new(vec![track]).with_mode(mode)
+    }
+
+    fn played(controller: &mut AnimationController, delta: f32) -> Vec<(f32, f32)>{
+        match controller.advance(delta){
+            Some((segments, _)) => segments.iter().map(|s| (s.from, s.to)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn approx(a: f32, b: f32) -> bool{
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn loop_wraps_around_the_end(){
+        let mut controller = controller(PlaybackMode::Loop);
+        let handle = controller.get_handle();
+        handle.seek(0.8);
+
+        let segments = played(&mut controller, 0.5);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0], (0.8, 1.0));
+        assert_eq!(segments[1].0, 0.0);
+        assert!(approx(segments[1].1, 0.3));
+        assert!(approx(handle.get_time(), 0.3));
+        assert!(handle.is_playing());
+    }
+
+    #[test]
+    fn ping_pong_reverses_at_both_ends(){
+        let mut controller = controller(PlaybackMode::PingPong);
+        let handle = controller.get_handle();
+        handle.seek(0.8);
+
+        let segments = played(&mut controller, 0.5);
+        assert_eq!(segments[0], (0.8, 1.0));
+        assert_eq!(segments[1].0, 1.0);
+        assert!(approx(handle.get_time(), 0.7));
+
+        //plays backwards now and turns around at the start
+        played(&mut controller, 0.2);
+        assert!(approx(handle.get_time(), 0.5));
+        let segments = played(&mut controller, 0.7);
+        assert_eq!(segments[1].0, 0.0);
+        assert!(approx(handle.get_time(), 0.2));
+    }
+
+    #[test]
+    fn clamp_stops_at_the_end(){
+        let mut controller = controller(PlaybackMode::Clamp);
+        let handle = controller.get_handle();
+        handle.seek(0.8);
+
+        assert_eq!(played(&mut controller, 0.5), vec![(0.8, 1.0)]);
+        assert_eq!(handle.get_time(), 1.0);
+        assert!(!handle.is_playing());
+        assert!(played(&mut controller, 0.5).is_empty());
+
+        //playing again restarts a finished clamped animation
+        handle.play();
+        assert_eq!(handle.get_time(), 0.0);
+        assert!(handle.is_playing());
+    }
+
+    #[test]
+    fn negative_speed_plays_backwards(){
+        let mut controller = controller(PlaybackMode::Loop).with_speed(-1.0);
+        let handle = controller.get_handle();
+        handle.seek(0.2);
+
+        let segments = played(&mut controller, 0.5);
+        assert_eq!(segments[0], (0.2, 0.0));
+        assert_eq!(segments[1].0, 1.0);
+        assert!(approx(handle.get_time(), 0.7));
+
+        let mut controller = self::controller(PlaybackMode::Clamp).with_speed(-1.0);
+        let handle = controller.get_handle();
+        handle.seek(0.2);
+        assert_eq!(played(&mut controller, 0.5), vec![(0.2, 0.0)]);
+        assert_eq!(handle.get_time(), 0.0);
+        assert!(!handle.is_playing());
+    }
+
+    #[test]
+    fn pause_stop_and_seek_through_the_handle(){
+        let mut controller = controller(PlaybackMode::Loop);
+        let handle = controller.get_handle();
+
+        handle.pause();
+        assert!(controller.advance(0.5).is_none());
+        assert_eq!(handle.get_time(), 0.0);
+
+        //a paused controller still applies the pose it was seeked to, once
+        handle.seek(0.4);
+        let (segments, time) = controller.advance(0.5).expect("seek was not applied");
+        assert!(segments.is_empty());
+        assert_eq!(time, 0.4);
+        assert!(controller.advance(0.5).is_none());
+
+        handle.play();
+        played(&mut controller, 0.1);
+        assert!(approx(handle.get_time(), 0.5));
+
+        handle.stop();
+        assert!(!handle.is_playing());
+        assert_eq!(controller.advance(0.5).map(|(_, time)| time), Some(0.0));
+    }
+
+    #[test]
+    fn events_on_segment_borders_are_sent_once(){
+        let (sender, receiver) = channel();
+        let mut controller = controller(PlaybackMode::Loop)
+        .with_event(0.0, "start")
+        .with_event(0.8, "seeked")
+        .with_event(1.0, "end")
+        .with_event_sender(sender);
+        controller.get_handle().seek(0.8);
+
+        let (segments, _) = controller.advance(0.5).expect("controller is not playing");
+        controller.send_events(&segments, "node");
+        let names: Vec<String> = receiver.try_iter().map(|event| event.name).collect();
+        //the start of the first segment was reached before, the wrap passes the end and the start
+        assert_eq!(names, vec![String::from("end"), String::from("start")]);
+
+        let (segments, _) = controller.advance(0.6).expect("controller is not playing");
+        controller.send_events(&segments, "node");
+        let events: Vec<AnimationEvent> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, "seeked");
+        assert_eq!(events[0].node_name, "node");
+    }
+
+    #[test]
+    fn ping_pong_sends_the_turning_point_once(){
+        let (sender, receiver) = channel();
+        let mut controller = controller(PlaybackMode::PingPong)
+        .with_event(1.0, "end")
+        .with_event_sender(sender);
+        controller.get_handle().seek(0.8);
+
+        let (segments, _) = controller.advance(0.5).expect("controller is not playing");
+        controller.send_events(&segments, "node");
+        assert_eq!(receiver.try_iter().count(), 1);
+    }
+}
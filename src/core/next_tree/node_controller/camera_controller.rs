@@ -94,7 +94,7 @@ impl NodeController<ContentType,SceneJobs,NodeAttributes> for CameraController
         let tranform = node.get_attrib().transform;
 
         if let ContentType::Camera(ref mut camera) = node.get_value_mut(){
-            camera.update(&tranform);
+            camera.update(&tranform.to_decomposed());
         }
 
         self.last_update = Instant::now();
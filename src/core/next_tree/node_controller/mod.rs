@@ -1,2 +1,4 @@
 ///Reads the current input and changes lcoation and rotation of this node accordingly.
 pub mod camera_controller;
+///Plays keyframe tracks on a node, for instance imported glTF node animations.
+pub mod animation_controller;
@@ -0,0 +1,294 @@
+use cgmath::*;
+use collision::*;
+
+use input::keymap::KeyMap;
+use core::next_tree::transform::NodeTransform;
+
+use std::f32;
+
+///A ray in world space. The direction is always normalized.
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray{
+    ///Creates a new ray, `direction` is normalized.
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self{
+        Ray{
+            origin: origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    ///Returns the point at `distance` along the ray.
+    pub fn get_point(&self, distance: f32) -> Point3<f32>{
+        self.origin + self.direction * distance
+    }
+}
+
+///The result of a successful ray cast.
+#[derive(Clone, Debug)]
+pub struct RayHit {
+    ///Name of the node which was hit, can be used with `get_node()` on the tree.
+    pub node_name: String,
+    ///The hit point in world space
+    pub point: Point3<f32>,
+    ///The distance from the ray origin to `point`
+    pub distance: f32,
+}
+
+///Returns the distance at which `ray` enters `bound`, or `None` if it misses the bound or enters
+/// it after `max_dist`. If the origin is inside the bound, 0.0 is returned.
+pub fn ray_aabb(ray: &Ray, bound: &Aabb3<f32>, max_dist: f32) -> Option<f32>{
+    let mut t_min = 0.0_f32;
+    let mut t_max = max_dist;
+
+    for axis in 0..3{
+        let origin = ray.origin[axis];
+        let direction = ray.direction[axis];
+        let (min, max) = (bound.min[axis], bound.max[axis]);
+
+        if direction.abs() < f32::EPSILON{
+            //parallel to this slab, only hits if we are already inside
+            if origin < min || origin > max{
+                return None;
+            }
+            continue;
+        }
+
+        let inv = 1.0 / direction;
+        let mut t0 = (min - origin) * inv;
+        let mut t1 = (max - origin) * inv;
+        if t0 > t1{
+            ::std::mem::swap(&mut t0, &mut t1);
+        }
+
+        t_min = t_min.max(t0);
+        t_max = t_max.min(t1);
+        if t_min > t_max{
+            return None;
+        }
+    }
+
+    Some(t_min)
+}
+
+///Tests `ray` against the triangle (a, b, c) (Moeller-Trumbore). Returns the distance to the hit
+/// point. Both sides of the triangle are hit.
+pub fn ray_triangle(ray: &Ray, a: Point3<f32>, b: Point3<f32>, c: Point3<f32>) -> Option<f32>{
+    let edge_1 = b - a;
+    let edge_2 = c - a;
+
+    let p = ray.direction.cross(edge_2);
+    let det = edge_1.dot(p);
+    if det.abs() < f32::EPSILON{
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let to_origin = ray.origin - a;
+    let u = to_origin.dot(p) * inv_det;
+    if u < 0.0 || u > 1.0{
+        return None;
+    }
+
+    let q = to_origin.cross(edge_1);
+    let v = ray.direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0{
+        return None;
+    }
+
+    let t = edge_2.dot(q) * inv_det;
+    if t < 0.0{
+        return None;
+    }
+    Some(t)
+}
+
+///Tests `ray` against a indexed triangle list which is transformed by `transform` first.
+/// Returns the closest hit distance which is smaller than `max_dist`.
+pub fn ray_mesh(
+    ray: &Ray,
+    positions: &Vec<[f32; 3]>,
+    indices: &Vec<u32>,
+    transform: &NodeTransform,
+    max_dist: f32,
+) -> Option<f32>{
+    let world_positions: Vec<Point3<f32>> = positions.iter()
+    .map(|p| transform.transform_point(Point3::from(*p)))
+    .collect();
+
+    let mut closest: Option<f32> = None;
+    for triangle in indices.chunks(3){
+        if triangle.len() < 3{
+            break;
+        }
+        let (a, b, c) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+        if a >= world_positions.len() || b >= world_positions.len() || c >= world_positions.len(){
+            continue;
+        }
+
+        if let Some(dist) = ray_triangle(ray, world_positions[a], world_positions[b], world_positions[c]){
+            let is_closer = match closest{
+                Some(current) => dist < current,
+                None => true,
+            };
+            if dist <= max_dist && is_closer{
+                closest = Some(dist);
+            }
+        }
+    }
+
+    closest
+}
+
+///Creates a ray which starts at the near plane below `mouse_location` (in pixel) and points
+/// into the screen. `view_projection` should be the one of the camera which rendered the frame,
+/// for instance `Camera::get_view_projection_matrix()`. Returns `None` if the matrix can't be
+/// inverted.
+pub fn pick_ray(
+    mouse_location: [i32; 2],
+    window_dimensions: [u32; 2],
+    view_projection: Matrix4<f32>
+) -> Option<Ray>{
+    if window_dimensions[0] == 0 || window_dimensions[1] == 0{
+        return None;
+    }
+
+    let inverse = match view_projection.invert(){
+        Some(inv) => inv,
+        None => return None,
+    };
+
+    //The projection is already flipped for vulkan, so y points down in ndc like the mouse does.
+    let ndc_x = (2.0 * mouse_location[0] as f32 / window_dimensions[0] as f32) - 1.0;
+    let ndc_y = (2.0 * mouse_location[1] as f32 / window_dimensions[1] as f32) - 1.0;
+
+    let unproject = |z: f32| {
+        let point = inverse * Vector4::new(ndc_x, ndc_y, z, 1.0);
+        Point3::new(point.x / point.w, point.y / point.w, point.z / point.w)
+    };
+
+    let near = unproject(-1.0);
+    let far = unproject(1.0);
+
+    Some(Ray::new(near, far - near))
+}
+
+///Creates the pick ray for the current mouse location stored in `key_map`.
+pub fn pick_ray_from_key_map(key_map: &KeyMap, view_projection: Matrix4<f32>) -> Option<Ray>{
+    pick_ray(key_map.mouse_location, key_map.window_dimensions, view_projection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb3<f32>{
+        Aabb3::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0))
+    }
+
+    fn assert_near(value: f32, expected: f32){
+        assert!((value - expected).abs() < 1e-5, "{} is not {}", value, expected);
+    }
+
+    #[test]
+    fn ray_enters_box_from_outside(){
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 2.0));
+        assert_near(ray_aabb(&ray, &unit_box(), 100.0).expect("ray missed the box"), 4.0);
+    }
+
+    #[test]
+    fn ray_misses_box(){
+        let beside = Ray::new(Point3::new(2.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(ray_aabb(&beside, &unit_box(), 100.0).is_none());
+
+        let away = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(ray_aabb(&away, &unit_box(), 100.0).is_none());
+
+        let too_short = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(ray_aabb(&too_short, &unit_box(), 3.0).is_none());
+    }
+
+    #[test]
+    fn ray_inside_box_hits_at_origin(){
+        let ray = Ray::new(Point3::new(0.5, 0.0, 0.0), Vector3::new(1.0, 1.0, 0.0));
+        assert_near(ray_aabb(&ray, &unit_box(), 100.0).expect("ray missed the box"), 0.0);
+    }
+
+    #[test]
+    fn ray_hits_triangle_from_both_sides(){
+        let (a, b, c) = (Point3::new(-1.0, -1.0, 0.0), Point3::new(1.0, -1.0, 0.0), Point3::new(0.0, 1.0, 0.0));
+
+        let front = Ray::new(Point3::new(0.0, 0.0, -3.0), Vector3::new(0.0, 0.0, 1.0));
+        assert_near(ray_triangle(&front, a, b, c).expect("missed the front"), 3.0);
+
+        let back = Ray::new(Point3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_near(ray_triangle(&back, a, b, c).expect("missed the back"), 2.0);
+    }
+
+    #[test]
+    fn ray_misses_triangle(){
+        let (a, b, c) = (Point3::new(-1.0, -1.0, 0.0), Point3::new(1.0, -1.0, 0.0), Point3::new(0.0, 1.0, 0.0));
+
+        let beside = Ray::new(Point3::new(2.0, 0.0, -3.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(ray_triangle(&beside, a, b, c).is_none());
+
+        let behind = Ray::new(Point3::new(0.0, 0.0, -3.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(ray_triangle(&behind, a, b, c).is_none());
+
+        let parallel = Ray::new(Point3::new(0.0, 0.0, -3.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(ray_triangle(&parallel, a, b, c).is_none());
+    }
+
+    #[test]
+    fn ray_mesh_returns_closest_transformed_triangle(){
+        //two quads at z = 0 and z = 2 in mesh space
+        let positions = vec![
+            [-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0],
+            [-1.0, -1.0, 2.0], [1.0, -1.0, 2.0], [1.0, 1.0, 2.0], [-1.0, 1.0, 2.0],
+        ];
+        let indices = vec![4, 5, 6, 4, 6, 7, 0, 1, 2, 0, 2, 3];
+        let mut transform = NodeTransform::one();
+        transform.disp = Vector3::new(0.0, 0.0, 10.0);
+
+        let ray = Ray::new(Point3::new(0.5, 0.5, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert_near(ray_mesh(&ray, &positions, &indices, &transform, 100.0).expect("missed the mesh"), 10.0);
+        assert!(ray_mesh(&ray, &positions, &indices, &transform, 5.0).is_none());
+
+        let beside = Ray::new(Point3::new(5.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(ray_mesh(&beside, &positions, &indices, &transform, 100.0).is_none());
+    }
+
+    #[test]
+    fn pick_ray_goes_through_the_mouse(){
+        let mut key_map = KeyMap::new();
+        key_map.window_dimensions = [100, 100];
+
+        //with an identity view projection the near plane is at z = -1 in world space
+        key_map.mouse_location = [50, 50];
+        let center = pick_ray_from_key_map(&key_map, Matrix4::identity()).expect("no pick ray");
+        assert_near(center.origin.x, 0.0);
+        assert_near(center.origin.y, 0.0);
+        assert_near(center.origin.z, -1.0);
+        assert_near(center.direction.z, 1.0);
+
+        key_map.mouse_location = [0, 100];
+        let corner = pick_ray_from_key_map(&key_map, Matrix4::identity()).expect("no pick ray");
+        assert_near(corner.origin.x, -1.0);
+        assert_near(corner.origin.y, 1.0);
+        assert_near(corner.direction.z, 1.0);
+    }
+
+    #[test]
+    fn pick_ray_needs_a_window_and_an_invertible_matrix(){
+        let mut key_map = KeyMap::new();
+        key_map.window_dimensions = [0, 100];
+        assert!(pick_ray_from_key_map(&key_map, Matrix4::identity()).is_none());
+
+        key_map.window_dimensions = [100, 100];
+        assert!(pick_ray_from_key_map(&key_map, Matrix4::from_scale(0.0)).is_none());
+    }
+}
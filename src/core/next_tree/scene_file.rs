@@ -0,0 +1,614 @@
+use jakar_tree;
+use jakar_tree::node::Node;
+
+use core::next_tree::content::ContentType;
+use core::next_tree::jobs::SceneJobs;
+use core::next_tree::attributes::{NodeAttributes, LAYER_DEFAULT, LAYER_ALL};
+use core::next_tree::transform::NodeTransform;
+use core::resources::{mesh, light, empty};
+use core::resources::reflection_probe::{ReflectionProbe, ProbeUpdate};
+use core::resources::prefab::{self, PrefabScene};
+use core::resources::camera::{DefaultCamera, Camera};
+use core::ReturnBoundInfo;
+use core::asset_error::AssetError;
+use physics::collider::Collider;
+use physics::rigid_body::RigidBody;
+
+use cgmath::*;
+use collision::*;
+use ron;
+
+use std::sync::{Arc, Mutex};
+
+
+///Is used by `SceneFile::into_tree()` to get the resources which are only stored by reference in
+/// a scene file. The scene manager implements this for the engines managers.
+pub trait SceneResources {
+    ///Should return the mesh with `mesh_name` from the mesh manager, using the material called
+    /// `material_name`. Other users of the mesh must keep their material. Returns an error if
+    /// the mesh or the material is unknown.
+    fn get_mesh(&self, mesh_name: &str, material_name: &str) -> Result<Arc<Mutex<mesh::Mesh>>, AssetError>;
+    ///Should return a new camera which is set up for the current engine settings.
+    fn get_camera(&self) -> DefaultCamera;
+    ///Should return the scene called `scene_name` from the scene manager, used by prefabs.
+    /// Returns `None` if the scene is unknown.
+    fn get_scene(&self, scene_name: &str) -> Option<PrefabScene>;
+}
+
+///The transform of a node as it is written to disk. The rotation is saved as [w,x,y,z].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransformFile {
+    pub location: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    ///Only saved if the node is sheared by a non-uniformly scaled parent
+    #[serde(default)]
+    pub shear: Option<[[f32; 3]; 3]>,
+}
+
+///An `Aabb3` as it is written to disk
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoundFile {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+///All `NodeAttributes` of a node as they are written to disk
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AttributesFile {
+    pub transform: TransformFile,
+    pub bound: BoundFile,
+    pub value_bound: BoundFile,
+    pub cast_shadow: bool,
+    pub is_transparent: bool,
+    pub hide_in_game: bool,
+    pub is_emessive: bool,
+    pub max_draw_distance: f32,
+    ///Missing in files written before physics existed
+    #[serde(default)]
+    pub rigid_body: Option<RigidBodyFile>,
+    ///Missing in files written before tags and layers existed
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default = "default_layers")]
+    pub layers: u32,
+}
+
+fn default_layers() -> u32{
+    LAYER_DEFAULT
+}
+
+fn all_layers() -> u32{
+    LAYER_ALL
+}
+
+fn default_cast_shadow() -> bool{
+    true
+}
+
+///The collider of a rigid body as it is written to disk
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ColliderFile {
+    Aabb{
+        center: [f32; 3],
+        half_extend: [f32; 3],
+    },
+    Sphere{
+        center: [f32; 3],
+        radius: f32,
+    },
+    ConvexHull{
+        points: Vec<[f32; 3]>,
+    },
+}
+
+///A `RigidBody` as it is written to disk
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RigidBodyFile {
+    pub collider: ColliderFile,
+    pub mass: f32,
+    pub restitution: f32,
+    pub use_gravity: bool,
+    pub initial_velocity: [f32; 3],
+}
+
+///The content of a node as it is written to disk. Meshes are stored by their name in the mesh
+/// manager as well as the name of their material in the material manager.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ContentFile {
+    Mesh{
+        mesh: String,
+        material: String,
+    },
+    PointLight{
+        name: String,
+        intensity: f32,
+        radius: f32,
+        color: [f32; 3],
+        ///Missing in files written before point light shadows existed
+        #[serde(default = "default_cast_shadow")]
+        cast_shadow: bool,
+    },
+    DirectionalLight{
+        name: String,
+        intensity: f32,
+        color: [f32; 3],
+        #[serde(default = "default_cast_shadow")]
+        cast_shadow: bool,
+    },
+    SpotLight{
+        name: String,
+        intensity: f32,
+        color: [f32; 3],
+        radius: f32,
+        outer_radius: f32,
+        inner_radius: f32,
+        ///Missing in files written before spot light shadows existed
+        #[serde(default = "default_cast_shadow")]
+        cast_shadow: bool,
+    },
+    Empty{
+        name: String,
+        bound: BoundFile,
+    },
+    Camera{
+        fov: f32,
+        ///Missing in files written before layers existed
+        #[serde(default = "all_layers")]
+        render_layers: u32,
+    },
+    ///Stores the name of the referenced scene, which has to be in the scene manager when loading.
+    Prefab{
+        name: String,
+        scene: String,
+    },
+    ///`interval` is `None` for probes which only capture on demand
+    ReflectionProbe{
+        name: String,
+        bound: BoundFile,
+        interval: Option<f32>,
+        blend_distance: f32,
+    },
+    ///Renderables can't be saved since we don't know their type, they are stored with their name
+    /// and loaded as an empty.
+    Unsupported{
+        name: String,
+    },
+}
+
+///A node and all of its children as they are written to disk
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NodeFile {
+    pub content: ContentFile,
+    pub attributes: AttributesFile,
+    pub children: Vec<NodeFile>,
+}
+
+///The top level of a saved scene. Does not depend on a device, so it can be created and read
+/// without a gpu.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SceneFile {
+    ///The name the scene had when it was saved
+    pub name: String,
+    pub root: NodeFile,
+}
+
+impl TransformFile{
+    pub fn from_transform(transform: &NodeTransform) -> Self{
+        TransformFile{
+            location: transform.disp.into(),
+            rotation: [transform.rot.s, transform.rot.v.x, transform.rot.v.y, transform.rot.v.z],
+            scale: transform.scale.into(),
+            shear: if transform.is_sheared() { Some(transform.shear.into()) } else { None },
+        }
+    }
+
+    pub fn to_transform(&self) -> NodeTransform{
+        let mut transform = NodeTransform::new(
+            Vector3::from(self.location),
+            Quaternion::new(self.rotation[0], self.rotation[1], self.rotation[2], self.rotation[3]),
+            Vector3::from(self.scale),
+        );
+        if let Some(shear) = self.shear{
+            transform.shear = Matrix3::from(shear);
+        }
+        transform
+    }
+}
+
+impl BoundFile{
+    pub fn from_aabb(bound: &Aabb3<f32>) -> Self{
+        BoundFile{
+            min: [bound.min.x, bound.min.y, bound.min.z],
+            max: [bound.max.x, bound.max.y, bound.max.z],
+        }
+    }
+
+    pub fn to_aabb(&self) -> Aabb3<f32>{
+        Aabb3::new(Point3::from(self.min), Point3::from(self.max))
+    }
+}
+
+impl AttributesFile{
+    pub fn from_attributes(attrib: &NodeAttributes) -> Self{
+        AttributesFile{
+            transform: TransformFile::from_transform(&attrib.transform),
+            bound: BoundFile::from_aabb(&attrib.bound),
+            value_bound: BoundFile::from_aabb(&attrib.value_bound),
+            cast_shadow: attrib.cast_shadow,
+            is_transparent: attrib.is_transparent,
+            hide_in_game: attrib.hide_in_game,
+            is_emessive: attrib.is_emessive,
+            max_draw_distance: attrib.max_draw_distance,
+            rigid_body: attrib.rigid_body.as_ref().map(|body| RigidBodyFile::from_rigid_body(body)),
+            tags: attrib.tags.iter().cloned().collect(),
+            layers: attrib.layers,
+        }
+    }
+
+    pub fn to_attributes(&self) -> NodeAttributes{
+        use jakar_tree::node::Attribute;
+        let mut attrib = NodeAttributes::default();
+        attrib.transform = self.transform.to_transform();
+        attrib.bound = self.bound.to_aabb();
+        attrib.value_bound = self.value_bound.to_aabb();
+        attrib.cast_shadow = self.cast_shadow;
+        attrib.is_transparent = self.is_transparent;
+        attrib.hide_in_game = self.hide_in_game;
+        attrib.is_emessive = self.is_emessive;
+        attrib.max_draw_distance = self.max_draw_distance;
+        attrib.rigid_body = self.rigid_body.as_ref().map(|body| body.to_rigid_body());
+        attrib.tags = self.tags.iter().cloned().collect();
+        attrib.layers = self.layers;
+        attrib
+    }
+}
+
+impl ColliderFile{
+    pub fn from_collider(collider: &Collider) -> Self{
+        match collider{
+            &Collider::Aabb{center, half_extend} => ColliderFile::Aabb{
+                center: center.into(),
+                half_extend: half_extend.into(),
+            },
+            &Collider::Sphere{center, radius} => ColliderFile::Sphere{
+                center: center.into(),
+                radius: radius,
+            },
+            &Collider::ConvexHull{ref points} => ColliderFile::ConvexHull{
+                points: points.iter().map(|p| (*p).into()).collect(),
+            },
+        }
+    }
+
+    pub fn to_collider(&self) -> Collider{
+        match self{
+            &ColliderFile::Aabb{center, half_extend} => Collider::Aabb{
+                center: Vector3::from(center),
+                half_extend: Vector3::from(half_extend),
+            },
+            &ColliderFile::Sphere{center, radius} => Collider::Sphere{
+                center: Vector3::from(center),
+                radius: radius,
+            },
+            &ColliderFile::ConvexHull{ref points} => Collider::ConvexHull{
+                points: points.iter().map(|p| Vector3::from(*p)).collect(),
+            },
+        }
+    }
+}
+
+impl RigidBodyFile{
+    pub fn from_rigid_body(body: &RigidBody) -> Self{
+        RigidBodyFile{
+            collider: ColliderFile::from_collider(&body.collider),
+            mass: body.mass,
+            restitution: body.restitution,
+            use_gravity: body.use_gravity,
+            initial_velocity: body.initial_velocity.into(),
+        }
+    }
+
+    pub fn to_rigid_body(&self) -> RigidBody{
+        RigidBody{
+            collider: self.collider.to_collider(),
+            mass: self.mass,
+            restitution: self.restitution,
+            use_gravity: self.use_gravity,
+            initial_velocity: Vector3::from(self.initial_velocity),
+        }
+    }
+}
+
+impl ContentFile{
+    ///Creates the file representation of a nodes `content`
+    pub fn from_content(content: &ContentType) -> Self{
+        match content{
+            &ContentType::Renderable(_) => {
+                use jakar_tree::node::NodeContent;
+                println!("WARNING: SCENE_FILE: Can't save renderable {}, saving as empty", content.get_name());
+                ContentFile::Unsupported{
+                    name: content.get_name(),
+                }
+            },
+            &ContentType::Mesh(ref mesh) => {
+                let mesh_lck = mesh.lock().expect("failed to lock mesh while saving");
+                ContentFile::Mesh{
+                    mesh: mesh_lck.name.clone(),
+                    material: mesh_lck.get_material_name(),
+                }
+            },
+            &ContentType::PointLight(ref light) => {
+                let mut light = light.clone();
+                ContentFile::PointLight{
+                    name: light.name.clone(),
+                    intensity: *light.get_intensity(),
+                    radius: *light.get_radius(),
+                    color: (*light.get_color()).into(),
+                    cast_shadow: light.get_cast_shadow(),
+                }
+            },
+            &ContentType::DirectionalLight(ref light) => {
+                let mut light = light.clone();
+                ContentFile::DirectionalLight{
+                    name: light.name.clone(),
+                    intensity: *light.get_intensity(),
+                    color: (*light.get_color()).into(),
+                    cast_shadow: light.get_cast_shadow(),
+                }
+            },
+            &ContentType::SpotLight(ref light) => {
+                let mut light = light.clone();
+                ContentFile::SpotLight{
+                    name: light.name.clone(),
+                    intensity: *light.get_intensity(),
+                    color: (*light.get_color()).into(),
+                    radius: *light.get_radius(),
+                    outer_radius: *light.get_outer_radius(),
+                    inner_radius: *light.get_inner_radius(),
+                    cast_shadow: light.get_cast_shadow(),
+                }
+            },
+            &ContentType::Empty(ref empty) => {
+                ContentFile::Empty{
+                    name: empty.name.clone(),
+                    bound: BoundFile::from_aabb(&empty.get_bound()),
+                }
+            },
+            &ContentType::Camera(ref camera) => {
+                ContentFile::Camera{
+                    fov: camera.get_fov(),
+                    render_layers: camera.get_render_layers(),
+                }
+            },
+            &ContentType::Prefab(ref prefab) => {
+                ContentFile::Prefab{
+                    name: prefab.name.clone(),
+                    scene: prefab.get_scene_name(),
+                }
+            },
+            &ContentType::ReflectionProbe(ref probe) => {
+                let interval = match probe.get_update(){
+                    ProbeUpdate::OnDemand => None,
+                    ProbeUpdate::Interval(seconds) => Some(seconds),
+                };
+                ContentFile::ReflectionProbe{
+                    name: probe.name.clone(),
+                    bound: BoundFile::from_aabb(&probe.get_influence()),
+                    interval: interval,
+                    blend_distance: probe.get_blend_distance(),
+                }
+            },
+        }
+    }
+
+    ///Creates the node content from this file representation. Returns an error if a referenced
+    /// resource could not be found.
+    pub fn to_content<R: SceneResources>(&self, resources: &R) -> Result<ContentType, AssetError>{
+        match self{
+            &ContentFile::Mesh{ref mesh, ref material} => {
+                Ok(ContentType::Mesh(resources.get_mesh(mesh, material)?))
+            },
+            &ContentFile::PointLight{ref name, intensity, radius, color, cast_shadow} => {
+                let mut light = light::LightPoint::new(name);
+                light.set_intensity(intensity);
+                light.set_radius(radius);
+                light.set_color(Vector3::from(color));
+                light.set_cast_shadow(cast_shadow);
+                Ok(ContentType::PointLight(light))
+            },
+            &ContentFile::DirectionalLight{ref name, intensity, color, cast_shadow} => {
+                let mut light = light::LightDirectional::new(name);
+                light.set_intensity(intensity);
+                light.set_color(Vector3::from(color));
+                light.set_cast_shadow(cast_shadow);
+                Ok(ContentType::DirectionalLight(light))
+            },
+            &ContentFile::SpotLight{ref name, intensity, color, radius, outer_radius, inner_radius, cast_shadow} => {
+                let mut light = light::LightSpot::new(name);
+                light.set_intensity(intensity);
+                light.set_color(Vector3::from(color));
+                light.set_radius(radius);
+                light.set_outer_radius(outer_radius);
+                light.set_inner_radius(inner_radius);
+                light.set_cast_shadow(cast_shadow);
+                Ok(ContentType::SpotLight(light))
+            },
+            &ContentFile::Empty{ref name, ref bound} => {
+                let mut empty = empty::Empty::new(name);
+                empty.set_bound(Point3::from(bound.min), Point3::from(bound.max));
+                Ok(ContentType::Empty(empty))
+            },
+            &ContentFile::Camera{fov, render_layers} => {
+                let mut camera = resources.get_camera();
+                camera.set_fov(fov);
+                camera.set_render_layers(render_layers);
+                Ok(ContentType::Camera(camera))
+            },
+            &ContentFile::Prefab{ref name, ref scene} => {
+                match resources.get_scene(scene){
+                    Some(prefab_scene) => Ok(ContentType::Prefab(prefab::Prefab::new(name, scene, prefab_scene))),
+                    None => Err(AssetError::NotFound(format!("the scene {} of prefab {} in the scene manager", scene, name))),
+                }
+            },
+            &ContentFile::ReflectionProbe{ref name, ref bound, interval, blend_distance} => {
+                let update = match interval{
+                    Some(seconds) => ProbeUpdate::Interval(seconds),
+                    None => ProbeUpdate::OnDemand,
+                };
+                let mut probe = ReflectionProbe::new(name)
+                .with_influence(Point3::from(bound.min), Point3::from(bound.max))
+                .with_update(update);
+                probe.set_blend_distance(blend_distance);
+                Ok(ContentType::ReflectionProbe(probe))
+            },
+            &ContentFile::Unsupported{ref name} => {
+                Ok(ContentType::Empty(empty::Empty::new(name)))
+            },
+        }
+    }
+}
+
+impl NodeFile{
+    ///Creates the file representation of `node` and all of its children
+    pub fn from_node(node: &Node<ContentType, SceneJobs, NodeAttributes>) -> Self{
+        let mut children = Vec::new();
+        for (_, child) in node.get_children().iter(){
+            children.push(NodeFile::from_node(child));
+        }
+
+        NodeFile{
+            content: ContentFile::from_content(node.get_value()),
+            attributes: AttributesFile::from_attributes(node.get_attrib()),
+            children: children,
+        }
+    }
+
+    ///Adds all children of self to the node called `parent_name` in `tree`
+    fn add_children<R: SceneResources>(
+        &self,
+        parent_name: String,
+        tree: &mut jakar_tree::tree::Tree<ContentType, SceneJobs, NodeAttributes>,
+        resources: &R,
+    ) -> Result<(), AssetError>{
+        for child in self.children.iter(){
+            let content = child.content.to_content(resources)?;
+            let child_name = match tree.add(content, parent_name.clone(), Some(child.attributes.to_attributes())){
+                Ok(name) => name,
+                Err(_) => return Err(AssetError::NotFound(format!("the parent node {} of a scene file node", parent_name))),
+            };
+            child.add_children(child_name, tree, resources)?;
+        }
+        Ok(())
+    }
+}
+
+impl SceneFile{
+    ///Creates the file representation of `tree`.
+    pub fn from_tree(tree: &jakar_tree::tree::Tree<ContentType, SceneJobs, NodeAttributes>) -> Self{
+        SceneFile{
+            name: tree.name.clone(),
+            root: NodeFile::from_node(&tree.root_node),
+        }
+    }
+
+    ///Rebuilds the tree described by self. Meshes and cameras are requested from `resources`.
+    pub fn into_tree<R: SceneResources>(&self, resources: &R)
+    -> Result<jakar_tree::tree::Tree<ContentType, SceneJobs, NodeAttributes>, AssetError>{
+        let root_content = self.root.content.to_content(resources)?;
+        let mut tree = jakar_tree::tree::Tree::new(root_content, self.root.attributes.to_attributes());
+        tree.name = self.name.clone();
+
+        let root_name = tree.root_node.get_name().clone();
+        self.root.add_children(root_name, &mut tree, resources)?;
+
+        Ok(tree)
+    }
+
+    ///Returns self as a string in the RON format
+    pub fn to_string(&self) -> Result<String, AssetError>{
+        match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()){
+            Ok(string) => Ok(string),
+            Err(er) => Err(AssetError::decode(&format!("scene {}", self.name), &format!("failed to serialize: {:?}", er))),
+        }
+    }
+
+    ///Reads a scene file from a string in the RON format. The error describes the data as "scene
+    /// file", callers which know the path should replace it.
+    pub fn from_str(string: &str) -> Result<Self, AssetError>{
+        match ron::de::from_str(string){
+            Ok(scene) => Ok(scene),
+            Err(er) => Err(AssetError::decode("scene file", &format!("{:?}", er))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jakar_tree::node::Attribute;
+
+    ///Creates cpu only meshes, the test scene has no cameras or prefabs
+    struct TestResources;
+
+    impl SceneResources for TestResources{
+        fn get_mesh(&self, mesh_name: &str, _material_name: &str) -> Result<Arc<Mutex<mesh::Mesh>>, AssetError>{
+            Ok(Arc::new(Mutex::new(mesh::Mesh::new_cpu_only(mesh_name))))
+        }
+
+        fn get_camera(&self) -> DefaultCamera{
+            panic!("the test scene has no camera")
+        }
+
+        fn get_scene(&self, _scene_name: &str) -> Option<PrefabScene>{
+            None
+        }
+    }
+
+    fn test_tree() -> jakar_tree::tree::Tree<ContentType, SceneJobs, NodeAttributes>{
+        let mut tree = jakar_tree::tree::Tree::new(
+            ContentType::Empty(empty::Empty::new("root")),
+            NodeAttributes::default()
+        );
+        tree.name = String::from("test_scene");
+        let root_name = tree.root_node.get_name().clone();
+
+        let mut light = light::LightPoint::new("lamp");
+        light.set_intensity(3.0);
+        light.set_radius(12.0);
+        let mut light_attributes = NodeAttributes::default();
+        light_attributes.transform.disp = Vector3::new(1.0, 2.0, 3.0);
+        light_attributes.cast_shadow = false;
+        let light_name = tree.add(ContentType::PointLight(light), root_name.clone(), Some(light_attributes))
+        .expect("failed to add light");
+
+        let mesh = Arc::new(Mutex::new(mesh::Mesh::new_cpu_only("cube")));
+        tree.add(ContentType::Mesh(mesh), light_name, Some(NodeAttributes::default()))
+        .expect("failed to add mesh");
+
+        let probe = ReflectionProbe::new("probe").with_update(ProbeUpdate::Interval(2.0));
+        tree.add(ContentType::ReflectionProbe(probe), root_name, Some(NodeAttributes::default()))
+        .expect("failed to add probe");
+
+        tree
+    }
+
+    #[test]
+    fn string_round_trip_is_lossless(){
+        let scene_file = SceneFile::from_tree(&test_tree());
+        let string = scene_file.to_string().expect("failed to write scene");
+        let read_file = SceneFile::from_str(&string).expect("failed to read scene");
+        assert_eq!(scene_file, read_file);
+
+        let tree = read_file.into_tree(&TestResources).expect("failed to rebuild tree");
+        assert_eq!(SceneFile::from_tree(&tree), scene_file);
+    }
+
+    #[test]
+    fn broken_string_is_a_decode_error(){
+        match SceneFile::from_str("SceneFile(name: "){
+            Err(AssetError::Decode{..}) => {},
+            other => panic!("expected a decode error, got {:?}", other.map(|scene| scene.name)),
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use cgmath::*;
+
+///The transform of a node. Works like cgmath's `Decomposed`, but has a scale per axis.
+/// The scale is applied first, then the shear, then the rotation, then the displacement.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeTransform {
+    ///Scale along the x, y and z axis of the node
+    pub scale: Vector3<f32>,
+    pub rot: Quaternion<f32>,
+    pub disp: Vector3<f32>,
+    ///Is the identity, except for world transforms of rotated children of a non-uniformly scaled
+    /// parent. Those are stretched along the axes of the parent, which can't be expressed by a
+    /// scale and rotation alone.
+    pub shear: Matrix3<f32>,
+}
+
+impl NodeTransform{
+    ///Returns a transform which changes nothing
+    pub fn one() -> Self{
+        NodeTransform::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Quaternion::from(Euler::new(Deg(0.0), Deg(0.0), Deg(0.0))),
+            Vector3::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    ///Creates a transform without shear
+    pub fn new(disp: Vector3<f32>, rot: Quaternion<f32>, scale: Vector3<f32>) -> Self{
+        NodeTransform{
+            scale: scale,
+            rot: rot,
+            disp: disp,
+            shear: Matrix3::identity(),
+        }
+    }
+
+    ///Creates a transform from a `Decomposed` with uniform scale
+    pub fn from_decomposed(transform: &Decomposed<Vector3<f32>, Quaternion<f32>>) -> Self{
+        NodeTransform::new(
+            transform.disp,
+            transform.rot,
+            Vector3::new(transform.scale, transform.scale, transform.scale),
+        )
+    }
+
+    ///Creates the transform with `disp`, `rot` and `scale` whose scale, shear and rotation
+    /// together are `linear`. The shear stays the identity as long as `linear` can be expressed
+    /// by `rot` and `scale`. Axis with a scale of zero are not sheared.
+    fn from_linear(disp: Vector3<f32>, rot: Quaternion<f32>, scale: Vector3<f32>, linear: Matrix3<f32>) -> Self{
+        let inv_scale = diagonal(inverse_scale(scale));
+        let mut shear = Matrix3::from(rot.invert()) * linear * inv_scale;
+        //a zero axis is lost, so it can't be sheared either
+        for axis in 0..3{
+            if scale[axis] == 0.0{
+                shear[axis] = Matrix3::<f32>::identity()[axis];
+            }
+        }
+        //keep the exact identity if there is nothing to correct
+        if shear_is_identity(&shear){
+            shear = Matrix3::identity();
+        }
+
+        NodeTransform{
+            scale: scale,
+            rot: rot,
+            disp: disp,
+            shear: shear,
+        }
+    }
+
+    ///Returns the scale, shear and rotation as one matrix
+    pub fn get_linear(&self) -> Matrix3<f32>{
+        Matrix3::from(self.rot) * self.shear * diagonal(self.scale)
+    }
+
+    ///Returns true if this transform is sheared
+    pub fn is_sheared(&self) -> bool{
+        self.shear != Matrix3::identity()
+    }
+
+    ///Scales this transform by `factor` along the axes of `axes` (in the space the transform is
+    /// in). The scale is multiplied per axis, the difference to the exact result is kept in the
+    /// shear. Does not change the displacement.
+    pub fn scale_along(&mut self, factor: Vector3<f32>, axes: Quaternion<f32>){
+        let delta = Matrix3::from(axes) * diagonal(factor) * Matrix3::from(axes.invert());
+        let linear = delta * self.get_linear();
+        *self = NodeTransform::from_linear(self.disp, self.rot, self.scale.mul_element_wise(factor), linear);
+    }
+
+    ///Returns self as `Decomposed`. Since `Decomposed` has only a uniform scale, the x scale is
+    /// used. Should only be used where the scale does not matter, like for cameras.
+    pub fn to_decomposed(&self) -> Decomposed<Vector3<f32>, Quaternion<f32>>{
+        Decomposed{
+            scale: self.scale.x,
+            rot: self.rot,
+            disp: self.disp,
+        }
+    }
+
+    ///Transforms `point` from the local space of this transform into its parent space.
+    pub fn transform_point(&self, point: Point3<f32>) -> Point3<f32>{
+        Point3::from_vec(self.transform_vector(point.to_vec()) + self.disp)
+    }
+
+    ///Transforms `vector` (scale, shear and rotation, no displacement).
+    pub fn transform_vector(&self, vector: Vector3<f32>) -> Vector3<f32>{
+        if self.is_sheared(){
+            self.get_linear() * vector
+        }else{
+            self.rot.rotate_vector(self.scale.mul_element_wise(vector))
+        }
+    }
+
+    ///Returns the largest scale of all three axis, useful to scale radii. For a sheared transform
+    /// this is an upper bound of the largest stretch.
+    pub fn get_max_scale(&self) -> f32{
+        if self.is_sheared(){
+            let linear = self.get_linear();
+            return (linear.x.magnitude2() + linear.y.magnitude2() + linear.z.magnitude2()).sqrt();
+        }
+        self.scale.x.abs().max(self.scale.y.abs()).max(self.scale.z.abs())
+    }
+
+    ///Applies `child` in the space of self. If self is the world transform of a parent, the
+    /// result is the world transform of the child. A rotated child of a non-uniformly scaled
+    /// parent is sheared.
+    pub fn concat(&self, child: &NodeTransform) -> Self{
+        NodeTransform::from_linear(
+            self.transform_vector(child.disp) + self.disp,
+            self.rot * child.rot,
+            self.scale.mul_element_wise(child.scale),
+            self.get_linear() * child.get_linear(),
+        )
+    }
+
+    ///The inverse of `concat()`: Returns the transform of self relative to `parent`, so that
+    /// `parent.concat(&self.relative_to(parent)) == self`. Axis with a scale of zero stay unscaled.
+    pub fn relative_to(&self, parent: &NodeTransform) -> Self{
+        let inv_rot = parent.rot.invert();
+        let scale = self.scale.mul_element_wise(inverse_scale(parent.scale));
+        match parent.get_linear().invert(){
+            Some(inv_linear) => NodeTransform::from_linear(
+                inv_linear * (self.disp - parent.disp),
+                inv_rot * self.rot,
+                scale,
+                inv_linear * self.get_linear(),
+            ),
+            //a flat parent can't be inverted, ignore its shear then
+            None => NodeTransform::new(
+                inv_rot.rotate_vector(self.disp - parent.disp).mul_element_wise(inverse_scale(parent.scale)),
+                inv_rot * self.rot,
+                scale,
+            ),
+        }
+    }
+
+    ///Returns the transform of the parent if self is the world transform of a child and `local`
+    /// the transform of the child relative to its parent, so that
+    /// `self.parent_transform(local).concat(local) == self`. Axis with a scale of zero stay unscaled.
+    pub fn parent_transform(&self, local: &NodeTransform) -> Self{
+        let scale = Vector3::new(
+            if local.scale.x != 0.0 { self.scale.x / local.scale.x } else { self.scale.x },
+            if local.scale.y != 0.0 { self.scale.y / local.scale.y } else { self.scale.y },
+            if local.scale.z != 0.0 { self.scale.z / local.scale.z } else { self.scale.z },
+        );
+        let rot = self.rot * local.rot.invert();
+        match local.get_linear().invert(){
+            Some(inv_local) => {
+                let linear = self.get_linear() * inv_local;
+                NodeTransform::from_linear(self.disp - linear * local.disp, rot, scale, linear)
+            },
+            //a flat child can't be inverted, ignore the shear then
+            None => NodeTransform::new(
+                self.disp - rot.rotate_vector(scale.mul_element_wise(local.disp)),
+                rot,
+                scale,
+            ),
+        }
+    }
+
+    ///Returns the model matrix of this transform.
+    pub fn get_matrix(&self) -> Matrix4<f32>{
+        Matrix4::from_translation(self.disp) * Matrix4::from(self.get_linear())
+    }
+}
+
+///Returns a matrix which scales by `scale`
+fn diagonal(scale: Vector3<f32>) -> Matrix3<f32>{
+    Matrix3::new(
+        scale.x, 0.0, 0.0,
+        0.0, scale.y, 0.0,
+        0.0, 0.0, scale.z,
+    )
+}
+
+///Returns 1 / `scale` per axis, axis with a scale of zero stay 1
+fn inverse_scale(scale: Vector3<f32>) -> Vector3<f32>{
+    Vector3::new(
+        if scale.x != 0.0 { 1.0 / scale.x } else { 1.0 },
+        if scale.y != 0.0 { 1.0 / scale.y } else { 1.0 },
+        if scale.z != 0.0 { 1.0 / scale.z } else { 1.0 },
+    )
+}
+
+///Returns true if `shear` is the identity within the precision of a f32
+fn shear_is_identity(shear: &Matrix3<f32>) -> bool{
+    let identity = Matrix3::<f32>::identity();
+    for column in 0..3{
+        for row in 0..3{
+            if (shear[column][row] - identity[column][row]).abs() > 1.0e-5{
+                return false;
+            }
+        }
+    }
+    true
+}
+
+impl From<NodeTransform> for Matrix4<f32>{
+    fn from(transform: NodeTransform) -> Matrix4<f32>{
+        transform.get_matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &NodeTransform, b: &NodeTransform){
+        assert!((a.disp - b.disp).magnitude() < 1.0e-4, "{:?} != {:?}", a, b);
+        assert!((a.scale - b.scale).magnitude() < 1.0e-4, "{:?} != {:?}", a, b);
+        assert!(a.rot.dot(b.rot).abs() > 0.9999, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_matrix_close(a: &Matrix4<f32>, b: &Matrix4<f32>){
+        for column in 0..4{
+            assert!((a[column] - b[column]).magnitude() < 1.0e-4, "{:?} != {:?}", a, b);
+        }
+    }
+
+    fn test_parent() -> NodeTransform{
+        NodeTransform::new(
+            Vector3::new(1.0, 2.0, 3.0),
+            Quaternion::from_angle_y(Deg(90.0)),
+            Vector3::new(2.0, 2.0, 2.0),
+        )
+    }
+
+    fn test_child() -> NodeTransform{
+        NodeTransform::new(
+            Vector3::new(0.0, 1.0, -2.0),
+            Quaternion::from_angle_x(Deg(30.0)),
+            Vector3::new(0.5, 1.0, 3.0),
+        )
+    }
+
+    fn stretched_parent() -> NodeTransform{
+        NodeTransform::new(
+            Vector3::new(1.0, 2.0, 3.0),
+            Quaternion::from_angle_z(Deg(0.0)),
+            Vector3::new(1.0, 2.0, 1.0),
+        )
+    }
+
+    fn rotated_child() -> NodeTransform{
+        NodeTransform::new(
+            Vector3::new(1.0, 1.0, 0.0),
+            Quaternion::from_angle_z(Deg(45.0)),
+            Vector3::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn relative_to_inverts_concat(){
+        let world = test_parent().concat(&test_child());
+        assert_close(&world.relative_to(&test_parent()), &test_child());
+    }
+
+    #[test]
+    fn parent_transform_inverts_concat(){
+        let world = test_parent().concat(&test_child());
+        assert_close(&world.parent_transform(&test_child()), &test_parent());
+    }
+
+    #[test]
+    fn concat_shears_rotated_child_of_stretched_parent(){
+        let world = stretched_parent().concat(&rotated_child());
+        assert!(world.is_sheared());
+        assert_matrix_close(
+            &world.get_matrix(),
+            &(stretched_parent().get_matrix() * rotated_child().get_matrix())
+        );
+        //the local transform is recovered without shear
+        let local = world.relative_to(&stretched_parent());
+        assert!(!local.is_sheared());
+        assert_close(&local, &rotated_child());
+        assert_matrix_close(
+            &world.parent_transform(&rotated_child()).get_matrix(),
+            &stretched_parent().get_matrix()
+        );
+    }
+
+    #[test]
+    fn scale_along_parent_axes_matches_concat(){
+        //scaling the world transform of the child like a parent job would
+        let mut world = rotated_child();
+        world.scale_along(Vector3::new(1.0, 2.0, 1.0), Quaternion::from_angle_z(Deg(0.0)));
+        world.disp = world.disp.mul_element_wise(Vector3::new(1.0, 2.0, 1.0));
+        assert_matrix_close(
+            &world.get_matrix(),
+            &(NodeTransform::new(
+                Vector3::new(0.0, 0.0, 0.0),
+                Quaternion::from_angle_z(Deg(0.0)),
+                Vector3::new(1.0, 2.0, 1.0)
+            ).get_matrix() * rotated_child().get_matrix())
+        );
+    }
+}
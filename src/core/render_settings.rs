@@ -10,6 +10,8 @@ pub enum DebugView {
     ShadowMaps,
     DirectionalDepth,
     Shaded,
+    ///Shows how blurry the depth of field makes each pixel, white is fully blurred
+    CircleOfConfusion,
 }
 
 impl DebugView{
@@ -20,6 +22,7 @@ impl DebugView{
             &DebugView::ShadowMaps => 2,
             &DebugView::DirectionalDepth => 3,
             &DebugView::Shaded => 4,
+            &DebugView::CircleOfConfusion => 5,
         }
     }
 }
@@ -59,6 +62,43 @@ impl BloomSettings{
     }
 }
 
+///Depth of field settings. Everything which is not at the focus distance gets blurred.
+#[derive(Clone)]
+pub struct DofSettings {
+    ///The distance (in meters) from the camera which is sharp
+    pub focus_distance: f32,
+    ///How fast the image gets blurry in front of and behind the focus distance. 0.0 turns the
+    /// depth of field off.
+    pub aperture: f32,
+    ///The blur radius (in pixels) of fully blurred pixels
+    pub max_blur_radius: f32,
+}
+
+impl DofSettings{
+    ///Creates the settings, negative values are set to 0.0
+    pub fn new(focus_distance: f32, aperture: f32, max_blur_radius: f32) -> Self{
+        DofSettings{
+            focus_distance: focus_distance.max(0.0),
+            aperture: aperture.max(0.0),
+            max_blur_radius: max_blur_radius.max(0.0),
+        }
+    }
+
+    ///Creates turned off settings:
+    /// - focus_distance: 10.0
+    /// - aperture: 0.0
+    /// - max_blur_radius: 8.0
+    pub fn default() -> Self{
+        DofSettings::new(10.0, 0.0, 8.0)
+    }
+
+    ///Returns true if the depth of field should be rendered
+    #[inline]
+    pub fn is_active(&self) -> bool{
+        self.aperture > 0.0 && self.max_blur_radius > 0.0
+    }
+}
+
 ///All settings needed for the auto exposure to work. Howevcer, there is an option to use
 /// no auto exposure. If it is turned on, the engine will use the min_exposure setting always.
 #[derive(Clone)]
@@ -190,58 +230,177 @@ impl DirectionalLightSettings{
 
 }
 
-///Defines variouse settings for point lights
+///Defines variouse settings for point and spot light shadows. Both share one shadow atlas, a spot
+/// light occupies one tile of it, a point light six (one per cube face).
+#[derive(Clone)]
 pub struct PointLightSettings {
-    pcf_samples: i32,
-    shadow_map_resolution: i32,
+    ///Describes how many samples should be taken in each direction when calculating the shadow
+    pcf_samples: u32,
+    ///The resolution of the whole atlas, the tiles get smaller the more lights cast a shadow
+    shadow_map_resolution: u32,
+    ///Controles how many percent of a tile a mesh must occupy to be actually rendered.
     occupy_bias: f32,
+    ///Describes how much the different samples are "spread" over the uv.
+    poisson_spread: f32,
+    ///How many of the nearest point and spot lights get a shadow
+    max_shadowed_lights: u32,
 }
 
 impl PointLightSettings{
     ///Creates a new set of settings from the supplied values
-    pub fn new(pcf_samples: i32, shadow_map_resolution: i32, occupy_bias: f32) -> Self{
+    pub fn new(pcf_samples: u32, shadow_map_resolution: u32, occupy_bias: f32, poisson_spread: f32, max_shadowed_lights: u32) -> Self{
         PointLightSettings{
             pcf_samples,
             shadow_map_resolution,
-            occupy_bias
+            occupy_bias,
+            poisson_spread,
+            max_shadowed_lights,
         }
     }
 
     ///Creates a default set as follows:
     /// - pcf_samples: 2
-    /// - shadow_map_resolution: 1024
+    /// - shadow_map_resolution: 2048
     /// - occupy_bias: 0.1
+    /// - poisson_spread: 800.0
+    /// - max_shadowed_lights: 4
     pub fn default() -> Self{
         PointLightSettings{
             pcf_samples: 2,
-            shadow_map_resolution: 1024,
-            occupy_bias: 0.1
+            shadow_map_resolution: 2048,
+            occupy_bias: 0.1,
+            poisson_spread: 800.0,
+            max_shadowed_lights: 4,
         }
     }
+
+    /// Describes how many samples are taken in each direction when rendering the shadows
+    pub fn set_pcf_samples(&mut self, new: u32){
+        self.pcf_samples = new;
+    }
+    pub fn get_pcf_samples(&self) -> u32{
+        self.pcf_samples
+    }
+    /// The resolution of the shadow atlas, can't be changed after starting the engine
+    pub fn get_shadow_map_resolution(&self) -> u32{
+        self.shadow_map_resolution
+    }
+    pub fn set_shadow_map_resolution(&mut self, new: u32){
+        self.shadow_map_resolution = new;
+    }
+    ///Controles how many percent of a tile a mesh must occupy to be actually rendered.
+    pub fn set_occupy_bias(&mut self, new: f32){
+        self.occupy_bias = new;
+    }
+    pub fn get_occupy_bias(&self) -> f32{
+        self.occupy_bias
+    }
+    ///Controlles how much the different samples for the shadows a spread over the shadow map.
+    pub fn set_poisson_spread(&mut self, new: f32){
+        self.poisson_spread = new;
+    }
+    pub fn get_poisson_spread(&self) -> f32{
+        self.poisson_spread
+    }
+    ///Sets how many of the nearest point and spot lights get a shadow, 0 turns them off.
+    pub fn set_max_shadowed_lights(&mut self, new: u32){
+        self.max_shadowed_lights = new;
+    }
+    pub fn get_max_shadowed_lights(&self) -> u32{
+        self.max_shadowed_lights
+    }
 }
 
 ///Defines several settings which will be used to determin how lights and their shadows are rendered
 #[derive(Clone)]
 pub struct LightSettings {
-    pub directional_settings: DirectionalLightSettings
+    pub directional_settings: DirectionalLightSettings,
+    ///Used for point and spot lights
+    pub point_settings: PointLightSettings,
 }
 
 impl LightSettings{
-    ///Creates a custom set of light settings
+    ///Creates a custom set of light settings, the point light settings are the default ones.
     pub fn new(directional_light: DirectionalLightSettings) -> Self{
         LightSettings{
             directional_settings: directional_light,
+            point_settings: PointLightSettings::default(),
         }
     }
 
+    ///Sets the settings used for point and spot light shadows
+    pub fn with_point_settings(mut self, point_light: PointLightSettings) -> Self{
+        self.point_settings = point_light;
+        self
+    }
+
     ///Creates the default settings, see the impls of the different settings to see them.
     pub fn default() -> Self{
         LightSettings{
             directional_settings: DirectionalLightSettings::default(),
+            point_settings: PointLightSettings::default(),
         }
     }
 }
 
+///Defines how reflection probes are captured. Every probe owns a cube map at `resolution` and a
+/// smaller, blurred one which is used for rough surfaces and the ambient light.
+#[derive(Clone)]
+pub struct ReflectionProbeSettings {
+    ///The size of one cube face in pixel
+    resolution: u32,
+    ///How many of the probes nearest to the camera are captured and used
+    max_probes: u32,
+    ///How many cube faces may be captured per frame, all probes share this budget
+    faces_per_frame: u32,
+}
+
+impl ReflectionProbeSettings{
+    ///Creates a new set of settings from the supplied values
+    pub fn new(resolution: u32, max_probes: u32, faces_per_frame: u32) -> Self{
+        ReflectionProbeSettings{
+            resolution,
+            max_probes,
+            faces_per_frame,
+        }
+    }
+
+    ///Creates a default set as follows:
+    /// - resolution: 256
+    /// - max_probes: 8
+    /// - faces_per_frame: 6
+    pub fn default() -> Self{
+        ReflectionProbeSettings{
+            resolution: 256,
+            max_probes: 8,
+            faces_per_frame: 6,
+        }
+    }
+
+    ///The size of a cube face, can't be changed after starting the engine
+    pub fn get_resolution(&self) -> u32{
+        self.resolution
+    }
+    pub fn set_resolution(&mut self, new: u32){
+        self.resolution = new;
+    }
+    ///Sets how many of the nearest probes are used, 0 turns them off.
+    pub fn set_max_probes(&mut self, new: u32){
+        self.max_probes = new;
+    }
+    pub fn get_max_probes(&self) -> u32{
+        self.max_probes
+    }
+    ///Sets how many cube faces may be captured per frame. A lower value spreads the capture of
+    /// a probe over several frames.
+    pub fn set_faces_per_frame(&mut self, new: u32){
+        self.faces_per_frame = new;
+    }
+    pub fn get_faces_per_frame(&self) -> u32{
+        self.faces_per_frame
+    }
+}
+
 ///Descibes settings the renderer can have. Most of the values can't be changed after
 /// starting the engine.
 ///Things to keep in mind:
@@ -269,6 +428,12 @@ pub struct RenderSettings {
     ///Defines the bloom settings. Mainly strength and scale.
     bloom: BloomSettings,
 
+    ///Defines the focus and strength of the depth of field
+    dof: DofSettings,
+
+    ///Defines how reflection probes are captured
+    probe_settings: ReflectionProbeSettings,
+
     ///Describes the several debug settings one cna change
     debug_settings: DebugSettings,
 
@@ -308,6 +473,10 @@ impl RenderSettings{
                 brightness: 1.0,
             },
 
+            dof: DofSettings::default(),
+
+            probe_settings: ReflectionProbeSettings::default(),
+
             debug_settings: DebugSettings{
                 draw_bounds: false,
                 debug_view: DebugView::Shaded,
@@ -534,6 +703,44 @@ impl RenderSettings{
         &mut self.bloom
     }
 
+    ///Sets the depth of field settings when building the rendering settings
+    #[inline]
+    pub fn with_dof(mut self, new: DofSettings) -> Self{
+        self.dof = new;
+        self
+    }
+
+    ///Returns the current depth of field settings. They might change per frame.
+    #[inline]
+    pub fn get_dof(&self) -> DofSettings{
+        self.dof.clone()
+    }
+
+    ///Returns the current depth of field settings as mutable reference.
+    #[inline]
+    pub fn get_dof_mut(&mut self) -> &mut DofSettings{
+        &mut self.dof
+    }
+
+    ///Sets the reflection probe settings when building the rendering settings
+    #[inline]
+    pub fn with_probe_settings(mut self, new: ReflectionProbeSettings) -> Self{
+        self.probe_settings = new;
+        self
+    }
+
+    ///Returns the current reflection probe settings as a clone.
+    #[inline]
+    pub fn get_probe_settings(&self) -> ReflectionProbeSettings{
+        self.probe_settings.clone()
+    }
+
+    ///Returns the current reflection probe settings as mutable reference.
+    #[inline]
+    pub fn get_probe_settings_mut(&mut self) -> &mut ReflectionProbeSettings{
+        &mut self.probe_settings
+    }
+
 }
 
 ///Tests for power of two
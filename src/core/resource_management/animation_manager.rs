@@ -0,0 +1,260 @@
+use core::resources::skeleton::Skeleton;
+use core::resources::animation::{AnimationClip, AnimationPlayer};
+use core::resources::mesh;
+use tools::math::time_tools::dur_as_f32;
+
+use cgmath::Matrix4;
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Instant;
+
+///Manages skeletons, animation clips and the players which currently animate a skeleton.
+/// Is updated by the asset manager, which deforms all skinned meshes by their skeletons.
+pub struct AnimationManager {
+    ///Skeletons by name, an instance of a skeleton shares the skeleton of its source
+    skeletons: BTreeMap<String, Arc<Skeleton>>,
+    clips: BTreeMap<String, Arc<AnimationClip>>,
+    ///The active player per skeleton name
+    players: BTreeMap<String, AnimationPlayer>,
+    ///All meshes which are deformed by a skeleton, they are weak to not keep unloaded meshes alive
+    skinned_meshes: Vec<Weak<Mutex<mesh::Mesh>>>,
+
+    last_update: Instant,
+}
+
+impl AnimationManager{
+    pub fn new() -> Self{
+        AnimationManager{
+            skeletons: BTreeMap::new(),
+            clips: BTreeMap::new(),
+            players: BTreeMap::new(),
+            skinned_meshes: Vec::new(),
+            last_update: Instant::now(),
+        }
+    }
+
+    ///Adds a skeleton, an existing one with the same name is replaced.
+    pub fn add_skeleton(&mut self, skeleton: Skeleton){
+        self.skeletons.insert(skeleton.name.clone(), Arc::new(skeleton));
+    }
+
+    ///Returns the skeleton called `name`
+    pub fn get_skeleton(&self, name: &str) -> Option<Arc<Skeleton>>{
+        self.skeletons.get(name).cloned()
+    }
+
+    ///Adds a new instance of the skeleton `skeleton` and returns its name. An instance can be
+    /// animated on its own, its meshes are created by `mesh::Mesh::copy_for_skeleton()`.
+    pub fn add_skeleton_instance(&mut self, skeleton: &str) -> Result<String, String>{
+        let source = match self.get_skeleton(skeleton){
+            Some(source) => source,
+            None => return Err(format!("there is no skeleton called {}", skeleton)),
+        };
+
+        let mut index = 0;
+        let mut instance_name = format!("{}@{}", skeleton, index);
+        while self.skeletons.contains_key(&instance_name){
+            index += 1;
+            instance_name = format!("{}@{}", skeleton, index);
+        }
+        self.skeletons.insert(instance_name.clone(), source);
+        Ok(instance_name)
+    }
+
+    ///Returns the names of the skeleton `skeleton` and all of its instances
+    pub fn get_skeleton_instances(&self, skeleton: &str) -> Vec<String>{
+        self.skeletons.iter()
+        .filter(|&(_, instance)| instance.name == skeleton)
+        .map(|(name, _)| name.clone())
+        .collect()
+    }
+
+    ///Adds an animation clip, an existing one with the same name is replaced.
+    pub fn add_clip(&mut self, clip: AnimationClip){
+        self.clips.insert(clip.name.clone(), Arc::new(clip));
+    }
+
+    ///Returns the clip called `name`
+    pub fn get_clip(&self, name: &str) -> Option<Arc<AnimationClip>>{
+        self.clips.get(name).cloned()
+    }
+
+    ///Returns the names of all clips which animate the skeleton called `skeleton`
+    pub fn get_clip_names_for(&self, skeleton: &str) -> Vec<String>{
+        self.clips.iter()
+        .filter(|&(_, clip)| clip.skeleton == skeleton)
+        .map(|(name, _)| name.clone())
+        .collect()
+    }
+
+    ///Registers a mesh which is deformed whenever its skeleton is animated
+    pub fn add_skinned_mesh(&mut self, mesh: Arc<Mutex<mesh::Mesh>>){
+        self.skinned_meshes.push(Arc::downgrade(&mesh));
+    }
+
+    ///Starts to play the clip called `clip_name` on its skeleton and all instances of it.
+    /// Replaces the clip which was played before on them.
+    pub fn play(&mut self, clip_name: &str, looping: bool) -> Result<(), String>{
+        let skeleton = match self.get_clip(clip_name){
+            Some(clip) => clip.skeleton.clone(),
+            None => return Err(format!("there is no animation clip called {}", clip_name)),
+        };
+        let instances = self.get_skeleton_instances(&skeleton);
+        if instances.is_empty(){
+            return Err(format!("the skeleton {} of clip {} is unknown", skeleton, clip_name));
+        }
+
+        for instance in instances.iter(){
+            self.play_on(instance, clip_name, looping)?;
+        }
+        Ok(())
+    }
+
+    ///Starts to play the clip called `clip_name` on the skeleton (or skeleton instance)
+    /// `skeleton` only. The clip has to animate the skeleton the instance was created from.
+    pub fn play_on(&mut self, skeleton: &str, clip_name: &str, looping: bool) -> Result<(), String>{
+        let clip = match self.get_clip(clip_name){
+            Some(clip) => clip,
+            None => return Err(format!("there is no animation clip called {}", clip_name)),
+        };
+        let skeleton_data = match self.get_skeleton(skeleton){
+            Some(skeleton_data) => skeleton_data,
+            None => return Err(format!("the skeleton {} is unknown", skeleton)),
+        };
+        if skeleton_data.name != clip.skeleton{
+            return Err(format!("clip {} animates {}, not {}", clip_name, clip.skeleton, skeleton_data.name));
+        }
+
+        let mut player = AnimationPlayer::new(skeleton_data, clip);
+        player.looping = looping;
+        self.players.insert(String::from(skeleton), player);
+        Ok(())
+    }
+
+    ///Stops the animation of `skeleton`, the meshes keep their current pose.
+    pub fn stop(&mut self, skeleton: &str){
+        self.players.remove(skeleton);
+    }
+
+    ///Returns the player of `skeleton` if it is animated at the moment
+    pub fn get_player(&mut self, skeleton: &str) -> Option<&mut AnimationPlayer>{
+        self.players.get_mut(skeleton)
+    }
+
+    ///Returns the current joint matrices of `skeleton` if it is animated at the moment
+    pub fn get_joint_matrices(&self, skeleton: &str) -> Option<Vec<Matrix4<f32>>>{
+        self.players.get(skeleton).map(|player| player.get_joint_matrices().clone())
+    }
+
+    ///Advances all players and deforms the skinned meshes of the playing skeletons. Returns true
+    /// if a mesh was deformed, the bounds of the scene containing it are outdated then.
+    pub fn update(&mut self) -> bool{
+        let delta = dur_as_f32(self.last_update.elapsed());
+        self.last_update = Instant::now();
+
+        let mut changed_skeletons = Vec::new();
+        for (skeleton, player) in self.players.iter_mut(){
+            if player.is_playing(){
+                player.update(delta);
+                changed_skeletons.push(skeleton.clone());
+            }
+        }
+
+        //forget the meshes which were unloaded
+        let skinned_meshes: Vec<Arc<Mutex<mesh::Mesh>>> = self.skinned_meshes.iter()
+        .filter_map(|mesh| mesh.upgrade())
+        .collect();
+        self.skinned_meshes = skinned_meshes.iter().map(|mesh| Arc::downgrade(mesh)).collect();
+
+        let mut has_deformed = false;
+        for mesh in skinned_meshes.iter(){
+            let mut mesh_lck = mesh.lock().expect("failed to lock skinned mesh");
+            let skeleton = match mesh_lck.get_skeleton_name(){
+                Some(name) => name,
+                None => continue,
+            };
+            if !changed_skeletons.contains(&skeleton){
+                continue;
+            }
+            if let Some(player) = self.players.get(&skeleton){
+                mesh_lck.apply_skinning(player.get_joint_matrices());
+                has_deformed = true;
+            }
+        }
+        has_deformed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::resources::skeleton::Joint;
+    use core::resources::animation::{Channel, ChannelValues, Interpolation};
+    use core::next_tree::transform::NodeTransform;
+    use core::ReturnBoundInfo;
+    use cgmath::*;
+
+    fn test_manager() -> AnimationManager{
+        let mut skeleton = Skeleton::new("skeleton");
+        skeleton.add_joint(Joint::new("root").with_rest_transform(NodeTransform::one()));
+
+        let mut manager = AnimationManager::new();
+        manager.add_skeleton(skeleton);
+        //moves the root by 2 along x from the start
+        manager.add_clip(AnimationClip::new("move", "skeleton", vec![Channel{
+            joint: 0,
+            times: vec![0.0, 1.0],
+            values: ChannelValues::Translation(vec![Vector3::new(2.0, 0.0, 0.0), Vector3::new(2.0, 0.0, 0.0)]),
+            interpolation: Interpolation::Linear,
+        }]));
+        manager
+    }
+
+    fn skinned_mesh() -> mesh::Mesh{
+        let mut mesh = mesh::Mesh::new_cpu_only("skinned");
+        let vertex = |x: f32| mesh::Vertex::new([x, 0.0, 0.0], [0.0; 2], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0, 1.0], [1.0; 4]);
+        mesh.set_cpu_vertices_and_indices(vec![vertex(0.0), vertex(1.0), vertex(2.0)], vec![0, 1, 2]);
+        mesh.set_skin(vec![mesh::SkinVertex::new([0; 4], [1.0, 0.0, 0.0, 0.0]); 3], "skeleton");
+        mesh
+    }
+
+    #[test]
+    fn instances_are_animated_on_their_own(){
+        let mut manager = test_manager();
+        let instance = manager.add_skeleton_instance("skeleton").expect("failed to add instance");
+        assert_eq!(manager.get_skeleton_instances("skeleton").len(), 2);
+
+        let original = Arc::new(Mutex::new(skinned_mesh()));
+        let copy = Arc::new(Mutex::new(original.lock().unwrap().copy_for_skeleton(&instance)));
+        manager.add_skinned_mesh(original.clone());
+        manager.add_skinned_mesh(copy.clone());
+
+        manager.play_on(&instance, "move", false).expect("failed to play clip");
+        assert!(manager.update());
+
+        //only the copy moved, its bound follows the deformed vertices
+        assert_eq!(original.lock().unwrap().get_all_positions()[0], [0.0, 0.0, 0.0]);
+        let copy_lck = copy.lock().unwrap();
+        assert_eq!(copy_lck.get_all_positions()[0], [2.0, 0.0, 0.0]);
+        assert_eq!(copy_lck.get_bound().min, Point3::new(2.0, 0.0, 0.0));
+        assert_eq!(copy_lck.get_bound().max, Point3::new(4.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn play_starts_all_instances(){
+        let mut manager = test_manager();
+        let instance = manager.add_skeleton_instance("skeleton").expect("failed to add instance");
+        manager.play("move", true).expect("failed to play clip");
+        assert!(manager.get_joint_matrices("skeleton").is_some());
+        assert!(manager.get_joint_matrices(&instance).is_some());
+    }
+
+    #[test]
+    fn clips_only_play_on_their_skeleton(){
+        let mut manager = test_manager();
+        manager.add_skeleton(Skeleton::new("other"));
+        assert!(manager.play_on("other", "move", false).is_err());
+        assert!(manager.add_skeleton_instance("unknown").is_err());
+    }
+}
@@ -1,16 +1,20 @@
 use std::sync::{Mutex, Arc, MutexGuard};
-use std::thread;
 use std::time::Instant;
+use std::panic;
+use std::sync::mpsc::Receiver;
 
 use jakar_tree::*;
 use jakar_tree::node::Attribute;
+use jakar_threadpool;
 use core::next_tree::content;
 use core::next_tree::attributes;
 use core::next_tree::jobs;
 use core::next_tree::*;
 use core::next_tree::{JakarNode, SceneTree};
 use core::next_tree::content::ContentType;
+use core::next_tree::transform::NodeTransform;
 use core::next_tree::node_controller::camera_controller::CameraController;
+use core::next_tree::behaviour::Behaviour;
 
 use tools::engine_state_machine::AssetUpdateState;
 
@@ -18,13 +22,26 @@ use core::resource_management::texture_manager;
 use core::resource_management::material_manager;
 use core::resource_management::mesh_manager;
 use tools::gltf_importer;
+use tools::gltf_exporter;
+use tools::obj_importer;
 use core::resource_management::scene_manager;
+use core::resource_management::animation_manager;
+use core::resource_management::ManagerAndRenderInfo;
+use core::resource_management::load_handle::{LoadHandle, LoadState};
+use core::resource_management::hot_reload::HotReloader;
+use core::resource_management::memory_budget::MemoryUsage;
+use core::resource_management::behaviour_manager::BehaviourManager;
+use core::resource_management::command_queue::{CommandQueue, SceneCommand, QueuedCommand, CommandState, CommandOutput};
 use core::resources::camera::Camera;
 use core::resources::camera::DefaultCamera;
 use core::engine_settings;
+use core::asset_error::AssetError;
+use core::event_bus::{EventBus, AssetLoadedEvent};
 use core::resources::texture;
+use core::resources::environment::Environment;
 use core::resources::material;
 use core::resources::empty;
+use core::resources::prefab::Prefab;
 use render;
 
 
@@ -45,26 +62,31 @@ pub struct AssetManager {
     //Holds the current active scene
     active_main_scene: tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>,
 
-    //holds all textures
-    texture_manager: Arc<Mutex<texture_manager::TextureManager>>,
+    //holds all textures, is `None` if the engine runs headless
+    texture_manager: Option<Arc<Mutex<texture_manager::TextureManager>>>,
 
-    //Holds the current material manager
-    material_manager: Arc<Mutex<material_manager::MaterialManager>>,
+    //Holds the current material manager, is `None` if the engine runs headless
+    material_manager: Option<Arc<Mutex<material_manager::MaterialManager>>>,
     //hold all meshes
     mesh_manager: Arc<Mutex<mesh_manager::MeshManager>>,
     //hoolds all scenes
     scene_manager: Arc<Mutex<scene_manager::SceneManager>>,
+    //holds all skeletons and animations
+    animation_manager: Arc<Mutex<animation_manager::AnimationManager>>,
 
     ///Holds a reference to the renderer
-    //things needed to create vulkano dependend data like textures and materials
-    pipeline_manager: Arc<Mutex<pipeline_manager::PipelineManager>>,
-    device: Arc<vulkano::device::Device>,
-    queue: Arc<vulkano::device::Queue>,
-    uniform_manager: Arc<Mutex<uniform_manager::UniformManager>>,
+    //things needed to create vulkano dependend data like textures and materials. They are all
+    // `None` if the engine runs headless.
+    pipeline_manager: Option<Arc<Mutex<pipeline_manager::PipelineManager>>>,
+    device: Option<Arc<vulkano::device::Device>>,
+    queue: Option<Arc<vulkano::device::Queue>>,
+    uniform_manager: Option<Arc<Mutex<uniform_manager::UniformManager>>>,
 
 
     ///Holds the current active camera, if non is set, falls back to a custom one
     active_camera: Option<String>,
+    ///The sky and image based lighting of the active scene
+    environment: Option<Environment>,
     fall_back: JakarNode,
 
     settings: Arc<Mutex<engine_settings::EngineSettings>>,
@@ -75,6 +97,19 @@ pub struct AssetManager {
     ///Documents the current state of the asset manager
     state: Arc<Mutex<AssetUpdateState>>,
 
+    //The imports are executed on this pool
+    thread_pool: Arc<Mutex<jakar_threadpool::ThreadPool>>,
+    //Scenes which finished loading, they are handed to the scene manager on the next update
+    loaded_scenes: Arc<Mutex<Vec<(LoadHandle, tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>)>>>,
+    //Watches the files of the loaded textures and meshes
+    hot_reloader: Arc<Mutex<HotReloader>>,
+    //Calls the behaviours of the nodes in the active scene
+    behaviour_manager: Arc<Mutex<BehaviourManager>>,
+    //Changes of the active scene, applied at the start of each update
+    command_queue: CommandQueue,
+    command_receiver: Arc<Mutex<Receiver<QueuedCommand>>>,
+    //Is told about finished imports
+    event_bus: EventBus,
 
 }
 
@@ -88,17 +123,10 @@ impl AssetManager {
         uniform_manager: Arc<Mutex<uniform_manager::UniformManager>>,
         settings: Arc<Mutex<engine_settings::EngineSettings>>,
         key_map: Arc<Mutex<KeyMap>>,
+        thread_pool: Arc<Mutex<jakar_threadpool::ThreadPool>>,
+        event_bus: EventBus,
     )->Self{
 
-
-        //The camera will be moved to a camera manager
-        let camera = DefaultCamera::new(settings.clone(), key_map.clone());
-        let mut fallback_camera_node = node::Node::new(
-            ContentType::Camera(camera), attributes::NodeAttributes::default()
-        );
-
-        fallback_camera_node.set_controller(CameraController::new(key_map.clone()));
-
         //Start up the texture manager
         let mut tmp_texture_manager = texture_manager::TextureManager::new(
             device.clone(), queue.clone(), settings.clone()
@@ -117,10 +145,64 @@ impl AssetManager {
             none_texture,
         );
 
+        AssetManager::from_managers(
+            Some(Arc::new(Mutex::new(tmp_texture_manager))),
+            Some(Arc::new(Mutex::new(tmp_material_manager))),
+            Some(pipeline_manager),
+            Some(device),
+            Some(queue),
+            Some(uniform_manager),
+            settings,
+            key_map,
+            thread_pool,
+            event_bus,
+        )
+    }
 
+    ///Creates an asset manager which doesn't need a gpu. It has no texture and material manager
+    /// and all meshes it imports only exist on the cpu.
+    pub fn new_headless(
+        settings: Arc<Mutex<engine_settings::EngineSettings>>,
+        key_map: Arc<Mutex<KeyMap>>,
+        thread_pool: Arc<Mutex<jakar_threadpool::ThreadPool>>,
+        event_bus: EventBus,
+    ) -> Self{
+        AssetManager::from_managers(None, None, None, None, None, None, settings, key_map, thread_pool, event_bus)
+    }
+
+    ///Creates the scene related parts of the asset manager, which are the same for the
+    /// normal and the headless mode.
+    fn from_managers(
+        texture_manager: Option<Arc<Mutex<texture_manager::TextureManager>>>,
+        material_manager: Option<Arc<Mutex<material_manager::MaterialManager>>>,
+        pipeline_manager: Option<Arc<Mutex<pipeline_manager::PipelineManager>>>,
+        device: Option<Arc<vulkano::device::Device>>,
+        queue: Option<Arc<vulkano::device::Queue>>,
+        uniform_manager: Option<Arc<Mutex<uniform_manager::UniformManager>>>,
+        settings: Arc<Mutex<engine_settings::EngineSettings>>,
+        key_map: Arc<Mutex<KeyMap>>,
+        thread_pool: Arc<Mutex<jakar_threadpool::ThreadPool>>,
+        event_bus: EventBus,
+    ) -> Self{
+
+        //The camera will be moved to a camera manager
+        let camera = DefaultCamera::new(settings.clone(), key_map.clone());
+        let mut fallback_camera_node = node::Node::new(
+            ContentType::Camera(camera), attributes::NodeAttributes::default()
+        );
+
+        fallback_camera_node.set_controller(CameraController::new(key_map.clone()));
+
+        let mesh_manager = Arc::new(Mutex::new(mesh_manager::MeshManager::new()));
 
         //create a empty scene manager
-        let new_scene_manager = Arc::new(Mutex::new(scene_manager::SceneManager::new()));
+        let new_scene_manager = Arc::new(Mutex::new(scene_manager::SceneManager::new(
+            mesh_manager.clone(),
+            material_manager.clone(),
+            settings.clone(),
+            key_map.clone(),
+            event_bus.clone(),
+        )));
 
 
         //create an empty main scene
@@ -129,12 +211,15 @@ impl AssetManager {
         let root_node = content::ContentType::Empty(empty);
         let main_scene = tree::Tree::new(root_node, attributes::NodeAttributes::default());
 
+        let (command_queue, command_receiver) = CommandQueue::new();
+
         AssetManager{
             active_main_scene: main_scene,
-            texture_manager: Arc::new(Mutex::new(tmp_texture_manager)),
-            material_manager: Arc::new(Mutex::new(tmp_material_manager)),
-            mesh_manager: Arc::new(Mutex::new(mesh_manager::MeshManager::new())),
+            texture_manager: texture_manager,
+            material_manager: material_manager,
+            mesh_manager: mesh_manager,
             scene_manager: new_scene_manager,
+            animation_manager: Arc::new(Mutex::new(animation_manager::AnimationManager::new())),
 
             pipeline_manager: pipeline_manager,
             device: device,
@@ -142,12 +227,21 @@ impl AssetManager {
             uniform_manager: uniform_manager,
 
             active_camera: None,
+            environment: None,
             fall_back: fallback_camera_node,
 
             settings: settings,
             key_map: key_map.clone(),
 
             state: Arc::new(Mutex::new(AssetUpdateState::wait())),
+
+            thread_pool: thread_pool,
+            loaded_scenes: Arc::new(Mutex::new(Vec::new())),
+            hot_reloader: Arc::new(Mutex::new(HotReloader::new())),
+            behaviour_manager: Arc::new(Mutex::new(BehaviourManager::new())),
+            command_queue: command_queue,
+            command_receiver: Arc::new(Mutex::new(command_receiver)),
+            event_bus: event_bus,
         }
     }
 
@@ -155,12 +249,12 @@ impl AssetManager {
     ///Updates all child components
     pub fn update(&mut self){
 
-        let (mut time_stamp, start_time, should_cap) = {
+        let (mut time_stamp, start_time, should_cap, hot_reload) = {
             let set_lck = self.settings.lock().expect("failed to lock engine settings");
             let sh_cap = set_lck.capture_frame.clone();
             let time_step = Instant::now();
 
-            (time_step, Instant::now(), sh_cap)
+            (time_step, Instant::now(), sh_cap, set_lck.hot_reload)
         };
         //Show the other system that we are working
         self.set_working();
@@ -174,12 +268,48 @@ impl AssetManager {
         }
 
         self.fall_back.update(0.0, &Vec::new());
+
+        //Hand the finished imports to the scene manager
+        self.add_loaded_scenes();
+
+        //Spawn and despawn what was queued since the last update, then run the gameplay logic
+        self.apply_commands();
+        {
+            let key_map = self.get_keymap();
+            let mut behaviour_lck = self.behaviour_manager.lock().expect("failed to lock behaviour manager");
+            behaviour_lck.update(&mut self.active_main_scene, &key_map, &self.command_queue);
+        }
+
+        //Swap changed textures and meshes before the materials are updated
+        if let Some(interval) = hot_reload{
+            let managers = self.get_managers();
+            let managers_lck = managers.lock().expect("failed to lock managers");
+            let mut reloader_lck = self.hot_reloader.lock().expect("failed to lock hot reloader");
+            reloader_lck.update(&managers_lck, interval);
+        }
         //println!("STATUS: ASSET_MANAGER: Now I'll update the materials", );
         //Update materials
-        self.get_material_manager().update();
+        if !self.is_headless(){
+            self.get_material_manager().update();
+        }
         //self.material_manager.update();
         //println!("STATUS: ASSET_MANAGER: Finished materials", );
 
+        //Evict unused resources if a manager is over its budget
+        self.get_mesh_manager().enforce_memory_budget();
+        if !self.is_headless(){
+            self.get_texture_manager().enforce_memory_budget();
+        }
+
+        //Play the animations and deform the skinned meshes, their bounds change with the pose
+        let has_deformed = {
+            let mut animation_lck = self.animation_manager.lock().expect("failed to lock animation manager");
+            animation_lck.update()
+        };
+        if has_deformed{
+            self.active_main_scene.rebuild_bounds();
+        }
+
         if should_cap{
             println!(
                 "\t \t AS: needed {}ms to update material manager",
@@ -235,6 +365,12 @@ impl AssetManager {
         scene_lock
     }
 
+    ///Returns the animation manager as a locked mutex, need to be returned
+    #[inline]
+    pub fn get_animation_manager<'a>(&'a mut self) -> MutexGuard<'a, animation_manager::AnimationManager>{
+        self.animation_manager.lock().expect("failed to hold lock for animation manager")
+    }
+
     ///Returns the camera in use TODO this will be managed by a independent camera manager in the future
     pub fn get_camera(&mut self) -> &mut DefaultCamera{
 
@@ -259,6 +395,28 @@ impl AssetManager {
         self.active_main_scene = new_scene_root;
     }
 
+    ///Casts a ray from the current mouse location through the active camera into the active
+    /// scene. Returns the closest node which fulfills `sorting` and is closer than the far plane.
+    /// Can be used to select objects with the mouse.
+    pub fn pick_at_mouse(&mut self, sorting: &Option<SceneComparer>) -> Option<raycast::RayHit>{
+        let key_map = {
+            let key_map_lck = self.key_map.lock().expect("failed to lock key map");
+            *key_map_lck
+        };
+
+        let (view_projection, far_plane) = {
+            let camera = self.get_camera();
+            (camera.get_view_projection_matrix(), camera.get_near_far().far_plane)
+        };
+
+        let ray = match raycast::pick_ray_from_key_map(&key_map, view_projection){
+            Some(ray) => ray,
+            None => return None,
+        };
+
+        self.active_main_scene.raycast(ray.origin, ray.direction, far_plane, sorting)
+    }
+
     ///Returns a reference to the active scene
     #[inline]
     pub fn get_active_scene(&mut self)
@@ -266,16 +424,28 @@ impl AssetManager {
         &mut self.active_main_scene
     }
 
-    //Returns a reference to the texture manager
+    ///Returns a reference to the texture manager
+    ///#panic panics if the engine runs headless
     #[inline]
     pub fn get_texture_manager(&mut self) -> MutexGuard<texture_manager::TextureManager>{
-        self.texture_manager.lock().expect("failed to lock texture manager")
+        self.texture_manager.as_ref()
+        .expect("there is no texture manager in headless mode")
+        .lock().expect("failed to lock texture manager")
     }
 
     ///Returns a reference to the material manager
+    ///#panic panics if the engine runs headless
     #[inline]
     pub fn get_material_manager(&mut self) -> MutexGuard<material_manager::MaterialManager>{
-        self.material_manager.lock().expect("failed to hold material manager")
+        self.material_manager.as_ref()
+        .expect("there is no material manager in headless mode")
+        .lock().expect("failed to hold material manager")
+    }
+
+    ///Returns true if this asset manager was created without a gpu
+    #[inline]
+    pub fn is_headless(&self) -> bool{
+        self.device.is_none()
     }
 
     ///Returns the mesh manager
@@ -300,52 +470,39 @@ impl AssetManager {
         self.active_main_scene.copy_all_nodes(&Some(new_sorter))
     }
 
-    ///Returns all meshes in the view frustum of the currently active camera
+    ///Returns all meshes in the view frustum and in the render layers of the currently active camera.
+    /// A layer mask of `sort_options` is combined with the render layers.
     #[inline]
     pub fn get_meshes_in_frustum(
         &mut self, sort_options: Option<SceneComparer>
     ) -> Vec<node::Node<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>>{
+        let render_layers = self.get_camera().get_render_layers();
         let new_sorter = {
             match sort_options{
                 Some(para) => para
                     .with_value_type(ValueTypeBool::none().with_mesh())
-                    .with_frustum(self.get_camera().get_frustum_bound()),
+                    .with_frustum(self.get_camera().get_frustum_bound())
+                    .within_layers(render_layers),
                 None => SceneComparer::new()
                     .with_value_type(ValueTypeBool::none().with_mesh())
                     .with_frustum(self.get_camera().get_frustum_bound())
+                    .with_layer(render_layers)
             }
         };
         self.active_main_scene.copy_all_nodes(&Some(new_sorter))
     }
 
-    ///Imports a new gltf scene file to a new scene with `name` as name from `path`
-    pub fn import_gltf(&mut self, name: &str, path: &str){
-        //Lock in scope to prevent dead lock while importing
-
-        let device_inst = {
-            self.device.clone()
-        };
-        let queue_inst = {
-            self.queue.clone()
-        };
-        let uniform_manager_inst = {
-            self.uniform_manager.clone()
-        };
-        let pipeline_manager_inst = {
-            self.pipeline_manager.clone()
-        };
-
-
-        use core;
-        let managers = Arc::new(Mutex::new(core::resource_management::ManagerAndRenderInfo{
+    ///Collects all managers and render infos an importer needs
+    fn get_managers(&self) -> Arc<Mutex<ManagerAndRenderInfo>>{
+        Arc::new(Mutex::new(ManagerAndRenderInfo{
             //The current pipeline manager
-            pipeline_manager: pipeline_manager_inst,
+            pipeline_manager: self.pipeline_manager.clone(),
             //The current uniform manager
-            uniform_manager: uniform_manager_inst,
+            uniform_manager: self.uniform_manager.clone(),
             //The current device used for rendering
-            device: device_inst,
+            device: self.device.clone(),
             //The currently used queues
-            queue: queue_inst,
+            queue: self.queue.clone(),
             //The current texture manager
             texture_manager: self.texture_manager.clone(),
             //The current material manager
@@ -354,24 +511,151 @@ impl AssetManager {
             mesh_manager: self.mesh_manager.clone(),
             //The current scene manager
             scene_manager: self.scene_manager.clone(),
-        }));
+            //The current animation manager
+            animation_manager: self.animation_manager.clone(),
+        }))
+    }
+
+    ///Runs `loader` on the thread pool. The returned handle reports the progress, the loaded
+    /// scene is handed to the scene manager on the next update.
+    ///The importers only lock the shared managers to look up or add a finished asset, files are
+    /// read and decoded without holding them. A panic while importing is caught and reported as
+    /// failed load, it can't poison the managers the rest of the engine uses.
+    fn load_async<F>(&mut self, name: &str, path: &str, loader: F) -> LoadHandle
+    where F: FnOnce(&str, &str, Arc<Mutex<ManagerAndRenderInfo>>, Option<&LoadHandle>)
+        -> Result<tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>, AssetError>
+        + Send + 'static
+    {
+        let handle = LoadHandle::new(name, path);
+        //Lock in scope to prevent dead lock while importing
+        let managers = self.get_managers();
+        let import_managers = managers.clone();
+        let loaded_scenes = self.loaded_scenes.clone();
+        let event_bus = self.event_bus.clone();
 
         let path_inst = path.to_owned();
         let name_inst = name.to_owned();
-        //now spawn a thread to load the gltf model
-        let _ = thread::spawn(move || {
-            gltf_importer::import_gltf(
-                &path_inst,
-                &name_inst,
-                managers,
-            );
+        let thread_handle = handle.clone();
+
+        let mut thread_pool_lck = self.thread_pool.lock().expect("failed to lock thread pool");
+        thread_pool_lck.execute(move ||{
+            thread_handle.set_progress(0.0);
+            //A broken file should not take down a pool thread
+            let result = panic::catch_unwind(panic::AssertUnwindSafe(||{
+                loader(&path_inst, &name_inst, import_managers, Some(&thread_handle))
+            }));
+
+            match result{
+                Ok(Ok(scene)) => {
+                    thread_handle.set_state(LoadState::Loaded);
+                    let mut loaded_lck = loaded_scenes.lock().expect("failed to lock loaded scenes");
+                    loaded_lck.push((thread_handle, scene));
+                },
+                Ok(Err(er)) => {
+                    println!("WARNING: ASSET_MANAGER: failed to load {}: {}", path_inst, er);
+                    thread_handle.set_state(LoadState::Failed(er.clone()));
+                    event_bus.publish(AssetLoadedEvent{
+                        name: name_inst.clone(),
+                        path: path_inst.clone(),
+                        result: Err(er),
+                    });
+                },
+                Err(payload) => {
+                    let er = AssetError::from_panic(&path_inst, payload);
+                    println!("WARNING: ASSET_MANAGER: {}", er);
+                    //The struct itself only belongs to this import, the managers in it are shared
+                    let poisoned = match managers.lock(){
+                        Ok(managers_lck) => managers_lck.get_poisoned_managers(),
+                        Err(poisoned_lck) => poisoned_lck.into_inner().get_poisoned_managers(),
+                    };
+                    if !poisoned.is_empty(){
+                        println!(
+                            "WARNING: ASSET_MANAGER: the import of {} panicked while holding the {} manager lock(s)",
+                            path_inst, poisoned.join(", ")
+                        );
+                    }
+                    thread_handle.set_state(LoadState::Failed(er.clone()));
+                    event_bus.publish(AssetLoadedEvent{
+                        name: name_inst.clone(),
+                        path: path_inst.clone(),
+                        result: Err(er),
+                    });
+                }
+            }
         });
+
+        handle
+    }
+
+    ///Adds all scenes which finished loading since the last update to the scene manager
+    fn add_loaded_scenes(&mut self){
+        let loaded = {
+            let mut loaded_lck = self.loaded_scenes.lock().expect("failed to lock loaded scenes");
+            loaded_lck.drain(..).collect::<Vec<_>>()
+        };
+
+        for (handle, scene) in loaded{
+            let scene_name = self.get_scene_manager().add_scene(scene);
+            handle.set_state(LoadState::Finished(scene_name.clone()));
+            self.event_bus.publish(AssetLoadedEvent{
+                name: handle.get_name(),
+                path: handle.get_path(),
+                result: Ok(scene_name),
+            });
+        }
+    }
+
+    ///Imports a new gltf scene file to a new scene with `name` as name from `path`. The import
+    /// runs on the thread pool, use the returned handle to check the progress. Once it is finished
+    /// the scene can be found in the scene manager under `handle.get_scene_name()`.
+    pub fn import_gltf(&mut self, name: &str, path: &str) -> LoadHandle{
+        self.load_async(name, path, gltf_importer::load_gltf)
+    }
+
+    ///Imports a Wavefront .obj file (and its .mtl files) to a new scene with `name` as name
+    /// from `path`. Every object / material group becomes a mesh node below the scene root.
+    /// Works like `import_gltf()`.
+    pub fn import_obj(&mut self, name: &str, path: &str) -> LoadHandle{
+        self.load_async(name, path, obj_importer::load_obj)
+    }
+
+
+    ///Checks the files of all loaded textures and glTF meshes right now and reloads the changed
+    /// ones in place. Returns the paths of the reloaded files. Works without enabling
+    /// `hot_reload` in the engine settings, but files are only compared from the second check on.
+    pub fn reload_changed_assets(&mut self) -> Vec<String>{
+        let managers = self.get_managers();
+        let managers_lck = managers.lock().expect("failed to lock managers");
+        let mut reloader_lck = self.hot_reloader.lock().expect("failed to lock hot reloader");
+        reloader_lck.reload_changed(&managers_lck)
     }
 
+    ///Saves the scene `name` from the local scene manager to a scene file at `path`.
+    #[inline]
+    pub fn save_scene(&mut self, name: &str, path: &str) -> Result<(), AssetError>{
+        self.get_scene_manager().save_scene(name, path)
+    }
+
+    ///Exports the active scene to a .gltf or .glb file at `path`, have a look at
+    /// `tools::gltf_exporter::export_gltf()` for what is written.
+    #[inline]
+    pub fn export_active_scene(&mut self, path: &str) -> Result<(), AssetError>{
+        gltf_exporter::export_gltf(&self.active_main_scene, path)
+    }
+
+    ///Loads a scene file from `path` into the local scene manager, returns the name it was added
+    /// under. The meshes used by the scene have to be loaded already.
+    #[inline]
+    pub fn load_scene(&mut self, path: &str) -> Result<String, AssetError>{
+        self.get_scene_manager().load_scene(path)
+    }
 
     ///Adds a scene from the local scene manager (based on `name`) to the local main scene
     /// at the `_root` node. If you want to add it at a specific node, do it like this:
     /// `get_active_scene().join(tree, node_name);`
+    ///NOTE: The scene is copied, use `add_prefab_instance()` to place a scene many times.
+    /// Skinned meshes of the copy get their own skeleton instance, see
+    /// `AnimationManager::get_skeleton_instances()` to animate them on their own.
     pub fn add_scene_to_main_scene(&mut self, name: &str)
      -> Result<(), tree::NodeErrors>
      {
@@ -384,9 +668,10 @@ impl AssetManager {
         match scene{
             Some(sc) =>{
                 //TODO make this to an Arc<GenericNode>
-                let scene_lck = sc.lock().expect("failed to hold scene lock while adding");
-                //Create a pass it to the main scene TODO make this reference the old scene
-                match self.active_main_scene.join_at_root(&(*scene_lck).clone()){
+                //Create a copy and pass it to the main scene
+                let mut scene_copy = sc.lock().expect("failed to hold scene lock while adding").clone();
+                self.instance_skinned_meshes(&mut scene_copy);
+                match self.active_main_scene.join_at_root(&scene_copy){
                     Ok(_) => {},
                     Err(r) => {
                         return Err(r);
@@ -403,6 +688,276 @@ impl AssetManager {
         Ok(())
     }
 
+    ///Replaces every skinned mesh in `scene` by a copy which is deformed by a new instance of
+    /// its skeleton. Otherwise all copies of a scene would share the meshes and show the same pose.
+    fn instance_skinned_meshes(
+        &mut self,
+        scene: &mut tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>
+    ){
+        let mesh_names = scene.get_all_names(
+            &Some(SceneComparer::new().with_value_type(ValueTypeBool::none().with_mesh()))
+        );
+
+        for node_name in mesh_names.iter(){
+            let node = match scene.get_node(node_name){
+                Some(node) => node,
+                None => continue,
+            };
+            let skinned_copy = match node.get_value_mut(){
+                &mut ContentType::Mesh(ref mesh) => {
+                    let mesh_lck = mesh.lock().expect("failed to lock mesh while copying the scene");
+                    let skeleton = match mesh_lck.get_skeleton_name(){
+                        Some(skeleton) => skeleton,
+                        None => continue,
+                    };
+                    let mut animation_lck = self.animation_manager.lock().expect("failed to lock animation manager");
+                    match animation_lck.add_skeleton_instance(&skeleton){
+                        Ok(instance) => {
+                            let copy = Arc::new(Mutex::new(mesh_lck.copy_for_skeleton(&instance)));
+                            animation_lck.add_skinned_mesh(copy.clone());
+                            copy
+                        },
+                        Err(er) => {
+                            println!("WARNING: ASSET_MANAGER: {} keeps its shared pose: {}", mesh_lck.name, er);
+                            continue;
+                        },
+                    }
+                },
+                _ => continue,
+            };
+
+            self.mesh_manager.lock().expect("failed to lock mesh manager").add_arc_mesh(skinned_copy.clone());
+            *node.get_value_mut() = ContentType::Mesh(skinned_copy);
+        }
+    }
+
+    ///Places an instance of the scene `scene_name` from the scene manager at `transform` in the
+    /// active scene. The scene is referenced instead of copied, its meshes are drawn instanced.
+    /// Returns the name of the new node.
+    pub fn add_prefab_instance(&mut self, scene_name: &str, transform: NodeTransform)
+     -> Result<String, tree::NodeErrors>
+     {
+        let mut names = self.add_prefab_instances(scene_name, &[transform])?;
+        Ok(names.remove(0))
+    }
+
+    ///Like `add_prefab_instance()`, but places one instance per transform and rebuilds the bounds
+    /// only once. Use this to place a lot of instances.
+    pub fn add_prefab_instances(&mut self, scene_name: &str, transforms: &[NodeTransform])
+     -> Result<Vec<String>, tree::NodeErrors>
+     {
+        let names = self.place_prefab_instances(scene_name, transforms)?;
+        self.get_active_scene().rebuild_bounds();
+        Ok(names)
+    }
+
+    ///Adds the instances of `add_prefab_instances()` without rebuilding the bounds
+    fn place_prefab_instances(&mut self, scene_name: &str, transforms: &[NodeTransform])
+     -> Result<Vec<String>, tree::NodeErrors>
+     {
+        let scene = match self.get_scene_manager().get_scene_arc(scene_name){
+            Some(scene) => scene,
+            None => return Err(tree::NodeErrors::NoNodeFound(format!("could not find the scene {}", scene_name))),
+        };
+
+        let prefab_name = String::from(scene_name) + "_instance";
+        let mut names = Vec::new();
+        for instance_transform in transforms.iter(){
+            let mut attributes = attributes::NodeAttributes::default();
+            attributes.transform = *instance_transform;
+            let content = ContentType::Prefab(Prefab::new(&prefab_name, scene_name, scene.clone()));
+            names.push(self.active_main_scene.add_at_root(content, Some(attributes))?);
+        }
+
+        Ok(names)
+    }
+
+    ///Attaches `behaviour` to the node `node_name` of the active scene. It is started on the next
+    /// update.
+    pub fn add_behaviour<B: Behaviour + 'static>(&mut self, node_name: &str, behaviour: B){
+        let mut behaviour_lck = self.behaviour_manager.lock().expect("failed to lock behaviour manager");
+        behaviour_lck.add_behaviour(node_name, Box::new(behaviour));
+    }
+
+    ///Destroys and removes all behaviours of the node `node_name`, returns how many were removed.
+    pub fn remove_behaviours(&mut self, node_name: &str) -> usize{
+        let key_map = self.get_keymap();
+        let mut behaviour_lck = self.behaviour_manager.lock().expect("failed to lock behaviour manager");
+        behaviour_lck.remove_behaviours(node_name, &mut self.active_main_scene, &key_map, &self.command_queue)
+    }
+
+    ///Returns the queue which can be used to change the active scene from any thread without
+    /// locking the asset manager. The commands are applied in batch at the start of the next
+    /// update, after finished imports were handed to the scene manager and before the behaviours
+    /// and the scene tree are updated.
+    pub fn get_command_queue(&self) -> CommandQueue{
+        self.command_queue.clone()
+    }
+
+    ///Removes the node `name` and its children from the active scene, the behaviours of all
+    /// removed nodes are destroyed first. Returns false if there is no such node or if `name` is
+    /// the root of the scene, which can't be removed.
+    pub fn despawn_node(&mut self, name: &str) -> bool{
+        if !self.remove_node_with_behaviours(name){
+            return false;
+        }
+        self.active_main_scene.rebuild_bounds();
+        true
+    }
+
+    ///Removes the node like `despawn_node()` without rebuilding the bounds
+    fn remove_node_with_behaviours(&mut self, name: &str) -> bool{
+        if self.active_main_scene.root_node.get_name() == name{
+            return false;
+        }
+
+        let removed_names = match self.active_main_scene.get_node(name){
+            Some(node) => node.get_all_names(&None),
+            None => return false,
+        };
+
+        for removed_name in removed_names.iter(){
+            self.remove_behaviours(removed_name);
+        }
+
+        self.active_main_scene.remove_node(name).is_some()
+    }
+
+    ///Applies all commands which were queued since the last update in the order they were queued.
+    /// The bounds are rebuilt once after the whole batch if a command changed the scene.
+    fn apply_commands(&mut self){
+        let commands = {
+            let receiver_lck = self.command_receiver.lock().expect("failed to lock command receiver");
+            receiver_lck.try_iter().collect::<Vec<_>>()
+        };
+
+        let mut bounds_changed = false;
+        for mut queued in commands.into_iter(){
+            let command = match queued.take_command(){
+                Some(command) => command,
+                None => continue,
+            };
+            let state = self.apply_command(command, &mut bounds_changed);
+            if let CommandState::Failed(ref reason) = state{
+                println!("WARNING: ASSET_MANAGER: command {} failed: {}", queued.handle.get_id(), reason);
+            }
+            queued.handle.set_state(state);
+        }
+
+        if bounds_changed{
+            self.active_main_scene.rebuild_bounds();
+        }
+    }
+
+    ///Applies a single command and returns its final state. Sets `bounds_changed` if the command
+    /// changed the scene, the bounds are not rebuilt here.
+    fn apply_command(&mut self, command: SceneCommand, bounds_changed: &mut bool) -> CommandState{
+        match command{
+            SceneCommand::Spawn{content, attributes, parent} => {
+                let result = match parent{
+                    Some(ref parent_name) => self.active_main_scene.add(content, parent_name.clone(), Some(attributes)),
+                    None => self.active_main_scene.add_at_root(content, Some(attributes)),
+                };
+                match result{
+                    Ok(name) => {
+                        *bounds_changed = true;
+                        CommandState::Applied(CommandOutput::Node(name))
+                    },
+                    Err(_) => CommandState::Failed(format!(
+                        "could not spawn the node below {}", parent.unwrap_or(String::from("the root"))
+                    )),
+                }
+            },
+            SceneCommand::SpawnPrefab{scene, transform} => {
+                match self.place_prefab_instances(&scene, &[transform]){
+                    Ok(mut names) => {
+                        *bounds_changed = true;
+                        CommandState::Applied(CommandOutput::Node(names.remove(0)))
+                    },
+                    Err(_) => CommandState::Failed(format!("could not spawn an instance of {}", scene)),
+                }
+            },
+            SceneCommand::Despawn(name) => {
+                if self.remove_node_with_behaviours(&name){
+                    *bounds_changed = true;
+                    CommandState::Applied(CommandOutput::Nothing)
+                }else{
+                    CommandState::Failed(format!("could not despawn {}, there is no such node", name))
+                }
+            },
+            SceneCommand::AddJob{node, job} => {
+                match self.active_main_scene.get_node(&node){
+                    Some(scene_node) => {
+                        scene_node.add_job(job);
+                        CommandState::Applied(CommandOutput::Nothing)
+                    },
+                    None => CommandState::Failed(format!("could not add a job to {}, there is no such node", node)),
+                }
+            },
+            SceneCommand::SetAttributes{node, mut change} => {
+                match self.active_main_scene.get_node(&node){
+                    Some(scene_node) => {
+                        //The change is user code, it should not take down the asset update
+                        let attributes = scene_node.get_attrib_mut();
+                        let result = panic::catch_unwind(panic::AssertUnwindSafe(||{
+                            change(attributes)
+                        }));
+                        //the change might have moved the node
+                        match result{
+                            Ok(_) => {
+                                *bounds_changed = true;
+                                CommandState::Applied(CommandOutput::Nothing)
+                            },
+                            Err(_) => CommandState::Failed(format!("the attribute change of {} panicked", node)),
+                        }
+                    },
+                    None => CommandState::Failed(format!("could not change the attributes of {}, there is no such node", node)),
+                }
+            },
+            SceneCommand::Import{name, path} => {
+                let handle = if path.to_lowercase().ends_with(".obj"){
+                    self.import_obj(&name, &path)
+                }else{
+                    self.import_gltf(&name, &path)
+                };
+                CommandState::Applied(CommandOutput::Import(handle))
+            },
+        }
+    }
+
+    ///Removes the scene `name` from the scene manager and unloads all meshes, materials and
+    /// textures which are not used anymore. Returns the names of the unloaded resources.
+    pub fn unload_scene(&mut self, name: &str) -> Result<Vec<String>, String>{
+        self.get_scene_manager().remove_scene(name)?;
+        Ok(self.collect_garbage())
+    }
+
+    ///Unloads every mesh, material and texture which is only referenced by its manager, returns
+    /// their names. Resources used by the active scene or a scene in the scene manager stay loaded.
+    pub fn collect_garbage(&mut self) -> Vec<String>{
+        //meshes keep their materials alive and materials their textures
+        let mut unloaded = self.get_mesh_manager().collect_garbage();
+        if !self.is_headless(){
+            unloaded.append(&mut self.get_material_manager().collect_garbage());
+            unloaded.append(&mut self.get_texture_manager().collect_garbage());
+        }
+        unloaded
+    }
+
+    ///Returns the memory used by the textures and meshes
+    pub fn get_memory_usage(&mut self) -> MemoryUsage{
+        let texture_bytes = if self.is_headless(){
+            0
+        }else{
+            self.get_texture_manager().get_memory_usage()
+        };
+
+        MemoryUsage{
+            texture_bytes: texture_bytes,
+            vertex_bytes: self.get_mesh_manager().get_memory_usage(),
+        }
+    }
+
     ///Returns true if a scene with `name` as name exists in the local scene manager
     #[inline]
     pub fn has_scene(&mut self, name: &str) -> bool{
@@ -410,41 +965,74 @@ impl AssetManager {
         scene_manager.has_scene(name.clone())
     }
 
-    ///Returns a texture builder for the specified image at `path`
-    pub fn create_texture(&mut self, path: &str) -> texture::TextureBuilder{
+    ///Returns a texture builder for the specified image at `path`, fails if the engine runs headless.
+    /// The image itself is read when the texture is built.
+    pub fn create_texture(&mut self, path: &str) -> Result<texture::TextureBuilder, AssetError>{
+        match (self.device.clone(), self.queue.clone()){
+            (Some(device), Some(queue)) => Ok(texture::TextureBuilder::from_image(path, device, queue)),
+            _ => Err(AssetError::Unsupported(format!("can't create the texture {} in headless mode", path))),
+        }
+    }
 
-        //lock the renderer
+    ///Returns a texture builder for a cube map made of the six images at `face_paths` in the order
+    /// +x, -x, +y, -y, +z, -z. Fails if the engine runs headless. Build it with
+    /// `build_environment()`.
+    pub fn create_cube_texture(&mut self, face_paths: [&str; 6]) -> Result<texture::TextureBuilder, AssetError>{
+        match (self.device.clone(), self.queue.clone()){
+            (Some(device), Some(queue)) => Ok(texture::TextureBuilder::from_cube_faces(face_paths, device, queue)),
+            _ => Err(AssetError::Unsupported(format!("can't create the cube texture {} in headless mode", face_paths[0]))),
+        }
+    }
 
-        //Create a second material
-        //create new texture
-        let new_texture = texture::TextureBuilder::from_image(
-            path,
-            self.device.clone(),
-            self.queue.clone(),
-        );
-        new_texture
+    ///Sets the environment which lights the active scene and is drawn as sky behind it, for
+    /// instance `asset_manager.create_texture("sky.hdr")?.build_environment("sky")?`.
+    pub fn set_environment(&mut self, environment: Environment){
+        self.environment = Some(environment);
+    }
+
+    ///Removes the environment, the scene gets the flat ambient light and a black background again
+    pub fn clear_environment(&mut self){
+        self.environment = None;
+    }
+
+    ///Returns a copy of the current environment, if there is one
+    pub fn get_environment(&self) -> Option<Environment>{
+        self.environment.clone()
+    }
+
+    ///Returns the current environment mutable, can be used to change its intensity
+    pub fn get_environment_mut(&mut self) -> Option<&mut Environment>{
+        self.environment.as_mut()
     }
 
     ///Takes a `texture::TextureBuilder` and adds the texture by `name` to the texture manager.
     ///builds the texture and adds it to the internal manager,
-    /// returns an error if the texture already exists
+    /// returns an error if the texture can't be built or already exists
     #[inline]
     pub fn add_texture_to_manager(
         &mut self, texture_builder: texture::TextureBuilder, tex_name: &str
-    ) -> Result<(), &'static str>
+    ) -> Result<(), AssetError>
     {
-        let final_texture = texture_builder.build_with_name(tex_name);
+        let final_texture = texture_builder.build_with_name(tex_name)?;
         self.get_texture_manager().add_texture(final_texture)
     }
 
     ///Takes an `material::MaterialBuilder` as well as the `name` for the new material
     ///and adds it to the internal manager. It assumes that this material is used on a mesh in the
     /// object pass as well as that it is opaque. It returns the name this material was actually added under.
+    /// Fails if the engine runs headless or the material can't be created.
     pub fn add_material_to_manager(&mut self, material: material::MaterialBuilder, name: &str)
-    -> String
+    -> Result<String, AssetError>
     {
+        let (pipeline_manager, uniform_manager, device) = match (
+            self.pipeline_manager.clone(), self.uniform_manager.clone(), self.device.clone()
+        ){
+            (Some(pipeline_manager), Some(uniform_manager), Some(device)) => (pipeline_manager, uniform_manager, device),
+            _ => return Err(AssetError::Unsupported(format!("can't create the material {} in headless mode", name))),
+        };
+
         let default_pipeline = {
-            let mut pipe_lck = self.pipeline_manager.lock().expect("failed to lock pipeline manager");
+            let mut pipe_lck = pipeline_manager.lock().expect("failed to lock pipeline manager");
             //Assume that we want a material for the object pass
             let config = render::pipeline_builder::PipelineConfig::default()
             .with_shader("Pbr".to_string())
@@ -455,11 +1043,16 @@ impl AssetManager {
         let final_material = material.build(
             name,
             default_pipeline,
-            self.uniform_manager.clone(),
-            self.device.clone(),
-        );
+            uniform_manager,
+            device,
+        )?;
+
+        Ok(self.get_material_manager().add_material(final_material))
+    }
 
-        self.get_material_manager().add_material(final_material)
+    ///Returns the event bus the asset manager publishes on
+    pub fn get_event_bus(&self) -> EventBus{
+        self.event_bus.clone()
     }
 
     ///A small helper function which returns the used engine settings, good if you have to transport
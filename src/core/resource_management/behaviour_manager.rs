@@ -0,0 +1,263 @@
+use jakar_tree::tree::Tree;
+use jakar_tree::node::Attribute;
+
+use core::next_tree::content::ContentType;
+use core::next_tree::jobs::SceneJobs;
+use core::next_tree::attributes::NodeAttributes;
+use core::next_tree::behaviour::{Behaviour, BehaviourContext};
+use core::resource_management::command_queue::CommandQueue;
+use input::keymap::KeyMap;
+use tools::math::time_tools::dur_as_f32;
+
+use std::collections::BTreeMap;
+use std::panic;
+use std::time::Instant;
+
+///One behaviour and its state
+struct BehaviourSlot {
+    behaviour: Box<Behaviour>,
+    is_started: bool,
+    ///Is set if a hook panicked, the behaviour is not called anymore
+    is_disabled: bool,
+}
+
+impl BehaviourSlot{
+    ///Calls `hook` on the behaviour, a panic disables the behaviour
+    fn call<F>(&mut self, node_name: &str, hook_name: &str, hook: F) where F: FnOnce(&mut Behaviour){
+        if self.is_disabled{
+            return;
+        }
+
+        let behaviour = &mut self.behaviour;
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(||{
+            hook(&mut **behaviour)
+        }));
+
+        if result.is_err(){
+            println!(
+                "WARNING: BEHAVIOUR_MANAGER: {} of a behaviour on {} panicked, the behaviour is disabled",
+                hook_name, node_name
+            );
+            self.is_disabled = true;
+        }
+    }
+}
+
+///Calls `on_destroy()` on all `slots` of the node `node_name`
+fn destroy_slots(
+    node_name: &str,
+    slots: &mut Vec<BehaviourSlot>,
+    attributes: &mut NodeAttributes,
+    key_map: &KeyMap,
+    commands: &CommandQueue,
+){
+    let mut context = BehaviourContext::new(node_name, attributes, key_map, commands);
+    for slot in slots.iter_mut(){
+        slot.call(node_name, "on_destroy", |behaviour| behaviour.on_destroy(&mut context));
+    }
+}
+
+///Holds the behaviours of the nodes in the active scene and calls their hooks. Is updated by the
+/// asset manager.
+pub struct BehaviourManager {
+    ///The behaviours per node name
+    behaviours: BTreeMap<String, Vec<BehaviourSlot>>,
+    last_update: Instant,
+}
+
+impl BehaviourManager{
+    pub fn new() -> Self{
+        BehaviourManager{
+            behaviours: BTreeMap::new(),
+            last_update: Instant::now(),
+        }
+    }
+
+    ///Attaches `behaviour` to the node `node_name`, it is started on the next update.
+    pub fn add_behaviour(&mut self, node_name: &str, behaviour: Box<Behaviour>){
+        self.behaviours.entry(String::from(node_name))
+        .or_insert(Vec::new())
+        .push(BehaviourSlot{
+            behaviour: behaviour,
+            is_started: false,
+            is_disabled: false,
+        });
+    }
+
+    ///Returns true if the node `node_name` has at least one behaviour
+    pub fn has_behaviours(&self, node_name: &str) -> bool{
+        self.behaviours.contains_key(node_name)
+    }
+
+    ///Returns the number of behaviours of `node_name` which are disabled because they panicked
+    pub fn get_disabled_count(&self, node_name: &str) -> usize{
+        match self.behaviours.get(node_name){
+            Some(slots) => slots.iter().filter(|slot| slot.is_disabled).count(),
+            None => 0,
+        }
+    }
+
+    ///Removes all behaviours of the node `node_name` after calling their `on_destroy()`. Returns
+    /// the number of removed behaviours.
+    pub fn remove_behaviours(
+        &mut self,
+        node_name: &str,
+        scene: &mut Tree<ContentType, SceneJobs, NodeAttributes>,
+        key_map: &KeyMap,
+        commands: &CommandQueue,
+    ) -> usize{
+        let mut slots = match self.behaviours.remove(node_name){
+            Some(slots) => slots,
+            None => return 0,
+        };
+
+        match scene.get_node(node_name){
+            Some(node) => destroy_slots(node_name, &mut slots, node.get_attrib_mut(), key_map, commands),
+            None => destroy_slots(node_name, &mut slots, &mut NodeAttributes::default(), key_map, commands),
+        }
+
+        slots.len()
+    }
+
+    ///Starts new behaviours and updates all others. Behaviours of nodes which don't exist anymore
+    /// are destroyed with default attributes.
+    pub fn update(
+        &mut self,
+        scene: &mut Tree<ContentType, SceneJobs, NodeAttributes>,
+        key_map: &KeyMap,
+        commands: &CommandQueue,
+    ){
+        let delta = dur_as_f32(self.last_update.elapsed());
+        self.last_update = Instant::now();
+
+        let mut removed_nodes = Vec::new();
+
+        for (node_name, slots) in self.behaviours.iter_mut(){
+            let node = match scene.get_node(node_name){
+                Some(node) => node,
+                None => {
+                    removed_nodes.push(node_name.clone());
+                    continue;
+                }
+            };
+
+            let jobs = {
+                let mut context = BehaviourContext::new(node_name, node.get_attrib_mut(), key_map, commands);
+                for slot in slots.iter_mut(){
+                    if !slot.is_started{
+                        slot.is_started = true;
+                        slot.call(node_name, "on_start", |behaviour| behaviour.on_start(&mut context));
+                    }
+                    slot.call(node_name, "on_update", |behaviour| behaviour.on_update(&mut context, delta));
+                }
+                context.into_jobs()
+            };
+
+            for job in jobs.into_iter(){
+                node.add_job(job);
+            }
+        }
+
+        for node_name in removed_nodes.iter(){
+            if let Some(mut slots) = self.behaviours.remove(node_name){
+                destroy_slots(node_name, &mut slots, &mut NodeAttributes::default(), key_map, commands);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::next_tree::SceneTree;
+    use core::next_tree::behaviour::ClosureBehaviour;
+    use core::resources::empty::Empty;
+
+    use std::sync::{Arc, Mutex};
+
+    ///A scene with one empty node below the root, returns the tree and the name of the node
+    fn test_scene() -> (Tree<ContentType, SceneJobs, NodeAttributes>, String){
+        let mut tree = Tree::new(ContentType::Empty(Empty::new("root")), NodeAttributes::default());
+        let name = tree.add_at_root(ContentType::Empty(Empty::new("door")), None).expect("failed to add node");
+        (tree, name)
+    }
+
+    ///A behaviour which pushes the name of every called hook to `log`
+    fn logging_behaviour(log: &Arc<Mutex<Vec<&'static str>>>) -> Box<Behaviour>{
+        let (start_log, update_log, destroy_log) = (log.clone(), log.clone(), log.clone());
+        Box::new(
+            ClosureBehaviour::new(move |_, _| update_log.lock().expect("failed to lock log").push("update"))
+            .with_start(move |_| start_log.lock().expect("failed to lock log").push("start"))
+            .with_destroy(move |_| destroy_log.lock().expect("failed to lock log").push("destroy"))
+        )
+    }
+
+    #[test]
+    fn panicking_update_only_disables_its_behaviour(){
+        let (mut scene, node_name) = test_scene();
+        let (commands, _receiver) = CommandQueue::new();
+        let key_map = KeyMap::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let mut manager = BehaviourManager::new();
+        manager.add_behaviour(&node_name, Box::new(ClosureBehaviour::new(|_, _| panic!("broken behaviour"))));
+        manager.add_behaviour(&node_name, logging_behaviour(&log));
+
+        manager.update(&mut scene, &key_map, &commands);
+        manager.update(&mut scene, &key_map, &commands);
+
+        assert_eq!(manager.get_disabled_count(&node_name), 1);
+        assert_eq!(*log.lock().expect("failed to lock log"), vec!["start", "update", "update"]);
+    }
+
+    #[test]
+    fn start_runs_once_before_the_first_update(){
+        let (mut scene, node_name) = test_scene();
+        let (commands, _receiver) = CommandQueue::new();
+        let key_map = KeyMap::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let mut manager = BehaviourManager::new();
+        manager.add_behaviour(&node_name, logging_behaviour(&log));
+        assert!(log.lock().expect("failed to lock log").is_empty());
+
+        manager.update(&mut scene, &key_map, &commands);
+        manager.update(&mut scene, &key_map, &commands);
+        assert_eq!(*log.lock().expect("failed to lock log"), vec!["start", "update", "update"]);
+    }
+
+    #[test]
+    fn removed_nodes_destroy_their_behaviours(){
+        let (mut scene, node_name) = test_scene();
+        let (commands, _receiver) = CommandQueue::new();
+        let key_map = KeyMap::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let mut manager = BehaviourManager::new();
+        manager.add_behaviour(&node_name, logging_behaviour(&log));
+        manager.update(&mut scene, &key_map, &commands);
+
+        assert!(scene.remove_node(&node_name).is_some());
+        manager.update(&mut scene, &key_map, &commands);
+
+        assert!(!manager.has_behaviours(&node_name));
+        assert_eq!(*log.lock().expect("failed to lock log"), vec!["start", "update", "destroy"]);
+    }
+
+    #[test]
+    fn removing_behaviours_destroys_them(){
+        let (mut scene, node_name) = test_scene();
+        let (commands, _receiver) = CommandQueue::new();
+        let key_map = KeyMap::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let mut manager = BehaviourManager::new();
+        manager.add_behaviour(&node_name, logging_behaviour(&log));
+        manager.add_behaviour(&node_name, logging_behaviour(&log));
+
+        assert_eq!(manager.remove_behaviours(&node_name, &mut scene, &key_map, &commands), 2);
+        assert!(!manager.has_behaviours(&node_name));
+        //never started behaviours are destroyed as well
+        assert_eq!(*log.lock().expect("failed to lock log"), vec!["destroy", "destroy"]);
+    }
+}
@@ -0,0 +1,297 @@
+use core::next_tree::content::ContentType;
+use core::next_tree::attributes::NodeAttributes;
+use core::next_tree::transform::NodeTransform;
+use core::next_tree::jobs::SceneJobs;
+use core::resource_management::load_handle::LoadHandle;
+use core::resource_management::shared_state::{FinalState, SharedState};
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::time::Duration;
+
+///A change of the active scene which is applied by the asset manager on its next update
+pub enum SceneCommand {
+    ///Adds a node with `content` below the node `parent`, or at the root if `None`
+    Spawn{
+        content: ContentType,
+        attributes: NodeAttributes,
+        parent: Option<String>,
+    },
+    ///Places an instance of the scene `scene` of the scene manager at `transform`
+    SpawnPrefab{
+        scene: String,
+        transform: NodeTransform,
+    },
+    ///Removes the node and its children, their behaviours are destroyed
+    Despawn(String),
+    ///Adds `job` to the node `node`
+    AddJob{
+        node: String,
+        job: SceneJobs,
+    },
+    ///Calls `change` with the attributes of the node `node`
+    SetAttributes{
+        node: String,
+        change: Box<FnMut(&mut NodeAttributes) + Send>,
+    },
+    ///Starts importing the file at `path` as scene `name`. Files ending with ".obj" are imported
+    /// as Wavefront obj, everything else as glTF.
+    Import{
+        name: String,
+        path: String,
+    },
+}
+
+///What an applied command produced
+#[derive(Clone)]
+pub enum CommandOutput {
+    ///The command has no output
+    Nothing,
+    ///The name of the spawned node
+    Node(String),
+    ///The handle of the started import
+    Import(LoadHandle),
+}
+
+///The state of a queued command
+#[derive(Clone)]
+pub enum CommandState {
+    ///Waits for the next asset manager update
+    Queued,
+    ///Was applied
+    Applied(CommandOutput),
+    ///Could not be applied, holds the reason
+    Failed(String),
+}
+
+impl FinalState for CommandState{
+    fn is_done(&self) -> bool{
+        match self{
+            &CommandState::Queued => false,
+            _ => true,
+        }
+    }
+}
+
+///Is returned for every queued command. It can be cloned and polled or waited on from any thread.
+#[derive(Clone)]
+pub struct CommandHandle {
+    id: usize,
+    state: SharedState<CommandState>,
+}
+
+impl CommandHandle{
+    fn new(id: usize) -> Self{
+        CommandHandle{
+            id: id,
+            state: SharedState::new(CommandState::Queued),
+        }
+    }
+
+    ///Returns the id of the command, ids are unique per command queue and increase in the order
+    /// the commands were queued.
+    pub fn get_id(&self) -> usize{
+        self.id
+    }
+
+    ///Returns the current state
+    pub fn get_state(&self) -> CommandState{
+        self.state.get()
+    }
+
+    ///Sets a new state and wakes everyone who waits on this handle
+    pub fn set_state(&self, new_state: CommandState){
+        self.state.set(new_state);
+    }
+
+    ///Returns true if the command was applied or failed
+    pub fn is_done(&self) -> bool{
+        self.state.is_done()
+    }
+
+    ///Returns the name of the spawned node if this was an applied spawn command
+    pub fn get_node_name(&self) -> Option<String>{
+        match self.get_state(){
+            CommandState::Applied(CommandOutput::Node(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    ///Returns the import handle if this was an applied import command
+    pub fn get_load_handle(&self) -> Option<LoadHandle>{
+        match self.get_state(){
+            CommandState::Applied(CommandOutput::Import(handle)) => Some(handle),
+            _ => None,
+        }
+    }
+
+    ///Blocks until the command was applied or failed and returns the final state.
+    ///NOTE: Commands are applied in the asset update, so don't wait on the thread which updates
+    /// the asset manager or while holding its lock.
+    pub fn wait(&self) -> CommandState{
+        self.state.wait()
+    }
+
+    ///Like `wait()` but returns the current state after `timeout` at the latest.
+    pub fn wait_timeout(&self, timeout: Duration) -> CommandState{
+        self.state.wait_timeout(timeout)
+    }
+}
+
+///A command and the handle which reports its state. If it is dropped before a final state was
+/// set, for instance because the asset manager was dropped with commands still queued, the handle
+/// fails so nobody waits on it forever.
+pub struct QueuedCommand {
+    command: Option<SceneCommand>,
+    pub handle: CommandHandle,
+}
+
+impl QueuedCommand{
+    ///Takes the command out, returns `None` if it was taken before
+    pub fn take_command(&mut self) -> Option<SceneCommand>{
+        self.command.take()
+    }
+}
+
+impl Drop for QueuedCommand{
+    fn drop(&mut self){
+        if !self.handle.is_done(){
+            self.handle.set_state(CommandState::Failed(String::from("the command was dropped before it was applied")));
+        }
+    }
+}
+
+///The sending side of the command queue of the asset manager. Can be cloned and moved to other
+/// threads, so the scene can be changed without locking the asset manager.
+#[derive(Clone)]
+pub struct CommandQueue {
+    sender: Sender<QueuedCommand>,
+    next_id: Arc<AtomicUsize>,
+}
+
+impl CommandQueue{
+    ///Creates a queue and the receiver the commands are read from
+    pub fn new() -> (Self, Receiver<QueuedCommand>){
+        let (sender, receiver) = channel();
+        (CommandQueue{ sender: sender, next_id: Arc::new(AtomicUsize::new(0)) }, receiver)
+    }
+
+    ///Queues `command`, the handle fails right away if the asset manager doesn't exist anymore.
+    pub fn push(&self, command: SceneCommand) -> CommandHandle{
+        let handle = CommandHandle::new(self.next_id.fetch_add(1, Ordering::SeqCst));
+        let queued = QueuedCommand{
+            command: Some(command),
+            handle: handle.clone(),
+        };
+
+        if self.sender.send(queued).is_err(){
+            handle.set_state(CommandState::Failed(String::from("the asset manager doesn't exist anymore")));
+        }
+        handle
+    }
+
+    ///Queues a node with `content` and `attributes` below `parent`, or at the root if `None`
+    pub fn spawn(&self, content: ContentType, attributes: NodeAttributes, parent: Option<&str>) -> CommandHandle{
+        self.push(SceneCommand::Spawn{
+            content: content,
+            attributes: attributes,
+            parent: parent.map(|name| String::from(name)),
+        })
+    }
+
+    ///Queues an instance of the scene `scene` at `transform`
+    pub fn spawn_prefab(&self, scene: &str, transform: NodeTransform) -> CommandHandle{
+        self.push(SceneCommand::SpawnPrefab{
+            scene: String::from(scene),
+            transform: transform,
+        })
+    }
+
+    ///Queues the removal of the node `name` and its children
+    pub fn despawn(&self, name: &str) -> CommandHandle{
+        self.push(SceneCommand::Despawn(String::from(name)))
+    }
+
+    ///Queues `job` for the node `node`
+    pub fn add_job(&self, node: &str, job: SceneJobs) -> CommandHandle{
+        self.push(SceneCommand::AddJob{
+            node: String::from(node),
+            job: job,
+        })
+    }
+
+    ///Queues a change of the attributes of `node`, for instance
+    /// `queue.set_attributes("door", |attrib| attrib.cast_shadow = false);`
+    pub fn set_attributes<F>(&self, node: &str, change: F) -> CommandHandle
+    where F: FnMut(&mut NodeAttributes) + Send + 'static
+    {
+        self.push(SceneCommand::SetAttributes{
+            node: String::from(node),
+            change: Box::new(change),
+        })
+    }
+
+    ///Queues the import of the file at `path` as scene `name`. The import is started on the next
+    /// update, `get_load_handle()` of the returned handle follows it from then on.
+    pub fn import(&self, name: &str, path: &str) -> CommandHandle{
+        self.push(SceneCommand::Import{
+            name: String::from(name),
+            path: String::from(path),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_failed(state: &CommandState) -> bool{
+        match state{
+            &CommandState::Failed(_) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn dropping_the_receiver_fails_queued_commands(){
+        let (queue, receiver) = CommandQueue::new();
+        let handle = queue.despawn("node");
+        assert!(!handle.is_done());
+
+        drop(receiver);
+        assert!(is_failed(&handle.wait()));
+    }
+
+    #[test]
+    fn pushing_without_receiver_fails(){
+        let (queue, receiver) = CommandQueue::new();
+        drop(receiver);
+        let handle = queue.despawn("node");
+        assert!(is_failed(&handle.wait_timeout(Duration::from_millis(100))));
+    }
+
+    #[test]
+    fn applied_commands_keep_their_state(){
+        let (queue, receiver) = CommandQueue::new();
+        let handle = queue.despawn("node");
+
+        let mut queued = receiver.try_recv().expect("failed to receive command");
+        assert!(queued.take_command().is_some());
+        queued.handle.set_state(CommandState::Applied(CommandOutput::Nothing));
+        drop(queued);
+
+        match handle.wait(){
+            CommandState::Applied(CommandOutput::Nothing) => {},
+            _ => panic!("the applied state was overwritten"),
+        }
+    }
+
+    #[test]
+    fn ids_increase(){
+        let (queue, _receiver) = CommandQueue::new();
+        let first = queue.despawn("a");
+        let second = queue.despawn("b");
+        assert!(first.get_id() < second.get_id());
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use core::resource_management::ManagerAndRenderInfo;
+use tools::gltf_importer;
+use tools::math::time_tools::dur_as_f32;
+
+///Watches the source files of all textures and imported glTF meshes by polling their modification
+/// time. Changed files are reloaded in place: textures are swapped in the texture manager and in
+/// every material which uses them, meshes get their new vertices and their materials the new
+/// factors and textures. Scene nodes keep pointing at the same meshes and materials and therefore
+/// show the changes directly.
+///NOTE: Changing which material a primitive uses still needs a new import.
+pub struct HotReloader {
+    //the modification time of every watched file at the last check
+    modified: BTreeMap<String, SystemTime>,
+    last_check: Instant,
+}
+
+impl HotReloader{
+    ///Creates a reloader which doesn't know any file yet, they are picked up on the first check.
+    pub fn new() -> Self{
+        HotReloader{
+            modified: BTreeMap::new(),
+            last_check: Instant::now(),
+        }
+    }
+
+    ///Reloads the changed files if at least `interval` seconds passed since the last check.
+    /// Returns the paths of the reloaded files.
+    pub fn update(&mut self, managers: &ManagerAndRenderInfo, interval: f32) -> Vec<String>{
+        if dur_as_f32(self.last_check.elapsed()) < interval{
+            return Vec::new();
+        }
+
+        self.reload_changed(managers)
+    }
+
+    ///Checks every watched file now and reloads the changed ones. Returns the paths of the
+    /// reloaded files.
+    pub fn reload_changed(&mut self, managers: &ManagerAndRenderInfo) -> Vec<String>{
+        self.last_check = Instant::now();
+
+        //Collect the current sources, they change whenever something is imported or removed
+        let texture_sources = match managers.texture_manager{
+            Some(ref texture_manager) => {
+                let texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+                texture_manager_lck.get_texture_sources()
+            },
+            None => Vec::new(),
+        };
+
+        let mesh_sources = {
+            let mesh_manager_lck = managers.mesh_manager.lock().expect("failed to lock mesh manager");
+            mesh_manager_lck.get_meshes_by_source()
+        };
+
+        let mut watched = BTreeSet::new();
+        for &(_, ref path) in texture_sources.iter(){
+            watched.insert(path.clone());
+        }
+        for path in mesh_sources.keys(){
+            watched.insert(path.clone());
+        }
+
+        //Forget files which are not used anymore, then look for changes
+        self.modified = self.modified.iter()
+        .filter(|&(path, _)| watched.contains(path))
+        .map(|(path, time)| (path.clone(), *time))
+        .collect();
+
+        let changed: BTreeSet<String> = watched.into_iter()
+        .filter(|path| self.has_changed(path))
+        .collect();
+
+        if changed.is_empty(){
+            return Vec::new();
+        }
+
+        let mut reloaded = Vec::new();
+
+        for (name, path) in texture_sources{
+            if !changed.contains(&path){
+                continue;
+            }
+
+            let texture_manager = managers.texture_manager.as_ref()
+            .expect("there are texture sources without a texture manager");
+
+            //Only the swap happens under the lock, decoding a big image would stall the renderer
+            let old_texture = {
+                let texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+                texture_manager_lck.find_texture(&name)
+            };
+            let old_texture = match old_texture{
+                Some(texture) => texture,
+                None => continue, //was removed since the sources were collected
+            };
+
+            let result = old_texture.reload().and_then(|new_texture|{
+                let mut texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+                texture_manager_lck.swap_texture(new_texture.clone())
+                .map(|old_texture| (old_texture, new_texture))
+            });
+
+            match result{
+                Ok((old_texture, new_texture)) => {
+                    if let Some(ref material_manager) = managers.material_manager{
+                        let mut material_manager_lck = material_manager.lock().expect("failed to lock material manager");
+                        material_manager_lck.replace_texture(&old_texture, &new_texture);
+                    }
+                    println!("STATUS: HOT_RELOAD: reloaded texture {} from {}", name, path);
+                    reloaded.push(path);
+                },
+                Err(er) => {
+                    println!("WARNING: HOT_RELOAD: {}, trying again on the next check", er);
+                    self.retry(&path);
+                }
+            }
+        }
+
+        for (path, meshes) in mesh_sources{
+            if !changed.contains(&path){
+                continue;
+            }
+
+            match gltf_importer::reload_gltf_meshes(&path, &meshes, managers){
+                Ok(_) => {
+                    //the new vertices might have another size
+                    managers.mesh_manager.lock().expect("failed to lock mesh manager").refresh_memory_usage();
+                    println!("STATUS: HOT_RELOAD: reloaded {} meshes from {}", meshes.len(), path);
+                    reloaded.push(path);
+                },
+                Err(er) => {
+                    println!("WARNING: HOT_RELOAD: {}, trying again on the next check", er);
+                    self.retry(&path);
+                }
+            }
+        }
+
+        reloaded
+    }
+
+    ///Returns true if the file at `path` changed since the last check. New files are only
+    /// remembered, files which can't be read (for instance while they are saved) count as unchanged.
+    fn has_changed(&mut self, path: &str) -> bool{
+        let modified = match fs::metadata(path).and_then(|meta| meta.modified()){
+            Ok(time) => time,
+            Err(_) => return false,
+        };
+
+        match self.modified.insert(String::from(path), modified){
+            Some(last_modified) => last_modified != modified,
+            None => false,
+        }
+    }
+
+    ///Marks the file as changed again, used if reloading failed because the file was incomplete.
+    fn retry(&mut self, path: &str){
+        self.modified.insert(String::from(path), UNIX_EPOCH);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use std::thread;
+    use std::time::Duration;
+
+    fn write_file(path: &str, content: &str){
+        let mut file = File::create(path).expect("failed to create test file");
+        file.write_all(content.as_bytes()).expect("failed to write test file");
+    }
+
+    fn temp_path(name: &str) -> String{
+        ::std::env::temp_dir().join(name).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn new_files_are_only_remembered(){
+        let path = temp_path("jakar_hot_reload_new.txt");
+        write_file(&path, "first");
+
+        let mut reloader = HotReloader::new();
+        assert!(!reloader.has_changed(&path));
+        assert!(reloader.modified.contains_key(&path));
+        assert!(!reloader.has_changed(&path));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn rewritten_files_changed_once(){
+        let path = temp_path("jakar_hot_reload_rewritten.txt");
+        write_file(&path, "first");
+
+        let mut reloader = HotReloader::new();
+        assert!(!reloader.has_changed(&path));
+
+        //rewrite till the modification time differs, some file systems only store seconds
+        let first_modified = reloader.modified[&path];
+        for _ in 0..30{
+            thread::sleep(Duration::from_millis(100));
+            write_file(&path, "second");
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).expect("failed to read test file");
+            if modified != first_modified{
+                break;
+            }
+        }
+
+        assert!(reloader.has_changed(&path));
+        assert!(!reloader.has_changed(&path));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn retry_marks_the_file_as_changed(){
+        let path = temp_path("jakar_hot_reload_retry.txt");
+        write_file(&path, "first");
+
+        let mut reloader = HotReloader::new();
+        assert!(!reloader.has_changed(&path));
+        reloader.retry(&path);
+        assert!(reloader.has_changed(&path));
+        assert!(!reloader.has_changed(&path));
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn missing_files_count_as_unchanged(){
+        let path = temp_path("jakar_hot_reload_missing.txt");
+        let _ = fs::remove_file(&path);
+
+        let mut reloader = HotReloader::new();
+        assert!(!reloader.has_changed(&path));
+        assert!(!reloader.modified.contains_key(&path));
+
+        //a file which can't be read while it is retried stays marked
+        reloader.retry(&path);
+        assert!(!reloader.has_changed(&path));
+        assert_eq!(reloader.modified[&path], UNIX_EPOCH);
+    }
+}
@@ -0,0 +1,186 @@
+use std::time::{Duration, Instant};
+
+use core::asset_error::AssetError;
+use core::resource_management::shared_state::{FinalState, SharedState};
+
+///The state of an asynchronous import
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadState {
+    ///Waits for a free thread in the thread pool
+    Queued,
+    ///Is loading, the value is the progress between 0.0 and 1.0
+    Loading(f32),
+    ///The file is loaded, the scene gets handed to the scene manager on the next asset update
+    Loaded,
+    ///The scene is in the scene manager under this name
+    Finished(String),
+    ///Loading failed with this error
+    Failed(AssetError),
+}
+
+impl FinalState for LoadState{
+    fn is_done(&self) -> bool{
+        match self{
+            &LoadState::Finished(_) | &LoadState::Failed(_) => true,
+            _ => false,
+        }
+    }
+}
+
+///Is returned by the asynchronous imports of the asset manager. It can be cloned and polled
+/// from any thread.
+#[derive(Clone)]
+pub struct LoadHandle {
+    name: String,
+    path: String,
+    state: SharedState<LoadState>,
+}
+
+impl LoadHandle{
+    ///Creates a queued handle for the scene `name` which is loaded from `path`
+    pub fn new(name: &str, path: &str) -> Self{
+        LoadHandle{
+            name: String::from(name),
+            path: String::from(path),
+            state: SharedState::new(LoadState::Queued),
+        }
+    }
+
+    ///Returns the name the scene was requested with. Since the scene manager renames scenes with
+    /// an existing name, use `get_scene_name()` to find the finished scene.
+    pub fn get_name(&self) -> String{
+        self.name.clone()
+    }
+
+    ///Returns the path of the loaded file
+    pub fn get_path(&self) -> String{
+        self.path.clone()
+    }
+
+    ///Returns the current state
+    pub fn get_state(&self) -> LoadState{
+        self.state.get()
+    }
+
+    ///Sets a new state and wakes everyone who waits on this handle
+    pub fn set_state(&self, new_state: LoadState){
+        self.state.set(new_state);
+    }
+
+    ///Sets the progress (0.0 - 1.0) while loading
+    pub fn set_progress(&self, progress: f32){
+        self.set_state(LoadState::Loading(progress.max(0.0).min(1.0)));
+    }
+
+    ///Returns the progress between 0.0 and 1.0, failed imports count as done.
+    pub fn get_progress(&self) -> f32{
+        match self.get_state(){
+            LoadState::Queued => 0.0,
+            LoadState::Loading(progress) => progress,
+            _ => 1.0,
+        }
+    }
+
+    ///Returns true if the import finished or failed
+    pub fn is_done(&self) -> bool{
+        self.state.is_done()
+    }
+
+    ///Returns the name of the scene in the scene manager if the import finished
+    pub fn get_scene_name(&self) -> Option<String>{
+        match self.get_state(){
+            LoadState::Finished(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    ///Returns the error if the import failed
+    pub fn get_error(&self) -> Option<AssetError>{
+        match self.get_state(){
+            LoadState::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    ///Blocks until the import finished or failed and returns the final state.
+    ///NOTE: The scene is handed over in the asset update, so don't wait on the thread which
+    /// updates the asset manager or while holding its lock.
+    pub fn wait(&self) -> LoadState{
+        self.state.wait()
+    }
+
+    ///Like `wait()` but returns the current state after `timeout` at the latest.
+    pub fn wait_timeout(&self, timeout: Duration) -> LoadState{
+        self.state.wait_timeout(timeout)
+    }
+}
+
+///A collection of handles, for instance everything a loading screen waits for.
+#[derive(Clone)]
+pub struct LoadGroup {
+    handles: Vec<LoadHandle>,
+}
+
+impl LoadGroup{
+    ///Creates an empty group
+    pub fn new() -> Self{
+        LoadGroup{
+            handles: Vec::new(),
+        }
+    }
+
+    ///Adds a handle to the group
+    pub fn with_handle(mut self, handle: LoadHandle) -> Self{
+        self.handles.push(handle);
+        self
+    }
+
+    ///Adds a handle to the group
+    pub fn add(&mut self, handle: LoadHandle){
+        self.handles.push(handle);
+    }
+
+    ///Returns all handles of this group
+    pub fn get_handles(&self) -> &Vec<LoadHandle>{
+        &self.handles
+    }
+
+    ///Returns the average progress of all handles, an empty group is done.
+    pub fn get_progress(&self) -> f32{
+        if self.handles.is_empty(){
+            return 1.0;
+        }
+        self.handles.iter().fold(0.0, |sum, handle| sum + handle.get_progress()) / self.handles.len() as f32
+    }
+
+    ///Returns true if every import finished or failed
+    pub fn is_done(&self) -> bool{
+        self.handles.iter().all(|handle| handle.is_done())
+    }
+
+    ///Returns (name, error) of all failed imports
+    pub fn get_errors(&self) -> Vec<(String, AssetError)>{
+        self.handles.iter()
+        .filter_map(|handle| handle.get_error().map(|error| (handle.get_name(), error)))
+        .collect()
+    }
+
+    ///Blocks until every import finished or failed, returns the final states in the order the
+    /// handles were added. Have a look at the note of `LoadHandle::wait()`.
+    pub fn wait(&self) -> Vec<LoadState>{
+        self.handles.iter().map(|handle| handle.wait()).collect()
+    }
+
+    ///Waits at most `timeout` for all imports, returns true if all are done.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool{
+        let start = Instant::now();
+        for handle in self.handles.iter(){
+            let elapsed = start.elapsed();
+            if elapsed >= timeout{
+                return self.is_done();
+            }
+            handle.wait_timeout(timeout - elapsed);
+        }
+        self.is_done()
+    }
+}
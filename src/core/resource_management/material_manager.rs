@@ -56,7 +56,8 @@ impl MaterialManager {
             "fallback",
             pipe,
             uniform_manager.clone(),
-            device.clone());
+            device.clone()
+        ).expect("failed to create the fallback material");
         tmp_map.insert("fallback".to_string(), Arc::new(Mutex::new(fallback_mat)));
 
 
@@ -131,6 +132,45 @@ impl MaterialManager {
         material_name
 
     }
+    ///Swaps the texture `old` for `new` in every material which uses it, returns the names of the
+    /// changed materials.
+    pub fn replace_texture(&mut self, old: &Arc<Texture>, new: &Arc<Texture>) -> Vec<String>{
+        let mut changed = Vec::new();
+        for (name, material) in self.material_vault.iter(){
+            let mut material_lck = material.lock().expect("failed to lock material for texture swap");
+            if material_lck.replace_texture(old, new){
+                changed.push(name.clone());
+            }
+        }
+        changed
+    }
+
+    ///Removes the material from the manager, meshes which use it keep their copy alive. The
+    /// fallback material can't be removed.
+    pub fn remove_material(&mut self, name: &str) -> Result<(), &'static str>{
+        if name == "fallback"{
+            return Err("the fallback material can't be removed");
+        }
+        match self.material_vault.remove(name){
+            Some(_) => Ok(()),
+            None => Err("the material could not be removed because it is not in the manager"),
+        }
+    }
+
+    ///Removes all materials which are only referenced by the manager, returns their names.
+    ///NOTE: Unload unused meshes first, they keep their materials alive.
+    pub fn collect_garbage(&mut self) -> Vec<String>{
+        let unused: Vec<String> = self.material_vault.iter()
+        .filter(|&(name, material)| Arc::strong_count(material) == 1 && name != "fallback")
+        .map(|(name, _)| name.clone())
+        .collect();
+
+        for name in unused.iter(){
+            self.material_vault.remove(name);
+        }
+        unused
+    }
+
     ///Checks for a material
     pub fn is_available(&self, name: &str) -> bool{
         self.material_vault.contains_key(&String::from(name))
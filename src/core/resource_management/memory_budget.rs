@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+///Is asked with the (name, bytes) of a resource before it is evicted to meet a budget. Return
+/// `false` to keep the resource.
+pub type EvictionCallback = Arc<Fn(&str, usize) -> bool + Send + Sync>;
+
+///A memory limit for a manager. If the manager uses more memory than allowed, resources which are
+/// only referenced by the manager itself are evicted, the biggest first.
+#[derive(Clone)]
+pub struct MemoryBudget {
+    limit: Option<usize>,
+    on_evict: Option<EvictionCallback>,
+}
+
+impl MemoryBudget{
+    ///Creates a budget without limit, nothing is evicted
+    pub fn unlimited() -> Self{
+        MemoryBudget{
+            limit: None,
+            on_evict: None,
+        }
+    }
+
+    ///Creates a budget which allows `limit` bytes
+    pub fn new(limit: usize) -> Self{
+        MemoryBudget{
+            limit: Some(limit),
+            on_evict: None,
+        }
+    }
+
+    ///Sets a callback which is asked before every eviction, can be used to log or to protect
+    /// resources which will be needed soon.
+    pub fn with_eviction_callback<F>(mut self, callback: F) -> Self
+    where F: Fn(&str, usize) -> bool + Send + Sync + 'static
+    {
+        self.on_evict = Some(Arc::new(callback));
+        self
+    }
+
+    ///Returns the limit in bytes, `None` if unlimited
+    pub fn get_limit(&self) -> Option<usize>{
+        self.limit
+    }
+
+    ///Returns true if `usage` bytes exceed the limit
+    pub fn is_exceeded(&self, usage: usize) -> bool{
+        match self.limit{
+            Some(limit) => usage > limit,
+            None => false,
+        }
+    }
+
+    ///Selects the resources to evict from `candidates` (name, bytes) until `usage` fits the limit.
+    /// Returns the selected names and the usage after evicting them.
+    pub fn select_evictions(&self, usage: usize, mut candidates: Vec<(String, usize)>) -> (Vec<String>, usize){
+        let mut usage = usage;
+        let mut evicted = Vec::new();
+        if !self.is_exceeded(usage){
+            return (evicted, usage);
+        }
+
+        candidates.sort_by(|a, b| b.1.cmp(&a.1));
+        for (name, bytes) in candidates{
+            if !self.is_exceeded(usage){
+                break;
+            }
+
+            let allowed = match self.on_evict{
+                Some(ref callback) => callback(&name, bytes),
+                None => true,
+            };
+
+            if allowed{
+                usage = usage.saturating_sub(bytes);
+                evicted.push(name);
+            }
+        }
+
+        (evicted, usage)
+    }
+}
+
+///The memory used by the resources of the asset manager in bytes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MemoryUsage {
+    ///The image data of all textures
+    pub texture_bytes: usize,
+    ///The vertices and indices of all meshes
+    pub vertex_bytes: usize,
+}
+
+impl MemoryUsage{
+    ///Returns the sum of all resources
+    pub fn total(&self) -> usize{
+        self.texture_bytes + self.vertex_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates() -> Vec<(String, usize)>{
+        vec![
+            (String::from("small"), 10),
+            (String::from("big"), 40),
+            (String::from("medium"), 30),
+        ]
+    }
+
+    #[test]
+    fn nothing_is_evicted_within_the_limit(){
+        let budget = MemoryBudget::new(100);
+        assert!(!budget.is_exceeded(100));
+        assert_eq!(budget.select_evictions(100, candidates()), (Vec::new(), 100));
+    }
+
+    #[test]
+    fn unlimited_budget_never_evicts(){
+        let budget = MemoryBudget::unlimited();
+        assert_eq!(budget.select_evictions(1_000_000, candidates()), (Vec::new(), 1_000_000));
+    }
+
+    #[test]
+    fn biggest_are_evicted_first_until_the_usage_fits(){
+        let budget = MemoryBudget::new(100);
+        let (evicted, usage) = budget.select_evictions(150, candidates());
+        assert_eq!(evicted, vec![String::from("big"), String::from("medium")]);
+        assert_eq!(usage, 80);
+    }
+
+    #[test]
+    fn callback_can_keep_a_resource(){
+        let budget = MemoryBudget::new(100).with_eviction_callback(|name, _| name != "big");
+        let (evicted, usage) = budget.select_evictions(150, candidates());
+        assert_eq!(evicted, vec![String::from("medium"), String::from("small")]);
+        //Only unused resources can be evicted, so the usage may stay over the limit
+        assert_eq!(usage, 110);
+        assert!(budget.is_exceeded(usage));
+    }
+}
@@ -1,5 +1,7 @@
 
 use core::resources::mesh;
+use core::resource_management::memory_budget::MemoryBudget;
+use core::asset_error::AssetError;
 
 use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
@@ -7,42 +9,32 @@ use std::sync::{Arc, Mutex};
 ///The structure containing all meshes and created scenes
 pub struct MeshManager {
     meshes: Arc<Mutex<BTreeMap<String, Arc<Mutex<mesh::Mesh>>>>>,
-
+    //limits the memory of the vertices and indices
+    budget: MemoryBudget,
+    //the size of all meshes in bytes when they were added, see `refresh_memory_usage()`
+    memory_usage: usize,
 }
 
 impl MeshManager {
     pub fn new() -> Self{
         MeshManager{
             meshes: Arc::new(Mutex::new(BTreeMap::new())),
+            budget: MemoryBudget::unlimited(),
+            memory_usage: 0,
         }
     }
 
-    ///Adds a mesh to the manager
-    pub fn add_mesh(&mut self, mut mesh: mesh::Mesh){
-
-
-        //have a look for this mesh in self
-        let b_contains = {
-            let mut mesh_lck = self.meshes.lock().expect("Failed to hold while adding mesh to mesh manager");
-            (*mesh_lck).contains_key(&String::from(mesh.name.clone()))
-        };
-
-        match b_contains{
-            true => {
-                //generate a unque mesh name
-                let unique_name = self.get_unique_name(mesh.name.clone());
-                println!("The name: {} is already in the mesh manager, will add as {}", mesh.name.clone(), unique_name);
-                //change the name in mesh
-                mesh.name = unique_name.clone();
-                let mut mesh_lck = self.meshes.lock().expect("Failed to hold while adding mesh to mesh manager");
-                (*mesh_lck).insert(unique_name, Arc::new(Mutex::new(mesh)));
-            },
-            false => {
-                let mut mesh_lck = self.meshes.lock().expect("Failed to hold while adding mesh to mesh manager");
-               (*mesh_lck).insert(mesh.name.clone(), Arc::new(Mutex::new(mesh)));
-            }
+    ///Adds a mesh to the manager, this will return an error if a mesh with the same name is
+    ///already in the manager. Use `add_arc_mesh()` to get a unique name instead.
+    pub fn add_mesh(&mut self, mesh: mesh::Mesh) -> Result<(), AssetError>{
+        let mut mesh_lck = self.meshes.lock().expect("Failed to hold while adding mesh to mesh manager");
+        if mesh_lck.contains_key(&mesh.name){
+            return Err(AssetError::AlreadyExists(format!("mesh {}", mesh.name)));
         }
 
+        self.memory_usage += mesh.get_byte_size();
+        mesh_lck.insert(mesh.name.clone(), Arc::new(Mutex::new(mesh)));
+        Ok(())
     }
 
     ///helper function to get a not taken name, returns the name + _id
@@ -64,6 +56,7 @@ impl MeshManager {
         //get the meshs name and check if its already in there, if, cahnge the meshs name
         let mesh_name ={
             let mesh_ref_lck = mesh.lock().expect("failed to lock mesh while adding to manager");
+            self.memory_usage += (*mesh_ref_lck).get_byte_size();
             (*mesh_ref_lck).name.clone()
         };
 
@@ -95,6 +88,22 @@ impl MeshManager {
         }
     }
 
+    ///Returns all meshes which were imported from a file, sorted by the path of the file
+    pub fn get_meshes_by_source(&self) -> BTreeMap<String, Vec<Arc<Mutex<mesh::Mesh>>>>{
+        let meshes = self.meshes.lock().expect("failed to lock meshes");
+        let mut sources = BTreeMap::new();
+        for (_, mesh) in meshes.iter(){
+            let source = {
+                let mesh_lck = mesh.lock().expect("failed to lock mesh");
+                mesh_lck.get_source()
+            };
+            if let Some(source) = source{
+                sources.entry(source.path).or_insert(Vec::new()).push(mesh.clone());
+            }
+        }
+        sources
+    }
+
     ///Returns a mesh by name without locking it (as a Arc<T> clone)
     pub fn get_mesh(&mut self, name: &str) -> Option<Arc<Mutex<mesh::Mesh>>>{
         let meshes = self.meshes.lock().expect("faield to lock meshes");
@@ -103,4 +112,97 @@ impl MeshManager {
             None => None,
         }
     }
+
+    ///Removes the mesh from the manager, scene nodes which use it keep their copy alive.
+    pub fn remove_mesh(&mut self, name: &str) -> Result<(), &'static str>{
+        let mut meshes = self.meshes.lock().expect("failed to lock meshes");
+        match meshes.remove(name){
+            Some(mesh) => {
+                let mesh_lck = mesh.lock().expect("failed to lock mesh");
+                self.memory_usage = self.memory_usage.saturating_sub(mesh_lck.get_byte_size());
+                Ok(())
+            },
+            None => Err("the mesh could not be removed because it is not in the manager"),
+        }
+    }
+
+    ///Removes all meshes which are only referenced by the manager, returns their names. Remove
+    /// the scenes first which use them.
+    pub fn collect_garbage(&mut self) -> Vec<String>{
+        let unused = self.get_unused();
+        self.remove_unused(&unused);
+        unused.into_iter().map(|(name, _)| name).collect()
+    }
+
+    ///Returns (name, bytes) of every mesh which is only referenced by the manager
+    fn get_unused(&self) -> Vec<(String, usize)>{
+        let meshes = self.meshes.lock().expect("failed to lock meshes");
+        meshes.iter()
+        .filter(|&(_, mesh)| Arc::strong_count(mesh) == 1)
+        .map(|(name, mesh)| {
+            let mesh_lck = mesh.lock().expect("failed to lock mesh");
+            (name.clone(), mesh_lck.get_byte_size())
+        })
+        .collect()
+    }
+
+    ///Removes the unused meshes (name, bytes) and their size from the memory usage
+    fn remove_unused(&mut self, unused: &[(String, usize)]){
+        let mut meshes = self.meshes.lock().expect("failed to lock meshes");
+        for &(ref name, bytes) in unused.iter(){
+            if meshes.remove(name).is_some(){
+                self.memory_usage = self.memory_usage.saturating_sub(bytes);
+            }
+        }
+    }
+
+    ///Returns the size of all vertices and indices in bytes. The size is tracked when meshes are
+    /// added or removed, call `refresh_memory_usage()` after changing the data of a mesh which
+    /// is already in the manager.
+    pub fn get_memory_usage(&self) -> usize{
+        self.memory_usage
+    }
+
+    ///Measures the size of all meshes again, this locks every mesh.
+    pub fn refresh_memory_usage(&mut self){
+        let meshes = self.meshes.lock().expect("failed to lock meshes");
+        self.memory_usage = meshes.values().fold(0, |sum, mesh| {
+            let mesh_lck = mesh.lock().expect("failed to lock mesh");
+            sum + mesh_lck.get_byte_size()
+        });
+    }
+
+    ///Sets the memory budget of the meshes, it is enforced by `enforce_memory_budget()`
+    pub fn set_memory_budget(&mut self, budget: MemoryBudget){
+        self.budget = budget;
+    }
+
+    ///Returns the current memory budget
+    pub fn get_memory_budget(&self) -> &MemoryBudget{
+        &self.budget
+    }
+
+    ///Evicts unused meshes until the budget is met, returns the names of the evicted ones.
+    /// Meshes in use are never evicted, so the usage might stay over budget. The meshes are only
+    /// locked if the tracked usage exceeds the budget.
+    pub fn enforce_memory_budget(&mut self) -> Vec<String>{
+        if !self.budget.is_exceeded(self.memory_usage){
+            return Vec::new();
+        }
+
+        //the tracked usage might be outdated if meshes changed, so measure before evicting
+        self.refresh_memory_usage();
+        let usage = self.memory_usage;
+        if !self.budget.is_exceeded(usage){
+            return Vec::new();
+        }
+
+        let unused = self.get_unused();
+        let (evicted, _) = self.budget.select_evictions(usage, unused.clone());
+        let evicted_unused: Vec<(String, usize)> = unused.into_iter()
+        .filter(|&(ref name, _)| evicted.contains(name))
+        .collect();
+        self.remove_unused(&evicted_unused);
+        evicted
+    }
 }
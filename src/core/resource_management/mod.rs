@@ -11,6 +11,20 @@ pub mod asset_manager;
 pub mod scene_manager;
 ///Manages all available textues and gives out `Arc<Texture>` copys on request
 pub mod texture_manager;
+///Manages skeletons and animation clips and plays them back
+pub mod animation_manager;
+///A state shared between threads which can be polled and waited on, the core of the handles
+pub mod shared_state;
+///Handles to poll or wait for asynchronous imports
+pub mod load_handle;
+///Reloads textures and glTF meshes when their files change on disk
+pub mod hot_reload;
+///Memory accounting and limits for the managers
+pub mod memory_budget;
+///Calls the behaviours which are attached to the nodes of the active scene
+pub mod behaviour_manager;
+///Queues changes of the active scene which are applied on the next asset manager update
+pub mod command_queue;
 
 
 use render::pipeline_manager;
@@ -21,22 +35,55 @@ use vulkano;
 use std::sync::{Arc, Mutex};
 
 ///A small struct containing all common types which need to be send between functions and thread
-///often
+///often. The render related types are `None` if the engine runs headless.
 pub struct ManagerAndRenderInfo {
     ///The current pipeline manager
-    pub pipeline_manager: Arc<Mutex<pipeline_manager::PipelineManager>>,
+    pub pipeline_manager: Option<Arc<Mutex<pipeline_manager::PipelineManager>>>,
     ///The current uniform manager
-    pub uniform_manager: Arc<Mutex<uniform_manager::UniformManager>>,
+    pub uniform_manager: Option<Arc<Mutex<uniform_manager::UniformManager>>>,
     ///The current device used for rendering
-    pub device: Arc<vulkano::device::Device>,
+    pub device: Option<Arc<vulkano::device::Device>>,
     ///The currently used queues
-    pub queue: Arc<vulkano::device::Queue>,
+    pub queue: Option<Arc<vulkano::device::Queue>>,
     ///The current texture manager
-    pub texture_manager: Arc<Mutex<texture_manager::TextureManager>>,
+    pub texture_manager: Option<Arc<Mutex<texture_manager::TextureManager>>>,
     ///The current material manager
-    pub material_manager: Arc<Mutex<material_manager::MaterialManager>>,
+    pub material_manager: Option<Arc<Mutex<material_manager::MaterialManager>>>,
     ///The current mesh manager
     pub mesh_manager: Arc<Mutex<mesh_manager::MeshManager>>,
     ///The current scene manager
-    pub scene_manager: Arc<Mutex<scene_manager::SceneManager>>
+    pub scene_manager: Arc<Mutex<scene_manager::SceneManager>>,
+    ///The current animation manager
+    pub animation_manager: Arc<Mutex<animation_manager::AnimationManager>>,
+}
+
+impl ManagerAndRenderInfo{
+    ///Returns true if there is no gpu to create textures and materials on
+    pub fn is_headless(&self) -> bool{
+        self.device.is_none()
+    }
+
+    ///Returns the names of the shared managers whose lock was poisoned by a panicking thread
+    pub fn get_poisoned_managers(&self) -> Vec<&'static str>{
+        let mut poisoned = Vec::new();
+        if self.texture_manager.as_ref().map_or(false, |manager| manager.is_poisoned()){
+            poisoned.push("texture");
+        }
+        if self.material_manager.as_ref().map_or(false, |manager| manager.is_poisoned()){
+            poisoned.push("material");
+        }
+        if self.pipeline_manager.as_ref().map_or(false, |manager| manager.is_poisoned()){
+            poisoned.push("pipeline");
+        }
+        if self.mesh_manager.is_poisoned(){
+            poisoned.push("mesh");
+        }
+        if self.scene_manager.is_poisoned(){
+            poisoned.push("scene");
+        }
+        if self.animation_manager.is_poisoned(){
+            poisoned.push("animation");
+        }
+        poisoned
+    }
 }
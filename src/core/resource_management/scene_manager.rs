@@ -1,24 +1,57 @@
 use core::next_tree::*;
+use core::next_tree::scene_file::{SceneFile, SceneResources};
+use core::resource_management::mesh_manager::MeshManager;
+use core::resource_management::material_manager::MaterialManager;
+use core::resources::mesh;
+use core::resources::prefab::PrefabScene;
+use core::resources::camera::{DefaultCamera, Camera};
+use core::engine_settings::EngineSettings;
+use core::asset_error::AssetError;
+use core::event_bus::{EventBus, SceneAddedEvent, SceneRemovedEvent, AssetLoadedEvent};
+use input::keymap::KeyMap;
 use jakar_tree;
 
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
 
 ///has a list of all available scenes
 pub struct SceneManager {
     scenes: BTreeMap<String, Arc<Mutex<jakar_tree::tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>>>>,
+
+    //Needed to resolve the meshes, materials and cameras of a loaded scene file
+    mesh_manager: Arc<Mutex<MeshManager>>,
+    material_manager: Option<Arc<Mutex<MaterialManager>>>,
+    settings: Arc<Mutex<EngineSettings>>,
+    key_map: Arc<Mutex<KeyMap>>,
+    //Is told about added and removed scenes
+    event_bus: EventBus,
 }
 
 impl SceneManager {
-    pub fn new() -> Self{
+    ///Creates an empty scene manager. The managers are used to find the meshes and materials
+    /// referenced in scene files. The material manager is `None` if the engine runs headless.
+    pub fn new(
+        mesh_manager: Arc<Mutex<MeshManager>>,
+        material_manager: Option<Arc<Mutex<MaterialManager>>>,
+        settings: Arc<Mutex<EngineSettings>>,
+        key_map: Arc<Mutex<KeyMap>>,
+        event_bus: EventBus,
+    ) -> Self{
         SceneManager{
             scenes: BTreeMap::new(),
+            mesh_manager,
+            material_manager,
+            settings,
+            key_map,
+            event_bus,
         }
     }
 
-    //Adds a scene to the scene manager by
-    pub fn add_scene(&mut self, mut scene: jakar_tree::tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>){
-        match self.scenes.contains_key(&scene.name.clone()){
+    ///Adds a scene to the scene manager by its name, returns the name it was actually added under.
+    pub fn add_scene(&mut self, mut scene: jakar_tree::tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>) -> String{
+        let name = match self.scenes.contains_key(&scene.name.clone()){
             true => {
                 //the scene exist, going to generate an indice which doesnt exist
                 let mut indice = 0;
@@ -36,13 +69,41 @@ impl SceneManager {
                 let new_name = String::from(scene.name.clone()) + "_" + &indice.to_string();
                 //change the internal name of this scene
                 scene.name = new_name.clone();
-                self.scenes.insert(new_name, Arc::new(Mutex::new(scene)));
+                self.scenes.insert(new_name.clone(), Arc::new(Mutex::new(scene)));
+                new_name
             },
             //All is fine, we can add it normaly to the manager
             false =>{
-                self.scenes.insert(scene.name.clone(), Arc::new(Mutex::new(scene)));
+                let name = scene.name.clone();
+                self.scenes.insert(name.clone(), Arc::new(Mutex::new(scene)));
+                name
             },
-        }
+        };
+
+        self.event_bus.publish(SceneAddedEvent{ name: name.clone() });
+        name
+    }
+
+    ///Adds the scene of a synchronous import from `path` and publishes an `AssetLoadedEvent` for
+    /// it, like the asset manager does for asynchronous imports. Returns the name the scene was
+    /// added under or the import error.
+    pub fn add_import_result(
+        &mut self,
+        name: &str,
+        path: &str,
+        result: Result<jakar_tree::tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>, AssetError>
+    ) -> Result<String, AssetError>{
+        let result = match result{
+            Ok(scene) => Ok(self.add_scene(scene)),
+            Err(er) => Err(er),
+        };
+
+        self.event_bus.publish(AssetLoadedEvent{
+            name: String::from(name),
+            path: String::from(path),
+            result: result.clone(),
+        });
+        result
     }
 
     ///Returns Some(scene) by name from the `scenes` Vector as a Mutex guard
@@ -84,6 +145,18 @@ impl SceneManager {
         self.scenes.contains_key(&String::from(name))
     }
 
+    ///Removes the scene from the manager. Its meshes and materials stay in their managers until
+    /// they are unloaded or garbage collected.
+    pub fn remove_scene(&mut self, name: &str) -> Result<(), String>{
+        match self.scenes.remove(name){
+            Some(_) => {
+                self.event_bus.publish(SceneRemovedEvent{ name: String::from(name) });
+                Ok(())
+            },
+            None => Err(format!("there is no scene called {}", name)),
+        }
+    }
+
     ///prints a list of all stored scenes
     pub fn print_all_scenes(&self){
         println!("Alls stored scenes: ", );
@@ -91,4 +164,120 @@ impl SceneManager {
             println!("\t {}", k);
         }
     }
+
+    ///Saves the scene with `name` as a RON file to `path`. Meshes and materials are only saved
+    /// by their name in the mesh and material manager.
+    pub fn save_scene(&mut self, name: &str, path: &str) -> Result<(), AssetError>{
+        let scene_string = {
+            let scene = match self.get_scene(name){
+                Some(sc) => sc,
+                None => return Err(AssetError::NotFound(format!("scene {} in the scene manager", name))),
+            };
+            SceneFile::from_tree(&scene).to_string()?
+        };
+
+        let mut file = match File::create(path){
+            Ok(f) => f,
+            Err(er) => return Err(AssetError::io(path, er)),
+        };
+
+        match file.write_all(scene_string.as_bytes()){
+            Ok(_) => Ok(()),
+            Err(er) => Err(AssetError::io(path, er)),
+        }
+    }
+
+    ///Loads a scene which was saved by `save_scene()` from `path` and adds it to the manager.
+    /// All referenced meshes and the scenes of prefabs have to be in their managers already.
+    ///Returns the name the scene was added under.
+    pub fn load_scene(&mut self, path: &str) -> Result<String, AssetError>{
+        let mut file = match File::open(path){
+            Ok(f) => f,
+            Err(er) => return Err(AssetError::io(path, er)),
+        };
+        let mut scene_string = String::new();
+        match file.read_to_string(&mut scene_string){
+            Ok(_) => {},
+            Err(er) => return Err(AssetError::io(path, er)),
+        }
+
+        let scene_file = match SceneFile::from_str(&scene_string){
+            Ok(scene_file) => scene_file,
+            Err(AssetError::Decode{reason, ..}) => return Err(AssetError::decode(path, &reason)),
+            Err(er) => return Err(er),
+        };
+        let mut scene = scene_file.into_tree(&*self)?;
+        scene.rebuild_bounds();
+
+        let name = self.add_scene(scene);
+        Ok(name)
+    }
+
+    ///Creates a new camera which uses the engine settings and key map, used when importing cameras.
+    pub fn new_camera(&self) -> DefaultCamera{
+        DefaultCamera::new(self.settings.clone(), self.key_map.clone())
+    }
+}
+
+impl SceneResources for SceneManager{
+    ///Returns the mesh if it already uses the material. Otherwise a copy of the mesh (sharing the
+    /// gpu buffers) which uses the material is added to the mesh manager as `mesh@material`, so
+    /// other nodes using the mesh keep their material.
+    fn get_mesh(&self, mesh_name: &str, material_name: &str) -> Result<Arc<Mutex<mesh::Mesh>>, AssetError>{
+        //a saved copy is loaded from the mesh it was made of
+        let copy_suffix = format!("@{}", material_name);
+        let base_name = if mesh_name.ends_with(&copy_suffix){
+            &mesh_name[..mesh_name.len() - copy_suffix.len()]
+        }else{
+            mesh_name
+        };
+
+        let mut mesh_man_lck = self.mesh_manager.lock().expect("failed to lock mesh manager");
+        let mesh = match mesh_man_lck.get_mesh(base_name){
+            Some(mesh) => mesh,
+            None => return Err(AssetError::NotFound(format!("mesh {}", base_name))),
+        };
+
+        //headless meshes have no material
+        let material_manager = match self.material_manager{
+            Some(ref material_manager) => material_manager,
+            None => return Ok(mesh),
+        };
+
+        let base_material = mesh.lock().expect("failed to lock mesh").get_material_name();
+        if base_material == material_name{
+            return Ok(mesh);
+        }
+
+        let copy_name = String::from(base_name) + &copy_suffix;
+        if let Some(copy) = mesh_man_lck.get_mesh(&copy_name){
+            return Ok(copy);
+        }
+
+        let material = {
+            let mut mat_man_lck = material_manager.lock().expect("failed to lock material manager");
+            match mat_man_lck.get_material_by_name(material_name){
+                Some(material) => material,
+                None => return Err(AssetError::NotFound(
+                    format!("material {} of mesh {}", material_name, base_name)
+                )),
+            }
+        };
+
+        let mut copy = mesh.lock().expect("failed to lock mesh").clone();
+        copy.name = copy_name;
+        copy.set_material(material);
+
+        let copy = Arc::new(Mutex::new(copy));
+        mesh_man_lck.add_arc_mesh(copy.clone());
+        Ok(copy)
+    }
+
+    fn get_camera(&self) -> DefaultCamera{
+        self.new_camera()
+    }
+
+    fn get_scene(&self, scene_name: &str) -> Option<PrefabScene>{
+        self.scenes.get(scene_name).cloned()
+    }
 }
@@ -0,0 +1,102 @@
+use std::sync::{Arc, Mutex, Condvar};
+use std::time::{Duration, Instant};
+
+///A state which knows if it is final, `SharedState::wait()` returns once it is.
+pub trait FinalState: Clone {
+    ///Returns true if the state won't change anymore
+    fn is_done(&self) -> bool;
+}
+
+///A state which can be cloned to other threads, polled and waited on. It is the core of the
+/// `LoadHandle` and the `CommandHandle`.
+#[derive(Clone)]
+pub struct SharedState<T: FinalState> {
+    state: Arc<(Mutex<T>, Condvar)>,
+}
+
+impl<T: FinalState> SharedState<T>{
+    ///Creates the shared state with `initial`
+    pub fn new(initial: T) -> Self{
+        SharedState{
+            state: Arc::new((Mutex::new(initial), Condvar::new())),
+        }
+    }
+
+    ///Returns the current state
+    pub fn get(&self) -> T{
+        let &(ref state, _) = &*self.state;
+        state.lock().expect("failed to lock shared state").clone()
+    }
+
+    ///Sets a new state and wakes everyone who waits on it
+    pub fn set(&self, new_state: T){
+        let &(ref state, ref condvar) = &*self.state;
+        let mut state_lck = state.lock().expect("failed to lock shared state");
+        *state_lck = new_state;
+        condvar.notify_all();
+    }
+
+    ///Returns true if the state is final
+    pub fn is_done(&self) -> bool{
+        self.get().is_done()
+    }
+
+    ///Blocks until the state is final and returns it
+    pub fn wait(&self) -> T{
+        let &(ref state, ref condvar) = &*self.state;
+        let mut state_lck = state.lock().expect("failed to lock shared state");
+        while !state_lck.is_done(){
+            state_lck = condvar.wait(state_lck).expect("failed to wait for shared state");
+        }
+        state_lck.clone()
+    }
+
+    ///Like `wait()` but returns the current state after `timeout` at the latest.
+    pub fn wait_timeout(&self, timeout: Duration) -> T{
+        let start = Instant::now();
+        let &(ref state, ref condvar) = &*self.state;
+        let mut state_lck = state.lock().expect("failed to lock shared state");
+        while !state_lck.is_done(){
+            let elapsed = start.elapsed();
+            if elapsed >= timeout{
+                break;
+            }
+            state_lck = condvar.wait_timeout(state_lck, timeout - elapsed)
+            .expect("failed to wait for shared state").0;
+        }
+        state_lck.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    impl FinalState for u32{
+        fn is_done(&self) -> bool{
+            *self >= 2
+        }
+    }
+
+    #[test]
+    fn wait_returns_the_final_state_of_another_thread(){
+        let shared = SharedState::new(0u32);
+        let setter = shared.clone();
+        let thread = thread::spawn(move ||{
+            setter.set(1);
+            setter.set(2);
+        });
+
+        assert_eq!(shared.wait(), 2);
+        assert!(shared.is_done());
+        thread.join().expect("setter panicked");
+    }
+
+    #[test]
+    fn wait_timeout_returns_the_current_state(){
+        let shared = SharedState::new(1u32);
+        assert_eq!(shared.wait_timeout(Duration::from_millis(10)), 1);
+        assert!(!shared.is_done());
+    }
+}
@@ -3,6 +3,8 @@ use std::collections::BTreeMap;
 
 use core::resources::texture;
 use core::engine_settings;
+use core::resource_management::memory_budget::MemoryBudget;
+use core::asset_error::AssetError;
 
 use vulkano::device::{Device, Queue};
 use vulkano;
@@ -15,9 +17,17 @@ pub struct TextureManager {
     //Some more copys of structures needed to create more textures
     device: Arc<Device>,
     queue: Arc<Queue>,
-    engine_settings: Arc<Mutex<engine_settings::EngineSettings>>
+    engine_settings: Arc<Mutex<engine_settings::EngineSettings>>,
+
+    //limits the memory of the textures
+    budget: MemoryBudget,
+    //the size of all textures in bytes, updated whenever a texture is added or removed
+    memory_usage: usize,
 }
 
+///The textures which are created by the manager itself, they are never unloaded
+const BUILTIN_TEXTURES: [&str; 4] = ["none", "fallback_albedo", "fallback_normal", "fallback_physical"];
+
 
 impl TextureManager{
     ///Creates a new Texture manager with two textures:
@@ -39,7 +49,8 @@ impl TextureManager{
             device.clone(),
             queue.clone(),
         )
-        .build_with_name("none");
+        .build_with_name("none")
+        .expect("failed to load data/nothing.png, the engine can't run without its data folder");
 
         //The fallback textures
         let fallback_albedo = texture::TextureBuilder::from_image(
@@ -47,21 +58,24 @@ impl TextureManager{
             device.clone(),
             queue.clone(),
         )
-        .build_with_name("fallback_albedo");
+        .build_with_name("fallback_albedo")
+        .expect("failed to load data/fallback_alb.png, the engine can't run without its data folder");
 
         let fallback_normal = texture::TextureBuilder::from_image(
             "data/fallback_nrm.png",
             device.clone(),
             queue.clone(),
         )
-        .build_with_name("fallback_normal");
+        .build_with_name("fallback_normal")
+        .expect("failed to load data/fallback_nrm.png, the engine can't run without its data folder");
 
         let fallback_physical = texture::TextureBuilder::from_image(
             "data/fallback_physical.png",
             device.clone(),
             queue.clone(),
         )
-        .build_with_name("fallback_physical");
+        .build_with_name("fallback_physical")
+        .expect("failed to load data/fallback_physical.png, the engine can't run without its data folder");
 
         //Now store all the textures
         let mut current_textures = BTreeMap::new();
@@ -71,13 +85,18 @@ impl TextureManager{
         current_textures.insert(String::from("fallback_normal"), fallback_normal);
         current_textures.insert(String::from("fallback_physical"), fallback_physical);
 
+        let memory_usage = current_textures.values().fold(0, |sum, texture| sum + texture.get_byte_size());
+
         //Create the struct and return it
         TextureManager{
             textures: current_textures,
             //Some more copys of structures needed to create more textures
             device: device.clone(),
             queue: queue.clone(),
-            engine_settings: engine_settings.clone()
+            engine_settings: engine_settings.clone(),
+
+            budget: MemoryBudget::unlimited(),
+            memory_usage: memory_usage,
         }
     }
 
@@ -123,29 +142,125 @@ impl TextureManager{
 
     ///Adds a new texture to the manager, this will return an error if the texture is already in
     ///the manager
-    pub fn add_texture(&mut self, texture: Arc<texture::Texture>) -> Result<(), &'static str>{
+    pub fn add_texture(&mut self, texture: Arc<texture::Texture>) -> Result<(), AssetError>{
 
         //get the texture name and test the already used textures
         let name = texture.name.clone();
         //check if already in
         if self.textures.contains_key(&name){
-            return Err("This texture is already in th manager");
+            return Err(AssetError::AlreadyExists(format!("texture {}", name)));
         }
 
         //If the texture passed the name test, we can add it
+        self.memory_usage += texture.get_byte_size();
         self.textures.insert(name, texture);
         Ok({})
     }
 
     ///Removes the texture from the manager, keep in mind that any copy will live till it is droped
-    ///by its holder.
-    pub fn remove_texture(mut self, texture_name: &str) -> Result<(), &'static str>{
+    ///by its holder. The none and fallback textures can't be removed.
+    pub fn remove_texture(&mut self, texture_name: &str) -> Result<(), &'static str>{
+        if BUILTIN_TEXTURES.contains(&texture_name){
+            return Err("the none and fallback textures can't be removed");
+        }
         match self.textures.remove(&String::from(texture_name)){
-            Some(_) => return Ok({}),
+            Some(texture) => {
+                self.memory_usage -= texture.get_byte_size();
+                return Ok({})
+            },
             None => Err("the texture could not removed because it is not in the manager"),
         }
     }
 
+    ///Removes all textures which are only referenced by the manager, returns their names.
+    ///NOTE: Unload unused materials first, they keep their textures alive.
+    pub fn collect_garbage(&mut self) -> Vec<String>{
+        let unused: Vec<String> = self.get_unused().into_iter().map(|(name, _)| name).collect();
+        for name in unused.iter(){
+            self.remove_unused(name);
+        }
+        unused
+    }
+
+    ///Removes the texture `name` and its size from the memory usage
+    fn remove_unused(&mut self, name: &str){
+        if let Some(texture) = self.textures.remove(name){
+            self.memory_usage -= texture.get_byte_size();
+        }
+    }
+
+    ///Returns (name, bytes) of every texture which is only referenced by the manager
+    fn get_unused(&self) -> Vec<(String, usize)>{
+        self.textures.iter()
+        .filter(|&(name, texture)| {
+            Arc::strong_count(texture) == 1 && !BUILTIN_TEXTURES.contains(&name.as_str())
+        })
+        .map(|(name, texture)| (name.clone(), texture.get_byte_size()))
+        .collect()
+    }
+
+    ///Returns the size of all textures including their mip maps in bytes
+    pub fn get_memory_usage(&self) -> usize{
+        self.memory_usage
+    }
+
+    ///Sets the memory budget of the textures, it is enforced by `enforce_memory_budget()`
+    pub fn set_memory_budget(&mut self, budget: MemoryBudget){
+        self.budget = budget;
+    }
+
+    ///Returns the current memory budget
+    pub fn get_memory_budget(&self) -> &MemoryBudget{
+        &self.budget
+    }
+
+    ///Evicts unused textures until the budget is met, returns the names of the evicted ones.
+    /// Textures in use are never evicted, so the usage might stay over budget.
+    pub fn enforce_memory_budget(&mut self) -> Vec<String>{
+        if self.budget.get_limit().is_none(){
+            return Vec::new();
+        }
+
+        let usage = self.get_memory_usage();
+        if !self.budget.is_exceeded(usage){
+            return Vec::new();
+        }
+
+        let (evicted, _) = self.budget.select_evictions(usage, self.get_unused());
+        for name in evicted.iter(){
+            self.remove_unused(name);
+        }
+        evicted
+    }
+
+    ///Returns (name, path) of every texture which was loaded from a file
+    pub fn get_texture_sources(&self) -> Vec<(String, String)>{
+        self.textures.iter()
+        .filter(|&(_, texture)| texture.is_reloadable())
+        .map(|(name, texture)| (name.clone(), texture.get_original_path()))
+        .collect()
+    }
+
+    ///Returns the texture `name`, or `None` if it is not in the manager
+    pub fn find_texture(&self, name: &str) -> Option<Arc<texture::Texture>>{
+        self.textures.get(name).cloned()
+    }
+
+    ///Replaces the texture which has the name of `new_texture` and returns the old one, so that
+    /// its users can be updated. Decode the new texture before locking the manager, this only
+    /// swaps it.
+    pub fn swap_texture(&mut self, new_texture: Arc<texture::Texture>)
+    -> Result<Arc<texture::Texture>, AssetError>{
+        let old_texture = match self.textures.get(&new_texture.name){
+            Some(texture) => texture.clone(),
+            None => return Err(AssetError::NotFound(format!("texture {} in the texture manager", new_texture.name))),
+        };
+
+        self.memory_usage = self.memory_usage - old_texture.get_byte_size() + new_texture.get_byte_size();
+        self.textures.insert(new_texture.name.clone(), new_texture);
+        Ok(old_texture)
+    }
+
     ///A small helper function which returns the used engine settings, good if you have to transport
     ///much data between function
     pub fn get_settings(&self) -> Arc<Mutex<engine_settings::EngineSettings>>{
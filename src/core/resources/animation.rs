@@ -0,0 +1,445 @@
+use cgmath::*;
+
+use core::next_tree::transform::NodeTransform;
+use core::resources::skeleton::Skeleton;
+
+use std::sync::Arc;
+
+///How the values between two keyframes are calculated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    ///Linear interpolation, rotations are blended along the shortest path.
+    Linear,
+    ///The value of the last keyframe is kept till the next one.
+    Step,
+}
+
+///The keyframe values of a channel, there has to be one value per keyframe time.
+#[derive(Clone, Debug)]
+pub enum ChannelValues {
+    Translation(Vec<Vector3<f32>>),
+    Rotation(Vec<Quaternion<f32>>),
+    Scale(Vec<Vector3<f32>>),
+}
+
+///Animates one property of one joint.
+#[derive(Clone, Debug)]
+pub struct Channel {
+    ///Index of the animated joint in the skeleton
+    pub joint: usize,
+    ///The keyframe times in seconds, sorted ascending
+    pub times: Vec<f32>,
+    pub values: ChannelValues,
+    pub interpolation: Interpolation,
+}
+
+impl Channel{
+    ///Overwrites the animated property of `transform` with the value at `time`.
+    pub fn apply(&self, time: f32, transform: &mut NodeTransform){
+        let (first, second, factor) = match find_keyframes(&self.times, time, self.interpolation){
+            Some(keys) => keys,
+            None => return,
+        };
+
+        match self.values{
+            ChannelValues::Translation(ref values) => {
+                if let Some(value) = sample_vector(values, first, second, factor){
+                    transform.disp = value;
+                }
+            },
+            ChannelValues::Rotation(ref values) => {
+                if let Some(value) = sample_rotation(values, first, second, factor){
+                    transform.rot = value;
+                }
+            },
+            ChannelValues::Scale(ref values) => {
+                if let Some(value) = sample_vector(values, first, second, factor){
+                    transform.scale = value;
+                }
+            },
+        }
+    }
+
+    ///Returns the time of the last keyframe
+    pub fn get_duration(&self) -> f32{
+        match self.times.last(){
+            Some(time) => *time,
+            None => 0.0,
+        }
+    }
+}
+
+///Returns the indices of the two keyframes around `time` and how far `time` is between them
+/// (0.0 - 1.0). Before the first and after the last keyframe the outer keyframe is used.
+/// Returns `None` if there are no keyframes.
+pub fn find_keyframes(times: &[f32], time: f32, interpolation: Interpolation) -> Option<(usize, usize, f32)>{
+    if times.is_empty(){
+        return None;
+    }
+
+    let last = times.len() - 1;
+    if time <= times[0]{
+        return Some((0, 0, 0.0));
+    }
+    if time >= times[last]{
+        return Some((last, last, 0.0));
+    }
+
+    //find the first keyframe after time
+    let next = match times.iter().position(|key_time| *key_time > time){
+        Some(index) => index,
+        None => last,
+    };
+    let previous = next - 1;
+
+    match interpolation{
+        Interpolation::Step => Some((previous, previous, 0.0)),
+        Interpolation::Linear => {
+            let span = times[next] - times[previous];
+            let factor = if span > 0.0 { (time - times[previous]) / span } else { 0.0 };
+            Some((previous, next, factor))
+        },
+    }
+}
+
+///Linear interpolation between two vectors of `values`
+pub fn sample_vector(values: &[Vector3<f32>], first: usize, second: usize, factor: f32) -> Option<Vector3<f32>>{
+    match (values.get(first), values.get(second)){
+        (Some(a), Some(b)) => Some(a.lerp(*b, factor)),
+        _ => None,
+    }
+}
+
+///Normalized linear interpolation between two rotations of `values`, along the shortest path.
+pub fn sample_rotation(values: &[Quaternion<f32>], first: usize, second: usize, factor: f32) -> Option<Quaternion<f32>>{
+    match (values.get(first), values.get(second)){
+        (Some(a), Some(b)) => {
+            let b = if a.dot(*b) < 0.0 { -*b } else { *b };
+            Some((*a * (1.0 - factor) + b * factor).normalize())
+        },
+        _ => None,
+    }
+}
+
+///A set of channels which animate the joints of one skeleton.
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    pub name: String,
+    ///Name of the skeleton in the animation manager this clip is made for
+    pub skeleton: String,
+    pub channels: Vec<Channel>,
+    duration: f32,
+}
+
+impl AnimationClip{
+    ///Creates a new clip, the duration is the time of the last keyframe of all channels.
+    pub fn new(name: &str, skeleton: &str, channels: Vec<Channel>) -> Self{
+        let duration = channels.iter().fold(0.0, |max: f32, channel| max.max(channel.get_duration()));
+        AnimationClip{
+            name: String::from(name),
+            skeleton: String::from(skeleton),
+            channels: channels,
+            duration: duration,
+        }
+    }
+
+    ///Returns the length of this clip in seconds
+    pub fn get_duration(&self) -> f32{
+        self.duration
+    }
+
+    ///Returns the local transform of every joint of `skeleton` at `time`. Joints without a
+    /// channel keep their rest transform.
+    pub fn sample_pose(&self, skeleton: &Skeleton, time: f32) -> Vec<NodeTransform>{
+        let mut pose = skeleton.get_rest_pose();
+        for channel in self.channels.iter(){
+            if let Some(transform) = pose.get_mut(channel.joint){
+                channel.apply(time, transform);
+            }
+        }
+        pose
+    }
+}
+
+///Blends the joint matrices of one vertex by its `weights`. Returns the identity if all weights
+/// are zero.
+pub fn blend_joint_matrices(joint_matrices: &[Matrix4<f32>], joints: [u32; 4], weights: [f32; 4]) -> Matrix4<f32>{
+    let mut blended = Matrix4::zero();
+    let mut weight_sum = 0.0;
+    for i in 0..4{
+        if weights[i] == 0.0{
+            continue;
+        }
+        if let Some(matrix) = joint_matrices.get(joints[i] as usize){
+            blended = blended + *matrix * weights[i];
+            weight_sum += weights[i];
+        }
+    }
+
+    if weight_sum == 0.0{
+        Matrix4::identity()
+    }else{
+        blended
+    }
+}
+
+///Plays an `AnimationClip` on a `Skeleton` and keeps the resulting joint matrices.
+#[derive(Clone)]
+pub struct AnimationPlayer {
+    skeleton: Arc<Skeleton>,
+    clip: Arc<AnimationClip>,
+    time: f32,
+    ///Multiplies the time, 1.0 is normal speed
+    pub speed: f32,
+    ///If true the clip starts at the beginning after reaching its end
+    pub looping: bool,
+    is_playing: bool,
+    joint_matrices: Vec<Matrix4<f32>>,
+}
+
+impl AnimationPlayer{
+    ///Creates a player which plays `clip` once on `skeleton`
+    pub fn new(skeleton: Arc<Skeleton>, clip: Arc<AnimationClip>) -> Self{
+        let joint_matrices = skeleton.get_joint_matrices(&clip.sample_pose(&skeleton, 0.0));
+        AnimationPlayer{
+            skeleton: skeleton,
+            clip: clip,
+            time: 0.0,
+            speed: 1.0,
+            looping: false,
+            is_playing: true,
+            joint_matrices: joint_matrices,
+        }
+    }
+
+    ///Lets the clip loop
+    pub fn with_looping(mut self) -> Self{
+        self.looping = true;
+        self
+    }
+
+    ///Sets the playback speed
+    pub fn with_speed(mut self, speed: f32) -> Self{
+        self.speed = speed;
+        self
+    }
+
+    ///Advances the animation by `delta` seconds and recalculates the joint matrices.
+    pub fn update(&mut self, delta: f32){
+        if !self.is_playing{
+            return;
+        }
+
+        let duration = self.clip.get_duration();
+        self.time += delta * self.speed;
+
+        if self.looping && duration > 0.0{
+            self.time = self.time % duration;
+            if self.time < 0.0{
+                self.time += duration;
+            }
+        }else if self.time >= duration || self.time < 0.0{
+            self.time = self.time.max(0.0).min(duration);
+            self.is_playing = false;
+        }
+
+        let pose = self.clip.sample_pose(&self.skeleton, self.time);
+        self.joint_matrices = self.skeleton.get_joint_matrices(&pose);
+    }
+
+    ///Returns the current joint matrices, one per joint of the skeleton
+    pub fn get_joint_matrices(&self) -> &Vec<Matrix4<f32>>{
+        &self.joint_matrices
+    }
+
+    ///Returns the current time in the clip
+    pub fn get_time(&self) -> f32{
+        self.time
+    }
+
+    ///Jumps to `time` in the clip
+    pub fn set_time(&mut self, time: f32){
+        self.time = time;
+    }
+
+    ///Returns false if a non looping clip reached its end or the player was paused
+    pub fn is_playing(&self) -> bool{
+        self.is_playing
+    }
+
+    pub fn pause(&mut self){
+        self.is_playing = false;
+    }
+
+    pub fn resume(&mut self){
+        self.is_playing = true;
+    }
+
+    ///Returns the clip which is played
+    pub fn get_clip(&self) -> Arc<AnimationClip>{
+        self.clip.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::resources::skeleton::Joint;
+
+    const EPSILON: f32 = 1.0e-5;
+
+    fn assert_rotation_close(a: Quaternion<f32>, b: Quaternion<f32>){
+        //q and -q are the same rotation
+        assert!(a.dot(b).abs() > 1.0 - EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_matrix_close(a: &Matrix4<f32>, b: &Matrix4<f32>){
+        let a: &[f32; 16] = a.as_ref();
+        let b: &[f32; 16] = b.as_ref();
+        for (x, y) in a.iter().zip(b.iter()){
+            assert!((x - y).abs() < EPSILON, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn keyframes_are_clamped_outside_of_the_track(){
+        let times = [1.0, 2.0, 3.0];
+        assert_eq!(find_keyframes(&times, 0.0, Interpolation::Linear), Some((0, 0, 0.0)));
+        assert_eq!(find_keyframes(&times, 1.0, Interpolation::Linear), Some((0, 0, 0.0)));
+        assert_eq!(find_keyframes(&times, 3.0, Interpolation::Linear), Some((2, 2, 0.0)));
+        assert_eq!(find_keyframes(&times, 7.0, Interpolation::Step), Some((2, 2, 0.0)));
+        assert_eq!(find_keyframes(&[], 1.0, Interpolation::Linear), None);
+    }
+
+    #[test]
+    fn linear_keyframes_are_interpolated(){
+        let times = [0.0, 2.0, 3.0];
+        assert_eq!(find_keyframes(&times, 0.5, Interpolation::Linear), Some((0, 1, 0.25)));
+        assert_eq!(find_keyframes(&times, 2.5, Interpolation::Linear), Some((1, 2, 0.5)));
+
+        let values = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(4.0, -2.0, 8.0)];
+        assert_eq!(sample_vector(&values, 0, 1, 0.25), Some(Vector3::new(1.0, -0.5, 2.0)));
+        assert_eq!(sample_vector(&values, 0, 2, 0.25), None);
+    }
+
+    #[test]
+    fn step_keyframes_keep_the_previous_value(){
+        let times = [0.0, 2.0, 3.0];
+        assert_eq!(find_keyframes(&times, 0.5, Interpolation::Step), Some((0, 0, 0.0)));
+        assert_eq!(find_keyframes(&times, 1.99, Interpolation::Step), Some((0, 0, 0.0)));
+        assert_eq!(find_keyframes(&times, 2.5, Interpolation::Step), Some((1, 1, 0.0)));
+    }
+
+    #[test]
+    fn rotations_are_blended_like_slerp(){
+        let from = Quaternion::from_angle_y(Deg(0.0));
+        let to = Quaternion::from_angle_y(Deg(90.0));
+        let values = [from, to];
+
+        let half = sample_rotation(&values, 0, 1, 0.5).expect("failed to sample rotation");
+        assert_rotation_close(half, from.slerp(to, 0.5));
+        assert_rotation_close(half, Quaternion::from_angle_y(Deg(45.0)));
+        assert!((half.magnitude() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn rotations_take_the_shortest_path(){
+        let from = Quaternion::from_angle_y(Deg(0.0));
+        //the same rotation as 90 degree, but with a negative dot product
+        let to = -Quaternion::from_angle_y(Deg(90.0));
+        let values = [from, to];
+
+        let half = sample_rotation(&values, 0, 1, 0.5).expect("failed to sample rotation");
+        assert_rotation_close(half, Quaternion::from_angle_y(Deg(45.0)));
+    }
+
+    fn test_skeleton() -> Skeleton{
+        let root_rest = NodeTransform::new(
+            Vector3::new(0.0, 1.0, 0.0),
+            Quaternion::one(),
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+        let child_rest = NodeTransform::new(
+            Vector3::new(0.0, 2.0, 0.0),
+            Quaternion::one(),
+            Vector3::new(1.0, 1.0, 1.0),
+        );
+
+        let mut skeleton = Skeleton::new("test_skeleton");
+        //the inverse bind matrices undo the rest pose, so the joint matrices are the identity at rest
+        skeleton.add_joint(Joint::new("root")
+            .with_rest_transform(root_rest)
+            .with_inverse_bind_matrix(Matrix4::from_translation(Vector3::new(0.0, -1.0, 0.0)))
+        );
+        skeleton.add_joint(Joint::new("child")
+            .with_parent(0)
+            .with_rest_transform(child_rest)
+            .with_inverse_bind_matrix(Matrix4::from_translation(Vector3::new(0.0, -3.0, 0.0)))
+        );
+        skeleton
+    }
+
+    fn test_clip() -> AnimationClip{
+        AnimationClip::new("test_clip", "test_skeleton", vec![
+            Channel{
+                joint: 0,
+                times: vec![0.0, 1.0],
+                values: ChannelValues::Translation(vec![Vector3::new(0.0, 1.0, 0.0), Vector3::new(2.0, 1.0, 0.0)]),
+                interpolation: Interpolation::Linear,
+            },
+            Channel{
+                joint: 1,
+                times: vec![0.0, 1.0],
+                values: ChannelValues::Rotation(vec![Quaternion::one(), Quaternion::from_angle_z(Deg(90.0))]),
+                interpolation: Interpolation::Step,
+            },
+        ])
+    }
+
+    #[test]
+    fn pose_samples_channels_and_keeps_rest_values(){
+        let skeleton = test_skeleton();
+        let clip = test_clip();
+        assert_eq!(clip.get_duration(), 1.0);
+
+        let pose = clip.sample_pose(&skeleton, 0.5);
+        assert_eq!(pose.len(), 2);
+        assert_eq!(pose[0].disp, Vector3::new(1.0, 1.0, 0.0));
+        assert_rotation_close(pose[0].rot, Quaternion::one());
+        //step interpolation keeps the first rotation, the translation is not animated
+        assert_rotation_close(pose[1].rot, Quaternion::one());
+        assert_eq!(pose[1].disp, Vector3::new(0.0, 2.0, 0.0));
+
+        let clamped = clip.sample_pose(&skeleton, 10.0);
+        assert_eq!(clamped[0].disp, Vector3::new(2.0, 1.0, 0.0));
+        assert_rotation_close(clamped[1].rot, Quaternion::from_angle_z(Deg(90.0)));
+    }
+
+    #[test]
+    fn joint_matrices_are_identity_in_rest_pose(){
+        let skeleton = test_skeleton();
+        for matrix in skeleton.get_joint_matrices(&skeleton.get_rest_pose()).iter(){
+            assert_matrix_close(matrix, &Matrix4::identity());
+        }
+    }
+
+    #[test]
+    fn joint_matrices_follow_the_parent(){
+        let skeleton = Arc::new(test_skeleton());
+        let mut player = AnimationPlayer::new(skeleton, Arc::new(test_clip()));
+        player.update(1.0);
+        assert!(!player.is_playing());
+
+        let matrices = player.get_joint_matrices();
+        //the root moved by 2 along x
+        let root_offset = Matrix4::from_translation(Vector3::new(2.0, 0.0, 0.0));
+        assert_matrix_close(&matrices[0], &root_offset);
+
+        //the child moved with the root and rotated around its own origin at (2, 3, 0)
+        let child_origin = Vector3::new(2.0, 3.0, 0.0);
+        let expected_child = Matrix4::from_translation(child_origin)
+            * Matrix4::from(Quaternion::from_angle_z(Deg(90.0)))
+            * Matrix4::from_translation(Vector3::new(0.0, -3.0, 0.0));
+        assert_matrix_close(&matrices[1], &expected_child);
+    }
+}
@@ -7,7 +7,7 @@ use std::sync::{Arc, Mutex};
 use render::shader::shader_inputs::default_data;
 use core::engine_settings::{EngineSettings,CameraSettings};
 use input::keymap::KeyMap;
-use core::next_tree::attributes::NodeAttributes;
+use core::next_tree::attributes::{NodeAttributes, LAYER_ALL};
 
 use std::time::{Instant};
 
@@ -44,6 +44,10 @@ pub trait Camera {
     ///Returns the uniform data of this camera as an `default_data::ty::Data`. The transform field
     /// has to be set to an identity matrix.
     fn as_uniform_data(&self) -> default_data::ty::Data;
+    ///Returns the layer mask of the nodes this camera renders
+    fn get_render_layers(&self) -> u32;
+    ///Sets the layer mask of the nodes this camera renders
+    fn set_render_layers(&mut self, layer_mask: u32);
 }
 
 
@@ -62,10 +66,20 @@ pub struct DefaultCamera {
     fov: f32,
 
     current_cam_settings: CameraSettings,
+    ///Only nodes in at least one of this layers are rendered
+    render_layers: u32,
 
     settings: Arc<Mutex<EngineSettings>>,
 }
 
+impl DefaultCamera{
+    ///Returns the current field of view of this camera in degree
+    #[inline]
+    pub fn get_fov(&self) -> f32{
+        self.fov
+    }
+}
+
 ///The Camera can use the opengl math beacuse be do
 ///```
 ///gl_Position.y = -gl_Position.y;
@@ -98,6 +112,7 @@ impl Camera for DefaultCamera{
             fov: fov,
 
             current_cam_settings: current_cam_settings,
+            render_layers: LAYER_ALL,
             settings: settings,
         }
     }
@@ -131,6 +146,7 @@ impl Camera for DefaultCamera{
             fov: fov,
 
             current_cam_settings: current_cam_settings,
+            render_layers: LAYER_ALL,
 
             settings: settings,
 
@@ -141,6 +157,8 @@ impl Camera for DefaultCamera{
 
     ///Updates the camera internal node transform to return the correct values for the view matrix.
     fn update(&mut self, transform: &Decomposed<Vector3<f32>, Quaternion<f32>>){
+        //the position is read back by get_position() and the uniform data
+        self.node_transform = *transform;
         //first update the view matrix
         let front = transform.rot.rotate_vector(Vector3::new(0.0,0.0,1.0));
         let tmp_target: Vector3<f32> = transform.disp + front;
@@ -334,4 +352,12 @@ impl Camera for DefaultCamera{
         uniform_data
     }
 
+    fn get_render_layers(&self) -> u32{
+        self.render_layers
+    }
+
+    fn set_render_layers(&mut self, layer_mask: u32){
+        self.render_layers = layer_mask;
+    }
+
 }
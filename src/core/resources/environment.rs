@@ -0,0 +1,841 @@
+use std::sync::Arc;
+use std::f32::consts::PI;
+
+use cgmath::*;
+
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{Dimensions, ImageUsage, ImageLayout, MipmapsCount};
+use vulkano::image::immutable::ImmutableImage;
+use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
+use vulkano::sync::GpuFuture;
+
+use core::asset_error::AssetError;
+
+///The largest face size of a sky which was created from a panorama
+const MAX_SKY_SIZE: u32 = 1024;
+///The smallest face size of a sky which was created from a panorama
+const MIN_SKY_SIZE: u32 = 64;
+///Face size of the diffuse irradiance map
+const IRRADIANCE_SIZE: u32 = 32;
+///The sky is reduced to this face size before the irradiance is integrated over it
+const IRRADIANCE_SOURCE_SIZE: u32 = 16;
+///Face size of the first level of the prefiltered specular map, every level has half the size
+const SPECULAR_SIZE: u32 = 128;
+///Samples per texel while prefiltering the specular map
+const SPECULAR_SAMPLES: u32 = 128;
+///Width and height of the brdf lookup table
+const BRDF_LUT_SIZE: u32 = 128;
+///Samples per texel while integrating the brdf
+const BRDF_SAMPLES: u32 = 256;
+
+///A linear rgb image on the cpu, used while creating an environment
+pub struct FloatImage {
+    pub width: u32,
+    pub height: u32,
+    ///The texels row by row, starting in the upper left corner
+    pub texels: Vec<[f32; 3]>,
+}
+
+impl FloatImage{
+    fn get_texel(&self, x: u32, y: u32) -> [f32; 3]{
+        self.texels[(y * self.width + x) as usize]
+    }
+
+    ///Samples bilinear at `u`, `v` in 0..1, wraps horizontally and clamps vertically
+    fn sample(&self, u: f32, v: f32) -> [f32; 3]{
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).max(0.0).min((self.height - 1) as f32);
+
+        let x_floor = x.floor();
+        let y_floor = y.floor();
+        let frac_x = x - x_floor;
+        let frac_y = y - y_floor;
+
+        let width = self.width as i64;
+        let x0 = ((x_floor as i64 % width + width) % width) as u32;
+        let x1 = (x0 + 1) % self.width;
+        let y0 = y_floor as u32;
+        let y1 = (y0 + 1).min(self.height - 1);
+
+        let top = lerp_texel(self.get_texel(x0, y0), self.get_texel(x1, y0), frac_x);
+        let bottom = lerp_texel(self.get_texel(x0, y1), self.get_texel(x1, y1), frac_x);
+        lerp_texel(top, bottom, frac_y)
+    }
+}
+
+///A cube map on the cpu. The faces are stored in the order +x, -x, +y, -y, +z, -z with the
+/// orientation Vulkan uses when sampling a cube map.
+#[derive(Clone)]
+pub struct CpuCube {
+    size: u32,
+    faces: Vec<Vec<[f32; 3]>>,
+}
+
+impl CpuCube{
+    ///Creates a cube map of `size` x `size` texels per face, `texel` is called with the
+    /// normalized direction through the center of every texel.
+    pub fn from_fn<F>(size: u32, texel: F) -> Self where F: Fn(Vector3<f32>) -> [f32; 3]{
+        let mut faces = Vec::with_capacity(6);
+        for face in 0..6{
+            let mut texels = Vec::with_capacity((size * size) as usize);
+            for y in 0..size{
+                for x in 0..size{
+                    texels.push(texel(texel_direction(face, x, y, size)));
+                }
+            }
+            faces.push(texels);
+        }
+
+        CpuCube{
+            size: size,
+            faces: faces,
+        }
+    }
+
+    ///Projects an equirectangular panorama onto a cube, the upper border of the image is the
+    /// sky above (+y).
+    pub fn from_equirectangular(panorama: &FloatImage) -> Self{
+        let size = (panorama.width / 4).max(MIN_SKY_SIZE).min(MAX_SKY_SIZE);
+        CpuCube::from_fn(size, |dir|{
+            let u = 0.5 + dir.z.atan2(dir.x) / (2.0 * PI);
+            let v = dir.y.max(-1.0).min(1.0).acos() / PI;
+            panorama.sample(u, v)
+        })
+    }
+
+    ///Creates a cube from six square images of the same size in the order +x, -x, +y, -y, +z, -z
+    pub fn from_faces(faces: Vec<FloatImage>) -> Result<Self, String>{
+        if faces.len() != 6{
+            return Err(format!("a cube map needs 6 faces, got {}", faces.len()));
+        }
+
+        let size = faces[0].width;
+        for face in faces.iter(){
+            if face.width != size || face.height != size{
+                return Err(format!(
+                    "all cube faces have to be square and of the same size, expected {0}x{0} got {1}x{2}",
+                    size, face.width, face.height
+                ));
+            }
+        }
+
+        Ok(CpuCube{
+            size: size,
+            faces: faces.into_iter().map(|face| face.texels).collect(),
+        })
+    }
+
+    ///Returns the size of one face in texels
+    pub fn get_size(&self) -> u32{
+        self.size
+    }
+
+    ///Samples bilinear in `dir`, the filtering stops at the borders of a face
+    pub fn sample(&self, dir: Vector3<f32>) -> [f32; 3]{
+        let (face, s, t) = direction_to_face(dir);
+        let max = (self.size - 1) as f32;
+        let x = (s * self.size as f32 - 0.5).max(0.0).min(max);
+        let y = (t * self.size as f32 - 0.5).max(0.0).min(max);
+
+        let x0 = x.floor() as u32;
+        let y0 = y.floor() as u32;
+        let x1 = (x0 + 1).min(self.size - 1);
+        let y1 = (y0 + 1).min(self.size - 1);
+        let frac_x = x - x0 as f32;
+        let frac_y = y - y0 as f32;
+
+        let texels = &self.faces[face];
+        let get = |x: u32, y: u32| texels[(y * self.size + x) as usize];
+
+        let top = lerp_texel(get(x0, y0), get(x1, y0), frac_x);
+        let bottom = lerp_texel(get(x0, y1), get(x1, y1), frac_x);
+        lerp_texel(top, bottom, frac_y)
+    }
+
+    ///Returns a copy of this cube with `size` texels per face
+    pub fn resized(&self, size: u32) -> Self{
+        CpuCube::from_fn(size, |dir| self.sample(dir))
+    }
+
+    ///Returns the faces one after another as rgba floats, like the gpu expects them
+    fn to_rgba(&self) -> Vec<f32>{
+        let mut data = Vec::with_capacity((self.size * self.size * 6 * 4) as usize);
+        for face in self.faces.iter(){
+            for texel in face.iter(){
+                data.extend_from_slice(&[texel[0], texel[1], texel[2], 1.0]);
+            }
+        }
+        data
+    }
+}
+
+///A sky and the image based lighting maps which were generated from it. Can be loaded with
+/// `TextureBuilder::build_environment()` and is used for the whole scene once it is set with
+/// `AssetManager::set_environment()`.
+#[derive(Clone)]
+pub struct Environment {
+    ///A name which can be used to reference the environment
+    pub name: String,
+    //The cube map which is drawn behind the scene
+    sky: Arc<ImmutableImage<Format>>,
+    //Cosine weighted irradiance, used for diffuse lighting
+    irradiance: Arc<ImmutableImage<Format>>,
+    //The sky convolved with the GGX lobe, the roughness increases with each mip level
+    specular: Arc<ImmutableImage<Format>>,
+    //Scale and bias to F0 based on the view angle (x) and the roughness (y)
+    brdf_lut: Arc<ImmutableImage<Format>>,
+
+    cube_sampler: Arc<Sampler>,
+    lut_sampler: Arc<Sampler>,
+    specular_levels: u32,
+    //Scales the sky as well as the ambient light
+    intensity: f32,
+}
+
+impl Environment{
+    ///Generates the irradiance, the prefiltered specular map and the brdf lookup table from `sky`
+    /// and uploads everything. This runs on the cpu and takes a moment, so better call it on a
+    /// loading thread.
+    pub fn from_sky(
+        name: &str,
+        sky: CpuCube,
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+    ) -> Result<Self, AssetError>{
+        let irradiance = generate_irradiance(&sky.resized(IRRADIANCE_SOURCE_SIZE), IRRADIANCE_SIZE);
+        let specular_levels = generate_specular(&sky);
+        let brdf_lut = generate_brdf_lut(BRDF_LUT_SIZE);
+
+        Environment::from_maps(
+            name,
+            &[sky],
+            &[irradiance],
+            &specular_levels,
+            brdf_lut,
+            BRDF_LUT_SIZE,
+            device,
+            queue
+        )
+    }
+
+    ///Creates a black environment with 1x1 texel maps, is used by the renderer if no
+    /// environment is set
+    pub fn black(device: Arc<Device>, queue: Arc<Queue>) -> Result<Self, AssetError>{
+        let black = CpuCube::from_fn(1, |_| [0.0; 3]);
+        Environment::from_maps(
+            "black_environment",
+            &[black.clone()],
+            &[black.clone()],
+            &[black],
+            vec![0.0; 2],
+            1,
+            device,
+            queue
+        )
+    }
+
+    fn from_maps(
+        name: &str,
+        sky: &[CpuCube],
+        irradiance: &[CpuCube],
+        specular: &[CpuCube],
+        brdf_lut: Vec<f32>,
+        brdf_lut_size: u32,
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+    ) -> Result<Self, AssetError>{
+        let sky_image = upload_cube(name, sky, device.clone(), queue.clone())?;
+        let irradiance_image = upload_cube(name, irradiance, device.clone(), queue.clone())?;
+        let specular_image = upload_cube(name, specular, device.clone(), queue.clone())?;
+
+        let (lut_image, lut_future) = ImmutableImage::from_iter(
+            brdf_lut.into_iter(),
+            Dimensions::Dim2d{ width: brdf_lut_size, height: brdf_lut_size },
+            Format::R32G32Sfloat,
+            queue.clone()
+        ).map_err(|er| AssetError::GpuUpload(
+            format!("failed to create the brdf lookup table of {}: {:?}", name, er)
+        ))?;
+        //drop the future to wait for gpu
+        drop(lut_future);
+
+        let cube_sampler = Sampler::new(
+            device.clone(),
+            Filter::Linear,
+            Filter::Linear,
+            MipmapMode::Linear,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            0.0,
+            1.0,
+            0.0,
+            specular.len() as f32,
+        ).map_err(|er| AssetError::GpuUpload(
+            format!("failed to create the cube sampler of {}: {:?}", name, er)
+        ))?;
+
+        let lut_sampler = Sampler::new(
+            device.clone(),
+            Filter::Linear,
+            Filter::Linear,
+            MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+        ).map_err(|er| AssetError::GpuUpload(
+            format!("failed to create the lookup table sampler of {}: {:?}", name, er)
+        ))?;
+
+        Ok(Environment{
+            name: String::from(name),
+            sky: sky_image,
+            irradiance: irradiance_image,
+            specular: specular_image,
+            brdf_lut: lut_image,
+            cube_sampler: cube_sampler,
+            lut_sampler: lut_sampler,
+            specular_levels: specular.len() as u32,
+            intensity: 1.0,
+        })
+    }
+
+    ///Sets the factor the sky and the ambient light are scaled with
+    #[inline]
+    pub fn with_intensity(mut self, intensity: f32) -> Self{
+        self.intensity = intensity;
+        self
+    }
+
+    ///Sets the factor the sky and the ambient light are scaled with
+    #[inline]
+    pub fn set_intensity(&mut self, intensity: f32){
+        self.intensity = intensity;
+    }
+
+    ///Returns the factor the sky and the ambient light are scaled with
+    #[inline]
+    pub fn get_intensity(&self) -> f32{
+        self.intensity
+    }
+
+    ///Returns the sky cube map
+    #[inline]
+    pub fn get_sky(&self) -> Arc<ImmutableImage<Format>>{
+        self.sky.clone()
+    }
+
+    ///Returns the diffuse irradiance cube map
+    #[inline]
+    pub fn get_irradiance(&self) -> Arc<ImmutableImage<Format>>{
+        self.irradiance.clone()
+    }
+
+    ///Returns the prefiltered specular cube map
+    #[inline]
+    pub fn get_specular(&self) -> Arc<ImmutableImage<Format>>{
+        self.specular.clone()
+    }
+
+    ///Returns the brdf integration lookup table
+    #[inline]
+    pub fn get_brdf_lut(&self) -> Arc<ImmutableImage<Format>>{
+        self.brdf_lut.clone()
+    }
+
+    ///Returns the trilinear sampler used for all cube maps
+    #[inline]
+    pub fn get_cube_sampler(&self) -> Arc<Sampler>{
+        self.cube_sampler.clone()
+    }
+
+    ///Returns the sampler of the lookup table
+    #[inline]
+    pub fn get_lut_sampler(&self) -> Arc<Sampler>{
+        self.lut_sampler.clone()
+    }
+
+    ///Returns the mip level of the specular map which belongs to a roughness of 1.0
+    #[inline]
+    pub fn get_max_lod(&self) -> f32{
+        (self.specular_levels - 1) as f32
+    }
+}
+
+///Uploads the mip `levels` of a cube map, the first level is the largest one
+fn upload_cube(
+    name: &str,
+    levels: &[CpuCube],
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+) -> Result<Arc<ImmutableImage<Format>>, AssetError>{
+    let upload_error = |er: String| AssetError::GpuUpload(
+        format!("failed to upload a cube map of {}: {}", name, er)
+    );
+
+    let usage = ImageUsage{
+        transfer_destination: true,
+        sampled: true,
+        .. ImageUsage::none()
+    };
+
+    let (image, initialization) = ImmutableImage::uninitialized(
+        device.clone(),
+        Dimensions::Cubemap{ size: levels[0].size },
+        Format::R32G32B32A32Sfloat,
+        MipmapsCount::Specific(levels.len() as u32),
+        usage,
+        ImageLayout::ShaderReadOnlyOptimal,
+        Some(queue.family())
+    ).map_err(|er| upload_error(format!("{:?}", er)))?;
+    //Every level is copied to the same image
+    let initialization = Arc::new(initialization);
+
+    let mut command_buffer = AutoCommandBufferBuilder::new(device.clone(), queue.family())
+    .map_err(|er| upload_error(format!("{:?}", er)))?;
+
+    for (level, cube) in levels.iter().enumerate(){
+        let buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(), BufferUsage::transfer_source(), cube.to_rgba().into_iter()
+        ).map_err(|er| upload_error(format!("{:?}", er)))?;
+
+        command_buffer = command_buffer.copy_buffer_to_image_dimensions(
+            buffer,
+            initialization.clone(),
+            [0, 0, 0],
+            [cube.size, cube.size, 1],
+            0,
+            6,
+            level as u32
+        ).map_err(|er| upload_error(format!("{:?}", er)))?;
+    }
+
+    let command_buffer = command_buffer.build().map_err(|er| upload_error(format!("{:?}", er)))?;
+    command_buffer.execute(queue.clone())
+    .map_err(|er| upload_error(format!("{:?}", er)))?
+    .then_signal_fence_and_flush()
+    .map_err(|er| upload_error(format!("{:?}", er)))?
+    .wait(None)
+    .map_err(|er| upload_error(format!("{:?}", er)))?;
+
+    Ok(image)
+}
+
+///Integrates the cosine weighted radiance of `source` over the hemisphere of every texel. The
+/// result is divided by pi, so it only has to be multiplied with the albedo when shading.
+fn generate_irradiance(source: &CpuCube, size: u32) -> CpuCube{
+    //The direction and the radiance weighted by the solid angle of every source texel
+    let mut weighted_texels = Vec::with_capacity((source.size * source.size * 6) as usize);
+    for face in 0..6{
+        for y in 0..source.size{
+            for x in 0..source.size{
+                let solid_angle = texel_solid_angle(x, y, source.size);
+                let radiance = source.faces[face][(y * source.size + x) as usize];
+                weighted_texels.push((
+                    texel_direction(face, x, y, source.size),
+                    [radiance[0] * solid_angle, radiance[1] * solid_angle, radiance[2] * solid_angle]
+                ));
+            }
+        }
+    }
+
+    CpuCube::from_fn(size, |normal|{
+        let mut irradiance = [0.0; 3];
+        for &(dir, radiance) in weighted_texels.iter(){
+            let cos_theta = normal.dot(dir);
+            if cos_theta > 0.0{
+                irradiance[0] += radiance[0] * cos_theta;
+                irradiance[1] += radiance[1] * cos_theta;
+                irradiance[2] += radiance[2] * cos_theta;
+            }
+        }
+        [irradiance[0] / PI, irradiance[1] / PI, irradiance[2] / PI]
+    })
+}
+
+///Creates the mip levels of the prefiltered specular map. The roughness goes linear from 0.0 at
+/// the first level to 1.0 at the last (1x1) level.
+fn generate_specular(sky: &CpuCube) -> Vec<CpuCube>{
+    //Smaller copies of the sky, sampling them instead of the full sky prevents fireflies at
+    // high roughness
+    let mut sky_levels = vec![sky.clone()];
+    while sky_levels[sky_levels.len() - 1].size > 1{
+        let next_size = sky_levels[sky_levels.len() - 1].size / 2;
+        let next_level = sky_levels[sky_levels.len() - 1].resized(next_size);
+        sky_levels.push(next_level);
+    }
+
+    let level_count = (SPECULAR_SIZE as f32).log2() as u32 + 1;
+    let mut levels = Vec::with_capacity(level_count as usize);
+    //A perfect mirror only needs the sky
+    levels.push(sky.resized(SPECULAR_SIZE));
+
+    for level in 1..level_count{
+        let roughness = level as f32 / (level_count - 1) as f32;
+        levels.push(prefilter(&sky_levels, SPECULAR_SIZE >> level, roughness));
+    }
+
+    levels
+}
+
+///Convolves the sky with the GGX lobe of `roughness`, assumes that the view direction is the
+/// normal. Uses importance sampling, every sample reads the sky level which fits its solid angle.
+fn prefilter(sky_levels: &[CpuCube], size: u32, roughness: f32) -> CpuCube{
+    let alpha = roughness * roughness;
+    let sky_size = sky_levels[0].size as f32;
+    let texel_solid_angle = 4.0 * PI / (6.0 * sky_size * sky_size);
+    let max_level = (sky_levels.len() - 1) as f32;
+
+    CpuCube::from_fn(size, |normal|{
+        let mut color = [0.0; 3];
+        let mut total_weight = 0.0;
+
+        for i in 0..SPECULAR_SAMPLES{
+            let halfway = importance_sample_ggx(hammersley(i, SPECULAR_SAMPLES), normal, alpha);
+            let light = halfway * (2.0 * normal.dot(halfway)) - normal;
+            let n_dot_l = normal.dot(light);
+            if n_dot_l <= 0.0{
+                continue;
+            }
+
+            //Since the view is the normal, NdotH is the same as HdotV and the pdf reduces to D/4
+            let n_dot_h = normal.dot(halfway).max(0.0);
+            let pdf = distribution_ggx(n_dot_h, alpha) / 4.0 + 0.0001;
+            let sample_solid_angle = 1.0 / (SPECULAR_SAMPLES as f32 * pdf);
+            let level = (0.5 * (sample_solid_angle / texel_solid_angle).log2() + 1.0)
+            .max(0.0).min(max_level);
+
+            let radiance = sky_levels[level.round() as usize].sample(light);
+            color[0] += radiance[0] * n_dot_l;
+            color[1] += radiance[1] * n_dot_l;
+            color[2] += radiance[2] * n_dot_l;
+            total_weight += n_dot_l;
+        }
+
+        if total_weight > 0.0{
+            [color[0] / total_weight, color[1] / total_weight, color[2] / total_weight]
+        }else{
+            [0.0; 3]
+        }
+    })
+}
+
+///Integrates the split sum brdf for all view angles (x) and roughness values (y). Returns the
+/// scale and bias to F0 per texel, row by row.
+fn generate_brdf_lut(size: u32) -> Vec<f32>{
+    let normal = Vector3::new(0.0, 0.0, 1.0);
+    let mut data = Vec::with_capacity((size * size * 2) as usize);
+
+    for y in 0..size{
+        let roughness = (y as f32 + 0.5) / size as f32;
+        let alpha = roughness * roughness;
+
+        for x in 0..size{
+            let n_dot_v = (x as f32 + 0.5) / size as f32;
+            let view = Vector3::new((1.0 - n_dot_v * n_dot_v).sqrt(), 0.0, n_dot_v);
+
+            let mut scale = 0.0;
+            let mut bias = 0.0;
+            for i in 0..BRDF_SAMPLES{
+                let halfway = importance_sample_ggx(hammersley(i, BRDF_SAMPLES), normal, alpha);
+                let light = halfway * (2.0 * view.dot(halfway)) - view;
+
+                let n_dot_l = light.z.max(0.0);
+                let n_dot_h = halfway.z.max(0.0);
+                let v_dot_h = view.dot(halfway).max(0.0);
+
+                if n_dot_l > 0.0{
+                    let geometry = geometry_smith_ibl(n_dot_v, n_dot_l, roughness);
+                    let visibility = (geometry * v_dot_h) / (n_dot_h * n_dot_v);
+                    let fresnel = (1.0 - v_dot_h).powi(5);
+
+                    scale += (1.0 - fresnel) * visibility;
+                    bias += fresnel * visibility;
+                }
+            }
+
+            data.push(scale / BRDF_SAMPLES as f32);
+            data.push(bias / BRDF_SAMPLES as f32);
+        }
+    }
+
+    data
+}
+
+///Returns the normalized direction through the center of a texel of `face`
+fn texel_direction(face: usize, x: u32, y: u32, size: u32) -> Vector3<f32>{
+    let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+    let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+
+    let dir = match face{
+        0 => Vector3::new(1.0, -t, -s),
+        1 => Vector3::new(-1.0, -t, s),
+        2 => Vector3::new(s, 1.0, t),
+        3 => Vector3::new(s, -1.0, -t),
+        4 => Vector3::new(s, -t, 1.0),
+        _ => Vector3::new(-s, -t, -1.0),
+    };
+    dir.normalize()
+}
+
+///Returns the face `dir` points to as well as the texture coordinates (0..1) on this face
+fn direction_to_face(dir: Vector3<f32>) -> (usize, f32, f32){
+    let abs_x = dir.x.abs();
+    let abs_y = dir.y.abs();
+    let abs_z = dir.z.abs();
+
+    let (face, s, t, major) = if abs_x >= abs_y && abs_x >= abs_z{
+        if dir.x > 0.0 { (0, -dir.z, -dir.y, abs_x) } else { (1, dir.z, -dir.y, abs_x) }
+    }else if abs_y >= abs_z{
+        if dir.y > 0.0 { (2, dir.x, dir.z, abs_y) } else { (3, dir.x, -dir.z, abs_y) }
+    }else{
+        if dir.z > 0.0 { (4, dir.x, -dir.y, abs_z) } else { (5, -dir.x, -dir.y, abs_z) }
+    };
+
+    (face, (s / major + 1.0) * 0.5, (t / major + 1.0) * 0.5)
+}
+
+///Returns the solid angle a texel covers on the unit sphere
+fn texel_solid_angle(x: u32, y: u32, size: u32) -> f32{
+    let texel_size = 2.0 / size as f32;
+    let x0 = x as f32 * texel_size - 1.0;
+    let y0 = y as f32 * texel_size - 1.0;
+    let x1 = x0 + texel_size;
+    let y1 = y0 + texel_size;
+
+    let area = |x: f32, y: f32| (x * y).atan2((x * x + y * y + 1.0).sqrt());
+    area(x0, y0) - area(x0, y1) - area(x1, y0) + area(x1, y1)
+}
+
+fn lerp_texel(a: [f32; 3], b: [f32; 3], factor: f32) -> [f32; 3]{
+    [
+        a[0] + (b[0] - a[0]) * factor,
+        a[1] + (b[1] - a[1]) * factor,
+        a[2] + (b[2] - a[2]) * factor,
+    ]
+}
+
+///Returns the `i`-th point of the Hammersley sequence with `count` points
+fn hammersley(i: u32, count: u32) -> (f32, f32){
+    let mut bits = i;
+    bits = (bits << 16) | (bits >> 16);
+    bits = ((bits & 0x55555555) << 1) | ((bits & 0xAAAAAAAA) >> 1);
+    bits = ((bits & 0x33333333) << 2) | ((bits & 0xCCCCCCCC) >> 2);
+    bits = ((bits & 0x0F0F0F0F) << 4) | ((bits & 0xF0F0F0F0) >> 4);
+    bits = ((bits & 0x00FF00FF) << 8) | ((bits & 0xFF00FF00) >> 8);
+    (i as f32 / count as f32, bits as f32 * 2.3283064365386963e-10)
+}
+
+///Returns a halfway vector around `normal` which is distributed like the GGX lobe of `alpha`
+fn importance_sample_ggx(xi: (f32, f32), normal: Vector3<f32>, alpha: f32) -> Vector3<f32>{
+    let phi = 2.0 * PI * xi.0;
+    let cos_theta = ((1.0 - xi.1) / (1.0 + (alpha * alpha - 1.0) * xi.1)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+    let up = if normal.z.abs() < 0.999{
+        Vector3::new(0.0, 0.0, 1.0)
+    }else{
+        Vector3::new(1.0, 0.0, 0.0)
+    };
+    let tangent = up.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+
+    (tangent * (phi.cos() * sin_theta) + bitangent * (phi.sin() * sin_theta) + normal * cos_theta).normalize()
+}
+
+fn distribution_ggx(n_dot_h: f32, alpha: f32) -> f32{
+    let alpha_sq = alpha * alpha;
+    let denom = n_dot_h * n_dot_h * (alpha_sq - 1.0) + 1.0;
+    alpha_sq / (PI * denom * denom)
+}
+
+///Smith geometry term with the k used for image based lighting
+fn geometry_smith_ibl(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32{
+    let k = (roughness * roughness) / 2.0;
+    let ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    ggx_v * ggx_l
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SKY_COLOR: [f32; 3] = [0.2, 0.5, 1.0];
+
+    fn assert_texel_eq(texel: [f32; 3], expected: [f32; 3], tolerance: f32){
+        for i in 0..3{
+            assert!(
+                (texel[i] - expected[i]).abs() <= tolerance,
+                "texel {:?} is not {:?}", texel, expected
+            );
+        }
+    }
+
+    fn constant_image(size: u32, color: [f32; 3]) -> FloatImage{
+        FloatImage{
+            width: size,
+            height: size,
+            texels: vec![color; (size * size) as usize],
+        }
+    }
+
+    fn axes() -> Vec<Vector3<f32>>{
+        vec![
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        ]
+    }
+
+    #[test]
+    fn constant_panorama_gives_constant_cube(){
+        let panorama = FloatImage{
+            width: 8,
+            height: 4,
+            texels: vec![SKY_COLOR; 32],
+        };
+        let cube = CpuCube::from_equirectangular(&panorama);
+        assert_eq!(cube.get_size(), MIN_SKY_SIZE);
+
+        for face in cube.faces.iter(){
+            for texel in face.iter(){
+                assert_texel_eq(*texel, SKY_COLOR, 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn upper_border_of_panorama_is_the_sky_above(){
+        let panorama = FloatImage{
+            width: 4,
+            height: 2,
+            texels: vec![
+                [1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0],
+            ],
+        };
+        let cube = CpuCube::from_equirectangular(&panorama);
+        assert_texel_eq(cube.sample(Vector3::new(0.0, 1.0, 0.0)), [1.0, 0.0, 0.0], 1e-3);
+        assert_texel_eq(cube.sample(Vector3::new(0.0, -1.0, 0.0)), [0.0, 0.0, 1.0], 1e-3);
+    }
+
+    #[test]
+    fn faces_are_sampled_in_their_direction(){
+        let colors = [
+            [1.0, 0.0, 0.0], [0.5, 0.0, 0.0],
+            [0.0, 1.0, 0.0], [0.0, 0.5, 0.0],
+            [0.0, 0.0, 1.0], [0.0, 0.0, 0.5],
+        ];
+        let faces = colors.iter().map(|color| constant_image(2, *color)).collect();
+        let cube = CpuCube::from_faces(faces).expect("failed to create cube from faces");
+        assert_eq!(cube.get_size(), 2);
+
+        for (dir, color) in axes().into_iter().zip(colors.iter()){
+            assert_texel_eq(cube.sample(dir), *color, 1e-6);
+        }
+    }
+
+    #[test]
+    fn invalid_faces_are_an_error(){
+        let five_faces = (0..5).map(|_| constant_image(2, SKY_COLOR)).collect();
+        assert!(CpuCube::from_faces(five_faces).is_err());
+
+        let mut faces: Vec<FloatImage> = (0..5).map(|_| constant_image(2, SKY_COLOR)).collect();
+        faces.push(constant_image(4, SKY_COLOR));
+        assert!(CpuCube::from_faces(faces).is_err());
+
+        let mut faces: Vec<FloatImage> = (0..5).map(|_| constant_image(2, SKY_COLOR)).collect();
+        faces.push(FloatImage{
+            width: 2,
+            height: 1,
+            texels: vec![SKY_COLOR; 2],
+        });
+        assert!(CpuCube::from_faces(faces).is_err());
+    }
+
+    #[test]
+    fn constant_sky_gives_irradiance_of_its_color(){
+        let sky = CpuCube::from_fn(IRRADIANCE_SOURCE_SIZE, |_| SKY_COLOR);
+        let irradiance = generate_irradiance(&sky, 4);
+        assert_eq!(irradiance.get_size(), 4);
+
+        //The cosine weighted integral over the hemisphere is pi, which the irradiance is divided by
+        for face in irradiance.faces.iter(){
+            for texel in face.iter(){
+                assert_texel_eq(*texel, SKY_COLOR, 0.01);
+            }
+        }
+    }
+
+    #[test]
+    fn irradiance_only_sees_the_upper_hemisphere(){
+        let sky = CpuCube::from_fn(IRRADIANCE_SOURCE_SIZE, |dir|{
+            if dir.y > 0.0 { [1.0; 3] } else { [0.0; 3] }
+        });
+        let irradiance = generate_irradiance(&sky, 4);
+
+        //A normal which is tilted by theta away from the sky receives (1 + cos(theta)) / 2
+        for face in 0..6{
+            for y in 0..4{
+                for x in 0..4{
+                    let normal = texel_direction(face, x, y, 4);
+                    let expected = (1.0 + normal.y) / 2.0;
+                    assert_texel_eq(irradiance.faces[face][(y * 4 + x) as usize], [expected; 3], 0.01);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn constant_sky_gives_constant_specular_levels(){
+        let sky = CpuCube::from_fn(8, |_| SKY_COLOR);
+        let levels = generate_specular(&sky);
+
+        assert_eq!(levels.len(), (SPECULAR_SIZE as f32).log2() as usize + 1);
+        for (index, level) in levels.iter().enumerate(){
+            assert_eq!(level.get_size(), SPECULAR_SIZE >> index);
+            for face in level.faces.iter(){
+                for texel in face.iter(){
+                    assert_texel_eq(*texel, SKY_COLOR, 1e-4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn brdf_lut_matches_known_values(){
+        //A single texel integrates n_dot_v = 0.5 and roughness = 0.5
+        let lut = generate_brdf_lut(1);
+        assert_eq!(lut.len(), 2);
+        assert!((lut[0] - 0.736).abs() < 0.005, "scale is {}", lut[0]);
+        assert!((lut[1] - 0.019).abs() < 0.005, "bias is {}", lut[1]);
+
+        //The view angle goes along x, the roughness along y
+        let size = 4;
+        let lut = generate_brdf_lut(size);
+        assert_eq!(lut.len(), (size * size * 2) as usize);
+        let texel = |x: u32, y: u32| (lut[((y * size + x) * 2) as usize], lut[((y * size + x) * 2 + 1) as usize]);
+
+        let (scale, bias) = texel(3, 0);
+        assert!((scale - 0.998).abs() < 0.005 && bias < 0.005, "smooth texel is {} {}", scale, bias);
+        let (scale, bias) = texel(0, 0);
+        assert!((scale - 0.443).abs() < 0.005 && (bias - 0.447).abs() < 0.005, "grazing texel is {} {}", scale, bias);
+        let (scale, bias) = texel(0, 3);
+        assert!((scale - 0.598).abs() < 0.005 && (bias - 0.020).abs() < 0.005, "rough texel is {} {}", scale, bias);
+
+        for value in lut.iter(){
+            assert!(*value >= 0.0 && *value <= 1.0, "{} is not in 0..1", value);
+        }
+    }
+}
@@ -11,6 +11,9 @@ use core::PointToVector;
 //use std::sync::{Arc,Mutex};
 use std::f64::consts;
 
+///Near plane of the perspective shadow frustums of point and spot lights
+const SHADOW_NEAR_PLANE: f32 = 0.1;
+
 
 ///A Generic Point Light
 #[derive(Clone)]
@@ -19,6 +22,7 @@ pub struct LightPoint {
     intensity: f32,
     radius: f32,
     color: Vector3<f32>,
+    cast_shadow: bool,
 
     bound: collision::Aabb3<f32>,
 }
@@ -30,6 +34,7 @@ pub struct LightDirectional {
     pub name: String,
     intensity: f32,
     color: Vector3<f32>,
+    cast_shadow: bool,
 
     bound: collision::Aabb3<f32>,
 }
@@ -46,6 +51,7 @@ pub struct LightSpot {
     radius: f32,
     outer_radius: f32,
     inner_radius: f32,
+    cast_shadow: bool,
 
     bound: collision::Aabb3<f32>,
 }
@@ -66,6 +72,7 @@ impl LightPoint{
             intensity: 1.0,
             color: Vector3::new(1.0, 1.0, 1.0),
             radius: 5.0,
+            cast_shadow: true,
 
             bound: collision::Aabb3::new(min, max),
         };
@@ -73,24 +80,79 @@ impl LightPoint{
         new_light.rebuild_bound();
         new_light
     }
-    ///Returns this lught as its shader-useable instance
-    pub fn as_shader_info(&self, location: &Vector3<f32>) -> lights::ty::PointLight{
+    ///Returns this lught as its shader-useable instance. `shadow_region` holds the region of each
+    /// cube face on the point and spot light shadow atlas, the light has no shadow if it is `None`.
+    pub fn as_shader_info(&self,
+        location: &Vector3<f32>,
+        pcf_samples: u32,
+        poisson_spreading: f32,
+        shadow_region: Option<[[f32; 4]; 6]>
+    ) -> lights::ty::PointLight{
         //convert to a Vec4 for 128 bit padding in the shader
         let color_type: [f32; 3] = self.color.into();
         let location_type: [f32; 3] = location.clone().into();
+
+        let (region, light_space, has_shadow): ([[f32; 4]; 6], [[[f32; 4]; 4]; 6], u32) = match shadow_region{
+            Some(region) => {
+                let matrixes = self.get_mvps(location);
+                let mut ret_mat = [[[0.0; 4]; 4]; 6];
+                for idx in 0..matrixes.len(){
+                    ret_mat[idx] = matrixes[idx].into();
+                }
+                (region, ret_mat, 1)
+            },
+            None => ([[0.0; 4]; 6], [[[0.0; 4]; 4]; 6], 0),
+        };
         //Return a native vulkano struct
         lights::ty::PointLight{
+            shadow_region: region,
+            light_space: light_space,
             color: color_type,
             location: location_type,
             intensity: self.intensity,
             radius: self.radius,
+            poisson_spread: poisson_spreading,
+            pcf_samples: pcf_samples,
+            has_shadow: has_shadow,
             _dummy0: [0; 4],
-            _dummy1: [0; 12],
         }
 
 
     }
 
+    ///Returns the view projection matrix of each cube face in the order +x, -x, +y, -y, +z, -z.
+    /// Each one has a field of view of 90 degree and reaches till the radius of the light.
+    pub fn get_mvps(&self, location: &Vector3<f32>) -> [Matrix4<f32>; 6]{
+        //The direction and up vector of each face
+        let faces = [
+            (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+            (Vector3::new(-1.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+            (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+            (Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 0.0, -1.0)),
+            (Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, -1.0, 0.0)),
+            (Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, -1.0, 0.0)),
+        ];
+
+        let projection = perspective(
+            Deg(90.0),
+            1.0,
+            SHADOW_NEAR_PLANE,
+            self.radius.max(SHADOW_NEAR_PLANE * 2.0)
+        );
+
+        let mut matrixes = [Matrix4::<f32>::identity(); 6];
+        for (idx, &(direction, up)) in faces.iter().enumerate(){
+            let view = Matrix4::look_at(
+                Point3::from_vec(*location),
+                Point3::from_vec(*location + direction),
+                up
+            );
+            matrixes[idx] = projection * view;
+        }
+
+        matrixes
+    }
+
     ///sets the lights intensity
     #[inline]
     pub fn set_intensity(&mut self, new_itensity: f32){
@@ -131,6 +193,18 @@ impl LightPoint{
     pub fn get_color(&mut self) -> &mut Vector3<f32>{
         &mut self.color
     }
+
+    ///Turns the shadow of this light on or off
+    #[inline]
+    pub fn set_cast_shadow(&mut self, cast_shadow: bool){
+        self.cast_shadow = cast_shadow;
+    }
+
+    ///Returns true if this light casts a shadow
+    #[inline]
+    pub fn get_cast_shadow(&self) -> bool{
+        self.cast_shadow
+    }
 }
 
 impl ReturnBoundInfo for LightPoint{
@@ -213,6 +287,7 @@ impl LightDirectional{
 
             intensity: 1.0,
             color: Vector3::new(1.0, 1.0, 1.0),
+            cast_shadow: true,
 
             bound: collision::Aabb3::new(min, max),
         };
@@ -253,8 +328,9 @@ impl LightDirectional{
             intensity: self.intensity,
             poisson_spread: poisson_spreading,
             pcf_samples: pcf_samples,
+            has_shadow: if self.cast_shadow { 1 } else { 0 },
             _dummy0: [0; 4],
-            _dummy1: [0; 8]
+            _dummy1: [0; 4]
         }
     }
 
@@ -369,6 +445,7 @@ impl LightDirectional{
         if new_itensity<=0.0{
             return;
         }
+        self.intensity = new_itensity;
         self.rebuild_bound()
     }
 
@@ -390,6 +467,18 @@ impl LightDirectional{
         &mut self.color
     }
 
+    ///Turns the shadow of this light on or off
+    #[inline]
+    pub fn set_cast_shadow(&mut self, cast_shadow: bool){
+        self.cast_shadow = cast_shadow;
+    }
+
+    ///Returns true if this light casts a shadow
+    #[inline]
+    pub fn get_cast_shadow(&self) -> bool{
+        self.cast_shadow
+    }
+
 
 }
 
@@ -470,6 +559,7 @@ impl LightSpot{
             radius: 5.0,
             outer_radius: outer_radius,
             inner_radius: inner_radius,
+            cast_shadow: true,
 
             bound: collision::Aabb3::new(min, max),
         };
@@ -478,17 +568,29 @@ impl LightSpot{
         new_light
     }
 
-    ///Returns this lught as its shader-useable instance
-    pub fn as_shader_info(&self, rotation: &Quaternion<f32>, location: &Vector3<f32>) -> lights::ty::SpotLight{
+    ///Returns this lught as its shader-useable instance. `shadow_region` is the region on the point
+    /// and spot light shadow atlas, the light has no shadow if it is `None`.
+    pub fn as_shader_info(&self,
+        rotation: &Quaternion<f32>,
+        location: &Vector3<f32>,
+        pcf_samples: u32,
+        poisson_spreading: f32,
+        shadow_region: Option<[f32; 4]>
+    ) -> lights::ty::SpotLight{
 
         let tmp_color: [f32;3] = self.color.into();
         //Transfere to the shader type [f32;3]
-        let tmp_direction: [f32;3] = rotation.rotate_vector(Vector3::new(1.0, 0.0, 0.0)).into();
+        let tmp_direction: [f32;3] = self.get_direction_vector(rotation).into();
         let location_type: [f32; 3] = location.clone().into();
 
-
+        let (region, light_space, has_shadow): ([f32; 4], [[f32; 4]; 4], u32) = match shadow_region{
+            Some(region) => (region, self.get_mvp(rotation, location).into(), 1),
+            None => ([0.0; 4], [[0.0; 4]; 4], 0),
+        };
 
         lights::ty::SpotLight{
+            shadow_region: region,
+            light_space: light_space,
             color: tmp_color,
             direction: tmp_direction,
             location: location_type,
@@ -498,12 +600,47 @@ impl LightSpot{
 
             outer_radius: to_radians(self.outer_radius).cos(),
             inner_radius: to_radians(self.inner_radius).cos(),
+            poisson_spread: poisson_spreading,
+            pcf_samples: pcf_samples,
+            has_shadow: has_shadow,
             _dummy0: [0; 4],
             _dummy1: [0; 4],
-            _dummy2: [0; 4],
+            _dummy2: [0; 8],
         }
     }
 
+    pub fn get_direction_vector(&self, rotation: &Quaternion<f32>) -> Vector3<f32>{
+        rotation.rotate_vector(Vector3::new(1.0, 0.0, 0.0))
+    }
+
+    ///Returns the view projection matrix of the shadow frustum. The field of view covers the
+    /// outer radius, the far plane is the radius of the light.
+    pub fn get_mvp(&self, rotation: &Quaternion<f32>, location: &Vector3<f32>) -> Matrix4<f32>{
+        let direction = self.get_direction_vector(rotation);
+        //look_at can't handle a direction parallel to the up vector
+        let up = if direction.y.abs() > 0.99{
+            Vector3::new(0.0, 0.0, 1.0)
+        }else{
+            Vector3::new(0.0, 1.0, 0.0)
+        };
+
+        let view = Matrix4::look_at(
+            Point3::from_vec(*location),
+            Point3::from_vec(*location + direction),
+            up
+        );
+
+        //the outer radius is the angle between the direction and the edge of the cone
+        let projection = perspective(
+            Deg((self.outer_radius * 2.0).max(1.0).min(170.0)),
+            1.0,
+            SHADOW_NEAR_PLANE,
+            self.radius.max(SHADOW_NEAR_PLANE * 2.0)
+        );
+
+        projection * view
+    }
+
     ///set intensity
     #[inline]
     pub fn set_intensity(&mut self, new_itensity: f32){
@@ -545,6 +682,18 @@ impl LightSpot{
         &mut self.color
     }
 
+    ///Turns the shadow of this light on or off
+    #[inline]
+    pub fn set_cast_shadow(&mut self, cast_shadow: bool){
+        self.cast_shadow = cast_shadow;
+    }
+
+    ///Returns true if this light casts a shadow
+    #[inline]
+    pub fn get_cast_shadow(&self) -> bool{
+        self.cast_shadow
+    }
+
     ///Sets the outer radius (point where the fallof ends) of this spot light
     #[inline]
     pub fn set_outer_radius(&mut self, new_radius: f32){
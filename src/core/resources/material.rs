@@ -6,6 +6,7 @@ use render::shader::shader_inputs::pbr_texture_info;
 use render::shader::shaders::shadow_fragment::ty::MaskedInfo;
 use render::light_system;
 use render::frame_system::FrameSystem;
+use render::probe_system::ProbeBinding;
 
 use vulkano::descriptor::descriptor_set::PersistentDescriptorSet;
 use vulkano::descriptor::descriptor_set::FixedSizeDescriptorSetsPool;
@@ -13,9 +14,12 @@ use vulkano::descriptor::descriptor_set::DescriptorSet;
 use vulkano::pipeline::GraphicsPipelineAbstract;
 use vulkano;
 
+use core::asset_error::AssetError;
+
 use cgmath::*;
 
 use std::sync::{Mutex,Arc};
+use std::fmt;
 
 //=================================================================================================
 
@@ -28,6 +32,7 @@ pub struct TextureUsageFlags {
     pub roughness: bool,
     pub occlusion: bool,
     pub emissive: bool,
+    pub height: bool,
     pub is_masked: bool,
 }
 
@@ -41,6 +46,7 @@ impl TextureUsageFlags{
             roughness: false,
             occlusion: false,
             emissive: false,
+            height: false,
             is_masked: false,
         }
     }
@@ -82,12 +88,32 @@ impl TextureUsageFlags{
         self
     }
 
+    ///Creates with a set height status, enables parallax occlusion mapping
+    pub fn with_height(mut self) ->Self{
+        self.height = true;
+        self
+    }
+
     ///Creates with a set emissive status
     pub fn is_masked(mut self) ->Self{
         self.is_masked = true;
         self
     }
 
+    ///Creates the flags from their shader representation
+    pub fn from_shader_flags(flags: &pbr_texture_info::ty::TextureUsageInfo) -> Self{
+        TextureUsageFlags{
+            albedo: flags.b_albedo != 0,
+            normal: flags.b_normal != 0,
+            metal: flags.b_metal != 0,
+            roughness: flags.b_roughness != 0,
+            occlusion: flags.b_occlusion != 0,
+            emissive: flags.b_emissive != 0,
+            height: flags.b_height != 0,
+            is_masked: flags.b_is_masked != 0,
+        }
+    }
+
     pub fn to_shader_flags(self) -> pbr_texture_info::ty::TextureUsageInfo{
         pbr_texture_info::ty::TextureUsageInfo{
             b_albedo: {
@@ -132,6 +158,13 @@ impl TextureUsageFlags{
                     0
                 }
             },
+            b_height: {
+                if self.height{
+                    1
+                }else{
+                    0
+                }
+            },
             b_is_masked: {
                 if self.is_masked{
                     1
@@ -157,8 +190,10 @@ pub struct MaterialFactors{
     roughness_factor: f32,
     occlusion_factor: f32,
     alpha_cutoff: f32,
-
-
+    ///How deep the height map reaches into the surface, in texture coordinates
+    height_scale: f32,
+    ///The maximum number of layers the parallax occlusion mapping steps through
+    height_steps: u32,
 }
 
 impl MaterialFactors{
@@ -174,6 +209,8 @@ impl MaterialFactors{
             roughness_factor: 1.0,
             occlusion_factor: 1.0,
             alpha_cutoff: 0.5,
+            height_scale: 0.05,
+            height_steps: 32,
         }
     }
 
@@ -234,7 +271,91 @@ impl MaterialFactors{
         self
     }
 
+    ///Sets how deep the height map reaches into the surface, in texture coordinates
+    #[inline]
+    pub fn with_height_scale(mut self, scale: f32) -> Self{
+        self.height_scale = scale;
+        self
+    }
+
+    ///Sets the maximum number of layers used for parallax occlusion mapping, more steps
+    /// remove stair artifacts at flat view angles but cost more texture samples
+    #[inline]
+    pub fn with_height_steps(mut self, steps: u32) -> Self{
+        self.height_steps = steps.max(1);
+        self
+    }
+
+
+    ///Creates the factors from their shader representation
+    pub fn from_shader_factors(factors: &pbr_texture_info::ty::TextureFactors) -> Self{
+        MaterialFactors{
+            albedo_factor: factors.albedo_factor,
+            normal_factor: factors.normal_factor,
+            emissive_factor: factors.emissive_factor,
+            max_emission: factors.max_emission,
+            metal_factor: factors.metal_factor,
+            roughness_factor: factors.roughness_factor,
+            occlusion_factor: factors.occlusion_factor,
+            alpha_cutoff: factors.alpha_cutoff,
+            height_scale: factors.height_scale,
+            height_steps: factors.height_steps,
+        }
+    }
+
+    ///Returns the albedo factor
+    #[inline]
+    pub fn get_factor_albedo(&self) -> [f32; 4]{
+        self.albedo_factor
+    }
+
+    ///Returns the normal factor
+    #[inline]
+    pub fn get_factor_normal(&self) -> f32{
+        self.normal_factor
+    }
+
+    ///Returns the metal factor
+    #[inline]
+    pub fn get_factor_metal(&self) -> f32{
+        self.metal_factor
+    }
+
+    ///Returns the roughness factor
+    #[inline]
+    pub fn get_factor_roughness(&self) -> f32{
+        self.roughness_factor
+    }
+
+    ///Returns the occlusion factor
+    #[inline]
+    pub fn get_factor_occlusion(&self) -> f32{
+        self.occlusion_factor
+    }
+
+    ///Returns the emissive factor
+    #[inline]
+    pub fn get_factor_emissive(&self) -> [f32; 3]{
+        self.emissive_factor
+    }
+
+    ///Returns the alpha cutoff
+    #[inline]
+    pub fn get_alpha_cutoff(&self) -> f32{
+        self.alpha_cutoff
+    }
+
+    ///Returns the height scale
+    #[inline]
+    pub fn get_height_scale(&self) -> f32{
+        self.height_scale
+    }
 
+    ///Returns the maximum number of parallax occlusion mapping steps
+    #[inline]
+    pub fn get_height_steps(&self) -> u32{
+        self.height_steps
+    }
 
     pub fn to_shader_factors(&self) -> pbr_texture_info::ty::TextureFactors{
         pbr_texture_info::ty::TextureFactors{
@@ -246,6 +367,8 @@ impl MaterialFactors{
             roughness_factor: self.roughness_factor,
             occlusion_factor: self.occlusion_factor,
             alpha_cutoff: self.alpha_cutoff,
+            height_scale: self.height_scale,
+            height_steps: self.height_steps,
         }
     }
 }
@@ -259,6 +382,7 @@ pub struct MaterialBuilder {
     metallic_roughness: Option<Arc<texture::Texture>>,
     occlusion: Option<Arc<texture::Texture>>,
     emissive: Option<Arc<texture::Texture>>,
+    height: Option<Arc<texture::Texture>>,
     fallback_texture: Arc<texture::Texture>,
     //texture and material infos
     texture_usage_info: TextureUsageFlags,
@@ -320,6 +444,7 @@ impl MaterialBuilder{
             metallic_roughness: metallic_roughness,
             occlusion: occlusion,
             emissive: emissive,
+            height: None,
             fallback_texture: fallback_texture,
             //texture and material infos as shader usable struct
             texture_usage_info: usage_flags,
@@ -336,6 +461,15 @@ impl MaterialBuilder{
         self
     }
 
+    ///Adds a height map (white is high) which is used for parallax occlusion mapping. Gltf can't
+    /// describe one, the obj importer uses the bump map of the .mtl file.
+    #[inline]
+    pub fn with_height_texture(mut self, height: Arc<texture::Texture>) -> Self{
+        self.height = Some(height);
+        self.texture_usage_info.height = true;
+        self
+    }
+
     ///can be used to set custom factors
     #[inline]
     pub fn with_factors(mut self, new_factors: MaterialFactors) -> Self{
@@ -354,14 +488,15 @@ impl MaterialBuilder{
         self.texture_usage_info.is_masked
     }
 
-    ///builds a material from the supplied textures and other info
+    ///builds a material from the supplied textures and other info, returns an error if the
+    /// descriptor sets or buffers of the material can't be created.
     pub fn build(
         self,
         name: &str,
         pipeline: Arc<pipeline::Pipeline>,
         uniform_manager: Arc<Mutex<uniform_manager::UniformManager>>,
         device: Arc<vulkano::device::Device>,
-    ) -> Material{
+    ) -> Result<Material, AssetError>{
         //find out if a texture was supplied per slot
         //if not return the fallback texture for this builder
         //should usally be the 1x1 pixel texture
@@ -400,6 +535,13 @@ impl MaterialBuilder{
             }
         };
 
+        let tmp_height = {
+            match self.height{
+                Some(texture) => texture,
+                None => self.fallback_texture.clone(),
+            }
+        };
+
         //Now get a teporary pipeline reference to create the first descriptorsets from
         let pipeline_ref = pipeline.get_pipeline_ref();
 
@@ -425,9 +567,9 @@ impl MaterialBuilder{
         let set_01 = Arc::new(PersistentDescriptorSet::start(
                 pipeline_ref.clone(), 0
             )
-            .add_buffer((*uniform_manager_lck).get_subbuffer_data(ident_mat_4).clone()).expect("Failed to create descriptor set")
-
-            .build().expect("failed to build first descriptor 01")
+            .add_buffer((*uniform_manager_lck).get_subbuffer_data(ident_mat_4).clone())
+            .map_err(|er| gpu_error(name, "set 01", er))?
+            .build().map_err(|er| gpu_error(name, "set 01", er))?
         );
 
 
@@ -438,53 +580,41 @@ impl MaterialBuilder{
                 pipeline_ref.clone(), 1
             )
             .add_sampled_image(tmp_albedo.get_raw_texture(), tmp_albedo.get_raw_sampler())
-            .expect("failed to add sampled albedo")
+            .map_err(|er| gpu_error(name, "albedo texture binding", er))?
             .add_sampled_image(tmp_normal.get_raw_texture(), tmp_normal.get_raw_sampler())
-            .expect("failed to add sampled nrm")
+            .map_err(|er| gpu_error(name, "normal texture binding", er))?
             .add_sampled_image(tmp_physical.get_raw_texture(), tmp_physical.get_raw_sampler())
-            .expect("failed to add sampled physical")
+            .map_err(|er| gpu_error(name, "metallic roughness texture binding", er))?
             .add_sampled_image(tmp_occlusion.get_raw_texture(), tmp_occlusion.get_raw_sampler())
-            .expect("failed to add occlusion texture")
+            .map_err(|er| gpu_error(name, "occlusion texture binding", er))?
             .add_sampled_image(tmp_emissive.get_raw_texture(), tmp_emissive.get_raw_sampler())
-            .expect("failed to add emissive texture")
-            .build().expect("failed to build set_02")
+            .map_err(|er| gpu_error(name, "emissive texture binding", er))?
+            .add_sampled_image(tmp_height.get_raw_texture(), tmp_height.get_raw_sampler())
+            .map_err(|er| gpu_error(name, "height texture binding", er))?
+            .build().map_err(|er| gpu_error(name, "set 02", er))?
         );
 
-        let usage_info_sub_buffer = {
-            match usage_info_pool.next(self.texture_usage_info.clone().to_shader_flags()){
-                Ok(k) => k,
-                Err(e) => {
-                    println!("{:?}", e);
-                    panic!("failed to allocate new sub buffer!")
-                },
-            }
-        };
+        let usage_info_sub_buffer = usage_info_pool.next(self.texture_usage_info.clone().to_shader_flags())
+        .map_err(|er| gpu_error(name, "usage info buffer", er))?;
 
-        let material_factor_sub_buffer = {
-            match material_factor_pool.next(self.material_factors.clone().to_shader_factors()){
-                Ok(k) => k,
-                Err(e) => {
-                    println!("{:?}", e);
-                    panic!("failed to allocate new sub buffer!")
-                },
-            }
-        };
+        let material_factor_sub_buffer = material_factor_pool.next(self.material_factors.clone().to_shader_factors())
+        .map_err(|er| gpu_error(name, "material factor buffer", er))?;
 
         //Create the Usage Flag descriptor
         let set_03 = Arc::new(PersistentDescriptorSet::start(
                 pipeline_ref.clone(), 2
             )
-            .add_buffer(usage_info_sub_buffer
-            ).expect("Failed to create descriptor set")
-            .add_buffer(material_factor_sub_buffer
-            ).expect("failed to create the first material factor pool")
-            .build().expect("failed to build first descriptor 03")
+            .add_buffer(usage_info_sub_buffer)
+            .map_err(|er| gpu_error(name, "set 03", er))?
+            .add_buffer(material_factor_sub_buffer)
+            .map_err(|er| gpu_error(name, "set 03", er))?
+            .build().map_err(|er| gpu_error(name, "set 03", er))?
         );
 
         let set_01_pool = FixedSizeDescriptorSetsPool::new(pipeline_ref.clone(), 0);
 
         //Now create the new material
-        Material{
+        Ok(Material{
             name: String::from(name),
             //albedo describtion
             t_albedo: tmp_albedo,
@@ -496,6 +626,7 @@ impl MaterialBuilder{
             t_occlusion: tmp_occlusion,
             //Additional textures
             t_emissive: tmp_emissive,
+            t_height: tmp_height,
 
             //All Unifrom infos
             pipeline: pipeline,
@@ -513,10 +644,15 @@ impl MaterialBuilder{
 
             material_factors: self.material_factors.to_shader_factors(),
             material_factor_pool: material_factor_pool,
-        }
+        })
     }
 }
 
+///Wraps a vulkano error which happened while creating `what` for the material `name`
+fn gpu_error<E: fmt::Debug>(name: &str, what: &str, error: E) -> AssetError{
+    AssetError::GpuUpload(format!("failed to create {} of material {}: {:?}", what, name, error))
+}
+
 //=================================================================================================
 
 ///Describes a standart material
@@ -527,6 +663,7 @@ impl MaterialBuilder{
 /// - normal: the normal representation of the surface
 /// - metallic-roughness: is a system texture which is split by channels:
 /// - occlusion: is a system texture used to make some areas darker
+/// - height: an optional height map used for parallax occlusion mapping
 ///
 /// The metallic-roughness  texture.
 ///
@@ -550,6 +687,8 @@ pub struct Material {
     t_occlusion: Arc<texture::Texture>,
     //Additional textures: TODO implent
     t_emissive: Arc<texture::Texture>,
+    ///height map for parallax occlusion mapping
+    t_height: Arc<texture::Texture>,
 
     //Technical implementation
     ///Reference to parent pipeline
@@ -602,6 +741,13 @@ impl Material {
         self.t_metallic_roughness = physical;
     }
 
+    ///Adds an occlusion texture
+    #[inline]
+    pub fn set_occlusion_texture(&mut self, occlusion: Arc<texture::Texture>){
+        self.t_occlusion = occlusion;
+        self.texture_usage_info.b_occlusion = 1;
+    }
+
     ///Adds a emissive texture
     #[inline]
     pub fn set_emissive_texture(&mut self, emissive: Arc<texture::Texture>){
@@ -609,6 +755,13 @@ impl Material {
         self.texture_usage_info.b_emissive = 1;
     }
 
+    ///Adds a height texture which is used for parallax occlusion mapping
+    #[inline]
+    pub fn set_height_texture(&mut self, height: Arc<texture::Texture>){
+        self.t_height = height;
+        self.texture_usage_info.b_height = 1;
+    }
+
     ///Overrwrites the old usage infor with the new ones.
     #[inline]
     pub fn set_texture_usage_info(&mut self, info: TextureUsageFlags){
@@ -621,6 +774,78 @@ impl Material {
         self.material_factors = info.to_shader_factors();
     }
 
+    ///Returns the current usage flags
+    #[inline]
+    pub fn get_texture_usage_info(&self) -> TextureUsageFlags{
+        TextureUsageFlags::from_shader_flags(&self.texture_usage_info)
+    }
+
+    ///Returns the current material factors
+    #[inline]
+    pub fn get_material_factor_info(&self) -> MaterialFactors{
+        MaterialFactors::from_shader_factors(&self.material_factors)
+    }
+
+    ///Returns the albedo texture, is the fallback texture if the material has none
+    #[inline]
+    pub fn get_albedo_texture(&self) -> Arc<texture::Texture>{
+        self.t_albedo.clone()
+    }
+
+    ///Returns the normal texture, is the fallback texture if the material has none
+    #[inline]
+    pub fn get_normal_texture(&self) -> Arc<texture::Texture>{
+        self.t_normal.clone()
+    }
+
+    ///Returns the metallic roughness texture, is the fallback texture if the material has none
+    #[inline]
+    pub fn get_metallic_roughness_texture(&self) -> Arc<texture::Texture>{
+        self.t_metallic_roughness.clone()
+    }
+
+    ///Returns the occlusion texture, is the fallback texture if the material has none
+    #[inline]
+    pub fn get_occlusion_texture(&self) -> Arc<texture::Texture>{
+        self.t_occlusion.clone()
+    }
+
+    ///Returns the emissive texture, is the fallback texture if the material has none
+    #[inline]
+    pub fn get_emissive_texture(&self) -> Arc<texture::Texture>{
+        self.t_emissive.clone()
+    }
+
+    ///Returns the height texture, is the fallback texture if the material has none
+    #[inline]
+    pub fn get_height_texture(&self) -> Arc<texture::Texture>{
+        self.t_height.clone()
+    }
+
+    ///Replaces every texture slot which uses `old` with `new` and recreates the descriptor sets.
+    /// Returns true if the material used `old`. The usage flags are not changed.
+    pub fn replace_texture(&mut self, old: &Arc<texture::Texture>, new: &Arc<texture::Texture>) -> bool{
+        let mut replaced = false;
+        for slot in [
+            &mut self.t_albedo,
+            &mut self.t_normal,
+            &mut self.t_metallic_roughness,
+            &mut self.t_occlusion,
+            &mut self.t_emissive,
+            &mut self.t_height,
+        ].iter_mut(){
+            if Arc::ptr_eq(&**slot, old){
+                **slot = new.clone();
+                replaced = true;
+            }
+        }
+
+        if replaced{
+            self.recreate_static_sets();
+        }
+        replaced
+    }
+
     ///Recreates set_02, set_03
     pub fn recreate_static_sets(&mut self){
 
@@ -650,6 +875,10 @@ impl Material {
                 self.t_emissive.get_raw_texture().clone(), self.t_emissive.get_raw_sampler().clone()
             )
             .expect("failed to add sampled physical")
+            .add_sampled_image(
+                self.t_height.get_raw_texture().clone(), self.t_height.get_raw_sampler().clone()
+            )
+            .expect("failed to add sampled height")
             .build().expect("failed to build set_02")
         );
 
@@ -764,6 +993,19 @@ impl Material {
         compute_sys.get_light_descriptorset(3, self.get_vulkano_pipeline(), frame_system) //for pbr materials this has to be the three
     }
 
+    ///Returns the 5th descriptor set which holds the image based lighting maps of the current
+    /// environment
+    #[inline]
+    pub fn get_set_05(&self, compute_sys: &light_system::LightSystem) -> Arc<DescriptorSet + Send + Sync>{
+        compute_sys.get_environment_descriptorset(4, self.get_vulkano_pipeline())
+    }
+
+    ///Returns the 6th descriptor set which holds the reflection probes influencing the object
+    #[inline]
+    pub fn get_set_06(&self, probes: &ProbeBinding) -> Arc<DescriptorSet + Send + Sync>{
+        probes.get_descriptorset(5, self.get_vulkano_pipeline())
+    }
+
     ///Returns the `MaskedInfo` for the shadows as well as the texture containing the alpha values
     /// of this material
     pub fn get_shadow_mask_info(&self) -> (MaskedInfo, Arc<texture::Texture>){
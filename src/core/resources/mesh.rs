@@ -1,22 +1,28 @@
 //TODO Add command buffer creation per mesh
 use std::sync::{Arc, Mutex};
+use std::mem;
 use cgmath::*;
 use collision;
 
 use render::frame_system::{FrameSystem};
 use render::light_system::LightSystem;
+use render::probe_system::ProbeBinding;
 use render::render_traits::ForwardRenderAble;
 use render::renderer::RenderDebug;
 
 use vulkano::buffer::ImmutableBuffer;
+use vulkano::buffer::CpuAccessibleBuffer;
 use vulkano::device::Device;
 use vulkano::buffer::BufferUsage;
 use vulkano::buffer::BufferAccess;
 use vulkano::device::Queue;
-use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::pipeline::GraphicsPipelineAbstract;
 
 use core::ReturnBoundInfo;
 use core::resources::material;
+use core::resources::animation;
+use core::asset_error::AssetError;
 
 use tools::callbacks::*;
 
@@ -34,6 +40,59 @@ pub struct Vertex {
 //Implements the vulkano::vertex trait on Vertex
 impl_vertex!(Vertex, position, tex_coord, normal, tangent, color);
 
+///The joints which influence a vertex and how much. Is stored alongside the `Vertex` of skinned
+/// meshes.
+#[derive(Clone,Copy)]
+pub struct SkinVertex {
+    joints: [u32; 4],
+    weights: [f32; 4],
+}
+
+impl_vertex!(SkinVertex, joints, weights);
+
+///The model matrix of one instance, is stored in a second vertex buffer next to the `Vertex`
+/// buffer for instanced draws. The matrix is split into its columns.
+#[derive(Clone,Copy)]
+pub struct InstanceData {
+    model_0: [f32; 4],
+    model_1: [f32; 4],
+    model_2: [f32; 4],
+    model_3: [f32; 4],
+}
+
+impl_vertex!(InstanceData, model_0, model_1, model_2, model_3);
+
+impl InstanceData{
+    ///Creates the instance data from the world transform of the instance
+    pub fn new(model: Matrix4<f32>) -> Self{
+        let columns: [[f32; 4]; 4] = model.into();
+        InstanceData{
+            model_0: columns[0],
+            model_1: columns[1],
+            model_2: columns[2],
+            model_3: columns[3],
+        }
+    }
+}
+
+impl SkinVertex{
+    ///Creates a new skin vertex from four joint indices and their weights
+    pub fn new(joints: [u32; 4], weights: [f32; 4]) -> Self{
+        SkinVertex{
+            joints: joints,
+            weights: weights,
+        }
+    }
+
+    pub fn get_joints(&self) -> [u32; 4]{
+        self.joints
+    }
+
+    pub fn get_weights(&self) -> [f32; 4]{
+        self.weights
+    }
+}
+
 //TODO
 //Every mesh needs its own indice and vertex buffer plus its pipeline to be drawn
 impl Vertex{
@@ -56,13 +115,81 @@ impl Vertex{
     }
 }
 
+///Calculates the tangent of every vertex from the positions and texture coordinates of the
+/// triangles in `indices`. The tangents are orthogonalized against the normal, `w` stores the
+/// handedness of the bitangent. Vertices without usable uvs get any tangent orthogonal to the normal.
+pub fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]){
+    let mut tangents = vec![Vector3::new(0.0, 0.0, 0.0); vertices.len()];
+    let mut bitangents = vec![Vector3::new(0.0, 0.0, 0.0); vertices.len()];
+
+    for triangle in indices.chunks(3){
+        if triangle.len() != 3{
+            continue;
+        }
+        let (i0, i1, i2) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+        if i0 >= vertices.len() || i1 >= vertices.len() || i2 >= vertices.len(){
+            continue;
+        }
+
+        let p0 = Vector3::from(vertices[i0].position);
+        let edge_1 = Vector3::from(vertices[i1].position) - p0;
+        let edge_2 = Vector3::from(vertices[i2].position) - p0;
+
+        let uv0 = vertices[i0].tex_coord;
+        let (du1, dv1) = (vertices[i1].tex_coord[0] - uv0[0], vertices[i1].tex_coord[1] - uv0[1]);
+        let (du2, dv2) = (vertices[i2].tex_coord[0] - uv0[0], vertices[i2].tex_coord[1] - uv0[1]);
+
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() < ::std::f32::EPSILON{
+            continue;
+        }
+        let r = 1.0 / det;
+        let tangent = (edge_1 * dv2 - edge_2 * dv1) * r;
+        let bitangent = (edge_2 * du1 - edge_1 * du2) * r;
+
+        for idx in [i0, i1, i2].iter(){
+            tangents[*idx] += tangent;
+            bitangents[*idx] += bitangent;
+        }
+    }
+
+    for (idx, vertex) in vertices.iter_mut().enumerate(){
+        let normal = Vector3::from(vertex.normal);
+        //Gram-Schmidt
+        let mut tangent = tangents[idx] - normal * normal.dot(tangents[idx]);
+        if tangent.magnitude2() < ::std::f32::EPSILON{
+            //pick any axis which is not parallel to the normal
+            let axis = if normal.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+            tangent = axis - normal * normal.dot(axis);
+        }
+        if tangent.magnitude2() > 0.0{
+            tangent = tangent.normalize();
+        }
+
+        let handedness = if normal.cross(tangent).dot(bitangents[idx]) < 0.0 { -1.0 } else { 1.0 };
+        vertex.tangent = [tangent.x, tangent.y, tangent.z, handedness];
+    }
+}
+
+///The file and primitive a mesh was imported from, used to reload the mesh if the file changes
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshSource {
+    ///Path of the imported file
+    pub path: String,
+    ///Index of the mesh in the file
+    pub mesh_index: usize,
+    ///Index of the primitive within this mesh
+    pub primitive_index: usize,
+}
+
 ///Defines a mesh, a mesh mostly consists of: Name, Vertices (and the corresbondig vertex buffer)
 ///, the vertex indices, a material and its AABB (bounding box)
 #[derive(Clone)]
 pub struct Mesh {
     pub name: String,
 
-    device: Arc<Device>,
+    ///Is `None` for meshes which only live on the cpu (headless mode)
+    device: Option<Arc<Device>>,
 
     ///Holds the raw vertices of this mesh
     vertices: Vec<Vertex>,
@@ -73,9 +200,23 @@ pub struct Mesh {
 
     index_buffer: Option<Arc<ImmutableBuffer<[u32]>>>,
 
-    material: Arc<Mutex<material::Material>>,
+    ///Is `None` for meshes which only live on the cpu (headless mode)
+    material: Option<Arc<Mutex<material::Material>>>,
 
     bound: collision::Aabb3<f32>,
+
+    ///The joints and weights per vertex, empty if the mesh is not skinned
+    skin: Vec<SkinVertex>,
+    ///The vertices in bind pose, skinning always starts from them
+    bind_vertices: Vec<Vertex>,
+    ///Name of the skeleton in the animation manager which deforms this mesh
+    skeleton: Option<String>,
+    ///The deformed vertices of a skinned mesh, they are written in place after each skinning.
+    /// Is used instead of `vertex_buffer` once the mesh was skinned.
+    skinned_buffer: Option<Arc<CpuAccessibleBuffer<[Vertex]>>>,
+
+    ///Where this mesh was imported from, `None` for meshes created at runtime
+    source: Option<MeshSource>,
 }
 
 impl Mesh {
@@ -93,7 +234,7 @@ impl Mesh {
         Mesh{
             name: String::from(name),
 
-            device: device.clone(),
+            device: Some(device.clone()),
 
             //TODO Create a persistend vertex and indice buffer
             vertices: Vec::new(),
@@ -103,43 +244,225 @@ impl Mesh {
 
             index_buffer: None,
 
-            material: material,
+            material: Some(material),
+
+            bound: collision::Aabb3::new(min, max),
+
+            skin: Vec::new(),
+            bind_vertices: Vec::new(),
+            skeleton: None,
+            skinned_buffer: None,
+            source: None,
+        }
+    }
+
+    ///Creates a mesh without device and material. The vertices of such a mesh are never uploaded
+    /// to the gpu, which is used when the engine runs headless.
+    pub fn new_cpu_only(name: &str) -> Self{
+        let min = Point3::new(0.5, 0.5, 0.5);
+        let max = Point3::new(0.5, 0.5, 0.5);
 
+        Mesh{
+            name: String::from(name),
+            device: None,
+            vertices: Vec::new(),
+            vertex_buffer: None,
+            indices: Vec::new(),
+            index_buffer: None,
+            material: None,
             bound: collision::Aabb3::new(min, max),
+            skin: Vec::new(),
+            bind_vertices: Vec::new(),
+            skeleton: None,
+            skinned_buffer: None,
+            source: None,
         }
     }
 
     ///Sets the vertex and indice buffer to a new set of `Vertex` and `u32` indices
     ///The supplied queue will be used for uploading the buffer. If there are several, try to to use
-    /// the worker queue for this job.
-    pub fn set_vertices_and_indices(&mut self, vertices: Vec<Vertex>, indices: Vec<u32>, upload_queue: Arc<Queue>){
+    /// the worker queue for this job. If the upload fails the mesh keeps its old vertices, indices
+    /// and buffers.
+    pub fn set_vertices_and_indices(
+        &mut self, vertices: Vec<Vertex>, indices: Vec<u32>, upload_queue: Arc<Queue>
+    ) -> Result<(), AssetError>{
+
+        //Upload first, the mesh is only changed if that worked
+        let (vertex_buffer, index_buffer) = self.upload_buffers(&vertices, &indices, upload_queue)?;
+        self.vertices = vertices;
+        self.indices = indices;
+        self.vertex_buffer = Some(vertex_buffer);
+        self.index_buffer = Some(index_buffer);
+        //the deformed vertices are written again by the next skinning
+        self.skinned_buffer = None;
+        Ok(())
+    }
+
+    ///Returns the size of the vertices and indices in bytes, skinned meshes count their bind
+    /// pose as well.
+    pub fn get_byte_size(&self) -> usize{
+        (self.vertices.len() + self.bind_vertices.len()) * mem::size_of::<Vertex>()
+        + self.indices.len() * mem::size_of::<u32>()
+        + self.skin.len() * mem::size_of::<SkinVertex>()
+    }
 
-        //Set the new values
+    ///Remembers the file and primitive this mesh was imported from
+    pub fn set_source(&mut self, source: MeshSource){
+        self.source = Some(source);
+    }
+
+    ///Returns the file and primitive this mesh was imported from
+    pub fn get_source(&self) -> Option<MeshSource>{
+        self.source.clone()
+    }
+
+    ///Sets the vertices and indices without uploading them to the gpu
+    pub fn set_cpu_vertices_and_indices(&mut self, vertices: Vec<Vertex>, indices: Vec<u32>){
         self.vertices = vertices;
         self.indices = indices;
-        //Rebuild vertex and indice buffer with new vertices
-        self.re_create_buffer(upload_queue);
-        //self.indices = indices;
     }
 
-    ///Returns the name of the material this mesh uses
+    ///Marks this mesh as skinned by the skeleton called `skeleton`. There has to be one
+    /// `SkinVertex` per vertex. The current vertices are used as bind pose.
+    pub fn set_skin(&mut self, skin: Vec<SkinVertex>, skeleton: &str){
+        if skin.len() != self.vertices.len(){
+            println!("WARNING: MESH: skin of {} does not match its vertex count, ignoring it", self.name);
+            return;
+        }
+        self.skin = skin;
+        self.bind_vertices = self.vertices.clone();
+        self.skeleton = Some(String::from(skeleton));
+    }
+
+    ///Removes the skin, the mesh keeps its current vertices
+    pub fn clear_skin(&mut self){
+        self.skin.clear();
+        self.bind_vertices.clear();
+        self.skeleton = None;
+        self.skinned_buffer = None;
+    }
+
+    ///Returns a copy of this skinned mesh which is deformed by the skeleton `skeleton` instead.
+    /// The copy shares the index buffer and the bind pose buffer, but gets its own buffer for
+    /// the deformed vertices. This way several instances of a scene can show different poses.
+    pub fn copy_for_skeleton(&self, skeleton: &str) -> Self{
+        let mut copy = self.clone();
+        copy.name = self.name.clone() + "@" + skeleton;
+        copy.skinned_buffer = None;
+        if copy.is_skinned(){
+            copy.skeleton = Some(String::from(skeleton));
+        }
+        copy
+    }
+
+    ///Returns true if this mesh is deformed by a skeleton
+    #[inline]
+    pub fn is_skinned(&self) -> bool{
+        self.skeleton.is_some()
+    }
+
+    ///Returns the name of the skeleton which deforms this mesh
+    #[inline]
+    pub fn get_skeleton_name(&self) -> Option<String>{
+        self.skeleton.clone()
+    }
+
+    ///Returns the joints and weights of each vertex
+    #[inline]
+    pub fn get_skin_vertices(&self) -> Vec<SkinVertex>{
+        self.skin.clone()
+    }
+
+    ///Deforms the bind pose vertices by `joint_matrices` and updates the bound to the deformed
+    /// positions. If the mesh lives on the gpu the vertices are written into its skinned vertex
+    /// buffer, the index buffer stays the same.
+    pub fn apply_skinning(&mut self, joint_matrices: &[Matrix4<f32>]){
+        if !self.is_skinned(){
+            return;
+        }
+
+        let mut skinned = Vec::with_capacity(self.bind_vertices.len());
+        for (vertex, skin) in self.bind_vertices.iter().zip(self.skin.iter()){
+            let matrix = animation::blend_joint_matrices(joint_matrices, skin.joints, skin.weights);
+            let position = matrix.transform_point(Point3::from(vertex.position));
+            let normal = matrix.transform_vector(Vector3::from(vertex.normal));
+            let tangent = matrix.transform_vector(
+                Vector3::new(vertex.tangent[0], vertex.tangent[1], vertex.tangent[2])
+            );
+
+            let mut new_vertex = *vertex;
+            new_vertex.position = position.into();
+            if normal.magnitude2() > 0.0{
+                new_vertex.normal = normal.normalize().into();
+            }
+            new_vertex.tangent = [tangent.x, tangent.y, tangent.z, vertex.tangent[3]];
+            skinned.push(new_vertex);
+        }
+        self.vertices = skinned;
+        self.rebuild_bound();
+
+        if let Err(er) = self.write_skinned_buffer(){
+            println!("WARNING: MESH: failed to upload skinned {}: {}", self.name, er);
+        }
+    }
+
+    ///Writes the current vertices into the skinned vertex buffer. The buffer is only created
+    /// again if it doesn't exist yet or is still read by the gpu.
+    fn write_skinned_buffer(&mut self) -> Result<(), AssetError>{
+        let device = match self.device{
+            Some(ref device) => device.clone(),
+            None => return Ok(()),
+        };
+
+        if let Some(ref buffer) = self.skinned_buffer{
+            if let Ok(mut content) = buffer.write(){
+                if content.len() == self.vertices.len(){
+                    content.copy_from_slice(&self.vertices);
+                    return Ok(());
+                }
+            }
+        }
+
+        match CpuAccessibleBuffer::from_iter(device, BufferUsage::vertex_buffer(), self.vertices.iter().cloned()){
+            Ok(buffer) => {
+                self.skinned_buffer = Some(buffer);
+                Ok(())
+            },
+            Err(er) => Err(AssetError::GpuUpload(
+                format!("failed to create skinned vertex buffer of {}: {:?}", self.name, er)
+            )),
+        }
+    }
+
+    ///Returns the name of the material this mesh uses, or "fallback" if it has no material
     #[inline]
     pub fn get_material_name(&self) -> String{
+        match self.material{
+            Some(ref mat) => {
+                let mat_lck = mat.lock().expect("failed to lock meshs material");
+                (mat_lck).get_name()
+            },
+            None => String::from("fallback"),
+        }
+    }
 
-        let mat_lck = self.material.lock().expect("failed to lock meshs material");
-        (mat_lck).get_name()
+    ///Returns true if this mesh has a material, which is only false for cpu only meshes.
+    #[inline]
+    pub fn has_material(&self) -> bool{
+        self.material.is_some()
     }
 
     ///Returns the material in use by this mesh as clone
+    ///#panic panics if the mesh was created without a material via `new_cpu_only()`
     #[inline]
     pub fn get_material(&self) -> Arc<Mutex<material::Material>>{
-        self.material.clone()
+        self.material.clone().expect("mesh has no material, it was created for a headless engine")
     }
 
     ///Can be used to set the mesh's material to a new one
     #[inline]
     pub fn set_material(&mut self, new_mat: Arc<Mutex<material::Material>>){
-        self.material = new_mat;
+        self.material = Some(new_mat);
     }
 
     ///Returns all indices
@@ -201,9 +524,14 @@ impl Mesh {
         return_vec
     }
 
-    ///Returns the current vertex buffer of this mesh
+    ///Returns the current vertex buffer of this mesh, which holds the deformed vertices if the
+    /// mesh is skinned
     pub fn get_vertex_buffer(&self) -> Option<Vec<Arc<BufferAccess + Send + Sync>>>{
         let mut return_vec = Vec::new();
+        if let Some(ref skinned) = self.skinned_buffer{
+            return_vec.push(skinned.clone() as Arc<BufferAccess + Send + Sync>);
+            return Some(return_vec);
+        }
         match self.vertex_buffer.clone(){
             Some(vb) => {
                 return_vec.push(vb as Arc<BufferAccess + Send + Sync>);
@@ -214,30 +542,48 @@ impl Mesh {
 
     }
 
-    ///Recreates the vertex buffer from a specified device and queue
-    pub fn re_create_buffer(&mut self, upload_queue: Arc<Queue>)
+    ///Recreates the vertex buffer from a specified device and queue, the old buffers are kept
+    /// if the upload fails.
+    pub fn re_create_buffer(&mut self, upload_queue: Arc<Queue>) -> Result<(), AssetError>
+    {
+        let (vertex_buffer, index_buffer) = self.upload_buffers(&self.vertices, &self.indices, upload_queue)?;
+
+        //overwrite internally
+        self.vertex_buffer = Some(vertex_buffer);
+        self.index_buffer = Some(index_buffer);
+        //the deformed vertices are written again by the next skinning
+        self.skinned_buffer = None;
+        Ok(())
+    }
+
+    ///Uploads `vertices` and `indices` into new buffers, self is not changed.
+    fn upload_buffers(&self, vertices: &[Vertex], indices: &[u32], upload_queue: Arc<Queue>)
+    -> Result<(Arc<ImmutableBuffer<[Vertex]>>, Arc<ImmutableBuffer<[u32]>>), AssetError>
     {
         //create both buffers and wait for the graphics card to actually upload them
-        let (vertex_buffer, buffer_future) = ImmutableBuffer::from_iter(
-            self.vertices.iter().cloned(),
+        let (vertex_buffer, _buffer_future) = match ImmutableBuffer::from_iter(
+            vertices.iter().cloned(),
             BufferUsage::all(),
             upload_queue.clone()
-        ).expect("failed to create vertex buffer");
-
-
-
-
+        ){
+            Ok(buffer) => buffer,
+            Err(er) => return Err(AssetError::GpuUpload(
+                format!("failed to create vertex buffer of {}: {:?}", self.name, er)
+            )),
+        };
 
-        let (index_buffer, future) = ImmutableBuffer::from_iter(
-            self.indices.iter().cloned(),
+        let (index_buffer, _future) = match ImmutableBuffer::from_iter(
+            indices.iter().cloned(),
             BufferUsage::all(),
             upload_queue.clone()
-        ).expect("failed to create index buffer");
-
-        //overwrite internally
-        self.vertex_buffer = Some(vertex_buffer);
-        self.index_buffer = Some(index_buffer);
+        ){
+            Ok(buffer) => buffer,
+            Err(er) => return Err(AssetError::GpuUpload(
+                format!("failed to create index buffer of {}: {:?}", self.name, er)
+            )),
+        };
 
+        Ok((vertex_buffer, index_buffer))
     }
 
 
@@ -251,6 +597,7 @@ impl Mesh {
     pub fn get_draw_call(&self,
         frame_system: &FrameSystem,
         light_system: &LightSystem,
+        probes: &ProbeBinding,
         transform: Matrix4<f32>,
         debug: &mut RenderDebug,
     ) -> Box<FnCbBox + Send + 'static>{
@@ -281,6 +628,14 @@ impl Mesh {
             material.get_set_04(&light_system, &frame_system)
         };
 
+        let set_05 = {
+            material.get_set_05(&light_system)
+        };
+
+        let set_06 = {
+            material.get_set_06(probes)
+        };
+
         let dyn_state = frame_system.get_dynamic_state().clone();
         let vertex_buf = self.get_vertex_buffer().expect("Failed to get vertex_buf");
         let index_buf = self.get_index_buffer().expect("Found no index buffer, should not happen");
@@ -293,7 +648,7 @@ impl Mesh {
                 dyn_state,
                 vertex_buf, //vertex buffer (static usually)
                 index_buf, //index buffer
-                (set_01, set_02, set_03, set_04), //descriptor sets (currently static)
+                (set_01, set_02, set_03, set_04, set_05, set_06), //descriptor sets (currently static)
                 ()
             )
             .expect("Failed to draw mesh in command buffer!");
@@ -304,12 +659,15 @@ impl Mesh {
         Box::new(call)
     }
 
-    ///Renders this mesh if the supplied framestage is in the froward stage
+    ///Renders this mesh if the supplied framestage is in the froward stage. `probes` are the
+    /// reflection probes which influence the mesh, `dynamic_state` has to cover the target image.
     pub fn draw(
         &self,
         command_buffer: AutoCommandBufferBuilder,
         frame_system: &FrameSystem,
         light_system: &LightSystem,
+        probes: &ProbeBinding,
+        dynamic_state: &DynamicState,
         transform: Matrix4<f32>,
         debug: &mut RenderDebug,
     ) -> AutoCommandBufferBuilder{
@@ -344,16 +702,24 @@ impl Mesh {
             material.get_set_04(&light_system, &frame_system)
         };
 
+        let set_05 = {
+            material.get_set_05(&light_system)
+        };
+
+        let set_06 = {
+            material.get_set_06(probes)
+        };
+
         debug.end_mesh_set();
         debug.start_draw_cmd();
 
         //extend the current command buffer by this mesh
         let new_cb = command_buffer.draw_indexed(
             pipeline,
-            frame_system.get_dynamic_state().clone(),
+            dynamic_state.clone(),
             self.get_vertex_buffer().expect("Found no vertex buffer, should not happen"), //vertex buffer (static usually)
             self.get_index_buffer().expect("Found no index buffer, should not happen"), //index buffer
-            (set_01, set_02, set_03, set_04), //descriptor sets (currently static)
+            (set_01, set_02, set_03, set_04, set_05, set_06), //descriptor sets (currently static)
             ()
         )
         .expect("Failed to draw mesh in command buffer!");
@@ -363,6 +729,59 @@ impl Mesh {
         new_cb
     }
 
+    ///Draws this mesh once per entry of `instance_buffer` with a single draw call. The `pipeline`
+    /// has to be the instanced variant of the material's pipeline, it reads the model matrix of
+    /// every instance from the `InstanceData` in the instance buffer.
+    pub fn draw_instanced(
+        &self,
+        command_buffer: AutoCommandBufferBuilder,
+        frame_system: &FrameSystem,
+        light_system: &LightSystem,
+        probes: &ProbeBinding,
+        dynamic_state: &DynamicState,
+        pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+        instance_buffer: Arc<BufferAccess + Send + Sync>,
+        debug: &mut RenderDebug,
+    ) -> AutoCommandBufferBuilder{
+        let (mut vertex_buffers, index_buffer) = match (self.get_vertex_buffer(), self.get_index_buffer()){
+            (Some(vertex_buffers), Some(index_buffer)) => (vertex_buffers, index_buffer),
+            _ => return command_buffer,
+        };
+        vertex_buffers.push(instance_buffer);
+
+        let material_locked = self.get_material();
+        let mut material = material_locked
+        .lock()
+        .expect("failed to lock mesh for command buffer generation");
+
+        debug.start_set_gen();
+
+        //The model matrix comes from the instance buffer, the one in set_01 is unused
+        let set_01 = material.get_set_01(Matrix4::identity());
+        let set_02 = material.get_set_02();
+        let set_03 = material.get_set_03();
+        let set_04 = material.get_set_04(&light_system, &frame_system);
+        let set_05 = material.get_set_05(&light_system);
+        let set_06 = material.get_set_06(probes);
+
+        debug.end_mesh_set();
+        debug.start_draw_cmd();
+
+        let new_cb = command_buffer.draw_indexed(
+            pipeline,
+            dynamic_state.clone(),
+            vertex_buffers,
+            index_buffer,
+            (set_01, set_02, set_03, set_04, set_05, set_06),
+            ()
+        )
+        .expect("Failed to draw instanced mesh in command buffer!");
+
+        debug.end_draw_cmd();
+
+        new_cb
+    }
+
 }
 
 impl ReturnBoundInfo for Mesh{
@@ -409,9 +828,21 @@ impl ReturnBoundInfo for Mesh{
         return_vector
     }
 
-    ///Rebuilds nothing, but might be able in the future to actually rebuild the bound based on all of the vertexes
+    ///Rebuilds the bound from the positions of all vertices, keeps it if there are none
     fn rebuild_bound(&mut self){
-        //
+        let first = match self.vertices.first(){
+            Some(vertex) => Point3::from(vertex.position),
+            None => return,
+        };
 
+        let mut min = first;
+        let mut max = first;
+        for vertex in self.vertices.iter(){
+            for axis in 0..3{
+                min[axis] = min[axis].min(vertex.position[axis]);
+                max[axis] = max[axis].max(vertex.position[axis]);
+            }
+        }
+        self.bound = collision::Aabb3::new(min, max);
     }
 }
@@ -12,3 +12,13 @@ pub mod empty;
 pub mod material;
 ///Defines a texture along with it's different settings, like mipmapping and tiling-mode
 pub mod texture;
+///A hierarchy of joints which deforms skinned meshes
+pub mod skeleton;
+///Animation clips for skeletons as well as the player which samples them
+pub mod animation;
+///Places a scene of the scene manager as instance in another scene
+pub mod prefab;
+///A sky cube map and the image based lighting maps generated from it
+pub mod environment;
+///Captures the scene into a cube map which replaces the environment within its influence volume
+pub mod reflection_probe;
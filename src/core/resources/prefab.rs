@@ -0,0 +1,262 @@
+use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+use std::cmp::Ordering;
+
+use jakar_tree;
+use cgmath::*;
+use collision::*;
+
+use core::next_tree::{SceneTree, SceneComparer, ValueTypeBool, JakarNode};
+use core::next_tree::content::ContentType;
+use core::next_tree::jobs::SceneJobs;
+use core::next_tree::attributes::NodeAttributes;
+use core::resources::mesh;
+
+///A scene of the scene manager as it is referenced by prefabs
+pub type PrefabScene = Arc<Mutex<jakar_tree::tree::Tree<ContentType, SceneJobs, NodeAttributes>>>;
+
+///Places a scene of the scene manager in another scene without copying it. The transform of the
+/// node which holds the prefab is the transform of this instance. Changes to the referenced scene
+/// show up in every instance.
+#[derive(Clone)]
+pub struct Prefab {
+    pub name: String,
+    scene_name: String,
+    scene: PrefabScene,
+}
+
+impl Prefab{
+    ///Creates an instance of `scene` which is called `scene_name` in the scene manager
+    pub fn new(name: &str, scene_name: &str, scene: PrefabScene) -> Self{
+        Prefab{
+            name: String::from(name),
+            scene_name: String::from(scene_name),
+            scene: scene,
+        }
+    }
+
+    ///Returns the name of the referenced scene
+    pub fn get_scene_name(&self) -> String{
+        self.scene_name.clone()
+    }
+
+    ///Returns the referenced scene
+    pub fn get_scene(&self) -> PrefabScene{
+        self.scene.clone()
+    }
+
+    ///Returns the bound of the whole referenced scene in its own space
+    pub fn get_bound(&self) -> Aabb3<f32>{
+        let scene_lck = self.scene.lock().expect("failed to lock prefab scene");
+        scene_lck.root_node.get_attrib().bound
+    }
+
+    ///Returns copies of all mesh nodes of the referenced scene. Prefabs within the referenced
+    /// scene are not expanded.
+    pub fn get_mesh_nodes(&self) -> Vec<JakarNode>{
+        let scene_lck = self.scene.lock().expect("failed to lock prefab scene");
+        scene_lck.copy_all_nodes(&Some(
+            SceneComparer::new().with_value_type(ValueTypeBool::none().with_mesh())
+        ))
+    }
+}
+
+///Every instance of one mesh, can be drawn with a single instanced draw call
+#[derive(Clone)]
+pub struct InstanceBatch {
+    pub mesh: Arc<Mutex<mesh::Mesh>>,
+    ///The world transform of every instance
+    pub transforms: Vec<Matrix4<f32>>,
+    ///True if the mesh node in the prefab scene is transparent. Transparent batches hold a single
+    /// instance, so they can be sorted by distance.
+    pub is_transparent: bool,
+}
+
+impl InstanceBatch{
+    ///Returns the per instance data of the instance buffer
+    pub fn get_instance_data(&self) -> Vec<mesh::InstanceData>{
+        self.transforms.iter().map(|transform| mesh::InstanceData::new(*transform)).collect()
+    }
+
+    ///Returns the distance from `position` to the origin of the farthest instance, like the
+    /// translucent meshes of the scene are sorted by the distance of their origin.
+    pub fn get_distance(&self, position: Vector3<f32>) -> f32{
+        self.transforms.iter().fold(0.0, |max: f32, transform| {
+            max.max((transform.w.truncate() - position).magnitude())
+        })
+    }
+}
+
+///Sorts `batches` from the farthest to the nearest to `position`, transparent batches have to be
+/// drawn in this order.
+pub fn sort_back_to_front(batches: &mut Vec<&InstanceBatch>, position: Vector3<f32>){
+    batches.sort_by(|a, b| {
+        b.get_distance(position).partial_cmp(&a.get_distance(position)).unwrap_or(Ordering::Equal)
+    });
+}
+
+///Collects the meshes of all prefab `nodes` into one batch per mesh. Each referenced scene is read
+/// only once, no matter how many instances it has. Nodes which hold no prefab are ignored.
+/// Transparent meshes are not batched, every instance gets its own batch so it can be sorted with
+/// `sort_back_to_front()`. Works without a gpu, the batches of cpu only meshes can be inspected as well.
+pub fn batch_instances(nodes: &[JakarNode]) -> Vec<InstanceBatch>{
+    //the instance transforms and the prefab per referenced scene
+    let mut instances: BTreeMap<String, (Prefab, Vec<Matrix4<f32>>)> = BTreeMap::new();
+    for node in nodes.iter(){
+        if let &ContentType::Prefab(ref prefab) = node.get_value(){
+            instances.entry(prefab.get_scene_name())
+            .or_insert_with(|| (prefab.clone(), Vec::new()))
+            .1.push(node.get_attrib().get_matrix());
+        }
+    }
+
+    let mut batches: Vec<InstanceBatch> = Vec::new();
+    //the index of the batch of each opaque mesh, the mesh is identified by its address
+    let mut batch_indices: BTreeMap<usize, usize> = BTreeMap::new();
+
+    for (_, (prefab, instance_transforms)) in instances.into_iter(){
+        for mesh_node in prefab.get_mesh_nodes().iter(){
+            let node_mesh = match mesh_node.get_value(){
+                &ContentType::Mesh(ref node_mesh) => node_mesh.clone(),
+                _ => continue,
+            };
+
+            let mesh_transform = mesh_node.get_attrib().get_matrix();
+            let is_transparent = mesh_node.get_attrib().is_transparent;
+            if is_transparent{
+                for instance_transform in instance_transforms.iter(){
+                    batches.push(InstanceBatch{
+                        mesh: node_mesh.clone(),
+                        transforms: vec![*instance_transform * mesh_transform],
+                        is_transparent: true,
+                    });
+                }
+                continue;
+            }
+
+            let key = &*node_mesh as *const Mutex<mesh::Mesh> as usize;
+            let batch_index = match batch_indices.get(&key).cloned(){
+                Some(index) => index,
+                None => {
+                    batches.push(InstanceBatch{
+                        mesh: node_mesh,
+                        transforms: Vec::new(),
+                        is_transparent: false,
+                    });
+                    batch_indices.insert(key, batches.len() - 1);
+                    batches.len() - 1
+                }
+            };
+
+            for instance_transform in instance_transforms.iter(){
+                batches[batch_index].transforms.push(*instance_transform * mesh_transform);
+            }
+        }
+    }
+
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jakar_tree::node::{Node, Attribute};
+    use core::resources::empty;
+
+    ///A scene with an opaque mesh one unit above the root and a transparent mesh at the root
+    fn test_scene(opaque: Arc<Mutex<mesh::Mesh>>) -> PrefabScene{
+        let mut tree = jakar_tree::tree::Tree::new(
+            ContentType::Empty(empty::Empty::new("prefab_root")),
+            NodeAttributes::default()
+        );
+        let root_name = tree.root_node.get_name().clone();
+
+        let mut opaque_attributes = NodeAttributes::default();
+        opaque_attributes.transform.disp = Vector3::new(0.0, 1.0, 0.0);
+        tree.add(ContentType::Mesh(opaque), root_name.clone(), Some(opaque_attributes))
+        .expect("failed to add opaque mesh");
+
+        let glass = Arc::new(Mutex::new(mesh::Mesh::new_cpu_only("glass")));
+        let mut glass_attributes = NodeAttributes::default();
+        glass_attributes.is_transparent = true;
+        tree.add(ContentType::Mesh(glass), root_name, Some(glass_attributes))
+        .expect("failed to add transparent mesh");
+
+        Arc::new(Mutex::new(tree))
+    }
+
+    fn instance(scene_name: &str, scene: &PrefabScene, x: f32) -> JakarNode{
+        let mut attributes = NodeAttributes::default();
+        attributes.transform.disp = Vector3::new(x, 0.0, 0.0);
+        Node::new(ContentType::Prefab(Prefab::new("instance", scene_name, scene.clone())), attributes)
+    }
+
+    fn get_translation(matrix: &Matrix4<f32>) -> Vector3<f32>{
+        matrix.w.truncate()
+    }
+
+    #[test]
+    fn opaque_meshes_are_batched_per_mesh(){
+        let scene = test_scene(Arc::new(Mutex::new(mesh::Mesh::new_cpu_only("opaque"))));
+        let nodes = vec![
+            instance("scene", &scene, 0.0),
+            instance("scene", &scene, 5.0),
+            instance("scene", &scene, 10.0),
+            Node::new(ContentType::Empty(empty::Empty::new("no_prefab")), NodeAttributes::default()),
+        ];
+
+        let batches = batch_instances(&nodes);
+        let opaque: Vec<&InstanceBatch> = batches.iter().filter(|batch| !batch.is_transparent).collect();
+        assert_eq!(opaque.len(), 1);
+        let translations: Vec<Vector3<f32>> = opaque[0].transforms.iter().map(get_translation).collect();
+        assert_eq!(translations, vec![
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(5.0, 1.0, 0.0),
+            Vector3::new(10.0, 1.0, 0.0),
+        ]);
+        assert_eq!(opaque[0].get_instance_data().len(), 3);
+    }
+
+    #[test]
+    fn transparent_meshes_get_a_batch_per_instance(){
+        let scene = test_scene(Arc::new(Mutex::new(mesh::Mesh::new_cpu_only("opaque"))));
+        let nodes = vec![instance("scene", &scene, 0.0), instance("scene", &scene, 5.0)];
+
+        let batches = batch_instances(&nodes);
+        let transparent: Vec<&InstanceBatch> = batches.iter().filter(|batch| batch.is_transparent).collect();
+        assert_eq!(transparent.len(), 2);
+        for batch in transparent.iter(){
+            assert_eq!(batch.transforms.len(), 1);
+        }
+    }
+
+    #[test]
+    fn scenes_which_share_a_mesh_share_its_batch(){
+        let shared = Arc::new(Mutex::new(mesh::Mesh::new_cpu_only("opaque")));
+        let first = test_scene(shared.clone());
+        let second = test_scene(shared);
+        let nodes = vec![instance("first", &first, 0.0), instance("second", &second, 5.0)];
+
+        let batches = batch_instances(&nodes);
+        let opaque: Vec<&InstanceBatch> = batches.iter().filter(|batch| !batch.is_transparent).collect();
+        assert_eq!(opaque.len(), 1);
+        assert_eq!(opaque[0].transforms.len(), 2);
+    }
+
+    #[test]
+    fn transparent_batches_are_sorted_back_to_front(){
+        let scene = test_scene(Arc::new(Mutex::new(mesh::Mesh::new_cpu_only("opaque"))));
+        let nodes = vec![
+            instance("scene", &scene, 5.0),
+            instance("scene", &scene, 20.0),
+            instance("scene", &scene, -10.0),
+        ];
+
+        let batches = batch_instances(&nodes);
+        let mut transparent: Vec<&InstanceBatch> = batches.iter().filter(|batch| batch.is_transparent).collect();
+        sort_back_to_front(&mut transparent, Vector3::new(0.0, 0.0, 0.0));
+
+        let order: Vec<f32> = transparent.iter().map(|batch| get_translation(&batch.transforms[0]).x).collect();
+        assert_eq!(order, vec![20.0, -10.0, 5.0]);
+    }
+}
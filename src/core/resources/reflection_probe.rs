@@ -0,0 +1,223 @@
+use cgmath::*;
+use collision;
+use collision::{Aabb, Aabb3};
+
+use core::ReturnBoundInfo;
+use core::next_tree::transform::NodeTransform;
+
+///When a reflection probe captures the scene around it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProbeUpdate {
+    ///Captures once and then only after `request_capture()` was called
+    OnDemand,
+    ///Captures again every `n` seconds
+    Interval(f32),
+}
+
+///Captures the scene at its location into a cube map which is used instead of the environment for
+/// the reflections and ambient light of every object within its influence volume. Place them in
+/// interiors, where the global environment map shows the wrong surrounding.
+#[derive(Clone)]
+pub struct ReflectionProbe {
+    pub name: String,
+    update: ProbeUpdate,
+    ///Over how many meters the influence fades out at the edges of the influence volume
+    blend_distance: f32,
+    ///Is increased by `request_capture()`, the renderer captures when it changes
+    capture_request: u32,
+    ///The influence volume in the space of the node
+    influence: collision::Aabb3<f32>,
+    ///The bound of the probe itself, a small box around the capture point. The influence volume
+    /// is kept out of it, so it doesn't grow the bounds of the parents and isn't hit by raycasts.
+    bound: collision::Aabb3<f32>,
+}
+
+impl ReflectionProbe{
+    ///Creates an on demand probe with a 10x10x10 influence volume
+    pub fn new(name: &str) -> Self{
+        let min = Point3::new(-5.0, -5.0, -5.0);
+        let max = Point3::new(5.0, 5.0, 5.0);
+
+        ReflectionProbe{
+            name: String::from(name),
+            update: ProbeUpdate::OnDemand,
+            blend_distance: 1.0,
+            capture_request: 0,
+            influence: Aabb3::new(min, max),
+            bound: Aabb3::new(Point3::new(-0.5, -0.5, -0.5), Point3::new(0.5, 0.5, 0.5)),
+        }
+    }
+
+    ///Sets the influence volume relative to the probe location
+    pub fn with_influence(mut self, min: Point3<f32>, max: Point3<f32>) -> Self{
+        self.set_influence(min, max);
+        self
+    }
+
+    ///Sets the influence volume relative to the probe location
+    #[inline]
+    pub fn set_influence(&mut self, min: Point3<f32>, max: Point3<f32>){
+        self.influence = Aabb3::new(min, max);
+    }
+
+    ///Returns the influence volume in the space of the node
+    #[inline]
+    pub fn get_influence(&self) -> Aabb3<f32>{
+        self.influence
+    }
+
+    ///Returns the influence volume in world space if the node of this probe has `transform`
+    pub fn get_world_influence(&self, transform: &NodeTransform) -> Aabb3<f32>{
+        let corners = self.influence.to_corners();
+        let first = transform.transform_point(corners[0]);
+        corners.iter().skip(1).fold(Aabb3::new(first, first), |bound, corner|{
+            bound.grow(transform.transform_point(*corner))
+        })
+    }
+
+    ///Sets when the probe captures the scene
+    pub fn with_update(mut self, update: ProbeUpdate) -> Self{
+        self.update = update;
+        self
+    }
+
+    ///Sets when the probe captures the scene
+    #[inline]
+    pub fn set_update(&mut self, update: ProbeUpdate){
+        self.update = update;
+    }
+
+    #[inline]
+    pub fn get_update(&self) -> ProbeUpdate{
+        self.update
+    }
+
+    ///Sets over how many meters the influence fades out at the edges of the volume, values
+    /// below 0.0 are ignored
+    #[inline]
+    pub fn set_blend_distance(&mut self, distance: f32){
+        if distance < 0.0{
+            return;
+        }
+        self.blend_distance = distance;
+    }
+
+    #[inline]
+    pub fn get_blend_distance(&self) -> f32{
+        self.blend_distance
+    }
+
+    ///Lets the renderer capture this probe again, for instance after a door was opened.
+    ///NOTE: Call it on the probe in the scene, not on a copy.
+    #[inline]
+    pub fn request_capture(&mut self){
+        self.capture_request = self.capture_request.wrapping_add(1);
+    }
+
+    ///Returns the counter of `request_capture()` calls, the renderer compares it to the one of
+    /// the last capture.
+    #[inline]
+    pub fn get_capture_request(&self) -> u32{
+        self.capture_request
+    }
+
+    ///Returns the view matrix of each cube face in the order +x, -x, +y, -y, +z, -z.
+    ///The scene is mirrored on y before the view is applied. This way a capture has the same
+    /// winding order as the main camera (which flips y in its projection) while the faces are in
+    /// the orientation a cube map expects. Cube map lookups therefore have to mirror y as well.
+    pub fn get_face_views(location: &Vector3<f32>) -> [Matrix4<f32>; 6]{
+        //The direction and up vector of each face
+        let faces = [
+            (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+            (Vector3::new(-1.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+            (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+            (Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 0.0, -1.0)),
+            (Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, -1.0, 0.0)),
+            (Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, -1.0, 0.0)),
+        ];
+
+        //The probe looks at the mirrored scene from its mirrored location
+        let mirror = Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0);
+        let mirrored_location = Point3::new(location.x, -location.y, location.z);
+
+        let mut views = [Matrix4::<f32>::identity(); 6];
+        for (idx, &(direction, up)) in faces.iter().enumerate(){
+            let view = Matrix4::look_at(
+                mirrored_location,
+                mirrored_location + direction,
+                up
+            );
+            views[idx] = view * mirror;
+        }
+
+        views
+    }
+
+    ///Returns the projection used for every cube face
+    pub fn get_face_projection(near_plane: f32, far_plane: f32) -> Matrix4<f32>{
+        perspective(Deg(90.0), 1.0, near_plane, far_plane)
+    }
+}
+
+impl ReturnBoundInfo for ReflectionProbe{
+    ///return the max size of its bound
+    #[inline]
+    fn get_bound_max(&self)-> Point3<f32>{
+        self.bound.max.clone()
+    }
+    ///return the min size of its bound
+    #[inline]
+    fn get_bound_min(&self)-> Point3<f32>{
+        self.bound.min.clone()
+    }
+    ///Sets the bound of the probe itself (in node space), use `set_influence()` for the volume
+    /// it reflects in
+    fn set_bound(&mut self, min: Point3<f32>, max: Point3<f32>){
+        self.bound = collision::Aabb3::new(min, max);
+    }
+
+    ///Returns the bound of the probe itself, not its influence volume
+    #[inline]
+    fn get_bound(&self) -> collision::Aabb3<f32>{
+        self.bound.clone()
+    }
+
+    ///Returns the corners of the bound, good for debuging
+    fn get_bound_points(&self)-> Vec<Vector3<f32>>{
+        self.bound.to_corners().iter().map(|point| point.to_vec()).collect()
+    }
+
+    ///Does nothing, the bound is always set directly
+    fn rebuild_bound(&mut self){
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn influence_is_not_part_of_the_bound(){
+        let probe = ReflectionProbe::new("probe")
+        .with_influence(Point3::new(-20.0, -1.0, -20.0), Point3::new(20.0, 1.0, 20.0));
+        assert_eq!(probe.get_influence().max, Point3::new(20.0, 1.0, 20.0));
+        assert!(probe.get_bound().max.x < 1.0);
+    }
+
+    #[test]
+    fn world_influence_follows_the_node(){
+        let probe = ReflectionProbe::new("probe")
+        .with_influence(Point3::new(-1.0, -2.0, -3.0), Point3::new(1.0, 2.0, 3.0));
+        let transform = NodeTransform::new(
+            Vector3::new(10.0, 0.0, 0.0),
+            Quaternion::from_angle_y(Deg(90.0)),
+            Vector3::new(2.0, 1.0, 1.0),
+        );
+        let world = probe.get_world_influence(&transform);
+        //x is scaled to 2, then x and z swap places by the rotation
+        let expected_min = Point3::new(7.0, -2.0, -2.0);
+        let expected_max = Point3::new(13.0, 2.0, 2.0);
+        assert!((world.min - expected_min).magnitude() < 1.0e-4, "{:?}", world);
+        assert!((world.max - expected_max).magnitude() < 1.0e-4, "{:?}", world);
+    }
+}
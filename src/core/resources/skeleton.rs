@@ -0,0 +1,143 @@
+use cgmath::*;
+
+use core::next_tree::transform::NodeTransform;
+
+///A single bone of a skeleton
+#[derive(Clone, Debug)]
+pub struct Joint {
+    pub name: String,
+    ///Index of the parent joint in the skeleton, `None` for root joints
+    pub parent: Option<usize>,
+    ///Transforms from the space of the skinned mesh into the space of this joint in bind pose
+    pub inverse_bind_matrix: Matrix4<f32>,
+    ///The transform relative to the parent joint when no animation is played
+    pub rest_transform: NodeTransform,
+}
+
+impl Joint{
+    ///Creates a root joint with an identity inverse bind matrix and rest transform
+    pub fn new(name: &str) -> Self{
+        Joint{
+            name: String::from(name),
+            parent: None,
+            inverse_bind_matrix: Matrix4::identity(),
+            rest_transform: NodeTransform::one(),
+        }
+    }
+
+    ///Sets the parent joint index
+    pub fn with_parent(mut self, parent: usize) -> Self{
+        self.parent = Some(parent);
+        self
+    }
+
+    ///Sets the inverse bind matrix
+    pub fn with_inverse_bind_matrix(mut self, matrix: Matrix4<f32>) -> Self{
+        self.inverse_bind_matrix = matrix;
+        self
+    }
+
+    ///Sets the rest transform
+    pub fn with_rest_transform(mut self, transform: NodeTransform) -> Self{
+        self.rest_transform = transform;
+        self
+    }
+}
+
+///A hierarchy of joints which can deform a skinned mesh. Is created from glTF skins and
+/// animated by `AnimationClip`s.
+#[derive(Clone, Debug)]
+pub struct Skeleton {
+    pub name: String,
+    joints: Vec<Joint>,
+}
+
+impl Skeleton{
+    ///Creates a skeleton without any joints
+    pub fn new(name: &str) -> Self{
+        Skeleton{
+            name: String::from(name),
+            joints: Vec::new(),
+        }
+    }
+
+    ///Adds a joint and returns its index. Joints can be added in any order, but the parent
+    /// indices have to be valid once the skeleton is used.
+    pub fn add_joint(&mut self, joint: Joint) -> usize{
+        self.joints.push(joint);
+        self.joints.len() - 1
+    }
+
+    ///Returns all joints
+    pub fn get_joints(&self) -> &Vec<Joint>{
+        &self.joints
+    }
+
+    ///Returns all joints as mutable reference
+    pub fn get_joints_mut(&mut self) -> &mut Vec<Joint>{
+        &mut self.joints
+    }
+
+    ///Returns the number of joints
+    pub fn joint_count(&self) -> usize{
+        self.joints.len()
+    }
+
+    ///Returns the index of the joint called `name`
+    pub fn get_joint_index(&self, name: &str) -> Option<usize>{
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    ///Returns the local transform of each joint in rest pose
+    pub fn get_rest_pose(&self) -> Vec<NodeTransform>{
+        self.joints.iter().map(|joint| joint.rest_transform).collect()
+    }
+
+    ///Returns the transform of each joint in the space of the skeleton root for a `pose`,
+    /// which holds one local transform per joint. Missing pose entries use the rest transform.
+    pub fn get_global_matrices(&self, pose: &[NodeTransform]) -> Vec<Matrix4<f32>>{
+        let mut globals: Vec<Option<Matrix4<f32>>> = vec![None; self.joints.len()];
+        for index in 0..self.joints.len(){
+            self.resolve_global(index, pose, &mut globals, 0);
+        }
+        globals.into_iter().map(|global| global.unwrap_or(Matrix4::identity())).collect()
+    }
+
+    ///Returns the matrices which move a vertex from the bind pose into `pose`, one per joint.
+    /// These are the ones a skinned vertex is transformed with.
+    pub fn get_joint_matrices(&self, pose: &[NodeTransform]) -> Vec<Matrix4<f32>>{
+        self.get_global_matrices(pose)
+        .into_iter()
+        .zip(self.joints.iter())
+        .map(|(global, joint)| global * joint.inverse_bind_matrix)
+        .collect()
+    }
+
+    fn resolve_global(
+        &self,
+        index: usize,
+        pose: &[NodeTransform],
+        globals: &mut Vec<Option<Matrix4<f32>>>,
+        depth: usize,
+    ) -> Matrix4<f32>{
+        if let Some(global) = globals[index]{
+            return global;
+        }
+
+        let local = match pose.get(index){
+            Some(transform) => transform.get_matrix(),
+            None => self.joints[index].rest_transform.get_matrix(),
+        };
+
+        //The depth check protects against broken files with cyclic parents
+        let global = match self.joints[index].parent{
+            Some(parent) if parent < self.joints.len() && depth < self.joints.len() => {
+                self.resolve_global(parent, pose, globals, depth + 1) * local
+            },
+            _ => local,
+        };
+
+        globals[index] = Some(global);
+        global
+    }
+}
@@ -1,4 +1,6 @@
 use std::sync::{Arc};
+use std::fs::File;
+use std::io::{Read, Cursor};
 
 use vulkano::image::immutable::ImmutableImage;
 use vulkano::sampler::Filter;
@@ -12,8 +14,14 @@ use vulkano::sync::GpuFuture;
 use vulkano;
 
 use image;
+use image::DynamicImage;
 use image::DynamicImage::*;
+use image::hdr::HDRDecoder;
 
+use core::asset_error::AssetError;
+use core::resources::environment::{Environment, CpuCube, FloatImage};
+
+#[derive(Clone)]
 pub struct TextureBuilder {
     //sampler
     //Sampling information if the image is larger or smaller than the original
@@ -58,6 +66,8 @@ pub struct TextureBuilder {
     image_path: String,
     //This is Some(data) if the image should be create from data
     image_data: Option<Vec<u8>>,
+    //The six images of a cube map, only used by `build_environment()`
+    cube_faces: Option<Vec<String>>,
     device: Arc<Device>,
     queue: Arc<Queue>,
 }
@@ -120,6 +130,7 @@ impl TextureBuilder {
             //Create info (this won't be included in the final texture)
             image_path: String::from(image_path),
             image_data: None,
+            cube_faces: None,
             device: device,
             queue: queue,
         }
@@ -174,11 +185,24 @@ impl TextureBuilder {
             //Create info (this won't be included in the final texture)
             image_path: String::from("None"),
             image_data: Some(data),
+            cube_faces: None,
             device: device,
             queue: queue,
         }
     }
 
+    ///Creates a builder for a cube map from six images in the order +x, -x, +y, -y, +z, -z. Can
+    /// only be built with `build_environment()`.
+    pub fn from_cube_faces(
+        face_paths: [&str; 6],
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+    ) -> Self{
+        let mut builder = TextureBuilder::from_image(face_paths[0], device, queue);
+        builder.cube_faces = Some(face_paths.iter().map(|path| String::from(*path)).collect());
+        builder
+    }
+
     ///Sets new filtering technic for the sampler
     #[inline]
     pub fn with_sampling_filter(mut self, mag_filter: Filter, min_filter: Filter) -> Self{
@@ -299,9 +323,16 @@ impl TextureBuilder {
     }
 
     ///This function will use the information currently present in the `TextureBuilder`
-    ///and create a `core::resources::Texture` from it
-    pub fn build_with_name(self, texture_name: &str) -> Arc<Texture>
+    ///and create a `core::resources::Texture` from it. Returns an error if the image can't be
+    /// loaded or uploaded.
+    pub fn build_with_name(self, texture_name: &str) -> Result<Arc<Texture>, AssetError>
     {
+        //Textures from files keep their builder to be reloaded if the file changes, data
+        // textures would only keep a copy of their data alive.
+        let reload_builder = match self.image_data{
+            Some(_) => None,
+            None => Some(self.clone()),
+        };
 
         // This variable will be modified during the function, and will correspond to when the
         // transfer commands are finished.
@@ -320,60 +351,37 @@ impl TextureBuilder {
             self.max_anisotropy,
             self.min_lod,
             self.max_lod,
-        ).expect("Failed to generate sampler");
+        ){
+            Ok(sampler) => sampler,
+            Err(er) => return Err(AssetError::GpuUpload(
+                format!("failed to create sampler for {}: {:?}", texture_name, er)
+            )),
+        };
         //Now load a the texture
-        let texture = {
+        let (texture, byte_size) = {
 
             //first load the image
             let image = {
                 //load the image::DynamicImage based on the type in the builder
-                let mut image = {
+                let loaded_image = {
                     match self.image_data{
-                        Some(image_data) => {
+                        Some(ref image_data) => {
                             //This image is some data buffer, will use this to load
                             //load with format from data
-                            image::load_from_memory(&image_data)
-                            .expect("failed to load image data based on guessing")
+                            image::load_from_memory(image_data)
+                            .map_err(|er| AssetError::from_image_error(
+                                &format!("image data of {}", texture_name), er
+                            ))
                         },
                         None => {
                             //There is no buffer, thats why we load it from the uri
-                            image::open(&self.image_path.to_string())
-                            .expect("failed to load png normal in creation")
+                            let image_path = self.image_path.clone();
+                            image::open(&image_path)
+                            .map_err(|er| AssetError::from_image_error(&image_path, er))
                         }
                     }
                 };
-
-                //now apply, based on the settings all the post progressing
-                //after applying everything we can convert the Dynamic image into the correct format
-                //blur
-                if self.b_blur {
-                    image = image.blur(self.blur_factor);
-                }
-                //unsharpening
-                if self.b_unsharpen {
-                    image = image.unsharpen(self.sharp_factor, self.sharp_threshold);
-                }
-                //brighten
-                if self.b_brighten {
-                    image = image.brighten(self.brighten_factor);
-                }
-                //flipping
-                if self.b_flipv{
-                    image = image.flipv();
-                }
-                if self.b_fliph {
-                    image = image.fliph();
-                }
-                //rotation 90-270 degree
-                if self.b_rotate90 {
-                    image = image.rotate90();
-                }
-                if self.b_rotate180 {
-                    image = image.rotate180();
-                }
-                if self.b_rotate270 {
-                    image = image.rotate270();
-                }
+                let image = self.post_progress_image(loaded_image?);
 
                 //now match the format of this image
                 match image{
@@ -432,21 +440,146 @@ impl TextureBuilder {
                     //Set format dependent on self.color_format
                     image.format,
                     self.queue.clone())
-                .expect("failed to create immutable image")
+                {
+                    Ok(image_and_future) => image_and_future,
+                    Err(er) => return Err(AssetError::GpuUpload(
+                        format!("failed to create image for {}: {:?}", texture_name, er)
+                    )),
+                }
             };
             //drop the future to wait for gpu
             let _  = Box::new(final_future.join(tex_future));
 
-            texture_tmp
+            let byte_size = mip_chain_byte_size(
+                image.data.len(),
+                image.dimensions.width(),
+                image.dimensions.height(),
+                texture_tmp.mipmap_levels()
+            );
+            (texture_tmp, byte_size)
         };
         let texture_struct = Texture{
             name: String::from(texture_name),
             texture: texture,
             sampler: tmp_sampler,
             original_path: self.image_path.clone(),
+            reload_builder: reload_builder,
+            byte_size: byte_size,
+        };
+
+        Ok(Arc::new(texture_struct))
+    }
+
+    ///Builds an `Environment` from the image(s) of this builder. A single image is read as
+    /// equirectangular panorama, a builder created by `from_cube_faces()` as cube map. Radiance
+    /// (.hdr) images are read as linear colors, all other images are expected to be sRGB and only
+    /// those get the post progressing of this builder. The sampler settings are not used.
+    ///NOTE: The irradiance and specular maps are generated on the cpu, which takes a moment.
+    pub fn build_environment(self, name: &str) -> Result<Environment, AssetError>{
+        let sky = match self.cube_faces{
+            Some(ref face_paths) => {
+                let mut faces = Vec::new();
+                for path in face_paths.iter(){
+                    faces.push(self.load_float_image(Some(path))?);
+                }
+                CpuCube::from_faces(faces).map_err(|er| AssetError::decode(&self.image_path, &er))?
+            },
+            None => CpuCube::from_equirectangular(&self.load_float_image(None)?),
         };
 
-        Arc::new(texture_struct)
+        Environment::from_sky(name, sky, self.device.clone(), self.queue.clone())
+    }
+
+    ///Applies the post progressing of this builder to `image`
+    fn post_progress_image(&self, image: DynamicImage) -> DynamicImage{
+        let mut image = image;
+        //now apply, based on the settings all the post progressing
+        //blur
+        if self.b_blur {
+            image = image.blur(self.blur_factor);
+        }
+        //unsharpening
+        if self.b_unsharpen {
+            image = image.unsharpen(self.sharp_factor, self.sharp_threshold);
+        }
+        //brighten
+        if self.b_brighten {
+            image = image.brighten(self.brighten_factor);
+        }
+        //flipping
+        if self.b_flipv{
+            image = image.flipv();
+        }
+        if self.b_fliph {
+            image = image.fliph();
+        }
+        //rotation 90-270 degree
+        if self.b_rotate90 {
+            image = image.rotate90();
+        }
+        if self.b_rotate180 {
+            image = image.rotate180();
+        }
+        if self.b_rotate270 {
+            image = image.rotate270();
+        }
+        image
+    }
+
+    ///Loads the image at `path`, or the image of this builder if `None`, as linear rgb floats
+    fn load_float_image(&self, path: Option<&String>) -> Result<FloatImage, AssetError>{
+        let (bytes, source) = match (path, &self.image_data){
+            (Some(path), _) => (read_file(path)?, path.clone()),
+            (None, &Some(ref data)) => (data.clone(), String::from("image data")),
+            (None, &None) => (read_file(&self.image_path)?, self.image_path.clone()),
+        };
+
+        //Radiance files start with "#?RADIANCE" or "#?RGBE"
+        if bytes.starts_with(b"#?"){
+            let decoder = HDRDecoder::new(Cursor::new(bytes))
+            .map_err(|er| AssetError::from_image_error(&source, er))?;
+            let metadata = decoder.metadata();
+            let pixels = decoder.read_image_hdr()
+            .map_err(|er| AssetError::from_image_error(&source, er))?;
+
+            return Ok(FloatImage{
+                width: metadata.width,
+                height: metadata.height,
+                texels: pixels.into_iter().map(|pixel| pixel.data).collect(),
+            });
+        }
+
+        let image = image::load_from_memory(&bytes)
+        .map_err(|er| AssetError::from_image_error(&source, er))?;
+        let rgb_image = self.post_progress_image(image).to_rgb();
+        let (width, height) = rgb_image.dimensions();
+
+        Ok(FloatImage{
+            width: width,
+            height: height,
+            texels: rgb_image.pixels().map(|pixel| [
+                srgb_to_linear(pixel.data[0]),
+                srgb_to_linear(pixel.data[1]),
+                srgb_to_linear(pixel.data[2]),
+            ]).collect(),
+        })
+    }
+}
+
+///Reads the whole file at `path`
+fn read_file(path: &str) -> Result<Vec<u8>, AssetError>{
+    let mut bytes = Vec::new();
+    let mut file = File::open(path).map_err(|er| AssetError::io(path, er))?;
+    file.read_to_end(&mut bytes).map_err(|er| AssetError::io(path, er))?;
+    Ok(bytes)
+}
+
+fn srgb_to_linear(value: u8) -> f32{
+    let value = value as f32 / 255.0;
+    if value <= 0.04045{
+        value / 12.92
+    }else{
+        ((value + 0.055) / 1.055).powf(2.4)
     }
 }
 
@@ -461,6 +594,10 @@ pub struct Texture {
     sampler: Arc<vulkano::sampler::Sampler>,
 
     original_path: String,
+    //The settings this texture was build with, `None` for textures created from data
+    reload_builder: Option<TextureBuilder>,
+    //size of the image data on the gpu, including all mip levels
+    byte_size: usize,
 }
 
 ///The implementation doesn't change anything on this texture
@@ -478,4 +615,66 @@ impl Texture{
     pub fn get_raw_sampler(&self) -> Arc<vulkano::sampler::Sampler>{
         self.sampler.clone()
     }
+
+    ///Returns the path of the image this texture was loaded from, is "None" if the texture was
+    /// created from data.
+    #[inline]
+    pub fn get_original_path(&self) -> String{
+        self.original_path.clone()
+    }
+
+    ///Returns the size of the image data including all mip levels in bytes
+    #[inline]
+    pub fn get_byte_size(&self) -> usize{
+        self.byte_size
+    }
+
+    ///Returns true if this texture was loaded from a file and can be reloaded
+    #[inline]
+    pub fn is_reloadable(&self) -> bool{
+        self.reload_builder.is_some()
+    }
+
+    ///Loads the image from the original path again and builds a new texture with the same name and
+    /// sampler settings. Returns an error if the texture was created from data or the image
+    /// can't be loaded (for instance because it is still being written).
+    pub fn reload(&self) -> Result<Arc<Texture>, AssetError>{
+        match self.reload_builder{
+            Some(ref builder) => builder.clone().build_with_name(&self.name),
+            None => Err(AssetError::Unsupported(
+                format!("texture {} was not loaded from a file and can't be reloaded", self.name)
+            )),
+        }
+    }
+}
+
+///Returns the size of an image with `levels` mip levels, `base_bytes` is the size of the first
+/// level which is `width` x `height` texels. Every level has half the size of the previous one,
+/// but at least one texel.
+fn mip_chain_byte_size(base_bytes: usize, width: u32, height: u32, levels: u32) -> usize{
+    let texels = (width as usize * height as usize).max(1);
+    let texel_bytes = base_bytes / texels;
+
+    (0..levels.max(1)).fold(0, |sum, level|{
+        let level_width = (width >> level).max(1) as usize;
+        let level_height = (height >> level).max(1) as usize;
+        sum + level_width * level_height * texel_bytes
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_level_is_the_image_data(){
+        assert_eq!(mip_chain_byte_size(4 * 16 * 8, 16, 8, 1), 4 * 16 * 8);
+    }
+
+    #[test]
+    fn mip_chain_adds_all_levels(){
+        //16x8, 8x4, 4x2, 2x1, 1x1, 1x1
+        let texels = 16 * 8 + 8 * 4 + 4 * 2 + 2 * 1 + 1 + 1;
+        assert_eq!(mip_chain_byte_size(4 * 16 * 8, 16, 8, 6), 4 * texels);
+    }
 }
@@ -1,6 +1,7 @@
 use std::sync::{Arc, Mutex};
 
 use core::engine_settings;
+use core::event_bus::*;
 use input::keymap::KeyMap;
 use winit;
 
@@ -8,6 +9,7 @@ pub struct InputHandler {
     key_map: Arc<Mutex<KeyMap>>,
     settings: Arc<Mutex<engine_settings::EngineSettings>>,
     events_loop: winit::EventsLoop,
+    event_bus: EventBus,
 }
 
 
@@ -16,13 +18,14 @@ impl InputHandler{
     pub fn new(
         key_map: Arc<Mutex<KeyMap>>,
         settings: Arc<Mutex<engine_settings::EngineSettings>>,
-
+        event_bus: EventBus,
     ) -> Self{
 
         InputHandler{
             key_map: key_map,
             settings: settings,
             events_loop: winit::EventsLoop::new(),
+            event_bus: event_bus,
         }
     }
 
@@ -42,6 +45,7 @@ impl InputHandler{
         current_keys.reset_data();
 
         let settings_copy = self.settings.clone();
+        let event_bus = self.event_bus.clone();
 
         //Now do the events polling
         self.events_loop.poll_events(|ev| {
@@ -67,6 +71,10 @@ impl InputHandler{
                                 width.clone() as u32,
                                 height.clone() as u32
                             );
+                            event_bus.publish(WindowResizedEvent{
+                                width: width as u32,
+                                height: height as u32,
+                            });
                             //println!("Resized to {} / {}", width, height );
                         },
                         Moved(width, height) =>{
@@ -74,10 +82,12 @@ impl InputHandler{
                         },
                         Closed => {
                             current_keys.closed = true;
+                            event_bus.publish(WindowClosedEvent);
                             //println!("STATUS: INPUT HANDLER: closing", );
                         },
                         DroppedFile(file_path) =>{
                             //println!("Droped file with path: {:?}", file_path );
+                            event_bus.publish(FileDroppedEvent{ path: file_path });
                         },
                         HoveredFile(file_path) => {
 
@@ -89,11 +99,18 @@ impl InputHandler{
 
                         },
                         Focused(b_state) =>{
-
+                            event_bus.publish(WindowFocusEvent{ is_focused: b_state });
                         },
                         KeyboardInput {device_id, input} =>{
                             use winit::VirtualKeyCode;
 
+                            if let Some(key) = input.virtual_keycode{
+                                event_bus.publish(KeyEvent{
+                                    key: key,
+                                    is_pressed: input.state == winit::ElementState::Pressed,
+                                });
+                            }
+
                             //Match the type of input
                             match input.state{
                                 //if pressed set true, else leave false
@@ -300,7 +317,7 @@ impl InputHandler{
                         },
                         */
                         CursorMoved {device_id, position, modifiers} =>{
-
+                            event_bus.publish(CursorMovedEvent{ position: position });
                         },
                         CursorEntered{device_id} =>{
 
@@ -309,10 +326,13 @@ impl InputHandler{
 
                         },
                         MouseWheel{device_id, delta, phase, modifiers} =>{
-
+                            event_bus.publish(MouseWheelEvent{ delta: delta });
                         },
                         MouseInput{device_id, state, button, modifiers} =>{
-
+                            event_bus.publish(MouseButtonEvent{
+                                button: button,
+                                is_pressed: state == winit::ElementState::Pressed,
+                            });
                         },
 
                         TouchpadPressure{device_id, pressure, stage} =>{
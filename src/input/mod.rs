@@ -9,6 +9,7 @@ use std::thread::*;
 use winit;
 
 use core::engine_settings;
+use core::event_bus::EventBus;
 use render::window::Window;
 
 use vulkano::instance::Instance;
@@ -21,13 +22,6 @@ pub mod keymap;
 
 
 
-//A enum which is used to message differen behavoirs to the Input system/thread. Since we can't just
-//keep a Arc<MutexT> of this struct.
-pub enum InputMessage {
-    ///Has to be implemented but it is there
-    RegisterCallback,
-}
-
 ///Describes the state in which the input manager is currently.
 //Can be used to end the system.
 pub enum InputState {
@@ -63,10 +57,11 @@ pub struct Input {
 
 impl Input{
     ///Creates a new Input instance. It needs to recive the vulkan instance which will target the
-    /// resulting window.
+    /// resulting window. Key, mouse and window changes are published on `event_bus`.
     pub fn new(
         settings: Arc<Mutex<engine_settings::EngineSettings>>,
         instance: Arc<Instance>,
+        event_bus: EventBus,
     ) -> Result<(Self, Window), String>{
 
 
@@ -86,7 +81,7 @@ impl Input{
         let input_thread = spawn(move||{
 
             let mut input_handler = input_handler::InputHandler::new(
-                key_map_inst.clone(), settings_inst.clone()
+                key_map_inst.clone(), settings_inst.clone(), event_bus
             );
             //now create a window for this loop and send it back
             let window = Window::new(
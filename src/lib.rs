@@ -21,6 +21,14 @@ extern crate gltf_utils;
 extern crate jakar_tree;
 //The threadpool implementation
 extern crate jakar_threadpool;
+//Used to save and load scenes
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate ron;
+//Reads and writes the glTF json where the gltf crate can't help
+#[macro_use]
+extern crate serde_json;
 
 
 ///The engine core defines most functions and
@@ -34,6 +42,8 @@ use render::renderer::BuildRender;
 ///A collection of helpfull tools for integration of data with the engine
 pub mod tools;
 use tools::engine_state_machine::NextStep;
+///A simple rigid body simulation which moves the nodes of the active scene.
+pub mod physics;
 ///A small thread who will run and administrate the winit window, as well as its input
 ///processing
 pub mod input;
@@ -74,10 +84,19 @@ pub enum EngineStatus {
 ///It is responsible for handling all sub systems of the engine as well as providing an API to
 /// the user which can be used to manipulate data
 pub struct JakarEngine {
-    ///The renderer
-    pub renderer: Arc<Mutex<render::renderer::Renderer>>,
+    ///The renderer, is `None` if the engine runs headless
+    pub renderer: Option<Arc<Mutex<render::renderer::Renderer>>>,
     pub asset_manager: Arc<Mutex<core::resource_management::asset_manager::AssetManager>>,
-    pub input_system: Arc<Mutex<input::Input>>,
+    ///Simulates all rigid bodies in the active scene
+    pub physics: Arc<Mutex<physics::PhysicsSystem>>,
+    ///The input system, is `None` if the engine runs headless
+    pub input_system: Option<Arc<Mutex<input::Input>>>,
+    ///The key map which is used by the input system, or a static one if the engine runs headless
+    key_map: Arc<Mutex<input::keymap::KeyMap>>,
+    ///Changes the active scene without locking the asset manager
+    command_queue: core::resource_management::command_queue::CommandQueue,
+    ///Publishes input, window, asset and renderer events
+    event_bus: core::event_bus::EventBus,
 
     pub engine_settings: Arc<Mutex<core::engine_settings::EngineSettings>>,
     pub engine_status: Arc<Mutex<EngineStatus>>,
@@ -103,13 +122,15 @@ impl JakarEngine {
     ///     - Mesh manager
     ///     - Scene manager
     ///     - Texture manager
+    /// - Physics system
     /// - Input system
+    ///
+    /// If the settings are in headless mode, only the asset manager is created.
     pub fn build(settings: Option<core::engine_settings::EngineSettings>) -> Result<Self, CreationErrors>{
         //first create the thread save engine settings and the engine status.
         //they are needed to start the input, asset and rendering thread.
         //Thoose will return their main features which will be an Arc<Mutex<T>> of the
-        //rendering struct, the asset manager and the input handler. Later there will be an physics
-        //handler as well.
+        //rendering struct, the asset manager, the physics system and the input handler.
         let engine_settings = {
             match settings{
                 Some(s_settings) => Arc::new(Mutex::new(s_settings)),
@@ -126,6 +147,15 @@ impl JakarEngine {
         };
         let engine_status = Arc::new(Mutex::new(EngineStatus::STARTING));
 
+        let is_headless = {
+            let settings_lck = engine_settings.lock().expect("failed to lock engine settings");
+            settings_lck.is_headless()
+        };
+
+        if is_headless{
+            return JakarEngine::build_headless(engine_settings, engine_status);
+        }
+
         //Now, first of all start the rendering builder
         let mut render_builder = render::render_builder::RenderBuilder::new(engine_settings.clone());
         //Configure======================================
@@ -156,6 +186,9 @@ impl JakarEngine {
             Err(error) => return Err(CreationErrors::FailedToCreateRenderer(error)),
         }
 
+        //Shared by the input system, the asset manager and the renderer
+        let event_bus = core::event_bus::EventBus::new();
+
         //We are now read to start the input system.
         //if will create the system itself as well as an input handler thread, which will poll
         // events in a continues speed.
@@ -163,6 +196,7 @@ impl JakarEngine {
             let result  = input::Input::new(
                 engine_settings.clone(),
                 render_builder.get_instance(),
+                event_bus.clone(),
             );
 
             match result{
@@ -178,6 +212,10 @@ impl JakarEngine {
                 Err(er) => return Err(CreationErrors::FailedToCreateRenderer(er)),
             }
         };
+        {
+            let mut ren_inst = renderer.lock().expect("failed to lock renderer");
+            ren_inst.set_event_bus(event_bus.clone());
+        }
 
 
         //Now clone the needed resources and create the asset manager last
@@ -204,6 +242,11 @@ impl JakarEngine {
             inp_sys.get_key_map()
         };
 
+        //The asset manager imports on the thread pool, so create it first
+        let thread_pool = Arc::new(Mutex::new(
+            jakar_threadpool::ThreadPool::new_hardware_optimal("Jakar_Engine".to_string())
+        ));
+
         let asset_manager = Arc::new(
             Mutex::new(
                 core::resource_management::asset_manager::AssetManager::new(
@@ -212,20 +255,84 @@ impl JakarEngine {
                     asset_t_queue,
                     asset_t_uniform_manager,
                     asset_t_settings,
-                    asset_t_keymap
+                    asset_t_keymap,
+                    thread_pool.clone(),
+                    event_bus.clone(),
                 )
             )
         );
 
+        let physics = Arc::new(Mutex::new(physics::PhysicsSystem::new(engine_settings.clone())));
+
+        let command_queue = {
+            let asset_lck = asset_manager.lock().expect("failed to lock asset manager");
+            asset_lck.get_command_queue()
+        };
+
+        let key_map = {
+            let inp_sys = input_system.lock().expect("failed to lock input system");
+            inp_sys.get_key_map()
+        };
+
+        //now create the engine struct and retun it to the instigator
+        Ok(JakarEngine{
+            renderer: Some(renderer),
+            asset_manager: asset_manager,
+            physics: physics,
+            input_system: Some(input_system),
+            key_map: key_map,
+            command_queue: command_queue,
+            event_bus: event_bus,
+
+            engine_settings: engine_settings,
+            engine_status: engine_status,
+            main_loop_thread: None,
+            thread_pool: thread_pool,
+        })
+    }
+
+    ///Builds the engine without a vulkan instance, window, input thread and renderer. Only the
+    /// asset manager is created, which keeps all meshes on the cpu.
+    fn build_headless(
+        engine_settings: Arc<Mutex<core::engine_settings::EngineSettings>>,
+        engine_status: Arc<Mutex<EngineStatus>>,
+    ) -> Result<Self, CreationErrors>{
+        //Nobody writes to this key map, but gameplay code and controllers still need one
+        let key_map = Arc::new(Mutex::new(input::keymap::KeyMap::new()));
+
         let thread_pool = Arc::new(Mutex::new(
             jakar_threadpool::ThreadPool::new_hardware_optimal("Jakar_Engine".to_string())
         ));
 
-        //now create the engine struct and retun it to the instigator
+        //Only asset and scene events are published without a window
+        let event_bus = core::event_bus::EventBus::new();
+
+        let asset_manager = Arc::new(
+            Mutex::new(
+                core::resource_management::asset_manager::AssetManager::new_headless(
+                    engine_settings.clone(),
+                    key_map.clone(),
+                    thread_pool.clone(),
+                    event_bus.clone(),
+                )
+            )
+        );
+
+        let physics = Arc::new(Mutex::new(physics::PhysicsSystem::new(engine_settings.clone())));
+
+        let command_queue = {
+            let asset_lck = asset_manager.lock().expect("failed to lock asset manager");
+            asset_lck.get_command_queue()
+        };
+
         Ok(JakarEngine{
-            renderer: renderer,
+            renderer: None,
             asset_manager: asset_manager,
-            input_system: input_system,
+            physics: physics,
+            input_system: None,
+            key_map: key_map,
+            command_queue: command_queue,
+            event_bus: event_bus,
 
             engine_settings: engine_settings,
             engine_status: engine_status,
@@ -238,9 +345,13 @@ impl JakarEngine {
     /// or the struct is droped.
     pub fn start(&mut self){
         let renderer_ref = self.renderer.clone();
-        let render_state = {
-            let render_lck = renderer_ref.lock().expect("failed to lock renderer");
-            render_lck.get_render_state()
+        //Without a renderer the render state stays idle forever
+        let render_state = match renderer_ref{
+            Some(ref renderer) => {
+                let render_lck = renderer.lock().expect("failed to lock renderer");
+                render_lck.get_render_state()
+            },
+            None => Arc::new(Mutex::new(tools::engine_state_machine::RenderState::Idle)),
         };
 
         let asset_manager_ref = self.asset_manager.clone();
@@ -250,6 +361,12 @@ impl JakarEngine {
         };
 
 
+        let physics_ref = self.physics.clone();
+        let physics_state = {
+            let physics_lck = physics_ref.lock().expect("failed to lock physics");
+            physics_lck.get_physics_state()
+        };
+
         let engine_state_ref = self.engine_status.clone();
         let thread_pool_ref = self.thread_pool.clone();
 
@@ -258,11 +375,13 @@ impl JakarEngine {
             let renderer = renderer_ref;
             let engine_state = engine_state_ref;
             let asset_manager = asset_manager_ref;
+            let physics = physics_ref;
             let thread_pool = thread_pool_ref;
 
             let mut state_machine = tools::engine_state_machine::EngineStateMachine::new(
                 render_state,
                 asset_state,
+                physics_state,
             );
 
             'main_loop: loop{
@@ -286,9 +405,13 @@ impl JakarEngine {
                 match next_step{
                     NextStep::Render => {
                         //println!("Rendering!", );
+                        //in headless mode there is nothing to render
+                        let render_loc = match renderer{
+                            Some(ref ren) => ren.clone(),
+                            None => continue,
+                        };
                         let mut thread_pool_lck = thread_pool.lock().expect("failed to lock thread pool");
                         let asset_man_loc = asset_manager.clone();
-                        let render_loc = renderer.clone();
                         //DEBUG Set render_state
                         state_machine.render_on_cpu();
                         thread_pool_lck.execute(move ||{
@@ -317,7 +440,16 @@ impl JakarEngine {
                     },
                     NextStep::UpdatePhysics => {
                         //println!("Doing Physics", );
-                        //TODO IMPLEMENT PHYSICS
+                        let mut thread_pool_lck = thread_pool.lock().expect("failed to lock thread pool");
+                        let asset_man_loc = asset_manager.clone();
+                        let physics_loc = physics.clone();
+                        state_machine.physics_working();
+                        thread_pool_lck.execute(move||{
+                            let mut physics_lck = physics_loc.lock().expect("failed to lock physics");
+                            let mut asset_manager_lck = asset_man_loc
+                            .lock().expect("failed to lock asset manager");
+                            physics_lck.update(&mut asset_manager_lck);
+                        });
                     },
                     NextStep::Nothing(_) => {
                         //println!("EmptyCycle! {:?}", remaining);
@@ -389,33 +521,57 @@ impl JakarEngine {
     /// ```
     ///
     /// This value can be of cause something `Arc<Mutex<T>>` for instance the engine_settings ;)
+    ///
+    /// To only spawn, despawn or change nodes, use `get_command_queue()` which never blocks.
     pub fn get_asset_manager<'a>(&'a mut self) -> MutexGuard<'a, core::resource_management::asset_manager::AssetManager>{
         let asset_lock = self.asset_manager.lock().expect("failed to lock asset manager");
         asset_lock
     }
 
-    ///Returns the renderer, for usage have a look at `get_asset_manager()`
-    pub fn get_renderer<'a>(&'a mut self) -> MutexGuard<'a, render::renderer::Renderer>{
-        let render_lock = self.renderer.lock().expect("failed to lock asset manager");
-        render_lock
+    ///Returns a queue which changes the active scene on the next asset update. It can be cloned
+    /// and used from any thread without locking the asset manager. Every command returns a handle
+    /// which reports when it was applied, for instance the name of a spawned node.
+    pub fn get_command_queue(&self) -> core::resource_management::command_queue::CommandQueue{
+        self.command_queue.clone()
+    }
+
+    ///Returns the event bus. Use `subscribe()` to get a callback for an event type, for instance
+    /// `core::event_bus::KeyEvent`, or `reader()` to poll the events once per frame.
+    pub fn get_event_bus(&self) -> core::event_bus::EventBus{
+        self.event_bus.clone()
+    }
+
+    ///Returns the physics system, for usage have a look at `get_asset_manager()`.
+    pub fn get_physics<'a>(&'a mut self) -> MutexGuard<'a, physics::PhysicsSystem>{
+        self.physics.lock().expect("failed to lock physics")
+    }
+
+    ///Returns the renderer, for usage have a look at `get_asset_manager()`. Returns `None` if the
+    /// engine runs headless.
+    pub fn get_renderer<'a>(&'a mut self) -> Option<MutexGuard<'a, render::renderer::Renderer>>{
+        match self.renderer{
+            Some(ref renderer) => Some(renderer.lock().expect("failed to lock renderer")),
+            None => None,
+        }
     }
 
     ///Returns the input handler, for usage have a look at `get_asset_manager()`
     pub fn get_current_keymap(&self) -> input::keymap::KeyMap{
         let map = {
-            let inp_sys = self.input_system.lock().expect("failed to lock input system");
-            inp_sys.get_key_map_copy()
+            let map_lck = self.key_map.lock().expect("failed to lock key map");
+            (*map_lck).clone()
         };
         map
     }
 
     ///Returns the key map with its Mutex guard.
     pub fn get_key_map(&self) -> Arc<Mutex<input::keymap::KeyMap>>{
-        let map = {
-            let inp_sys = self.input_system.lock().expect("failed to lock input system");
-            inp_sys.get_key_map()
-        };
-        map
+        self.key_map.clone()
+    }
+
+    ///Returns true if the engine was build without renderer and input system
+    pub fn is_headless(&self) -> bool{
+        self.renderer.is_none()
     }
 
     ///Returns the unlocked settings for easy changing. However the engine won't do anything as long as the
@@ -0,0 +1,643 @@
+use cgmath::*;
+use collision::*;
+
+use core::resources::mesh::Mesh;
+use core::next_tree::get_min_max;
+use core::next_tree::transform::NodeTransform;
+use core::PointToVector;
+
+use std::f32;
+
+///How many iterations GJK and EPA do at most, curved shapes like spheres would never finish.
+const MAX_ITERATIONS: usize = 64;
+///EPA stops once the polytope can't be expanded by more than this.
+const EPA_TOLERANCE: f32 = 1.0e-4;
+
+///The shape of a rigid body in the object space of its node.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Collider {
+    ///An axis aligned box, defined by its center and half extend. If the node is rotated the
+    /// box is fitted around the rotated corners.
+    Aabb{
+        center: Vector3<f32>,
+        half_extend: Vector3<f32>,
+    },
+    ///A sphere around `center`.
+    Sphere{
+        center: Vector3<f32>,
+        radius: f32,
+    },
+    ///A convex hull around the points. The points don't have to be the actual hull, since only
+    /// their support function is used.
+    ConvexHull{
+        points: Vec<Vector3<f32>>,
+    },
+}
+
+impl Collider{
+    ///Creates a box collider which fits the object space `bound`. Usually this is the bound of
+    /// the nodes content, which is the one the `value_bound` of a node is build from.
+    pub fn aabb_from_bound(bound: &Aabb3<f32>) -> Self{
+        let center = bound.center().into_vec();
+        let half_extend = (bound.max - bound.min) / 2.0;
+        Collider::Aabb{
+            center: center,
+            half_extend: half_extend,
+        }
+    }
+
+    ///Creates a sphere collider which encloses the object space `bound`.
+    pub fn sphere_from_bound(bound: &Aabb3<f32>) -> Self{
+        let center = bound.center().into_vec();
+        let radius = ((bound.max - bound.min) / 2.0).magnitude();
+        Collider::Sphere{
+            center: center,
+            radius: radius,
+        }
+    }
+
+    ///Creates a convex hull collider from the vertex positions of `mesh`.
+    pub fn convex_hull_from_mesh(mesh: &Mesh) -> Self{
+        let points = mesh.get_all_positions()
+        .into_iter()
+        .map(|p| Vector3::from(p))
+        .collect();
+
+        Collider::ConvexHull{
+            points: points,
+        }
+    }
+
+    ///Transforms the collider into world space via the nodes `transform`.
+    pub fn to_world(&self, transform: &NodeTransform) -> WorldShape{
+        match self{
+            &Collider::Aabb{center, half_extend} => {
+                let mut corners = Vec::new();
+                for x in [-1.0, 1.0].iter(){
+                    for y in [-1.0, 1.0].iter(){
+                        for z in [-1.0, 1.0].iter(){
+                            let corner = center + Vector3::new(
+                                half_extend.x * x, half_extend.y * y, half_extend.z * z
+                            );
+                            corners.push(transform.transform_point(Point3::from_vec(corner)));
+                        }
+                    }
+                }
+                let (min, max) = get_min_max(corners);
+                WorldShape::Aabb(Aabb3::new(min, max))
+            },
+            &Collider::Sphere{center, radius} => {
+                WorldShape::Sphere{
+                    center: transform.transform_point(Point3::from_vec(center)),
+                    //a non-uniform scale turns the sphere into an ellipsoid, use the largest axis
+                    radius: radius * transform.get_max_scale(),
+                }
+            },
+            &Collider::ConvexHull{ref points} => {
+                WorldShape::ConvexHull(
+                    points.iter().map(|p| transform.transform_point(Point3::from_vec(*p))).collect()
+                )
+            },
+        }
+    }
+}
+
+///A collider in world space, this is what the collision tests work on.
+#[derive(Clone, Debug)]
+pub enum WorldShape {
+    Aabb(Aabb3<f32>),
+    Sphere{
+        center: Point3<f32>,
+        radius: f32,
+    },
+    ConvexHull(Vec<Point3<f32>>),
+}
+
+impl WorldShape{
+    ///Returns the world space bound of this shape, used for the broad phase.
+    pub fn get_bound(&self) -> Aabb3<f32>{
+        match self{
+            &WorldShape::Aabb(bound) => bound,
+            &WorldShape::Sphere{center, radius} => {
+                let extend = Vector3::new(radius, radius, radius);
+                Aabb3::new(center - extend, center + extend)
+            },
+            &WorldShape::ConvexHull(ref points) => {
+                if points.len() == 0{
+                    return Aabb3::new(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, 0.0));
+                }
+                let (min, max) = get_min_max(points.clone());
+                Aabb3::new(min, max)
+            },
+        }
+    }
+
+    ///Returns true if the shape has no volume to collide with, which is the case for a convex
+    /// hull without points.
+    pub fn is_empty(&self) -> bool{
+        match self{
+            &WorldShape::ConvexHull(ref points) => points.is_empty(),
+            _ => false,
+        }
+    }
+
+    ///Returns the center of this shape
+    pub fn get_center(&self) -> Point3<f32>{
+        match self{
+            &WorldShape::Sphere{center, ..} => center,
+            _ => self.get_bound().center(),
+        }
+    }
+
+    ///Returns the point of this shape which is the furthest along `direction`.
+    pub fn support(&self, direction: Vector3<f32>) -> Point3<f32>{
+        match self{
+            &WorldShape::Aabb(bound) => {
+                Point3::new(
+                    if direction.x >= 0.0 { bound.max.x } else { bound.min.x },
+                    if direction.y >= 0.0 { bound.max.y } else { bound.min.y },
+                    if direction.z >= 0.0 { bound.max.z } else { bound.min.z },
+                )
+            },
+            &WorldShape::Sphere{center, radius} => {
+                if direction.magnitude2() == 0.0{
+                    return center;
+                }
+                center + direction.normalize() * radius
+            },
+            &WorldShape::ConvexHull(ref points) => {
+                let mut best = Point3::new(0.0, 0.0, 0.0);
+                let mut best_dist = f32::MIN;
+                for point in points.iter(){
+                    let dist = point.into_vec().dot(direction);
+                    if dist > best_dist{
+                        best_dist = dist;
+                        best = *point;
+                    }
+                }
+                best
+            },
+        }
+    }
+}
+
+///Describes how two shapes intersect.
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    ///The direction in which the second shape has to be moved to separate the two.
+    pub normal: Vector3<f32>,
+    ///How deep the shapes are inside each other along the normal.
+    pub depth: f32,
+}
+
+///Tests `a` against `b` and returns the contact if they intersect. The normal of the contact
+/// always points from `a` to `b`. Shapes which only touch don't intersect.
+pub fn collide(a: &WorldShape, b: &WorldShape) -> Option<Contact>{
+    if a.is_empty() || b.is_empty(){
+        return None;
+    }
+
+    match (a, b){
+        (&WorldShape::Sphere{center: ca, radius: ra}, &WorldShape::Sphere{center: cb, radius: rb}) => {
+            let delta = cb - ca;
+            let dist = delta.magnitude();
+            if dist >= ra + rb{
+                return None;
+            }
+            let normal = if dist > 0.0 { delta / dist } else { Vector3::new(0.0, 1.0, 0.0) };
+            Some(Contact{
+                normal: normal,
+                depth: ra + rb - dist,
+            })
+        },
+        (&WorldShape::Aabb(ba), &WorldShape::Aabb(bb)) => {
+            aabb_aabb(&ba, &bb)
+        },
+        (&WorldShape::Sphere{center, radius}, &WorldShape::Aabb(bound)) => {
+            //the box normal points from the box to the sphere, so flip it
+            sphere_aabb(center, radius, &bound).map(|c| Contact{normal: -c.normal, depth: c.depth})
+        },
+        (&WorldShape::Aabb(bound), &WorldShape::Sphere{center, radius}) => {
+            sphere_aabb(center, radius, &bound)
+        },
+        _ => {
+            gjk_epa(a, b)
+        },
+    }
+}
+
+fn aabb_aabb(a: &Aabb3<f32>, b: &Aabb3<f32>) -> Option<Contact>{
+    let delta = b.center() - a.center();
+    let overlap = Vector3::new(
+        (a.max.x.min(b.max.x)) - (a.min.x.max(b.min.x)),
+        (a.max.y.min(b.max.y)) - (a.min.y.max(b.min.y)),
+        (a.max.z.min(b.max.z)) - (a.min.z.max(b.min.z)),
+    );
+
+    if overlap.x <= 0.0 || overlap.y <= 0.0 || overlap.z <= 0.0{
+        return None;
+    }
+
+    //Resolve along the axis with the smallest overlap
+    let sign = |v: f32| if v < 0.0 { -1.0 } else { 1.0 };
+    if overlap.x <= overlap.y && overlap.x <= overlap.z{
+        Some(Contact{ normal: Vector3::new(sign(delta.x), 0.0, 0.0), depth: overlap.x })
+    }else if overlap.y <= overlap.z{
+        Some(Contact{ normal: Vector3::new(0.0, sign(delta.y), 0.0), depth: overlap.y })
+    }else{
+        Some(Contact{ normal: Vector3::new(0.0, 0.0, sign(delta.z)), depth: overlap.z })
+    }
+}
+
+///Returns the contact with the normal pointing from the box to the sphere.
+fn sphere_aabb(center: Point3<f32>, radius: f32, bound: &Aabb3<f32>) -> Option<Contact>{
+    let closest = Point3::new(
+        center.x.max(bound.min.x).min(bound.max.x),
+        center.y.max(bound.min.y).min(bound.max.y),
+        center.z.max(bound.min.z).min(bound.max.z),
+    );
+
+    let delta = center - closest;
+    let dist = delta.magnitude();
+
+    if dist > 0.0{
+        if dist >= radius{
+            return None;
+        }
+        return Some(Contact{
+            normal: delta / dist,
+            depth: radius - dist,
+        });
+    }
+
+    //The center is inside the box, push the sphere out over the closest face
+    let sphere_bound = Aabb3::new(
+        center - Vector3::new(radius, radius, radius),
+        center + Vector3::new(radius, radius, radius)
+    );
+    aabb_aabb(bound, &sphere_bound)
+}
+
+///Returns the point of the minkowski difference `a - b` which is the furthest along `direction`.
+fn minkowski_support(a: &WorldShape, b: &WorldShape, direction: Vector3<f32>) -> Vector3<f32>{
+    a.support(direction).into_vec() - b.support(-direction).into_vec()
+}
+
+///Returns true if `a` points into the same half space as `b`.
+fn same_direction(a: Vector3<f32>, b: Vector3<f32>) -> bool{
+    a.dot(b) > 0.0
+}
+
+///Tests the shapes with GJK and computes the contact with EPA, both only use the support
+/// functions. This is exact for any pair of convex shapes, but touching shapes don't collide.
+fn gjk_epa(a: &WorldShape, b: &WorldShape) -> Option<Contact>{
+    let simplex = gjk(a, b)?;
+    Some(epa(a, b, simplex))
+}
+
+///Returns a tetrahedron of the minkowski difference `a - b` which contains the origin, or `None`
+/// if the shapes are separated. The newest point of a simplex is always the first one.
+fn gjk(a: &WorldShape, b: &WorldShape) -> Option<Vec<Vector3<f32>>>{
+    let mut direction = b.get_center() - a.get_center();
+    if direction.magnitude2() == 0.0{
+        direction = Vector3::new(1.0, 0.0, 0.0);
+    }
+
+    let first = minkowski_support(a, b, direction);
+    let mut simplex = vec![first];
+    direction = -first;
+
+    for _ in 0..MAX_ITERATIONS{
+        //the simplex is degenerated, there is no direction left to search in
+        if direction.magnitude2() < 1.0e-12{
+            return None;
+        }
+
+        let point = minkowski_support(a, b, direction);
+        if !same_direction(point, direction){
+            return None;
+        }
+
+        simplex.insert(0, point);
+        if next_simplex(&mut simplex, &mut direction){
+            return Some(simplex);
+        }
+    }
+    None
+}
+
+///Reduces `simplex` to the feature closest to the origin and sets `direction` towards the
+/// origin from there. Returns true if the simplex is a tetrahedron containing the origin.
+fn next_simplex(simplex: &mut Vec<Vector3<f32>>, direction: &mut Vector3<f32>) -> bool{
+    match simplex.len(){
+        2 => line_case(simplex, direction),
+        3 => triangle_case(simplex, direction),
+        _ => tetrahedron_case(simplex, direction),
+    }
+}
+
+fn line_case(simplex: &mut Vec<Vector3<f32>>, direction: &mut Vector3<f32>) -> bool{
+    let (a, b) = (simplex[0], simplex[1]);
+    let ab = b - a;
+    let ao = -a;
+    if same_direction(ab, ao){
+        *direction = ab.cross(ao).cross(ab);
+        //the origin is on the line, continue in any direction perpendicular to it
+        if direction.magnitude2() < 1.0e-12{
+            let other = if ab.x.abs() < 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 1.0, 0.0) };
+            *direction = ab.cross(other);
+        }
+    }else{
+        *simplex = vec![a];
+        *direction = ao;
+    }
+    false
+}
+
+fn triangle_case(simplex: &mut Vec<Vector3<f32>>, direction: &mut Vector3<f32>) -> bool{
+    let (a, b, c) = (simplex[0], simplex[1], simplex[2]);
+    let ab = b - a;
+    let ac = c - a;
+    let ao = -a;
+    let abc = ab.cross(ac);
+
+    if same_direction(abc.cross(ac), ao){
+        if same_direction(ac, ao){
+            *simplex = vec![a, c];
+            *direction = ac.cross(ao).cross(ac);
+            return false;
+        }
+        *simplex = vec![a, b];
+        return line_case(simplex, direction);
+    }
+
+    if same_direction(ab.cross(abc), ao){
+        *simplex = vec![a, b];
+        return line_case(simplex, direction);
+    }
+
+    if same_direction(abc, ao){
+        *direction = abc;
+    }else{
+        *simplex = vec![a, c, b];
+        *direction = -abc;
+    }
+    false
+}
+
+fn tetrahedron_case(simplex: &mut Vec<Vector3<f32>>, direction: &mut Vector3<f32>) -> bool{
+    let (a, b, c, d) = (simplex[0], simplex[1], simplex[2], simplex[3]);
+    let ab = b - a;
+    let ac = c - a;
+    let ad = d - a;
+    let ao = -a;
+
+    if same_direction(ab.cross(ac), ao){
+        *simplex = vec![a, b, c];
+        return triangle_case(simplex, direction);
+    }
+    if same_direction(ac.cross(ad), ao){
+        *simplex = vec![a, c, d];
+        return triangle_case(simplex, direction);
+    }
+    if same_direction(ad.cross(ab), ao){
+        *simplex = vec![a, d, b];
+        return triangle_case(simplex, direction);
+    }
+    true
+}
+
+///A triangle of the EPA polytope with its outward normal and distance to the origin
+struct PolytopeFace {
+    indices: [usize; 3],
+    normal: Vector3<f32>,
+    distance: f32,
+}
+
+///Creates the face of the points `indices` in `polytope`, the normal points away from the
+/// origin. Returns `None` for degenerated faces.
+fn polytope_face(polytope: &[Vector3<f32>], indices: [usize; 3]) -> Option<PolytopeFace>{
+    let a = polytope[indices[0]];
+    let normal = (polytope[indices[1]] - a).cross(polytope[indices[2]] - a);
+    if normal.magnitude2() < 1.0e-12{
+        return None;
+    }
+    let mut normal = normal.normalize();
+    let mut distance = normal.dot(a);
+    let mut indices = indices;
+    if distance < 0.0{
+        normal = -normal;
+        distance = -distance;
+        indices.swap(1, 2);
+    }
+    Some(PolytopeFace{
+        indices: indices,
+        normal: normal,
+        distance: distance,
+    })
+}
+
+///Expands the `simplex` of GJK over the minkowski difference `a - b` till the face closest to
+/// the origin is found. Its normal is the direction in which `b` has to be moved and its distance
+/// the depth.
+fn epa(a: &WorldShape, b: &WorldShape, simplex: Vec<Vector3<f32>>) -> Contact{
+    let mut polytope = simplex;
+    let mut faces: Vec<PolytopeFace> = [[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]].iter()
+    .filter_map(|indices| polytope_face(&polytope, *indices))
+    .collect();
+
+    let mut best = Contact{
+        normal: Vector3::new(0.0, 1.0, 0.0),
+        depth: 0.0,
+    };
+
+    for _ in 0..MAX_ITERATIONS{
+        let closest = match faces.iter().enumerate()
+        .min_by(|x, y| x.1.distance.partial_cmp(&y.1.distance).unwrap_or(::std::cmp::Ordering::Equal))
+        {
+            Some((index, _)) => index,
+            //a flat simplex, use the last result
+            None => break,
+        };
+
+        best = Contact{
+            normal: faces[closest].normal,
+            depth: faces[closest].distance,
+        };
+
+        let support = minkowski_support(a, b, best.normal);
+        if support.dot(best.normal) - best.depth < EPA_TOLERANCE{
+            break;
+        }
+
+        //remove all faces which can see the new point and remember their border
+        let mut border: Vec<(usize, usize)> = Vec::new();
+        let mut index = 0;
+        while index < faces.len(){
+            if same_direction(faces[index].normal, support - polytope[faces[index].indices[0]]){
+                let face = faces.swap_remove(index);
+                for edge in 0..3{
+                    let edge = (face.indices[edge], face.indices[(edge + 1) % 3]);
+                    //an edge shared by two removed faces is not on the border
+                    match border.iter().position(|other| *other == (edge.1, edge.0)){
+                        Some(shared) => { border.swap_remove(shared); },
+                        None => border.push(edge),
+                    }
+                }
+            }else{
+                index += 1;
+            }
+        }
+
+        polytope.push(support);
+        let new_index = polytope.len() - 1;
+        for &(from, to) in border.iter(){
+            if let Some(face) = polytope_face(&polytope, [from, to, new_index]){
+                faces.push(face);
+            }
+        }
+    }
+
+    best
+}
+
+///Returns true if the two bounds overlap or touch.
+pub fn bounds_overlap(a: &Aabb3<f32>, b: &Aabb3<f32>) -> bool{
+    a.min.x <= b.max.x && a.max.x >= b.min.x &&
+    a.min.y <= b.max.y && a.max.y >= b.min.y &&
+    a.min.z <= b.max.z && a.max.z >= b.min.z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box(x: f32, y: f32, z: f32) -> WorldShape{
+        WorldShape::Aabb(Aabb3::new(Point3::new(x - 0.5, y - 0.5, z - 0.5), Point3::new(x + 0.5, y + 0.5, z + 0.5)))
+    }
+
+    fn sphere(x: f32, y: f32, z: f32, radius: f32) -> WorldShape{
+        WorldShape::Sphere{
+            center: Point3::new(x, y, z),
+            radius: radius,
+        }
+    }
+
+    ///A unit cube as convex hull, rotated around z by `angle`
+    fn cube_hull(center: Vector3<f32>, angle: f32) -> WorldShape{
+        let rotation = Quaternion::from_angle_z(Deg(angle));
+        let mut points = Vec::new();
+        for x in [-0.5, 0.5].iter(){
+            for y in [-0.5, 0.5].iter(){
+                for z in [-0.5, 0.5].iter(){
+                    points.push(Point3::from_vec(center + rotation.rotate_vector(Vector3::new(*x, *y, *z))));
+                }
+            }
+        }
+        WorldShape::ConvexHull(points)
+    }
+
+    fn assert_contact(contact: Option<Contact>, normal: Vector3<f32>, depth: f32){
+        let contact = contact.expect("the shapes don't collide");
+        assert!((contact.normal - normal).magnitude() < 1.0e-3, "{:?}", contact);
+        assert!((contact.depth - depth).abs() < 1.0e-3, "{:?}", contact);
+    }
+
+    #[test]
+    fn sphere_sphere(){
+        assert!(collide(&sphere(0.0, 0.0, 0.0, 1.0), &sphere(3.0, 0.0, 0.0, 1.0)).is_none());
+        assert!(collide(&sphere(0.0, 0.0, 0.0, 1.0), &sphere(2.0, 0.0, 0.0, 1.0)).is_none());
+        assert_contact(
+            collide(&sphere(0.0, 0.0, 0.0, 1.0), &sphere(0.0, 1.5, 0.0, 1.0)),
+            Vector3::new(0.0, 1.0, 0.0), 0.5
+        );
+    }
+
+    #[test]
+    fn aabb_aabb(){
+        assert!(collide(&unit_box(0.0, 0.0, 0.0), &unit_box(2.0, 0.0, 0.0)).is_none());
+        assert!(collide(&unit_box(0.0, 0.0, 0.0), &unit_box(1.0, 0.0, 0.0)).is_none());
+        assert_contact(
+            collide(&unit_box(0.0, 0.0, 0.0), &unit_box(0.75, 0.1, 0.0)),
+            Vector3::new(1.0, 0.0, 0.0), 0.25
+        );
+        assert_contact(
+            collide(&unit_box(0.0, 0.0, 0.0), &unit_box(0.0, 0.0, -0.9)),
+            Vector3::new(0.0, 0.0, -1.0), 0.1
+        );
+    }
+
+    #[test]
+    fn sphere_aabb(){
+        assert!(collide(&sphere(2.0, 0.0, 0.0, 0.5), &unit_box(0.0, 0.0, 0.0)).is_none());
+        assert!(collide(&sphere(1.0, 0.0, 0.0, 0.5), &unit_box(0.0, 0.0, 0.0)).is_none());
+        //the normal always points from the first to the second shape
+        assert_contact(
+            collide(&unit_box(0.0, 0.0, 0.0), &sphere(0.75, 0.0, 0.0, 0.5)),
+            Vector3::new(1.0, 0.0, 0.0), 0.25
+        );
+        assert_contact(
+            collide(&sphere(0.75, 0.0, 0.0, 0.5), &unit_box(0.0, 0.0, 0.0)),
+            Vector3::new(-1.0, 0.0, 0.0), 0.25
+        );
+        //a center inside the box is pushed out over the closest face
+        assert_contact(
+            collide(&unit_box(0.0, 0.0, 0.0), &sphere(0.0, 0.4, 0.0, 0.5)),
+            Vector3::new(0.0, 1.0, 0.0), 0.6
+        );
+    }
+
+    #[test]
+    fn hull_hull(){
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        assert!(collide(&cube_hull(origin, 0.0), &cube_hull(Vector3::new(2.0, 0.0, 0.0), 0.0)).is_none());
+        assert!(collide(&cube_hull(origin, 0.0), &cube_hull(Vector3::new(1.0, 0.0, 0.0), 0.0)).is_none());
+        assert_contact(
+            collide(&cube_hull(origin, 0.0), &cube_hull(Vector3::new(0.75, 0.0, 0.0), 0.0)),
+            Vector3::new(1.0, 0.0, 0.0), 0.25
+        );
+        assert_contact(
+            collide(&cube_hull(origin, 0.0), &cube_hull(Vector3::new(-0.75, 0.1, 0.0), 0.0)),
+            Vector3::new(-1.0, 0.0, 0.0), 0.25
+        );
+    }
+
+    #[test]
+    fn rotated_hulls_use_their_face_normals(){
+        //both cubes are rotated by 30 degree and offset along their own x and y axis, only the
+        // rotated x axis separates them
+        let rotation = Quaternion::from_angle_z(Deg(30.0));
+        let axis_x = rotation.rotate_vector(Vector3::new(1.0, 0.0, 0.0));
+        let axis_y = rotation.rotate_vector(Vector3::new(0.0, 1.0, 0.0));
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+
+        let separated = cube_hull(axis_x * 1.05 + axis_y * 0.3, 30.0);
+        assert!(collide(&cube_hull(origin, 30.0), &separated).is_none());
+
+        let overlapping = cube_hull(axis_x * 0.9 + axis_y * 0.3, 30.0);
+        assert_contact(collide(&cube_hull(origin, 30.0), &overlapping), axis_x, 0.1);
+    }
+
+    #[test]
+    fn hull_against_box_and_sphere(){
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        assert!(collide(&cube_hull(origin, 0.0), &unit_box(1.5, 0.0, 0.0)).is_none());
+        assert_contact(
+            collide(&unit_box(0.0, 0.8, 0.0), &cube_hull(origin, 0.0)),
+            Vector3::new(0.0, -1.0, 0.0), 0.2
+        );
+        assert!(collide(&cube_hull(origin, 0.0), &sphere(1.1, 0.0, 0.0, 0.5)).is_none());
+        assert_contact(
+            collide(&cube_hull(origin, 0.0), &sphere(0.75, 0.0, 0.0, 0.5)),
+            Vector3::new(1.0, 0.0, 0.0), 0.25
+        );
+    }
+
+    #[test]
+    fn empty_hull_never_collides(){
+        let empty = WorldShape::ConvexHull(Vec::new());
+        assert!(collide(&empty, &unit_box(0.0, 0.0, 0.0)).is_none());
+        assert!(collide(&sphere(0.0, 0.0, 0.0, 1.0), &empty).is_none());
+        assert!(collide(&empty, &empty).is_none());
+    }
+}
@@ -0,0 +1,372 @@
+///Defines the shapes a rigid body can have and the collision tests between them.
+pub mod collider;
+///The physical description of a node, is stored in the nodes attributes.
+pub mod rigid_body;
+
+use cgmath::*;
+use collision::*;
+
+use core::engine_settings::EngineSettings;
+use core::resource_management::asset_manager::AssetManager;
+use core::next_tree::{SceneTree, SceneComparer};
+use core::next_tree::jobs::SceneJobs;
+use core::next_tree::transform::NodeTransform;
+use tools::engine_state_machine::PhysicsUpdateState;
+use tools::math::time_tools::dur_as_f32;
+
+use physics::collider::{Contact, WorldShape, collide, bounds_overlap};
+use physics::rigid_body::RigidBody;
+
+use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+///How much penetration is allowed before bodies are pushed apart. Prevents jittering of resting
+/// bodies.
+const PENETRATION_SLOP: f32 = 0.01;
+///The fraction of the penetration which is corrected each step.
+const CORRECTION_FACTOR: f32 = 0.8;
+
+///Global settings of the physics simulation.
+#[derive(Clone)]
+pub struct PhysicsSettings{
+    ///If false the physics step does nothing.
+    pub enabled: bool,
+    ///The acceleration applied to every body which uses gravity.
+    pub gravity: Vector3<f32>,
+    ///The fixed time step of one simulation step in seconds.
+    pub time_step: f32,
+    ///The max number of steps done per update. If the engine can't keep up, the remaining time
+    /// is dropped to not fall further behind.
+    pub max_steps: u32,
+}
+
+impl PhysicsSettings{
+    ///Creates settings with earth gravity and 60 steps per second.
+    pub fn default() -> Self{
+        PhysicsSettings{
+            enabled: true,
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            time_step: 1.0 / 60.0,
+            max_steps: 5,
+        }
+    }
+
+    ///Sets the gravity
+    pub fn with_gravity(mut self, gravity: Vector3<f32>) -> Self{
+        self.gravity = gravity;
+        self
+    }
+
+    ///Sets how many simulation steps are done per second.
+    pub fn with_steps_per_second(mut self, steps: u32) -> Self{
+        self.time_step = 1.0 / steps as f32;
+        self
+    }
+
+    ///Disables the physics simulation
+    pub fn disabled(mut self) -> Self{
+        self.enabled = false;
+        self
+    }
+}
+
+///A body while it is simulated.
+struct SimulatedBody {
+    name: String,
+    body: RigidBody,
+    transform: NodeTransform,
+    start_location: Vector3<f32>,
+    velocity: Vector3<f32>,
+}
+
+///Simulates every node in the active scene which has a `rigid_body` in its attributes.
+/// The results are written back as `SceneJobs::Move` which get applied on the next asset update.
+///
+/// NOTE: Only the location is simulated, bodies don't rotate. Since jobs are passed down to the
+/// children, a rigid body should not be a child of another rigid body.
+pub struct PhysicsSystem {
+    ///The velocity of each body, by node name
+    velocities: BTreeMap<String, Vector3<f32>>,
+    ///Impulses which are applied at the next step
+    impulses: BTreeMap<String, Vector3<f32>>,
+    ///Time which has not been simulated yet
+    accumulator: f32,
+    last_update: Instant,
+
+    settings: Arc<Mutex<EngineSettings>>,
+    state: Arc<Mutex<PhysicsUpdateState>>,
+}
+
+impl PhysicsSystem{
+    pub fn new(settings: Arc<Mutex<EngineSettings>>) -> Self{
+        PhysicsSystem{
+            velocities: BTreeMap::new(),
+            impulses: BTreeMap::new(),
+            accumulator: 0.0,
+            last_update: Instant::now(),
+
+            settings: settings,
+            state: Arc::new(Mutex::new(PhysicsUpdateState::Idle)),
+        }
+    }
+
+    ///Returns the state of the physics system, used by the engines state machine.
+    pub fn get_physics_state(&self) -> Arc<Mutex<PhysicsUpdateState>>{
+        self.state.clone()
+    }
+
+    ///Returns the current velocity of the body at the node called `name`, or `None` if it has
+    /// not been simulated yet.
+    pub fn get_velocity(&self, name: &str) -> Option<Vector3<f32>>{
+        self.velocities.get(name).cloned()
+    }
+
+    ///Overwrites the velocity of the body at the node called `name`.
+    pub fn set_velocity(&mut self, name: &str, velocity: Vector3<f32>){
+        self.velocities.insert(name.to_string(), velocity);
+    }
+
+    ///Adds an impulse to the body at node `name`, is applied at the next simulation step.
+    pub fn add_impulse(&mut self, name: &str, impulse: Vector3<f32>){
+        let current = self.impulses.get(name).cloned().unwrap_or(Vector3::new(0.0, 0.0, 0.0));
+        self.impulses.insert(name.to_string(), current + impulse);
+    }
+
+    ///Forgets all velocities and impulses, should be called when the active scene changes.
+    pub fn reset(&mut self){
+        self.velocities.clear();
+        self.impulses.clear();
+        self.accumulator = 0.0;
+    }
+
+    ///Does as many fixed steps as time passed since the last update.
+    pub fn update(&mut self, asset_manager: &mut AssetManager){
+        self.set_working();
+
+        let physics_settings = {
+            let set_lck = self.settings.lock().expect("failed to lock engine settings");
+            set_lck.physics.clone()
+        };
+
+        let delta = dur_as_f32(self.last_update.elapsed());
+        self.last_update = Instant::now();
+
+        if !physics_settings.enabled || physics_settings.time_step <= 0.0{
+            self.accumulator = 0.0;
+            self.set_waiting();
+            return;
+        }
+
+        self.accumulator += delta;
+        let mut steps = 0;
+        while self.accumulator >= physics_settings.time_step && steps < physics_settings.max_steps{
+            self.accumulator -= physics_settings.time_step;
+            steps += 1;
+        }
+        //We can't keep up, drop the rest
+        if steps == physics_settings.max_steps{
+            self.accumulator = 0.0;
+        }
+
+        if steps > 0{
+            self.simulate(
+                asset_manager,
+                steps,
+                physics_settings.time_step,
+                physics_settings.gravity
+            );
+        }
+
+        self.set_waiting();
+    }
+
+    fn simulate(
+        &mut self,
+        asset_manager: &mut AssetManager,
+        steps: u32,
+        time_step: f32,
+        gravity: Vector3<f32>
+    ){
+        let scene = asset_manager.get_active_scene();
+        let nodes = scene.copy_all_nodes(&Some(SceneComparer::new().with_rigid_body()));
+
+        let mut bodies = Vec::new();
+        for node in nodes.iter(){
+            let body = match node.get_attrib().rigid_body{
+                Some(ref body) => body.clone(),
+                None => continue,
+            };
+
+            let name = node.get_name().clone();
+            let mut velocity = match self.velocities.get(&name){
+                Some(velocity) => *velocity,
+                None => body.initial_velocity,
+            };
+            if let Some(impulse) = self.impulses.get(&name){
+                velocity = velocity + *impulse * body.get_inverse_mass();
+            }
+
+            let transform = node.get_attrib().transform;
+            bodies.push(SimulatedBody{
+                name: name,
+                body: body,
+                transform: transform,
+                start_location: transform.disp,
+                velocity: velocity,
+            });
+        }
+        self.impulses.clear();
+
+        for _ in 0..steps{
+            step(&mut bodies, time_step, gravity);
+        }
+
+        //Write back the movement, bodies which are not in the scene anymore are forgotten
+        let mut new_velocities = BTreeMap::new();
+        for sim in bodies.into_iter(){
+            let delta = sim.transform.disp - sim.start_location;
+            if !sim.body.is_static() && delta.magnitude2() > 0.0{
+                match scene.get_node(&sim.name){
+                    Some(node) => node.add_job(SceneJobs::Move(delta)),
+                    None => {},
+                }
+            }
+            new_velocities.insert(sim.name, sim.velocity);
+        }
+        self.velocities = new_velocities;
+    }
+
+    fn set_working(&mut self){
+        let mut state_lck = self.state.lock().expect("failed to lock physics state");
+        *state_lck = PhysicsUpdateState::working();
+    }
+
+    fn set_waiting(&mut self){
+        let mut state_lck = self.state.lock().expect("failed to lock physics state");
+        *state_lck = PhysicsUpdateState::wait();
+    }
+}
+
+///Does one fixed step: integrates the velocities and locations of all dynamic `bodies`, then
+/// finds and resolves the collisions between them.
+fn step(bodies: &mut [SimulatedBody], time_step: f32, gravity: Vector3<f32>){
+    //Integrate
+    for sim in bodies.iter_mut(){
+        if sim.body.is_static(){
+            continue;
+        }
+        if sim.body.use_gravity{
+            sim.velocity = sim.velocity + gravity * time_step;
+        }
+        sim.transform.disp = sim.transform.disp + sim.velocity * time_step;
+    }
+
+    //Find and resolve the collisions
+    let shapes: Vec<WorldShape> = bodies.iter()
+    .map(|sim| sim.body.collider.to_world(&sim.transform))
+    .collect();
+    let bounds: Vec<Aabb3<f32>> = shapes.iter().map(|shape| shape.get_bound()).collect();
+
+    for a in 0..bodies.len(){
+        for b in (a + 1)..bodies.len(){
+            if bodies[a].body.is_static() && bodies[b].body.is_static(){
+                continue;
+            }
+            if !bounds_overlap(&bounds[a], &bounds[b]){
+                continue;
+            }
+            if let Some(contact) = collide(&shapes[a], &shapes[b]){
+                let (first, second) = bodies.split_at_mut(b);
+                resolve_contact(&mut first[a], &mut second[0], &contact);
+            }
+        }
+    }
+}
+
+///Changes the velocities of `a` and `b` so they don't move into each other anymore and pushes
+/// them out of each other.
+fn resolve_contact(a: &mut SimulatedBody, b: &mut SimulatedBody, contact: &Contact){
+    let inv_mass_a = a.body.get_inverse_mass();
+    let inv_mass_b = b.body.get_inverse_mass();
+    let inv_mass_sum = inv_mass_a + inv_mass_b;
+    if inv_mass_sum <= 0.0{
+        return;
+    }
+
+    //Only bounce if the bodies are moving towards each other
+    let relative_velocity = b.velocity - a.velocity;
+    let along_normal = relative_velocity.dot(contact.normal);
+    if along_normal < 0.0{
+        let restitution = a.body.restitution.min(b.body.restitution);
+        let impulse = contact.normal * (-(1.0 + restitution) * along_normal / inv_mass_sum);
+        a.velocity = a.velocity - impulse * inv_mass_a;
+        b.velocity = b.velocity + impulse * inv_mass_b;
+    }
+
+    let correction = contact.normal
+    * ((contact.depth - PENETRATION_SLOP).max(0.0) / inv_mass_sum * CORRECTION_FACTOR);
+    a.transform.disp = a.transform.disp - correction * inv_mass_a;
+    b.transform.disp = b.transform.disp + correction * inv_mass_b;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use physics::collider::Collider;
+
+    fn unit_box() -> Collider{
+        Collider::Aabb{
+            center: Vector3::new(0.0, 0.0, 0.0),
+            half_extend: Vector3::new(0.5, 0.5, 0.5),
+        }
+    }
+
+    fn body_at(name: &str, body: RigidBody, location: Vector3<f32>) -> SimulatedBody{
+        let mut transform = NodeTransform::one();
+        transform.disp = location;
+        let velocity = body.initial_velocity;
+        SimulatedBody{
+            name: String::from(name),
+            body: body,
+            transform: transform,
+            start_location: location,
+            velocity: velocity,
+        }
+    }
+
+    #[test]
+    fn step_integrates_gravity_and_velocity(){
+        let gravity = Vector3::new(0.0, -10.0, 0.0);
+        let mut bodies = vec![
+            body_at("falling", RigidBody::new_dynamic(unit_box(), 1.0)
+                .with_velocity(Vector3::new(1.0, 0.0, 0.0)), Vector3::new(0.0, 10.0, 0.0)),
+            body_at("floor", RigidBody::new_static(unit_box()), Vector3::new(0.0, 0.0, 0.0)),
+        ];
+        step(&mut bodies, 0.5, gravity);
+
+        //semi implicit euler: the velocity is updated first, then used to move
+        assert_eq!(bodies[0].velocity, Vector3::new(1.0, -5.0, 0.0));
+        assert_eq!(bodies[0].transform.disp, Vector3::new(0.5, 7.5, 0.0));
+        //static bodies never move
+        assert_eq!(bodies[1].transform.disp, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(bodies[1].velocity, Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn step_stops_a_body_on_the_floor(){
+        let mut bodies = vec![
+            body_at("floor", RigidBody::new_static(unit_box()), Vector3::new(0.0, 0.0, 0.0)),
+            body_at("box", RigidBody::new_dynamic(unit_box(), 1.0).with_restitution(0.0)
+                .with_velocity(Vector3::new(0.0, -1.0, 0.0)), Vector3::new(0.0, 1.0, 0.0)),
+        ];
+        step(&mut bodies, 0.25, Vector3::new(0.0, 0.0, 0.0));
+
+        //the box moved 0.25 into the floor, it is stopped and pushed back out
+        let penetration = 0.25;
+        let expected_y = 0.75 + (penetration - PENETRATION_SLOP) * CORRECTION_FACTOR;
+        assert!(bodies[1].velocity.magnitude() < 1.0e-6);
+        assert!((bodies[1].transform.disp.y - expected_y).abs() < 1.0e-5);
+        assert_eq!(bodies[0].transform.disp, Vector3::new(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,76 @@
+use cgmath::*;
+
+use physics::collider::Collider;
+
+///Describes how a node behaves in the physics simulation. Is attached to a node via
+/// `NodeAttributes::rigid_body`. The current velocity of a body is kept by the `PhysicsSystem`,
+/// the node transform is only changed through `SceneJobs`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RigidBody {
+    ///The shape used for collision tests, in object space of the node.
+    pub collider: Collider,
+    ///Mass in kg. A mass of `0.0` or less marks a static body which is never moved.
+    pub mass: f32,
+    ///How much energy is kept when bouncing off another body. 0.0 = none, 1.0 = all.
+    pub restitution: f32,
+    ///If false, the global gravity won't be applied to this body.
+    pub use_gravity: bool,
+    ///The velocity the body starts with when it is first seen by the physics system.
+    pub initial_velocity: Vector3<f32>,
+}
+
+impl RigidBody{
+    ///Creates a body with `mass` which is affected by gravity and collisions.
+    pub fn new_dynamic(collider: Collider, mass: f32) -> Self{
+        RigidBody{
+            collider: collider,
+            mass: mass,
+            restitution: 0.2,
+            use_gravity: true,
+            initial_velocity: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    ///Creates a body which never moves, but other bodies collide with, like the floor.
+    pub fn new_static(collider: Collider) -> Self{
+        RigidBody{
+            collider: collider,
+            mass: 0.0,
+            restitution: 0.2,
+            use_gravity: false,
+            initial_velocity: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    ///Sets the restitution (bounciness) of this body.
+    pub fn with_restitution(mut self, restitution: f32) -> Self{
+        self.restitution = restitution;
+        self
+    }
+
+    ///Disables gravity for this body.
+    pub fn without_gravity(mut self) -> Self{
+        self.use_gravity = false;
+        self
+    }
+
+    ///Sets the velocity the body starts with.
+    pub fn with_velocity(mut self, velocity: Vector3<f32>) -> Self{
+        self.initial_velocity = velocity;
+        self
+    }
+
+    ///Returns true if this body is never moved by the simulation.
+    pub fn is_static(&self) -> bool{
+        self.mass <= 0.0
+    }
+
+    ///Returns 1/mass, or 0.0 for static bodies.
+    pub fn get_inverse_mass(&self) -> f32{
+        if self.is_static(){
+            0.0
+        }else{
+            1.0 / self.mass
+        }
+    }
+}
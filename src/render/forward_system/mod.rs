@@ -2,23 +2,36 @@ use core::engine_settings;
 use render::render_helper;
 use render::frame_system::FrameSystem;
 use render::light_system::LightSystem;
+use render::probe_system::{ProbeSystem, ProbeBinding, ProbeCaptureTarget};
 use render::post_progress::PostProgress;
 use render::pipeline;
+use render::pipeline_manager::PipelineManager;
+use render::pipeline_builder::{PipelineConfig, DepthStencilConfig};
+use render::render_passes::{RenderPassConf, ObjectPassSubPasses};
 use core::resource_management::asset_manager::AssetManager;
 use core::next_tree::{SceneTree, ValueTypeBool, SceneComparer};
 use core::next_tree::content::ContentType;
 use core::resources::camera::Camera;
+use core::resources::mesh::{Mesh, InstanceData};
+use core::resources::prefab::{self, InstanceBatch};
 use render::renderer::RenderDebug;
 use render::shader::shaders::hdr_resolve;
+use render::shader::shaders::skybox_fragment;
 
 
 
-use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::image::traits::ImageViewAccess;
 use vulkano::descriptor::descriptor_set::FixedSizeDescriptorSetsPool;
 use vulkano::buffer::cpu_pool::CpuBufferPool;
+use vulkano::buffer::BufferAccess;
 use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::pipeline::depth_stencil::{DepthStencil, Compare};
 use vulkano;
 
+use cgmath::*;
+use collision::{Aabb3, Frustum};
+
 use std::sync::{Arc, Mutex};
 
 use jakar_threadpool::*;
@@ -34,7 +47,15 @@ pub struct ForwardSystem {
     ///A pipeline used to sort hdr fragments
     resolve_pipe: Arc<pipeline::Pipeline>,
 
+    ///Draws the sky of the environment behind the opaque meshes
+    sky_pipe: Arc<pipeline::Pipeline>,
+    sky_buffer_pool: CpuBufferPool<skybox_fragment::ty::SkyInfo>,
+    sky_desc_pool: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
 
+    ///Holds the model matrices of the prefab instances
+    instance_pool: CpuBufferPool<InstanceData>,
+    ///Creates the instanced variants of the material pipelines
+    pipeline_manager: Arc<Mutex<PipelineManager>>,
 }
 
 
@@ -43,18 +64,41 @@ impl ForwardSystem{
         engine_settings:  Arc<Mutex<engine_settings::EngineSettings>>,
         device: Arc<vulkano::device::Device>,
         resolve_pipe: Arc<pipeline::Pipeline>,
+        pipeline_manager: Arc<Mutex<PipelineManager>>,
     ) -> Self{
 
 
         let sort_buffer_pool = CpuBufferPool::uniform_buffer(device.clone());
         let sort_desc_pool = FixedSizeDescriptorSetsPool::new(resolve_pipe.get_pipeline_ref(), 0);
+        let instance_pool = CpuBufferPool::vertex_buffer(device.clone());
+
+        //The sky is drawn on the far plane, only where no mesh wrote its depth
+        let sky_depth = DepthStencil{
+            depth_write: false,
+            depth_compare: Compare::LessOrEqual,
+            .. DepthStencil::simple_depth_test()
+        };
+        let sky_pipe = pipeline_manager.lock().expect("failed to lock pipeline manager")
+        .get_pipeline_by_config(
+            PipelineConfig::default()
+                .with_shader("Skybox".to_string())
+                .with_render_pass(RenderPassConf::ObjectPass(ObjectPassSubPasses::ForwardRenderingPass))
+                .with_depth_and_stencil_settings(DepthStencilConfig::CustomDepthAndStencil(sky_depth))
+        );
+        let sky_buffer_pool = CpuBufferPool::uniform_buffer(device.clone());
+        let sky_desc_pool = FixedSizeDescriptorSetsPool::new(sky_pipe.get_pipeline_ref(), 0);
 
 
         ForwardSystem{
             engine_settings,
             resolve_pipe,
             sort_buffer_pool,
-            sort_desc_pool
+            sort_desc_pool,
+            sky_pipe,
+            sky_buffer_pool,
+            sky_desc_pool,
+            instance_pool,
+            pipeline_manager,
         }
     }
 
@@ -64,6 +108,7 @@ impl ForwardSystem{
         &mut self,
         frame_system: &FrameSystem,
         light_system: &LightSystem,
+        probe_system: &ProbeSystem,
         post_progress: &PostProgress,
         asset_manager: &mut AssetManager,
         command_buffer: AutoCommandBufferBuilder,
@@ -73,14 +118,25 @@ impl ForwardSystem{
 
         debug.start_node_getting();
 
+        //only nodes in the layers of the camera are rendered
+        let render_layers = asset_manager.get_camera().get_render_layers();
+
         let mesh_comparer = SceneComparer::new()
         .with_value_type(ValueTypeBool::none().with_mesh())
         .with_frustum(asset_manager.get_camera().get_frustum_bound())
         .with_cull_distance(0.1, asset_manager.get_camera().get_view_projection_matrix())
+        .with_layer(render_layers)
         .without_transparency();
 
         let mesh_comp_trans = mesh_comparer.clone()
         .with_transparency();
+
+        //the instances are culled as a whole by the bound of the referenced scene
+        let prefab_comparer = SceneComparer::new()
+        .with_value_type(ValueTypeBool::none().with_prefab())
+        .with_frustum(asset_manager.get_camera().get_frustum_bound())
+        .with_cull_distance(0.1, asset_manager.get_camera().get_view_projection_matrix())
+        .with_layer(render_layers);
         //now we can actually start the frame
         //get all opaque meshes
         let opaque_meshes = asset_manager
@@ -94,6 +150,15 @@ impl ForwardSystem{
         let trans_recv = render_helper::order_by_distance(
             translucent_meshes, asset_manager.get_camera()
         );
+        //collect the meshes of all visible prefabs into one batch per mesh
+        let instance_batches = prefab::batch_instances(
+            &asset_manager.get_active_scene().copy_all_nodes(&Some(prefab_comparer))
+        );
+
+        let (view_projection, camera_position) = {
+            let camera = asset_manager.get_camera();
+            (camera.get_view_projection_matrix(), camera.get_position())
+        };
 
         debug.end_node_getting();
 
@@ -143,10 +208,13 @@ impl ForwardSystem{
 
 
                 */
+                let probes = probe_system.get_binding(&opaque_mesh.get_attrib().value_bound);
                 new_cb = mesh_lck.draw(
                     new_cb,
                     frame_system,
                     light_system,
+                    &probes,
+                    frame_system.get_dynamic_state(),
                     transform,
                     debug
                 );
@@ -163,22 +231,74 @@ impl ForwardSystem{
             }
         }
 
+        //Draw the opaque prefab meshes, one draw per mesh
+        let (instanced_cb, instanced_draws) = self.draw_instance_batches(
+            new_cb,
+            instance_batches.iter().filter(|batch| !batch.is_transparent),
+            frame_system,
+            light_system,
+            &|bound| probe_system.get_binding(bound),
+            frame_system.get_dynamic_state(),
+            debug
+        );
+        new_cb = instanced_cb;
+        draw_count += instanced_draws;
+
+        //The sky only covers what is left, the translucent meshes are blended over it
+        new_cb = self.draw_sky(
+            new_cb,
+            light_system,
+            post_progress,
+            view_projection,
+            camera_position,
+            frame_system.get_dynamic_state()
+        );
+
         //Now recive the translucent ones and draw them
         let trans_meshses = trans_recv.recv().expect("failed to recive translucent meshes");
+        //The transparent prefab instances are merged into the back to front order of the meshes
+        let mut trans_batches: Vec<&InstanceBatch> = instance_batches.iter()
+        .filter(|batch| batch.is_transparent)
+        .collect();
+        prefab::sort_back_to_front(&mut trans_batches, camera_position);
+        let mut next_batch = 0;
+
         //now we are in the main render pass in the forward pass, using this to draw all meshes
         //add all opaque meshes to the command buffer
         for trans_mesh in trans_meshses.iter(){
             let transform = trans_mesh.get_attrib().get_matrix();
 
+            //first the instances which are farther away than this mesh
+            let mesh_distance = (trans_mesh.get_attrib().get_transform().disp - camera_position).magnitude();
+            let mut farther_end = next_batch;
+            while farther_end < trans_batches.len() && trans_batches[farther_end].get_distance(camera_position) > mesh_distance{
+                farther_end += 1;
+            }
+            let (instanced_cb, instanced_draws) = self.draw_instance_batches(
+                new_cb,
+                trans_batches[next_batch..farther_end].iter().map(|batch| *batch),
+                frame_system,
+                light_system,
+                &|bound| probe_system.get_binding(bound),
+                frame_system.get_dynamic_state(),
+                debug
+            );
+            new_cb = instanced_cb;
+            draw_count += instanced_draws;
+            next_batch = farther_end;
+
             if let ContentType::Mesh(ref mesh) = trans_mesh.get_value(){
 
 
                 let mesh_lck = mesh.lock().expect("failed to lock mesh for drawing!");
 
+                let probes = probe_system.get_binding(&trans_mesh.get_attrib().value_bound);
                 new_cb = mesh_lck.draw(
                     new_cb,
                     frame_system,
                     light_system,
+                    &probes,
+                    frame_system.get_dynamic_state(),
                     transform,
                     debug,
                 );
@@ -192,6 +312,19 @@ impl ForwardSystem{
             }
         }
 
+        //The transparent prefab instances which are nearer than all transparent meshes
+        let (instanced_cb, instanced_draws) = self.draw_instance_batches(
+            new_cb,
+            trans_batches[next_batch..].iter().map(|batch| *batch),
+            frame_system,
+            light_system,
+            &|bound| probe_system.get_binding(bound),
+            frame_system.get_dynamic_state(),
+            debug
+        );
+        new_cb = instanced_cb;
+        draw_count += instanced_draws;
+
         debug.set_draw_calls(draw_count);
 
 
@@ -201,12 +334,117 @@ impl ForwardSystem{
         let next_stage = new_cb.next_subpass(false).expect("failed to change to Hdr Sorting render pass");
 
         //now draw to the sorted image
-        let mut final_cb = self.sort_hdr(next_stage, frame_system, post_progress);
+        let forward_diffuse = frame_system.get_passes().gbuffer.forward_diffuse.clone();
+        let mut final_cb = self.sort_hdr(
+            next_stage,
+            frame_system,
+            post_progress,
+            forward_diffuse,
+            frame_system.get_dynamic_state()
+        );
         //finally end this pass end return
         final_cb = final_cb.end_render_pass().expect("failed to end object pass");
 
         final_cb
     }
+
+    ///Renders one face of a reflection probe to `target`. Only opaque meshes, opaque prefab
+    /// meshes and the sky are captured. The uniform manager has to hold the view and projection
+    /// of the face already.
+    pub fn capture_probe_face(
+        &mut self,
+        command_buffer: AutoCommandBufferBuilder,
+        target: &ProbeCaptureTarget,
+        view_projection: Matrix4<f32>,
+        location: Vector3<f32>,
+        render_layers: u32,
+        probes: &ProbeBinding,
+        frame_system: &FrameSystem,
+        light_system: &LightSystem,
+        post_progress: &PostProgress,
+        asset_manager: &mut AssetManager,
+        debug: &mut RenderDebug,
+    ) -> AutoCommandBufferBuilder{
+        let face_frustum = Frustum::from_matrix4(view_projection)
+        .expect("failed to create probe face frustum");
+
+        let mesh_comparer = SceneComparer::new()
+        .with_value_type(ValueTypeBool::none().with_mesh())
+        .with_frustum(face_frustum.clone())
+        .with_cull_distance(0.1, view_projection)
+        .with_layer(render_layers)
+        .without_transparency();
+
+        let prefab_comparer = SceneComparer::new()
+        .with_value_type(ValueTypeBool::none().with_prefab())
+        .with_frustum(face_frustum)
+        .with_cull_distance(0.1, view_projection)
+        .with_layer(render_layers);
+
+        let opaque_meshes = asset_manager
+        .get_active_scene()
+        .copy_all_nodes(&Some(mesh_comparer));
+
+        let instance_batches = prefab::batch_instances(
+            &asset_manager.get_active_scene().copy_all_nodes(&Some(prefab_comparer))
+        );
+
+        let clearing_values = vec![
+            [0.0, 0.0, 0.0, 1.0].into(),
+            1f32.into(),
+            [0.0, 0.0, 0.0, 1.0].into(),
+            [0.0, 0.0, 0.0, 1.0].into(),
+        ];
+
+        let mut new_cb = command_buffer.begin_render_pass(target.framebuffer.clone(), false, clearing_values)
+            .expect("failed to start probe capture pass");
+
+        for opaque_mesh in opaque_meshes.iter(){
+            if let ContentType::Mesh(ref mesh) = opaque_mesh.get_value(){
+                let mesh_lck = mesh.lock().expect("failed to lock mesh for drawing!");
+                new_cb = mesh_lck.draw(
+                    new_cb,
+                    frame_system,
+                    light_system,
+                    probes,
+                    &target.dynamic_state,
+                    opaque_mesh.get_attrib().get_matrix(),
+                    debug
+                );
+            }
+        }
+
+        let (instanced_cb, _) = self.draw_instance_batches(
+            new_cb,
+            instance_batches.iter().filter(|batch| !batch.is_transparent),
+            frame_system,
+            light_system,
+            &|_| probes.clone(),
+            &target.dynamic_state,
+            debug
+        );
+        new_cb = instanced_cb;
+
+        new_cb = self.draw_sky(
+            new_cb,
+            light_system,
+            post_progress,
+            view_projection,
+            location,
+            &target.dynamic_state
+        );
+
+        let next_stage = new_cb.next_subpass(false).expect("failed to change to probe resolve pass");
+        let resolved_cb = self.sort_hdr(
+            next_stage,
+            frame_system,
+            post_progress,
+            target.color_input.clone(),
+            &target.dynamic_state
+        );
+
+        resolved_cb.end_render_pass().expect("failed to end probe capture pass")
+    }
 /* An option to generate the drawcalls however not implemented yet
     ///Takes a collection of nodes and creates a collection of drawcalls from them
     fn gen_draw_calls(&self
@@ -254,11 +492,145 @@ impl ForwardSystem{
     }
     */
 
-    ///Sorts the current rendered image to an hdr fragments only image
+    ///Draws each batch with one instanced draw call. Meshes whose shader has no instanced variant
+    /// are drawn once per instance. Returns the command buffer and the number of draw calls.
+    ///`probe_source` returns the reflection probes for the world space bound of a batch.
+    fn draw_instance_batches<'a, I>(
+        &mut self,
+        command_buffer: AutoCommandBufferBuilder,
+        batches: I,
+        frame_system: &FrameSystem,
+        light_system: &LightSystem,
+        probe_source: &Fn(&Aabb3<f32>) -> ProbeBinding,
+        dynamic_state: &DynamicState,
+        debug: &mut RenderDebug,
+    ) -> (AutoCommandBufferBuilder, u32)
+    where I: Iterator<Item = &'a InstanceBatch>
+    {
+        let mut new_cb = command_buffer;
+        let mut draw_count = 0;
+
+        for batch in batches{
+            let mesh_lck = batch.mesh.lock().expect("failed to lock mesh for drawing!");
+            //cpu only meshes can't be drawn
+            if !mesh_lck.has_material(){
+                continue;
+            }
+
+            //All instances share the probes which influence the bound of the whole batch
+            let probes = probe_source(&get_batch_bound(&mesh_lck.get_bound(), &batch.transforms));
+
+            match self.get_instanced_pipeline(&mesh_lck){
+                Some(pipeline) => {
+                    let instance_buffer = self.instance_pool.chunk(batch.get_instance_data())
+                    .expect("failed to allocate instance buffer");
+
+                    new_cb = mesh_lck.draw_instanced(
+                        new_cb,
+                        frame_system,
+                        light_system,
+                        &probes,
+                        dynamic_state,
+                        pipeline,
+                        Arc::new(instance_buffer) as Arc<BufferAccess + Send + Sync>,
+                        debug
+                    );
+                    draw_count += 1;
+                },
+                None => {
+                    for transform in batch.transforms.iter(){
+                        new_cb = mesh_lck.draw(
+                            new_cb,
+                            frame_system,
+                            light_system,
+                            &probes,
+                            dynamic_state,
+                            *transform,
+                            debug
+                        );
+                        draw_count += 1;
+                    }
+                }
+            }
+        }
+
+        (new_cb, draw_count)
+    }
+
+    ///Draws the sky cube map of the current environment on the far plane. Does nothing if no
+    /// environment is set.
+    fn draw_sky(
+        &mut self,
+        command_buffer: AutoCommandBufferBuilder,
+        light_system: &LightSystem,
+        post_progress: &PostProgress,
+        view_projection: Matrix4<f32>,
+        camera_position: Vector3<f32>,
+        dynamic_state: &DynamicState,
+    ) -> AutoCommandBufferBuilder{
+        let environment = match light_system.get_environment(){
+            Some(environment) => environment,
+            None => return command_buffer,
+        };
+
+        let inverse_view_projection = match view_projection.invert(){
+            Some(matrix) => matrix,
+            None => return command_buffer,
+        };
+
+        let sky_info = skybox_fragment::ty::SkyInfo{
+            inverse_view_projection: inverse_view_projection.into(),
+            camera_position: camera_position.into(),
+            intensity: environment.get_intensity(),
+        };
+
+        let sky_buffer = self.sky_buffer_pool.next(sky_info)
+        .expect("failed to allocate sky info");
+
+        let sky_set = self.sky_desc_pool.next()
+        .add_buffer(sky_buffer)
+        .expect("failed to add sky info to sky descriptor set")
+        .add_sampled_image(environment.get_sky(), environment.get_cube_sampler())
+        .expect("failed to add sky cube map to sky descriptor set")
+        .build()
+        .expect("failed to build sky descriptor");
+
+        command_buffer.draw(
+            self.sky_pipe.get_pipeline_ref(),
+            dynamic_state.clone(),
+            vec![post_progress.get_screen_vb()],
+            sky_set,
+            ()
+        ).expect("failed to draw sky")
+    }
+
+    ///Returns the instanced variant of the pipeline the material of `mesh` uses, or `None` if its
+    /// shader set has no instanced variant.
+    fn get_instanced_pipeline(&mut self, mesh: &Mesh) -> Option<Arc<GraphicsPipelineAbstract + Send + Sync>>{
+        let config = {
+            let material = mesh.get_material();
+            let material_lck = material.lock().expect("failed to lock material");
+            material_lck.get_pipeline().pipeline_config.clone()
+        };
+
+        if config.shader_set != "Pbr"{
+            return None;
+        }
+
+        let mut pipeline_manager_lck = self.pipeline_manager.lock().expect("failed to lock pipeline manager");
+        let pipeline = pipeline_manager_lck.get_pipeline_by_config(
+            config.with_shader(String::from("PbrInstanced"))
+        );
+        Some(pipeline.get_pipeline_ref())
+    }
+
+    ///Sorts the multisampled `input` image to an hdr fragments only image
     fn sort_hdr(&mut self,
         command_buffer: AutoCommandBufferBuilder,
         frame_system: &FrameSystem,
         post_progress: &PostProgress,
+        input: Arc<ImageViewAccess + Send + Sync>,
+        dynamic_state: &DynamicState,
     ) -> AutoCommandBufferBuilder{
 
         let (sampling_rate, bloom_brightness) = {
@@ -279,7 +651,7 @@ impl ForwardSystem{
         .expect("Failed to get sorting settings");
 
         let sorting_attachment = self.sort_desc_pool.next()
-        .add_image(frame_system.get_passes().gbuffer.forward_diffuse.clone())
+        .add_image(input)
         .expect("failed to add hdr_image to sorting pass descriptor set")
         .add_buffer(settings_buffer)
         .expect("failed to add hdr image settings buffer to post progress attachment")
@@ -290,7 +662,7 @@ impl ForwardSystem{
         //perform the post progress
         let new_command_buffer = command_buffer.draw(
             self.resolve_pipe.get_pipeline_ref(),
-            frame_system.get_dynamic_state().clone(),
+            dynamic_state.clone(),
             vec![post_progress.get_screen_vb()],
             sorting_attachment,
             ()
@@ -299,3 +671,19 @@ impl ForwardSystem{
         new_command_buffer
     }
 }
+
+///Returns the world space bound of all instances of a mesh with the bound `mesh_bound`
+fn get_batch_bound(mesh_bound: &Aabb3<f32>, transforms: &Vec<Matrix4<f32>>) -> Aabb3<f32>{
+    let mut min = Point3::new(::std::f32::MAX, ::std::f32::MAX, ::std::f32::MAX);
+    let mut max = Point3::new(::std::f32::MIN, ::std::f32::MIN, ::std::f32::MIN);
+
+    for transform in transforms.iter(){
+        for corner in mesh_bound.to_corners().iter(){
+            let point = transform.transform_point(*corner);
+            min = Point3::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z));
+            max = Point3::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z));
+        }
+    }
+
+    Aabb3::new(min, max)
+}
@@ -4,6 +4,7 @@ use core::resource_management::asset_manager::AssetManager;
 use core::next_tree::content::ContentType;
 use core::next_tree::jobs::SceneJobs;
 use core::next_tree::attributes::NodeAttributes;
+use core::resources::environment::Environment;
 
 use jakar_tree::node::Node;
 
@@ -16,6 +17,7 @@ use vulkano::buffer::*;
 use vulkano::pipeline::ComputePipeline;
 use vulkano::pipeline::ComputePipelineAbstract;
 use render::shader::shader_inputs::lights;
+use render::shader::shader_inputs::environment;
 use vulkano::buffer::cpu_pool::CpuBufferPoolSubbuffer;
 use vulkano::buffer::device_local::DeviceLocalBuffer;
 use vulkano::descriptor::descriptor_set::FixedSizeDescriptorSetsPool;
@@ -85,6 +87,13 @@ pub struct LightSystem {
     compute_pipeline: Arc<vulkano::pipeline::ComputePipelineAbstract + Send + Sync>,
 
     shadow_map_sampler: Arc<Sampler>,
+
+    //The environment of the asset manager, or a black one if there is none
+    environment: Environment,
+    has_environment: bool,
+    fallback_environment: Environment,
+    current_environment_info: CpuBufferPoolSubbuffer<environment::ty::EnvironmentInfo, Arc<vulkano::memory::pool::StdMemoryPool>>,
+    buffer_pool_environment: vulkano::buffer::cpu_pool::CpuBufferPool<environment::ty::EnvironmentInfo>,
 }
 
 impl LightSystem{
@@ -181,6 +190,20 @@ impl LightSystem{
         let c_light_count = tmp_uniform_buffer_pool_05
         .next(light_count_tmp).expect("Failed to alloc first light count buffer");
 
+        //Is bound as long as no environment is set
+        let fallback_environment = Environment::black(device.clone(), queue.clone())
+        .expect("failed to create fallback environment");
+
+        let environment_pool = CpuBufferPool::<environment::ty::EnvironmentInfo>::new(
+            device.clone(), BufferUsage::all()
+        );
+
+        let c_environment_info = environment_pool.next(environment::ty::EnvironmentInfo{
+            intensity: 0.0,
+            max_lod: 0.0,
+            has_environment: 0,
+        }).expect("Failed to alloc first environment info buffer");
+
 
         LightSystem{
             queue: queue,
@@ -198,13 +221,20 @@ impl LightSystem{
 
             compute_pipeline: compute_pipeline,
 
-            shadow_map_sampler: shadow_map_sampler
+            shadow_map_sampler: shadow_map_sampler,
+
+            environment: fallback_environment.clone(),
+            has_environment: false,
+            fallback_environment: fallback_environment,
+            current_environment_info: c_environment_info,
+            buffer_pool_environment: environment_pool,
         }
     }
 
     ///Analyses the lights we currently need, sends the to the shadow system to decide which light
     /// gets a shadow, and where. Then builds the uniform buffers for the lights which get used
-    /// in the compute and shadow passes.
+    /// in the compute and shadow passes. Also takes over the current environment of the asset
+    /// manager.
     pub fn update_light_set(
         &mut self,
         shadow_system: &mut ShadowSystem,
@@ -215,6 +245,8 @@ impl LightSystem{
             asset_manager,
         );
 
+        self.update_environment(asset_manager);
+
         //Now create a buffer from theese lights
         let light_counts = LightCount{
             points: self.light_store.point_lights.len() as u32,
@@ -296,6 +328,26 @@ impl LightSystem{
     }
 
 
+    ///Uses the environment of the asset manager for the image based lighting, or the black
+    /// fallback if none is set
+    fn update_environment(&mut self, asset_manager: &mut AssetManager){
+        let (environment, has_environment) = match asset_manager.get_environment(){
+            Some(environment) => (environment, true),
+            None => (self.fallback_environment.clone(), false),
+        };
+
+        let environment_info = environment::ty::EnvironmentInfo{
+            intensity: environment.get_intensity(),
+            max_lod: environment.get_max_lod(),
+            has_environment: if has_environment { 1 } else { 0 },
+        };
+
+        self.current_environment_info = self.buffer_pool_environment.next(environment_info)
+        .expect("Failed to allocate new environment info buffer");
+        self.environment = environment;
+        self.has_environment = has_environment;
+    }
+
     pub fn dispatch_compute_shader(
         &mut self,
         command_buffer: AutoCommandBufferBuilder,
@@ -347,11 +399,13 @@ impl LightSystem{
     /// one decriptorset based on the needed set id when asked for it.
     ///TODO: Have a look if we can put this in a ring buffer (cpubufferpool)
     ///NOTE:
-    /// - Binding 0 = point lights
-    /// - Binding 1 = directional lights
-    /// - Binding 2 = spot lights
-    /// - Binding 3 = struct which describes how many actual lights where send
-    /// - Binding 4 = The texture with all directional shadows.
+    /// - Binding 0 = the cluster buffer
+    /// - Binding 1 = point lights
+    /// - Binding 2 = directional lights
+    /// - Binding 3 = spot lights
+    /// - Binding 4 = struct which describes how many actual lights where send
+    /// - Binding 5 = The texture with all directional shadows.
+    /// - Binding 6 = The atlas with all point and spot light shadows.
     pub fn get_light_descriptorset(
         &self,
         binding_id: u32,
@@ -378,11 +432,51 @@ impl LightSystem{
                 self.shadow_map_sampler.clone()
             )
             .expect("Failed to add shadow map image")
+            .add_sampled_image(
+                frame_system.get_passes().gbuffer.local_shadow_map.clone(),
+                self.shadow_map_sampler.clone()
+            )
+            .expect("Failed to add point and spot light shadow atlas")
             .build().expect("failed to build descriptor 04")
         );
 
         new_set
     }
+
+    ///Returns the environment used in the current frame, `None` if the asset manager has none
+    pub fn get_environment(&self) -> Option<&Environment>{
+        if self.has_environment{
+            Some(&self.environment)
+        }else{
+            None
+        }
+    }
+
+    ///Creates the descriptor set with the image based lighting maps of the current environment.
+    ///NOTE:
+    /// - Binding 0 = the `EnvironmentInfo`
+    /// - Binding 1 = the diffuse irradiance cube map
+    /// - Binding 2 = the prefiltered specular cube map
+    /// - Binding 3 = the brdf lookup table
+    pub fn get_environment_descriptorset(
+        &self,
+        binding_id: u32,
+        pipeline: Arc<vulkano::pipeline::GraphicsPipelineAbstract + Send + Sync>,
+    ) -> Arc<DescriptorSet + Send + Sync>{
+        Arc::new(PersistentDescriptorSet::start(
+                pipeline.clone(), binding_id as usize
+            )
+            .add_buffer(self.current_environment_info.clone())
+            .expect("failed to add environment info")
+            .add_sampled_image(self.environment.get_irradiance(), self.environment.get_cube_sampler())
+            .expect("failed to add irradiance map")
+            .add_sampled_image(self.environment.get_specular(), self.environment.get_cube_sampler())
+            .expect("failed to add prefiltered specular map")
+            .add_sampled_image(self.environment.get_brdf_lut(), self.environment.get_lut_sampler())
+            .expect("failed to add brdf lookup table")
+            .build().expect("failed to build environment descriptor")
+        )
+    }
 }
 
 ///The compute shader used to compute the light matrix in world space.
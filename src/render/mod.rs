@@ -47,3 +47,6 @@ pub mod shader_manager;
 
 ///Handels the rendering of all shadow maps
 pub mod shadow_system;
+
+///Captures the reflection probes of the active scene into cube maps
+pub mod probe_system;
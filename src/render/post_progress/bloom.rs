@@ -112,7 +112,7 @@ impl Bloom{
         new_cb
     }
     ///Helper function which takes two image acces images and resizes `source` to `target`
-    fn resize_to(
+    pub fn resize_to(
         &self,
         command_buffer: AutoCommandBufferBuilder,
         source: Arc<ImageAccess + Send + Sync + 'static>,
@@ -184,6 +184,12 @@ impl Bloom{
         };
 
 
+        let blur_size = {
+            self.engine_settings
+            .lock().expect("failed to lock settings.")
+            .get_render_settings().get_bloom().size
+        };
+
         let mut is_first_img = true;
         for idx in (initial_blur_level..num_blur_levels).rev(){
 
@@ -205,12 +211,25 @@ impl Bloom{
                 sampler.clone(),
                 target_stack,
                 previouse_stack,
+                blur_size,
             );
 
         }
         local_cb
     }
 
+    ///Blurs the `input_image` of `stage` horizontal and vertical into its `final_image`. The
+    /// samples are `blur_size` texels apart.
+    pub fn blur_stage(
+        &mut self,
+        command_buffer: AutoCommandBufferBuilder,
+        sampler: Arc<Sampler>,
+        stage: BlurStage,
+        blur_size: f32,
+    ) -> AutoCommandBufferBuilder{
+        self.blur_comp(command_buffer, sampler, stage, None, blur_size)
+    }
+
     ///Blures a source, image to a target image via a compute shader. Can also add a optional
     /// second image on top of the resulting image.
     fn blur_comp(
@@ -219,14 +238,9 @@ impl Bloom{
         sampler: Arc<Sampler>,
         target_stack: BlurStage,
         previouse_stack: Option<BlurStage>,
+        blur_size: f32,
     )-> AutoCommandBufferBuilder{
 
-        let blur_size = {
-            self.engine_settings
-            .lock().expect("failed to lock settings.")
-            .get_render_settings().get_bloom().size
-        };
-
         //Construct the new shader descriptor
         let settings_hori = blur_cmp_shader::ty::blur_settings{
             is_horizontal: 1,
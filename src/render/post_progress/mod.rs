@@ -175,7 +175,7 @@ impl PostProgress{
     pub fn get_hdr_settings(&self) -> vulkano::buffer::cpu_pool::CpuBufferPoolSubbuffer
     <default_pstprg_fragment::ty::hdr_settings, Arc<vulkano::memory::pool::StdMemoryPool>> {
         //Might add screen extend
-        let (gamma, msaa, show_mode_int, far, near, auto_exp_setting) = {
+        let (gamma, msaa, show_mode_int, far, near, auto_exp_setting, dof) = {
             let es_lck = self.engine_settings
             .lock()
             .expect("failed to lock settings for frame creation");
@@ -192,7 +192,15 @@ impl PostProgress{
                     es_lck.get_render_settings().get_exposure().min_exposure
                 }
             };
-            (gamma, msaa, debug_int, far_plane, near_plane, auto_exp_setting)
+            let dof = es_lck.get_render_settings().get_dof();
+            (gamma, msaa, debug_int, far_plane, near_plane, auto_exp_setting, dof)
+        };
+
+        //a zero aperture lets the shader skip the depth of field
+        let aperture = if dof.is_active(){
+            dof.aperture
+        }else{
+            0.0
         };
 
 
@@ -203,6 +211,8 @@ impl PostProgress{
               show_mode: show_mode_int,
               near: near,
               far: far,
+              focus_distance: dof.focus_distance,
+              aperture: aperture,
         };
 
 
@@ -211,7 +221,7 @@ impl PostProgress{
     }
 
     ///Changes into the blur pass, blurs the current hdr values several times to create a nice
-    /// Bloom efect, then dispatches a compute shader to get the current average lumiosity,
+    /// Bloom efect, blurs a half sized copy of the frame for the depth of field, then dispatches a compute shader to get the current average lumiosity,
     /// after that renders a fullscreen image which combines the ldr and hdr fragments as well
     /// as does tone mapping, and writes the output to the swapchain image.
     pub fn do_post_progress<I>(
@@ -226,6 +236,7 @@ impl PostProgress{
             frame_system,
             self.screen_sampler.clone(),
         );
+        new_command_buffer = self.blur_dof(new_command_buffer, frame_system);
         //After bluring its time to downscale our image to one pixel to be able
         //to read it back in a compute shader and get the average value.
         //Since this is all in a compute shader we don't need to change passes here.
@@ -237,6 +248,38 @@ impl PostProgress{
 
 
 
+    ///Scales the resolved color down to half the size and blurs it by the max blur radius of the
+    /// depth of field. The assemble stage blends it in by the circle of confusion of each pixel.
+    fn blur_dof(&mut self,
+        command_buffer: AutoCommandBufferBuilder,
+        frame_system: &FrameSystem,
+    ) -> AutoCommandBufferBuilder{
+        let dof = {
+            self.engine_settings.lock().expect("failed to lock settings")
+            .get_render_settings().get_dof()
+        };
+
+        if !dof.is_active(){
+            return command_buffer;
+        }
+
+        let dof_stage = frame_system.get_passes().gbuffer.dof_blur.clone();
+        let new_cb = self.bloom_system.resize_to(
+            command_buffer,
+            frame_system.get_passes().gbuffer.diffuse_ambient.clone(),
+            dof_stage.input_image.clone()
+        );
+
+        //the kernel reaches 10 samples to each side on a half sized image, so a sample distance of
+        // radius / 20 texels covers the radius in full sized pixels
+        self.bloom_system.blur_stage(
+            new_cb,
+            self.screen_sampler.clone(),
+            dof_stage,
+            dof.max_blur_radius / 20.0
+        )
+    }
+
     ///Takes the hdr_image computes the average lumiosity and stores it in its buffer. The information is used
     /// in the assamble stage to set the exposure setting.
     fn compute_lumiosity(&mut self,
@@ -412,6 +455,7 @@ impl PostProgress{
         let blur = frame_system.get_passes().get_final_bloom_img();
         //let blur = frame_system.get_passes().blur_pass.get_images().bloom[0].after_h_img.clone();
         let dir_shadow = frame_system.get_passes().gbuffer.directional_shadow_map.clone();
+        let dof_blur = frame_system.get_passes().gbuffer.dof_blur.final_image.clone();
 
         let attachments_ds = PersistentDescriptorSet::start(self.pipeline.get_pipeline_ref(), 0) //at binding 0
             .add_sampled_image(
@@ -436,6 +480,10 @@ impl PostProgress{
                 dir_shadow,
                 self.screen_sampler.clone()
             ).expect("failed to add shadow texture to assemble stage")
+            .add_sampled_image(
+                dof_blur,
+                self.screen_sampler.clone()
+            ).expect("failed to add depth of field blur to assemble stage")
             .build()
             .expect("failed to build postprogress cb");
 
@@ -0,0 +1,559 @@
+use vulkano;
+use vulkano::buffer::cpu_pool::{CpuBufferPool, CpuBufferPoolSubbuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::format::Format;
+use vulkano::framebuffer::FramebufferAbstract;
+use vulkano::image::{Dimensions, ImageUsage, StorageImage};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::image::traits::ImageViewAccess;
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
+
+use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+use std::cmp::Ordering;
+use std::time::Instant;
+
+use cgmath::*;
+use collision::{Aabb, Aabb3};
+
+use core::engine_settings::EngineSettings;
+use core::next_tree::{SceneTree, SceneComparer, ValueTypeBool};
+use core::next_tree::content::ContentType;
+use core::resource_management::asset_manager::AssetManager;
+use core::resources::camera::Camera;
+use core::resources::reflection_probe::{ReflectionProbe, ProbeUpdate};
+use render::forward_system::ForwardSystem;
+use render::frame_system::FrameSystem;
+use render::light_system::LightSystem;
+use render::post_progress::PostProgress;
+use render::renderer::RenderDebug;
+use render::uniform_manager::UniformManager;
+use render::shader::shader_inputs::reflection_probes::ty::ProbeInfo;
+use tools::math::time_tools::dur_as_f32;
+
+///How many probes can influence one object, has to match the shader
+pub const MAX_PROBES_PER_OBJECT: usize = 2;
+
+///The probes which influence one object and their cube maps. Is bound as one descriptor set.
+#[derive(Clone)]
+pub struct ProbeBinding {
+    info: CpuBufferPoolSubbuffer<ProbeInfo, Arc<vulkano::memory::pool::StdMemoryPool>>,
+    ///The sharp and the blurred cube map of each probe
+    cubes: [(Arc<ImageViewAccess + Send + Sync>, Arc<ImageViewAccess + Send + Sync>); MAX_PROBES_PER_OBJECT],
+    sampler: Arc<Sampler>,
+}
+
+impl ProbeBinding{
+    ///Creates the descriptor set at `binding_id` of `pipeline`
+    pub fn get_descriptorset(
+        &self,
+        binding_id: u32,
+        pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    ) -> Arc<DescriptorSet + Send + Sync>{
+        Arc::new(PersistentDescriptorSet::start(
+                pipeline.clone(), binding_id as usize
+            )
+            .add_buffer(self.info.clone())
+            .expect("failed to add probe info")
+            .add_sampled_image(self.cubes[0].0.clone(), self.sampler.clone())
+            .expect("failed to add sharp cube map of the first probe")
+            .add_sampled_image(self.cubes[0].1.clone(), self.sampler.clone())
+            .expect("failed to add rough cube map of the first probe")
+            .add_sampled_image(self.cubes[1].0.clone(), self.sampler.clone())
+            .expect("failed to add sharp cube map of the second probe")
+            .add_sampled_image(self.cubes[1].1.clone(), self.sampler.clone())
+            .expect("failed to add rough cube map of the second probe")
+            .build().expect("failed to build reflection probe descriptor")
+        )
+    }
+}
+
+///The images a probe face is rendered to. They are compatible to the object pass, so every
+/// material pipeline can be used while capturing.
+pub struct ProbeCaptureTarget {
+    pub framebuffer: Arc<FramebufferAbstract + Send + Sync>,
+    ///The multisampled color image, is resolved in the second subpass
+    pub color_input: Arc<ImageViewAccess + Send + Sync>,
+    ///Covers the whole face
+    pub dynamic_state: DynamicState,
+    ///The resolved color which is copied to the cube map
+    resolved_color: Arc<AttachmentImage<Format>>,
+}
+
+///The captured cube maps of one probe
+struct ProbeState {
+    sharp: Arc<StorageImage<Format>>,
+    rough: Arc<StorageImage<Format>>,
+    location: Vector3<f32>,
+    ///The influence volume in world space
+    influence: Aabb3<f32>,
+    blend_distance: f32,
+    ///The location of the last capture, the probe is captured again if it moved
+    captured_location: Vector3<f32>,
+    last_capture: Instant,
+    ///The capture request of the probe when the last capture started
+    handled_request: u32,
+    ///The next face to capture if a capture is running
+    next_face: Option<u32>,
+    ///True once all faces have been captured at least once
+    is_valid: bool,
+}
+
+///Captures the nearest reflection probes of the active scene into cube maps and provides the
+/// probes which influence an object while it is forward shaded.
+///NOTE: Point and spot lights are only visible in a capture within the light clusters of the main
+/// camera. Translucent meshes are not captured.
+pub struct ProbeSystem {
+    engine_settings: Arc<Mutex<EngineSettings>>,
+    device: Arc<vulkano::device::Device>,
+    queue: Arc<vulkano::device::Queue>,
+    uniform_manager: Arc<Mutex<UniformManager>>,
+
+    resolution: u32,
+    target: ProbeCaptureTarget,
+    ///The sharp cube map is scaled down over these to the blurred one
+    half_cube: Arc<StorageImage<Format>>,
+    quarter_cube: Arc<StorageImage<Format>>,
+    ///Is bound for probes which don't exist
+    fallback_cube: Arc<ImageViewAccess + Send + Sync>,
+
+    probes: BTreeMap<String, ProbeState>,
+
+    info_pool: CpuBufferPool<ProbeInfo>,
+    sampler: Arc<Sampler>,
+}
+
+impl ProbeSystem{
+    ///Creates the capture images at the probe resolution of the render settings
+    pub fn new(
+        device: Arc<vulkano::device::Device>,
+        queue: Arc<vulkano::device::Queue>,
+        engine_settings: Arc<Mutex<EngineSettings>>,
+        uniform_manager: Arc<Mutex<UniformManager>>,
+        frame_system: &FrameSystem,
+    ) -> Self{
+        let resolution = {
+            let mut settings_lck = engine_settings.lock().expect("failed to lock settings");
+            settings_lck.get_render_settings().get_probe_settings().get_resolution().max(1)
+        };
+
+        let target = create_capture_target(device.clone(), frame_system, resolution);
+
+        let half_cube = create_cube(device.clone(), queue.clone(), (resolution / 2).max(1));
+        let quarter_cube = create_cube(device.clone(), queue.clone(), (resolution / 4).max(1));
+
+        //Fills the unused probe slots, the shader never samples probes without weight
+        let fallback_cube = create_cube(device.clone(), queue.clone(), 1) as Arc<ImageViewAccess + Send + Sync>;
+
+        let sampler = Sampler::new(
+            device.clone(),
+            Filter::Linear,
+            Filter::Linear,
+            MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+        ).expect("failed to create reflection probe sampler");
+
+        ProbeSystem{
+            engine_settings,
+            device: device.clone(),
+            queue,
+            uniform_manager,
+
+            resolution,
+            target,
+            half_cube,
+            quarter_cube,
+            fallback_cube,
+
+            probes: BTreeMap::new(),
+
+            info_pool: CpuBufferPool::uniform_buffer(device),
+            sampler,
+        }
+    }
+
+    ///Finds the nearest probes of the active scene and captures the faces which are due, within
+    /// the face budget of the render settings. Has to be called outside of a render pass, after
+    /// the light system was updated.
+    pub fn update_probes(
+        &mut self,
+        command_buffer: AutoCommandBufferBuilder,
+        forward_system: &mut ForwardSystem,
+        frame_system: &FrameSystem,
+        light_system: &LightSystem,
+        post_progress: &PostProgress,
+        asset_manager: &mut AssetManager,
+        debug: &mut RenderDebug,
+    ) -> AutoCommandBufferBuilder{
+        let probe_settings = {
+            let mut settings_lck = self.engine_settings.lock().expect("failed to lock settings");
+            settings_lck.get_render_settings().get_probe_settings()
+        };
+
+        let (camera_location, camera_data, render_layers) = {
+            let camera = asset_manager.get_camera();
+            (camera.get_position(), camera.as_uniform_data(), camera.get_render_layers())
+        };
+
+        //Find the nearest probes
+        let probe_nodes = asset_manager.get_active_scene().copy_all_nodes(&Some(
+            SceneComparer::new().with_value_type(ValueTypeBool::none().with_reflection_probe())
+        ));
+
+        let mut candidates = Vec::new();
+        for node in probe_nodes.iter(){
+            if let ContentType::ReflectionProbe(ref probe) = node.get_value(){
+                let transform = node.get_attrib().transform;
+                let distance = (transform.disp - camera_location).magnitude();
+                candidates.push((
+                    distance,
+                    node.get_name().clone(),
+                    probe.clone(),
+                    transform.disp,
+                    probe.get_world_influence(&transform)
+                ));
+            }
+        }
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        candidates.truncate(probe_settings.get_max_probes() as usize);
+
+        //Probes which are too far away or were removed free their cube maps. The states are keyed
+        // by node name, copies of a probe (for instance in prefabs) share the probe name.
+        let unused_probes: Vec<String> = self.probes.keys()
+        .filter(|name| !candidates.iter().any(|&(_, ref node_name, _, _, _)| node_name == *name))
+        .cloned()
+        .collect();
+        for name in unused_probes.iter(){
+            self.probes.remove(name);
+        }
+
+        //Update the states and decide which probes have to be captured
+        for &(_, ref node_name, ref probe, location, influence) in candidates.iter(){
+            if !self.probes.contains_key(node_name){
+                let state = ProbeState{
+                    sharp: create_cube(self.device.clone(), self.queue.clone(), self.resolution),
+                    rough: create_cube(self.device.clone(), self.queue.clone(), (self.resolution / 8).max(1)),
+                    location: location,
+                    influence: influence,
+                    blend_distance: probe.get_blend_distance(),
+                    captured_location: location,
+                    last_capture: Instant::now(),
+                    handled_request: probe.get_capture_request(),
+                    next_face: None,
+                    is_valid: false,
+                };
+                self.probes.insert(node_name.clone(), state);
+            }
+
+            let state = self.probes.get_mut(node_name).expect("failed to find probe state");
+            state.location = location;
+            state.influence = influence;
+            state.blend_distance = probe.get_blend_distance();
+
+            if state.next_face.is_some(){
+                continue;
+            }
+
+            let interval_elapsed = match probe.get_update(){
+                ProbeUpdate::OnDemand => false,
+                ProbeUpdate::Interval(seconds) => dur_as_f32(state.last_capture.elapsed()) >= seconds,
+            };
+
+            if !state.is_valid ||
+                interval_elapsed ||
+                state.handled_request != probe.get_capture_request() ||
+                state.captured_location != location
+            {
+                state.handled_request = probe.get_capture_request();
+                state.captured_location = location;
+                state.next_face = Some(0);
+            }
+        }
+
+        //Spend the face budget, the nearest probes first
+        let (near_plane, far_plane) = (camera_data.near, camera_data.far);
+        let empty_binding = self.get_empty_binding();
+        let mut faces_left = probe_settings.get_faces_per_frame();
+        let mut new_cb = command_buffer;
+
+        for &(_, ref node_name, _, _, _) in candidates.iter(){
+            if faces_left == 0{
+                break;
+            }
+
+            let (sharp, location, first_face) = {
+                let state = self.probes.get(node_name).expect("failed to find probe state");
+                match state.next_face{
+                    Some(face) => (state.sharp.clone(), state.location, face),
+                    None => continue,
+                }
+            };
+
+            let views = ReflectionProbe::get_face_views(&location);
+            let projection = ReflectionProbe::get_face_projection(near_plane, far_plane);
+
+            let mut face = first_face;
+            while face < 6 && faces_left > 0{
+                let view = views[face as usize];
+                let mut face_data = camera_data.clone();
+                face_data.camera_position = location.into();
+                face_data.view = view.into();
+                face_data.proj = projection.into();
+                self.uniform_manager.lock().expect("failed to lock uniform manager").update(face_data);
+
+                new_cb = forward_system.capture_probe_face(
+                    new_cb,
+                    &self.target,
+                    projection * view,
+                    location,
+                    render_layers,
+                    &empty_binding,
+                    frame_system,
+                    light_system,
+                    post_progress,
+                    asset_manager,
+                    debug
+                );
+
+                let size = self.resolution as i32;
+                new_cb = new_cb.blit_image(
+                    self.target.resolved_color.clone(),
+                    [0; 3],
+                    [size, size, 1],
+                    0,
+                    0,
+                    sharp.clone(),
+                    [0; 3],
+                    [size, size, 1],
+                    face,
+                    0,
+                    1,
+                    Filter::Nearest
+                ).expect("failed to copy probe face to cube map");
+
+                face += 1;
+                faces_left -= 1;
+            }
+
+            if face < 6{
+                self.probes.get_mut(node_name).expect("failed to find probe state").next_face = Some(face);
+                continue;
+            }
+
+            //All faces are captured, scale the cube map down to the blurred one
+            let rough = self.probes.get(node_name).expect("failed to find probe state").rough.clone();
+            new_cb = blit_cube(new_cb, sharp, self.half_cube.clone());
+            new_cb = blit_cube(new_cb, self.half_cube.clone(), self.quarter_cube.clone());
+            new_cb = blit_cube(new_cb, self.quarter_cube.clone(), rough);
+
+            let state = self.probes.get_mut(node_name).expect("failed to find probe state");
+            state.next_face = None;
+            state.is_valid = true;
+            state.last_capture = Instant::now();
+        }
+
+        //The forward pass uses the main camera again
+        self.uniform_manager.lock().expect("failed to lock uniform manager").update(camera_data);
+
+        new_cb
+    }
+
+    ///Returns the probes whose influence volume overlaps `bound` (in world space), the nearest
+    /// to its center first.
+    pub fn get_binding(&self, bound: &Aabb3<f32>) -> ProbeBinding{
+        let center = bound.center().to_vec();
+
+        let mut overlapping: Vec<(f32, &ProbeState)> = self.probes.values()
+        .filter(|state| state.is_valid && overlaps(&state.influence, bound))
+        .map(|state| ((state.location - center).magnitude2(), state))
+        .collect();
+        overlapping.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut info = empty_info();
+        let mut cubes = [
+            (self.fallback_cube.clone(), self.fallback_cube.clone()),
+            (self.fallback_cube.clone(), self.fallback_cube.clone()),
+        ];
+
+        for (idx, &(_, state)) in overlapping.iter().take(MAX_PROBES_PER_OBJECT).enumerate(){
+            let (min, max) = (state.influence.min, state.influence.max);
+            info.location[idx] = [state.location.x, state.location.y, state.location.z, 1.0];
+            info.influence_min[idx] = [min.x, min.y, min.z, state.blend_distance];
+            info.influence_max[idx] = [max.x, max.y, max.z, 0.0];
+            info.count += 1;
+            cubes[idx] = (
+                state.sharp.clone() as Arc<ImageViewAccess + Send + Sync>,
+                state.rough.clone() as Arc<ImageViewAccess + Send + Sync>
+            );
+        }
+
+        ProbeBinding{
+            info: self.info_pool.next(info).expect("failed to allocate probe info"),
+            cubes: cubes,
+            sampler: self.sampler.clone(),
+        }
+    }
+
+    ///Returns a binding without any probe, used while capturing so probes don't reflect each
+    /// other.
+    pub fn get_empty_binding(&self) -> ProbeBinding{
+        ProbeBinding{
+            info: self.info_pool.next(empty_info()).expect("failed to allocate probe info"),
+            cubes: [
+                (self.fallback_cube.clone(), self.fallback_cube.clone()),
+                (self.fallback_cube.clone(), self.fallback_cube.clone()),
+            ],
+            sampler: self.sampler.clone(),
+        }
+    }
+}
+
+fn empty_info() -> ProbeInfo{
+    ProbeInfo{
+        location: [[0.0; 4]; MAX_PROBES_PER_OBJECT],
+        influence_min: [[0.0; 4]; MAX_PROBES_PER_OBJECT],
+        influence_max: [[0.0; 4]; MAX_PROBES_PER_OBJECT],
+        count: 0,
+    }
+}
+
+///Returns true if `a` and `b` overlap
+fn overlaps(a: &Aabb3<f32>, b: &Aabb3<f32>) -> bool{
+    a.min.x <= b.max.x && a.max.x >= b.min.x &&
+    a.min.y <= b.max.y && a.max.y >= b.min.y &&
+    a.min.z <= b.max.z && a.max.z >= b.min.z
+}
+
+///Creates a hdr cube map which can be blitted to and from
+fn create_cube(
+    device: Arc<vulkano::device::Device>,
+    queue: Arc<vulkano::device::Queue>,
+    size: u32,
+) -> Arc<StorageImage<Format>>{
+    let usage = ImageUsage{
+        transfer_source: true,
+        transfer_destination: true,
+        sampled: true,
+        ..ImageUsage::none()
+    };
+
+    StorageImage::with_usage(
+        device,
+        Dimensions::Cubemap{ size: size },
+        Format::R16G16B16A16Sfloat,
+        usage,
+        vec![queue.family()].into_iter()
+    ).expect("failed to create reflection probe cube map")
+}
+
+///Creates the images and the framebuffer a probe face is rendered to
+fn create_capture_target(
+    device: Arc<vulkano::device::Device>,
+    frame_system: &FrameSystem,
+    resolution: u32,
+) -> ProbeCaptureTarget{
+    let passes = frame_system.get_passes();
+    let dimensions = [resolution, resolution];
+
+    let resolved_usage = ImageUsage{
+        transfer_source: true,
+        sampled: true,
+        color_attachment: true,
+        input_attachment: true,
+        ..ImageUsage::none()
+    };
+
+    let color_input = AttachmentImage::transient_multisampled_input_attachment(
+        device.clone(),
+        dimensions,
+        passes.static_msaa_factor,
+        passes.image_hdr_msaa_format
+    ).expect("failed to create probe capture color image");
+
+    let depth = AttachmentImage::transient_multisampled_input_attachment(
+        device.clone(),
+        dimensions,
+        passes.static_msaa_factor,
+        passes.image_msaa_depth_format
+    ).expect("failed to create probe capture depth image");
+
+    //Only needed since the hdr sorting writes it
+    let hdr_fragments = AttachmentImage::with_usage(
+        device.clone(),
+        dimensions,
+        passes.image_hdr_msaa_format,
+        resolved_usage.clone()
+    ).expect("failed to create probe capture hdr image");
+
+    let resolved_color = AttachmentImage::with_usage(
+        device.clone(),
+        dimensions,
+        passes.image_hdr_msaa_format,
+        resolved_usage
+    ).expect("failed to create probe capture resolve image");
+
+    let framebuffer = Arc::new(
+        vulkano::framebuffer::Framebuffer::start(passes.object_pass.render_pass.clone())
+        .add(color_input.clone()).expect("failed to add probe color image")
+        .add(depth).expect("failed to add probe depth image")
+        .add(hdr_fragments).expect("failed to add probe hdr image")
+        .add(resolved_color.clone()).expect("failed to add probe resolve image")
+        .build()
+        .expect("failed to build probe capture framebuffer")
+    );
+
+    let dynamic_state = DynamicState{
+        line_width: None,
+        viewports: Some(vec![vulkano::pipeline::viewport::Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [resolution as f32, resolution as f32],
+            depth_range: 0.0 .. 1.0,
+        }]),
+        scissors: None,
+    };
+
+    ProbeCaptureTarget{
+        framebuffer: framebuffer,
+        color_input: color_input,
+        dynamic_state: dynamic_state,
+        resolved_color: resolved_color,
+    }
+}
+
+///Scales all six faces of `source` to `target`
+fn blit_cube(
+    command_buffer: AutoCommandBufferBuilder,
+    source: Arc<StorageImage<Format>>,
+    target: Arc<StorageImage<Format>>,
+) -> AutoCommandBufferBuilder{
+    let source_size = cube_size(&source);
+    let target_size = cube_size(&target);
+
+    command_buffer.blit_image(
+        source,
+        [0; 3],
+        [source_size, source_size, 1],
+        0,
+        0,
+        target,
+        [0; 3],
+        [target_size, target_size, 1],
+        0,
+        0,
+        6,
+        Filter::Linear
+    ).expect("failed to scale probe cube map")
+}
+
+fn cube_size(cube: &StorageImage<Format>) -> i32{
+    use vulkano::image::traits::ImageAccess;
+    ImageAccess::dimensions(cube).width() as i32
+}
@@ -20,6 +20,7 @@ use render::render_passes::{RenderPassConf, ObjectPassSubPasses};
 use render::window::Window;
 use render::shadow_system::ShadowSystem;
 use render::forward_system::ForwardSystem;
+use render::probe_system::ProbeSystem;
 
 use core::engine_settings;
 use tools::engine_state_machine::RenderState;
@@ -364,6 +365,15 @@ impl BuildRender for RenderBuilder{
             self.settings.clone(),
             device.clone(),
             resolve_pipeline,
+            pipeline_manager_arc.clone(),
+        );
+
+        let probe_system = ProbeSystem::new(
+            device.clone(),
+            queue.clone(),
+            self.settings.clone(),
+            uniform_manager.clone(),
+            &frame_system,
         );
 
         let thread_pool = ThreadPool::new_hardware_optimal("RenderThreadPool".to_string());
@@ -384,6 +394,7 @@ impl BuildRender for RenderBuilder{
             shadow_system,
             forward_system,
             light_system,
+            probe_system,
             post_progress,
 
             false,
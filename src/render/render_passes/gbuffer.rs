@@ -94,7 +94,8 @@ pub struct GBuffer {
 
     //Shadows
     pub directional_shadow_map: Arc<AttachmentImage<Format>>,
-    //Non else atm
+    ///The atlas which holds the shadows of point and spot lights
+    pub local_shadow_map: Arc<AttachmentImage<Format>>,
 
     ///The actual gbuffer images
 
@@ -112,6 +113,8 @@ pub struct GBuffer {
     ///PostProgressImages
     pub scaled_hdr: Vec<BlurStage>,
     pub scaled_ldr: Vec<Arc<StorageImage<Format>>>,
+    ///The resolved color at half resolution, blurred for the depth of field
+    pub dof_blur: BlurStage,
 }
 
 
@@ -144,7 +147,7 @@ impl GBuffer{
         };
 
 
-        let directional_shadow_map = create_shadow_maps(
+        let (directional_shadow_map, local_shadow_map) = create_shadow_maps(
             settings.clone(),
             device.clone(),
             shadow_depth_format
@@ -190,6 +193,16 @@ impl GBuffer{
             hdr_msaa_format
         );
 
+        let dof_blur = BlurStage::new(
+            device.clone(),
+            queue.clone(),
+            hdr_msaa_format,
+            [
+                (current_dimensions[0] / 2).max(1),
+                (current_dimensions[1] / 2).max(1)
+            ]
+        );
+
         GBuffer {
             ///Recreation infos
             settings,
@@ -202,7 +215,7 @@ impl GBuffer{
 
             //Shadows
             directional_shadow_map,
-            //Non else atm
+            local_shadow_map,
 
             ///The actual gbuffer images
 
@@ -220,6 +233,7 @@ impl GBuffer{
             ///PostProgressImages
             scaled_hdr,
             scaled_ldr,
+            dof_blur,
         }
     }
     ///Returns the framebuffer for writing the the horizontal blured images for the level at idx.
@@ -416,15 +430,17 @@ fn create_shadow_maps(
     settings: Arc<Mutex<EngineSettings>>,
     device: Arc<vulkano::device::Device>,
     depth_format: Format
-) -> Arc<AttachmentImage<Format>>{
-    let dimensions = {
+) -> (Arc<AttachmentImage<Format>>, Arc<AttachmentImage<Format>>){
+    let (dimensions, local_dimensions) = {
         let mut settings_lck = settings.lock().expect("Failed to lock settings for shadow map size");
-        let res = settings_lck
+        let light_settings = settings_lck
         .get_render_settings()
-        .get_light_settings()
-        .directional_settings.get_shadow_map_resolution();
+        .get_light_settings();
 
-        [res; 2]
+        (
+            [light_settings.directional_settings.get_shadow_map_resolution(); 2],
+            [light_settings.point_settings.get_shadow_map_resolution(); 2]
+        )
     };
 
     let directional_image = AttachmentImage::sampled_input_attachment(
@@ -433,5 +449,11 @@ fn create_shadow_maps(
         depth_format
     ).expect("failed to create hdr_fragments buffer!");
 
-    directional_image
+    let local_image = AttachmentImage::sampled_input_attachment(
+        device.clone(),
+        local_dimensions,
+        depth_format
+    ).expect("failed to create point and spot light shadow atlas!");
+
+    (directional_image, local_image)
 }
@@ -159,6 +159,16 @@ impl RenderPasses{
         )
     }
 
+    ///Returns the framebuffer for the point and spot light shadow atlas
+    pub fn get_framebuff_local(&self) -> Arc<FramebufferAbstract + Send + Sync>{
+        Arc::new(
+            vulkano::framebuffer::Framebuffer::start(self.shadow_pass.render_pass.clone())
+            .add(self.gbuffer.local_shadow_map.clone()).expect("failed to add local shadow atlas")
+            .build()
+            .expect("failed to build local shadow framebuffer!")
+        )
+    }
+
 
     ///Returns the final blur image. *This could be not the first image in the bloom stack!*
     pub fn get_final_bloom_img(&self) -> Arc<StorageImage<Format>>{
@@ -10,8 +10,10 @@ use render::light_system;
 use render::render_passes::RenderPasses;
 use render::shadow_system;
 use render::forward_system::ForwardSystem;
+use render::probe_system::ProbeSystem;
 
 use core::engine_settings;
+use core::event_bus::{EventBus, SwapchainRecreatedEvent, FrameFinishedEvent};
 //use core::simple_scene_system::node_helper;
 use tools::engine_state_machine::RenderState;
 use tools::math::time_tools::*;
@@ -159,6 +161,7 @@ pub struct Renderer {
     shadow_system: shadow_system::ShadowSystem,
     forward_system: ForwardSystem,
     light_system: light_system::LightSystem,
+    probe_system: ProbeSystem,
     render_passes: Arc<Mutex<RenderPasses>>,
     ///The post progresser
     post_progress: post_progress::PostProgress,
@@ -176,6 +179,11 @@ pub struct Renderer {
     render_thread_pool: ThreadPool,
 
     debug_info: RenderDebug,
+
+    ///Is told when the swapchain was recreated and when a frame was presented, set by the engine
+    event_bus: Option<EventBus>,
+    ///Counts the presented frames
+    frame_index: u64,
 }
 
 impl Renderer {
@@ -196,6 +204,7 @@ impl Renderer {
         shadow_system: shadow_system::ShadowSystem,
         forward_system: ForwardSystem,
         light_system: light_system::LightSystem,
+        probe_system: ProbeSystem,
         post_progress: post_progress::PostProgress,
 
         recreate_swapchain: bool,
@@ -220,6 +229,7 @@ impl Renderer {
             forward_system: forward_system,
             render_passes: render_passes,
             light_system: light_system,
+            probe_system: probe_system,
             post_progress: post_progress,
 
             recreate_swapchain: recreate_swapchain,
@@ -231,9 +241,17 @@ impl Renderer {
             render_thread_pool,
 
             debug_info: RenderDebug::new(),
+
+            event_bus: None,
+            frame_index: 0,
         }
     }
 
+    ///Sets the bus the renderer publishes its events on
+    pub fn set_event_bus(&mut self, event_bus: EventBus){
+        self.event_bus = Some(event_bus);
+    }
+
     ///Recreates swapchain for the window size.
     ///Returns true if successfully recreated chain
     pub fn recreate_swapchain(&mut self) -> bool{
@@ -277,6 +295,12 @@ impl Renderer {
 
         //Now when can mark the swapchain as "fine" again
         self.recreate_swapchain = false;
+
+        if let Some(ref event_bus) = self.event_bus{
+            event_bus.publish(SwapchainRecreatedEvent{
+                dimensions: new_dimensions,
+            });
+        }
         true
     }
 
@@ -410,10 +434,28 @@ impl Renderer {
             time_step = Instant::now()
         }
 
+        //Capture the reflection probes which are due, they are sampled while forward shading
+        command_buffer = self.probe_system.update_probes(
+            command_buffer,
+            &mut self.forward_system,
+            &self.frame_system,
+            &self.light_system,
+            &self.post_progress,
+            asset_manager,
+            &mut self.debug_info
+        );
+
+        if should_capture{
+            let time_needed = time_step.elapsed().subsec_nanos();
+            println!("\tRE: Nedded {} ms to capture reflection probes!", time_needed as f32 / 1_000_000.0);
+            time_step = Instant::now()
+        }
+
         //Now we render all the forward stuff
         command_buffer = self.forward_system.do_forward_shading(
             &self.frame_system,
             &self.light_system,
+            &self.probe_system,
             &self.post_progress,
             asset_manager,
             command_buffer,
@@ -497,6 +539,14 @@ impl Renderer {
         //now we overwrite the internal "last_frame_end" with the finish future of this frame
         self.last_frame_end = Some(Arc::new(this_frame));
 
+        if let Some(ref event_bus) = self.event_bus{
+            event_bus.publish(FrameFinishedEvent{
+                frame: self.frame_index,
+                cpu_time: dur_as_f32(start_time.elapsed()),
+            });
+        }
+        self.frame_index += 1;
+
         //Resetting debug options
         if should_capture{
             //ait for the gput to mesure frame time
@@ -7,6 +7,9 @@ pub mod shader_inputs;
 ///The default pbr shader set used for drawing in a realistic scene
 pub mod set_pbr;
 
+///The pbr shader set for instanced draws, takes the model matrices from a second vertex buffer
+pub mod set_pbr_instanced;
+
 ///The current default post progress set
 pub mod set_post_progress;
 
@@ -22,6 +25,12 @@ pub mod set_blur;
 ///A custom vertex and fragment shader for easy depth map rendering
 pub mod set_shadow;
 
+///The depth map shader set for instanced draws, takes the model matrices from a second vertex buffer
+pub mod set_shadow_instanced;
+
+///Draws the sky cube map of the environment behind the scene
+pub mod set_skybox;
+
 use vulkano::device::Device;
 
 use render::shader_manager::ToPipeline;
@@ -32,11 +41,14 @@ use std::sync::Arc;
 ///Indentifies the shader sets.
 /// Contains:
 /// - "Shadow"
+/// - "ShadowInstanced"
 /// - "Pbr"
+/// - "PbrInstanced"
 /// - "PpBlur"
 /// - "Wireframe"
 /// - "PpExposure"
 /// - "PpResolveHdr"
+/// - "Skybox"
 
 #[derive(PartialEq)]
 pub struct DefaultShaderSets {
@@ -53,11 +65,14 @@ impl ShaderLibrary for DefaultShaderSets{
     fn has_shader_set(&self, name: String) -> bool{
         match name.as_ref(){
             "Pbr" => true,
+            "PbrInstanced" => true,
             "Shadow" => true,
+            "ShadowInstanced" => true,
             "Wireframe" => true,
             "PpBlur" => true,
             "PpExposure" => true,
             "PpResolveHdr" => true,
+            "Skybox" => true,
             _ => false,
         }
     }
@@ -65,11 +80,14 @@ impl ShaderLibrary for DefaultShaderSets{
     fn get_shader_set(&self, name: String, device: Arc<Device>) -> Option<Arc<ToPipeline + Send + Sync>>{
         match name.as_ref(){
             "Pbr" => return Some(Arc::new(set_pbr::PbrSet::load(device))),
+            "PbrInstanced" => return Some(Arc::new(set_pbr_instanced::PbrInstancedSet::load(device))),
             "Shadow" => return Some(Arc::new(set_shadow::SetShadow::load(device))),
+            "ShadowInstanced" => return Some(Arc::new(set_shadow_instanced::SetShadowInstanced::load(device))),
             "Wireframe" => return Some(Arc::new(set_wireframe::SetWireframe::load(device))),
             "PpBlur" => return Some(Arc::new(set_blur::BlurSet::load(device))),
             "PpExposure" => return Some(Arc::new(set_post_progress::PostProgressSet::load(device))),
             "PpResolveHdr" => return Some(Arc::new(set_resolve::ResolveSet::load(device))),
+            "Skybox" => return Some(Arc::new(set_skybox::SkyboxSet::load(device))),
             _ => {}, //will return none
         }
         println!("Could not find shader set: {}", name);
@@ -38,6 +38,8 @@ impl PbrSet{
         descriptors.push(DescriptorSetFamiliy::MaterialTextures);
         descriptors.push(DescriptorSetFamiliy::MaterialData);
         descriptors.push(DescriptorSetFamiliy::Lights);
+        descriptors.push(DescriptorSetFamiliy::Environment);
+        descriptors.push(DescriptorSetFamiliy::ReflectionProbes);
 
         let vertex_buffer_def = SingleBufferDefinition::<Vertex>::new();
 
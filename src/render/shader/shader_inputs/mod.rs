@@ -14,6 +14,12 @@ pub mod pbr_texture_info;
 ///Defines the texture sets usable in a pbr material
 pub mod pbr_texture_sets;
 
+///Defines the info about the image based lighting maps
+pub mod environment;
+
+///Defines the info about the reflection probes of an object
+pub mod reflection_probes;
+
 
 ///Keeps track of all the available shader inputs grouped by Descriptorset
 #[derive(Clone)]
@@ -21,6 +27,12 @@ pub enum DescriptorSetFamiliy{
     //Data for the objects
     CameraData,
     Lights,
+    //Irradiance, prefiltered specular map and brdf lookup table
+    Environment,
+    //The two nearest reflection probes of an object and their influence volumes
+    ReflectionProbes,
+    //The sky cube map and the camera needed to draw it
+    Skybox,
     MaterialTextures,
     MaterialData,
     ShadowMaskInfo,
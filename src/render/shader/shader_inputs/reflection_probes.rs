@@ -0,0 +1,4 @@
+#[derive(VulkanoShader)]
+#[ty = "fragment"]
+#[path = "data/shader/input_info/reflection_probes.glsl"]
+struct Dummy;
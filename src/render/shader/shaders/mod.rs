@@ -4,6 +4,9 @@ pub mod pbr_fragment;
 ///The normal PBR vertex shader
 pub mod pbr_vertex;
 
+///The PBR vertex shader which reads the model matrix per instance
+pub mod pbr_instanced_vertex;
+
 ///A wireframe shader for debuging
 pub mod wireframe_vertex;
 
@@ -25,5 +28,14 @@ pub mod blur;
 ///A easy framgent shader which only transfors default vertices to a light space
 pub mod shadow_vertex;
 
+///The depth vertex shader which reads the model matrix per instance
+pub mod shadow_instanced_vertex;
+
 ///A fragment shader that outputs nothing but the depth calculated in the vertex shader before
 pub mod shadow_fragment;
+
+///Draws the sky cube map of the environment on the far plane
+pub mod skybox_vertex;
+
+///Samples the sky cube map in the view direction of each fragment
+pub mod skybox_fragment;
@@ -0,0 +1,4 @@
+#[derive(VulkanoShader)]
+#[ty = "vertex"]
+#[path = "data/shader/shadow_instanced.vs"]
+struct Dummy;
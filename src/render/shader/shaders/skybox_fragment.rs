@@ -0,0 +1,4 @@
+#[derive(VulkanoShader)]
+#[ty = "fragment"]
+#[path = "data/shader/skybox.fs"]
+struct Dummy;
@@ -0,0 +1,4 @@
+#[derive(VulkanoShader)]
+#[ty = "vertex"]
+#[path = "data/shader/skybox.vs"]
+struct Dummy;
@@ -1,17 +1,21 @@
 use vulkano;
 use vulkano::buffer::cpu_pool::CpuBufferPool;
+use vulkano::buffer::BufferAccess;
 use vulkano::descriptor::descriptor_set::FixedSizeDescriptorSetsPool;
 use vulkano::pipeline::GraphicsPipelineAbstract;
 use vulkano::command_buffer::AutoCommandBufferBuilder;
 
 use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+use std::cmp::Ordering;
 
 use collision::Frustum;
 use cgmath::*;
 
 use core::resources::camera::Camera;
+use core::resources::mesh::{Mesh, InstanceData};
+use core::resources::prefab;
 use core::next_tree::{SceneTree, SceneComparer, ValueTypeBool};
-use jakar_tree::node::Node;
 
 use core::resource_management::asset_manager;
 use core::engine_settings::EngineSettings;
@@ -26,8 +30,9 @@ use render::shader::shader_inputs::default_data::ty::LightData;
 use tools::node_tools;
 
 use core::next_tree::content::ContentType;
-use core::next_tree::attributes::NodeAttributes;
 use core::next_tree::jobs::SceneJobs;
+use core::next_tree::attributes::NodeAttributes;
+use jakar_tree::tree::Tree;
 
 // the shader infors we return
 
@@ -38,25 +43,32 @@ pub struct ShadowSystem {
 
     shadow_pipeline_front_culled: Arc<Pipeline>,
     shadow_pipeline_none_culled: Arc<Pipeline>,
+    //the same pipelines, but the model matrices come from an instance buffer
+    instanced_pipeline_front_culled: Arc<Pipeline>,
+    instanced_pipeline_none_culled: Arc<Pipeline>,
 
     data_buffer_pool: CpuBufferPool<LightData>,
     mask_buffer_pool: CpuBufferPool<MaskedInfo>,
+    ///Holds the model matrices of the prefab instances
+    instance_pool: CpuBufferPool<InstanceData>,
 
     data_descriptor_pool_cull: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
-    data_descriptor_pool_no_cull: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>
+    data_descriptor_pool_no_cull: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+    instanced_descriptor_pool_cull: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+    instanced_descriptor_pool_no_cull: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
 }
 
 impl ShadowSystem{
-    /// Creates a new shadow system. Currently only abel to create the depth map of one directional
-    /// light.
+    /// Creates a new shadow system which renders the cascades of the directional lights as well
+    /// as the atlas of the point and spot lights.
     pub fn new(
         device: Arc<vulkano::device::Device>,
         engine_settings: Arc<Mutex<EngineSettings>>,
         pipeline_manager: Arc<Mutex<PipelineManager>>
     ) -> Self{
 
-        //first of all build the two pipelines
-        let (default_pipe, no_cull_pipe) = {
+        //first of all build the two pipelines and their instanced variants
+        let (default_pipe, no_cull_pipe, instanced_pipe, instanced_no_cull_pipe) = {
             let mut manager_lck = pipeline_manager.lock()
             .expect("failed to lock pipeline manager for shadow pipeline creation");
 
@@ -74,9 +86,17 @@ impl ShadowSystem{
             //also one without culling for the masked materials
             config = config.with_cull_mode(pipeline_builder::CullMode::Disabled);
 
-            let masked_pipe = manager_lck.get_pipeline_by_config(config);
+            let masked_pipe = manager_lck.get_pipeline_by_config(config.clone());
 
-            (default_pipe, masked_pipe)
+            let instanced_masked_pipe = manager_lck.get_pipeline_by_config(
+                config.clone().with_shader("ShadowInstanced".to_string())
+            );
+            let instanced_pipe = manager_lck.get_pipeline_by_config(
+                config.with_shader("ShadowInstanced".to_string())
+                .with_cull_mode(pipeline_builder::CullMode::Front)
+            );
+
+            (default_pipe, masked_pipe, instanced_pipe, instanced_masked_pipe)
         };
 
 
@@ -84,18 +104,25 @@ impl ShadowSystem{
         let mask_pool = CpuBufferPool::uniform_buffer(device.clone());
         let descriptor_pool_cull = FixedSizeDescriptorSetsPool::new(default_pipe.get_pipeline_ref().clone(), 0);
         let descriptor_pool_none_cull = FixedSizeDescriptorSetsPool::new(no_cull_pipe.get_pipeline_ref().clone(), 0);
+        let instanced_pool_cull = FixedSizeDescriptorSetsPool::new(instanced_pipe.get_pipeline_ref().clone(), 0);
+        let instanced_pool_none_cull = FixedSizeDescriptorSetsPool::new(instanced_no_cull_pipe.get_pipeline_ref().clone(), 0);
 
         ShadowSystem{
             engine_settings: engine_settings,
 
             shadow_pipeline_front_culled: default_pipe,
             shadow_pipeline_none_culled: no_cull_pipe,
+            instanced_pipeline_front_culled: instanced_pipe,
+            instanced_pipeline_none_culled: instanced_no_cull_pipe,
 
             data_buffer_pool: data_pool,
             mask_buffer_pool: mask_pool,
+            instance_pool: CpuBufferPool::vertex_buffer(device.clone()),
 
             data_descriptor_pool_cull: descriptor_pool_cull,
             data_descriptor_pool_no_cull: descriptor_pool_none_cull,
+            instanced_descriptor_pool_cull: instanced_pool_cull,
+            instanced_descriptor_pool_no_cull: instanced_pool_none_cull,
 
         }
     }
@@ -163,18 +190,26 @@ impl ShadowSystem{
         };
 
         //Get some settings info we want TODO decide if this needs to be dynamic or not...
-        let dir_settings = {
+        let (dir_settings, point_settings) = {
             let mut set_lck = self.engine_settings.lock().expect("Failed to lock settings");
-            set_lck.get_render_settings().get_light_settings().directional_settings.clone()
+            let light_settings = set_lck.get_render_settings().get_light_settings();
+            (light_settings.directional_settings.clone(), light_settings.point_settings.clone())
         };
 
         // While we sort the point and spot lights, we calculate the space we can occupy per
-        // directional light.
-        let d_light_spaces = get_dir_light_areas(
-            directional_lights.len() as u32, dir_settings.get_num_cascades()
-        );
+        // directional light. Only lights which cast a shadow get a space.
+        let casting_dir_lights = directional_lights.iter().filter(|d_light|{
+            match d_light.get_value(){
+                ContentType::DirectionalLight(ref light) => light.get_cast_shadow(),
+                _ => false,
+            }
+        }).count();
+
+        let mut d_light_spaces = get_dir_light_areas(
+            casting_dir_lights as u32, dir_settings.get_num_cascades()
+        ).into_iter();
         //now, iterate through ech light/ lightspace on ther directional shadowmap and
-        for (region, d_light) in d_light_spaces.into_iter().zip(directional_lights.into_iter()){
+        for d_light in directional_lights.into_iter(){
             let light_rotation = d_light.get_attrib().transform.rot;
             let light = {
                 match d_light.get_value(){
@@ -184,6 +219,12 @@ impl ShadowSystem{
                     }
                 }
             };
+
+            let region = if light.get_cast_shadow(){
+                d_light_spaces.next().unwrap_or([[0.0; 4]; 4])
+            }else{
+                [[0.0; 4]; 4]
+            };
             //currently have only one region
             let shader_info = light.as_shader_info(
                 &light_rotation,
@@ -201,12 +242,61 @@ impl ShadowSystem{
         // Finally we convert all of them to shader infos, count the lights with shadows and calculate
         // an optimal atlas for each.
 
-        //Since the directional lights are processed, try to get the point lights
+        //Since the directional lights are processed, try to get the point and spot lights
         let new_points = {
             point_recv.recv().expect("Failed to get ordered point lights!")
         };
+        let new_spot_lights = {
+            spot_recv.recv().expect("Failed to recive spot_lights")
+        };
+
+        //The nearest point and spot lights which cast a shadow share the local atlas. A spot
+        // light needs one tile, a point light one per cube face.
+        let mut shadow_candidates = Vec::new();
+        for (idx, p_light) in new_points.iter().enumerate(){
+            let casts_shadow = match p_light.get_value(){
+                ContentType::PointLight(ref light) => light.get_cast_shadow(),
+                _ => false,
+            };
+            if casts_shadow{
+                let distance = (p_light.get_attrib().transform.disp - camera_loc).magnitude();
+                shadow_candidates.push((distance, true, idx));
+            }
+        }
+        for (idx, s_light) in new_spot_lights.iter().enumerate(){
+            let casts_shadow = match s_light.get_value(){
+                ContentType::SpotLight(ref light) => light.get_cast_shadow(),
+                _ => false,
+            };
+            if casts_shadow{
+                let distance = (s_light.get_attrib().transform.disp - camera_loc).magnitude();
+                shadow_candidates.push((distance, false, idx));
+            }
+        }
+
+        shadow_candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        shadow_candidates.truncate(point_settings.get_max_shadowed_lights() as usize);
+
+        let tile_count = shadow_candidates.iter()
+        .map(|&(_, is_point, _)| if is_point { 6 } else { 1 })
+        .sum::<u32>();
+        let mut tiles = get_atlas_tiles(tile_count).into_iter();
+
+        let mut point_regions = BTreeMap::new();
+        let mut spot_regions = BTreeMap::new();
+        for &(_, is_point, idx) in shadow_candidates.iter(){
+            if is_point{
+                let mut regions = [[0.0; 4]; 6];
+                for face in 0..6{
+                    regions[face] = tiles.next().expect("shadow atlas has too few tiles");
+                }
+                point_regions.insert(idx, regions);
+            }else{
+                spot_regions.insert(idx, tiles.next().expect("shadow atlas has too few tiles"));
+            }
+        }
 
-        for p_light in new_points.into_iter(){
+        for (idx, p_light) in new_points.into_iter().enumerate(){
             let light_location = p_light.get_attrib().transform.disp;
             let light = {
                 match p_light.get_value(){
@@ -214,15 +304,16 @@ impl ShadowSystem{
                     _ => continue, //Is no pointlight, test next
                 }
             };
-            let shader_info = light.as_shader_info(&light_location);
+            let shader_info = light.as_shader_info(
+                &light_location,
+                point_settings.get_pcf_samples(),
+                point_settings.get_poisson_spread(),
+                point_regions.get(&idx).cloned()
+            );
             light_store.point_lights.push((p_light, shader_info));
         }
         //Same with the spot lights
-        let new_spot_lights = {
-            spot_recv.recv().expect("Failed to recive spot_lights")
-        };
-
-        for s_light in new_spot_lights.into_iter(){
+        for (idx, s_light) in new_spot_lights.into_iter().enumerate(){
             let light_location = s_light.get_attrib().transform.disp;
             let light_rotation = s_light.get_attrib().transform.rot;
             let light = {
@@ -231,14 +322,19 @@ impl ShadowSystem{
                     _ => continue, //Is no pointlight, test next
                 }
             };
-            let shader_info = light.as_shader_info(&light_rotation, &light_location);
+            let shader_info = light.as_shader_info(
+                &light_rotation,
+                &light_location,
+                point_settings.get_pcf_samples(),
+                point_settings.get_poisson_spread(),
+                spot_regions.get(&idx).cloned()
+            );
             light_store.spot_lights.push((s_light, shader_info));
         }
 
         light_store
     }
 
-    //TODO configure shadow pass by light which was send here.
     ///Takes a neutral state command buffer, changes into the shadow pass, renders all
     /// shadowmaps and the changes back into neutral mode.
     pub fn render_shadows(
@@ -274,7 +370,26 @@ impl ShadowSystem{
         //Now end directional shadow pass
         new_cb = new_cb.end_render_pass().expect("failed to end directional light shadow pass");
 
-        //TODO change to other subpass for spot and point light rendering.
+        //The point and spot lights are rendered to their own atlas, but only if one of them
+        // got a region on it.
+        let has_local_shadows =
+            light_store.point_lights.iter().any(|&(_, ref info)| info.has_shadow == 1) ||
+            light_store.spot_lights.iter().any(|&(_, ref info)| info.has_shadow == 1);
+
+        if has_local_shadows{
+            let local_fb = frame_system.get_passes().get_framebuff_local();
+            new_cb = new_cb.begin_render_pass(local_fb, false, vec![1f32.into()])
+                .expect("failed to start point and spot light shadow pass");
+
+            new_cb = self.render_local_light_map(
+                new_cb,
+                light_store,
+                asset_manager,
+                frame_system
+            );
+
+            new_cb = new_cb.end_render_pass().expect("failed to end point and spot light shadow pass");
+        }
 
         //return the cb with the shadow map rendering
         new_cb
@@ -288,6 +403,8 @@ impl ShadowSystem{
         asset_manager: &mut asset_manager::AssetManager,
         frame_system: &FrameSystem,
     ) -> AutoCommandBufferBuilder{
+        //nodes which are not in the layers of the camera don't cast shadows either
+        let render_layers = asset_manager.get_camera().get_render_layers();
         //first of all get all directional lights
         let scene = asset_manager.get_active_scene();
         //declare a new cb object which will be updated per draw call
@@ -302,10 +419,20 @@ impl ShadowSystem{
             (bias, should)
         };
 
+        //image dimensions
+        let img_dim = {
+            let tmp_dim = frame_system.get_passes().gbuffer.directional_shadow_map.dimensions();
+
+            [tmp_dim[0] as f32, tmp_dim[1] as f32]
+        };
+
         let mut draw_counter = 0;
 
         //Now for each light and its cascade, render the light
         for &mut (ref mut _light_node, ref light_info) in light_store.directional_lights.iter_mut(){
+            if light_info.has_shadow != 1{
+                continue;
+            }
             //Get the mvp matrix of the current light from the used matrixes in the
             //light buffer
             let light_mvps = {
@@ -315,102 +442,207 @@ impl ShadowSystem{
                 }
                 ret_vec
             };
-            //image dimensions
-            let img_dim = {
-                let tmp_dim = frame_system.get_passes().gbuffer.directional_shadow_map.dimensions();
-
-                [tmp_dim[0] as f32, tmp_dim[1] as f32]
-            };
             //no cycle through the light cascades and render to the correct region on the image
             for (idx, cascade_mvp) in light_mvps.into_iter().enumerate(){
+                let (cb, draw_count) = self.render_region(
+                    new_cb,
+                    scene,
+                    cascade_mvp,
+                    light_info.shadow_region[idx],
+                    img_dim,
+                    cover_bias[idx],
+                    render_layers
+                );
+                new_cb = cb;
+                draw_counter += draw_count;
+            }
+        }
+        if should_capture{
+            println!("\t RE: {} Shadow draw calls", draw_counter);
+        }
+        new_cb
+    }
 
-                //let cascade_start_time = Instant::now();
-
-                let view_frustum = Frustum::from_matrix4(cascade_mvp).expect("failed to create ortho frustum");
-
-                let meshes_in_light_frustum = scene
-                .copy_all_nodes(&Some(
-                    SceneComparer::new()
-                    .with_frustum(view_frustum)
-                    .with_value_type(ValueTypeBool::none().with_mesh())
-                    //also pull in the culling info
-                    .with_cull_distance(cover_bias[idx], cascade_mvp)
-                ));
+    //Renders every cube face of the point lights and every spot light with a shadow to its region
+    // on the local shadow atlas.
+    fn render_local_light_map(
+        &mut self,
+        command_buffer: AutoCommandBufferBuilder,
+        light_store: &mut LightStore,
+        asset_manager: &mut asset_manager::AssetManager,
+        frame_system: &FrameSystem,
+    ) -> AutoCommandBufferBuilder{
+        let render_layers = asset_manager.get_camera().get_render_layers();
+        let scene = asset_manager.get_active_scene();
+        let mut new_cb = command_buffer;
 
-                //println!("cas {} copy_time: {}ms", idx, as_ms(cascade_start_time.elapsed()));
+        let (cover_bias, should_capture) = {
+            let set_lck = self.engine_settings.lock().expect("failed to lock settings");
+            let bias = set_lck.get_render_settings()
+            .get_light_settings().point_settings.get_occupy_bias();
 
-                //find the current region in the directional light map to render to
-                let origin = [
-                    //upper corner
-                    img_dim[0] * light_info.shadow_region[idx][0],
-                    img_dim[1] * light_info.shadow_region[idx][1],
-                ];
-                //the pixels from origin to the target location
-                let dim = [
-                    img_dim[0] * light_info.shadow_region[idx][2] - origin[0],
-                    img_dim[1] * light_info.shadow_region[idx][3] - origin[1],
-                ];
+            (bias, set_lck.capture_frame)
+        };
 
-                //TODO configure based on the current shadow map region
-                let dynamic_state = vulkano::command_buffer::DynamicState{
-                    line_width: None,
-                    viewports: Some(vec![vulkano::pipeline::viewport::Viewport {
-                        origin: origin,
-                        dimensions: dim,
-                        depth_range: 0.0 .. 1.0,
-                    }]),
-                    scissors: None,
-                };
-
-                //let render_start_time = Instant::now();
-                //let mesh_count = meshes_in_light_frustum.len();
-                //After setting each element, render the different shadow mapps
-                for node in meshes_in_light_frustum.into_iter(){
-                    new_cb = self.render_depth_mesh(
-                        new_cb,
-                        &node,
-                        cascade_mvp.clone(),
-                        dynamic_state.clone()
-                    );
-
-                    draw_counter += 1;
+        let img_dim = {
+            let tmp_dim = frame_system.get_passes().gbuffer.local_shadow_map.dimensions();
+            [tmp_dim[0] as f32, tmp_dim[1] as f32]
+        };
 
-                }
-                //println!("cas {} time for rendering {} meshes in {}ms", idx, mesh_count, as_ms(render_start_time.elapsed()));
-                //println!("Time for cascade {}: {}s {}ms \n", idx, dur_as_f32(cascade_start_time.elapsed()), as_ms(cascade_start_time.elapsed()));
+        //Collect the matrix and region of every tile
+        let mut tiles = Vec::new();
+        for &(_, ref light_info) in light_store.point_lights.iter(){
+            if light_info.has_shadow != 1{
+                continue;
+            }
+            for face in 0..6{
+                tiles.push((Matrix4::from(light_info.light_space[face]), light_info.shadow_region[face]));
+            }
+        }
+        for &(_, ref light_info) in light_store.spot_lights.iter(){
+            if light_info.has_shadow != 1{
+                continue;
             }
+            tiles.push((Matrix4::from(light_info.light_space), light_info.shadow_region));
+        }
+
+        let mut draw_counter = 0;
+        for (mvp, region) in tiles.into_iter(){
+            let (cb, draw_count) = self.render_region(
+                new_cb,
+                scene,
+                mvp,
+                region,
+                img_dim,
+                cover_bias,
+                render_layers
+            );
+            new_cb = cb;
+            draw_counter += draw_count;
         }
+
         if should_capture{
-            println!("\t RE: {} Shadow draw calls", draw_counter);
+            println!("\t RE: {} Point and spot shadow draw calls", draw_counter);
         }
         new_cb
     }
 
-    //Renders a single mesh to the current active image with a depth pipeline
+    //Renders all meshes and prefab instances within the frustum of `mvp` to `region` (in uv
+    // coordinates) of the currently bound shadow map. Returns the number of draw calls as well.
+    fn render_region(
+        &mut self,
+        command_buffer: AutoCommandBufferBuilder,
+        scene: &Tree<ContentType, SceneJobs, NodeAttributes>,
+        mvp: Matrix4<f32>,
+        region: [f32; 4],
+        img_dim: [f32; 2],
+        cover_bias: f32,
+        render_layers: u32,
+    ) -> (AutoCommandBufferBuilder, u32){
+        let mut new_cb = command_buffer;
+        let mut draw_counter = 0;
+
+        let view_frustum = Frustum::from_matrix4(mvp).expect("failed to create shadow frustum");
+
+        let meshes_in_light_frustum = scene
+        .copy_all_nodes(&Some(
+            SceneComparer::new()
+            .with_frustum(view_frustum)
+            .with_value_type(ValueTypeBool::none().with_mesh())
+            .with_layer(render_layers)
+            //also pull in the culling info
+            .with_cull_distance(cover_bias, mvp)
+        ));
+
+        //find the current region in the shadow map to render to
+        let origin = [
+            //upper corner
+            img_dim[0] * region[0],
+            img_dim[1] * region[1],
+        ];
+        //the pixels from origin to the target location
+        let dim = [
+            img_dim[0] * region[2] - origin[0],
+            img_dim[1] * region[3] - origin[1],
+        ];
+
+        let dynamic_state = vulkano::command_buffer::DynamicState{
+            line_width: None,
+            viewports: Some(vec![vulkano::pipeline::viewport::Viewport {
+                origin: origin,
+                dimensions: dim,
+                depth_range: 0.0 .. 1.0,
+            }]),
+            scissors: None,
+        };
+
+        //After setting each element, render the different shadow mapps
+        for node in meshes_in_light_frustum.into_iter(){
+            let mesh = match node.get_value(){
+                ContentType::Mesh(ref mesh) => mesh.clone(),
+                _ => {
+                    println!("No Mesh!", );
+                    continue;
+                }, //is no mesh :(
+            };
+
+            new_cb = self.render_depth_mesh(
+                new_cb,
+                &mesh,
+                node.get_attrib().get_matrix(),
+                mvp.clone(),
+                dynamic_state.clone(),
+                None
+            );
+
+            draw_counter += 1;
+        }
+
+        //The prefab meshes are drawn with one instanced draw per mesh
+        let prefabs_in_light_frustum = scene
+        .copy_all_nodes(&Some(
+            SceneComparer::new()
+            .with_frustum(view_frustum)
+            .with_value_type(ValueTypeBool::none().with_prefab())
+            .with_layer(render_layers)
+            .with_cull_distance(cover_bias, mvp)
+        ));
+
+        for batch in prefab::batch_instances(&prefabs_in_light_frustum).iter(){
+            let instance_buffer = self.instance_pool.chunk(batch.get_instance_data())
+            .expect("failed to allocate shadow instance buffer");
+
+            new_cb = self.render_depth_mesh(
+                new_cb,
+                &batch.mesh,
+                Matrix4::identity(),
+                mvp.clone(),
+                dynamic_state.clone(),
+                Some(Arc::new(instance_buffer) as Arc<BufferAccess + Send + Sync>)
+            );
+
+            draw_counter += 1;
+        }
+
+        (new_cb, draw_counter)
+    }
+
+    //Renders a single mesh at `mesh_transform` to the current active image with a depth pipeline.
+    // If there are `instances` the mesh is drawn once per instance with the instanced pipeline, the
+    // model matrices are taken from the instance buffer and `mesh_transform` is unused.
     #[inline]
     fn render_depth_mesh(
         &mut self,
         command_buffer: AutoCommandBufferBuilder,
-        node: &Node<
-            ContentType,
-            SceneJobs,
-            NodeAttributes
-        >,
+        mesh_locked: &Arc<Mutex<Mesh>>,
+        mesh_transform: Matrix4<f32>,
         mvp_mat: Matrix4<f32>,
         dynamic_state: vulkano::command_buffer::DynamicState,
+        instances: Option<Arc<BufferAccess + Send + Sync>>,
     ) -> AutoCommandBufferBuilder {
         //get the actual mesh as well as its pipeline an create the descriptor sets
-        let mesh_locked = match node.get_value(){
-            ContentType::Mesh(ref mesh) => mesh.clone(),
-            _ => {
-                println!("No Mesh!", );
-                return command_buffer;
-            }, //is no mesh :(
-        };
         let mesh = mesh_locked.lock().expect("failed to lock mesh in cb creation");
 
-        let mesh_transform = node.get_attrib().get_matrix();
-
         let data = LightData{
             model: mesh_transform.into(),
             viewproj: mvp_mat.into(),
@@ -437,10 +669,11 @@ impl ShadowSystem{
         let descriptor =
         {
             //find right descriptor pool and build
-            if should_be_double{
-                &mut self.data_descriptor_pool_no_cull
-            }else{
-                &mut self.data_descriptor_pool_cull
+            match (should_be_double, instances.is_some()){
+                (true, false) => &mut self.data_descriptor_pool_no_cull,
+                (false, false) => &mut self.data_descriptor_pool_cull,
+                (true, true) => &mut self.instanced_descriptor_pool_no_cull,
+                (false, true) => &mut self.instanced_descriptor_pool_cull,
             }.next()
             .add_buffer(data_buffer).expect("failed to add data buffer")
             .add_sampled_image(
@@ -454,14 +687,22 @@ impl ShadowSystem{
 
         };
         //checkfor the vertex and index buffer, if there are none we won't render at all
-        if let Some(vertex_buffer) = mesh.get_vertex_buffer(){
+        if let Some(mut vertex_buffer) = mesh.get_vertex_buffer(){
             if let Some(index_buffer) = mesh.get_index_buffer(){
+                //find right pipeline fitting to the descriptor and execute
+                let pipeline = match (should_be_double, instances.is_some()){
+                    (true, false) => &mut self.shadow_pipeline_none_culled,
+                    (false, false) => &mut self.shadow_pipeline_front_culled,
+                    (true, true) => &mut self.instanced_pipeline_none_culled,
+                    (false, true) => &mut self.instanced_pipeline_front_culled,
+                }.get_pipeline_ref();
+
+                if let Some(instance_buffer) = instances{
+                    vertex_buffer.push(instance_buffer);
+                }
+
                 let new_cb = command_buffer.draw_indexed(
-                    if should_be_double { //find right pipeline fitting to the descriptor and execute
-                        &mut self.shadow_pipeline_none_culled
-                    }else{
-                        &mut self.shadow_pipeline_front_culled
-                    }.get_pipeline_ref(),
+                    pipeline,
                     dynamic_state,
                     vertex_buffer,
                     index_buffer,
@@ -529,3 +770,29 @@ fn get_dir_light_areas(num_lights: u32, num_cascades: u32) -> Vec<[[f32; 4];4]>{
 
     lights_vec
 }
+
+/// calculates `num_tiles` square tiles on one atlas in uv coords (0.0 - 1.0)
+fn get_atlas_tiles(num_tiles: u32) -> Vec<[f32; 4]>{
+    let mut count = 1;
+    while (count * count) < num_tiles {
+        count +=1;
+    }
+
+    let split_distance = 1.0 / count as f32;
+    let mut tiles = Vec::new();
+    for u in 0..count{
+        for v in 0..count{
+            if tiles.len() as u32 == num_tiles{
+                return tiles;
+            }
+            tiles.push([
+                u as f32 * split_distance,
+                v as f32 * split_distance,
+                (u+1) as f32 * split_distance,
+                (v+1) as f32 * split_distance,
+            ]);
+        }
+    }
+
+    tiles
+}
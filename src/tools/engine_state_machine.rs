@@ -112,6 +112,25 @@ impl AssetUpdateState{
     }
 }
 
+pub enum PhysicsUpdateState{
+    ///When the physics system did not run yet
+    Idle,
+    ///While simulating and writing the results back into the scene
+    Working(Instant),
+    ///The physics system is waiting since some time
+    Waiting(Instant),
+}
+
+impl PhysicsUpdateState{
+    pub fn working() -> Self {
+        PhysicsUpdateState::Working(Instant::now())
+    }
+
+    pub fn wait() -> Self{
+        PhysicsUpdateState::Waiting(Instant::now())
+    }
+}
+
 enum LastStep{
     Asset,
     Render,
@@ -123,6 +142,7 @@ enum LastStep{
 pub struct EngineStateMachine{
     render_state: Arc<Mutex<RenderState>>,
     asset_state: Arc<Mutex<AssetUpdateState>>,
+    physics_state: Arc<Mutex<PhysicsUpdateState>>,
     last_step: LastStep,
 }
 
@@ -130,11 +150,13 @@ impl EngineStateMachine{
     pub fn new(
         render_state: Arc<Mutex<RenderState>>,
         asset_state: Arc<Mutex<AssetUpdateState>>,
+        physics_state: Arc<Mutex<PhysicsUpdateState>>,
     ) -> Self{
 
         EngineStateMachine{
             render_state,
             asset_state,
+            physics_state,
             last_step: LastStep::Asset,
         }
     }
@@ -158,6 +180,16 @@ impl EngineStateMachine{
             }
         };
 
+        let physics_is_working = {
+            let physics_state_lck = self.physics_state.lock().expect("failed to lock physics state");
+            match *physics_state_lck{
+                PhysicsUpdateState::Working(_) => true,
+                _ => false,
+            }
+        };
+
+        //The physics results are applied as jobs by the asset update, therefore the physics step
+        // only runs after a finished asset update and the other way around.
         match self.last_step{
             LastStep::Asset => {
                 if !render_working_on_cpu{
@@ -166,16 +198,17 @@ impl EngineStateMachine{
                 }
             },
             LastStep::Render => {
-                if !asset_is_working{
-                    self.last_step = LastStep::Asset;
-                    return NextStep::UpdateAssets;
+                if !asset_is_working && !physics_is_working{
+                    self.last_step = LastStep::Physics;
+                    return NextStep::UpdatePhysics;
                 }
 
             }
-            _ => {
-                println!("In Physics, Cant be!", );
-                self.last_step = LastStep::Render;
-                return NextStep::Render;
+            LastStep::Physics => {
+                if !asset_is_working && !physics_is_working{
+                    self.last_step = LastStep::Asset;
+                    return NextStep::UpdateAssets;
+                }
             }
         }
         let remaining = Duration::from_secs(0);
@@ -189,6 +222,11 @@ impl EngineStateMachine{
         *state = AssetUpdateState::working();
     }
 
+    pub fn physics_working(&mut self){
+        let mut state = self.physics_state.lock().expect("failed to lock physics_state");
+        *state = PhysicsUpdateState::working();
+    }
+
     pub fn render_on_cpu(&mut self){
         let mut state = self.render_state.lock().expect("failed to lock asset_state");
         *state = RenderState::work_cpu();
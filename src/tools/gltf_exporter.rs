@@ -0,0 +1,799 @@
+use jakar_tree::*;
+use jakar_tree::node::Node;
+use core::next_tree::*;
+use core::next_tree::transform::NodeTransform;
+use core::resources::{mesh, material, texture};
+use core::resources::camera::Camera;
+use core::asset_error::AssetError;
+use render::pipeline_builder;
+use tools::gltf_importer::LUMINOUS_EFFICACY;
+
+use cgmath::*;
+use serde_json;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+//The gltf constants we need
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+///Collects the json objects and the binary data while walking the tree
+struct GltfWriter {
+    nodes: Vec<Value>,
+    meshes: Vec<Value>,
+    materials: Vec<Value>,
+    textures: Vec<Value>,
+    images: Vec<Value>,
+    accessors: Vec<Value>,
+    buffer_views: Vec<Value>,
+    cameras: Vec<Value>,
+    lights: Vec<Value>,
+    buffer: Vec<u8>,
+
+    ///mesh name -> primitive, so every mesh is written once
+    primitives: BTreeMap<String, Value>,
+    ///material name -> index
+    material_indices: BTreeMap<String, usize>,
+    ///image path -> texture index
+    texture_indices: BTreeMap<String, usize>,
+    ///the directory of the exported file, image paths are written relative to it
+    base: PathBuf,
+}
+
+///Exports `scene` to `path`. If the path ends with ".glb" a binary gltf is written, otherwise
+/// a .gltf with the binary data in a .bin file next to it.
+///Every child of the root node becomes a root node of the gltf scene. Mesh, camera and light nodes
+/// which are direct, childless children of an empty with the same transform are written into that
+/// empty, like `gltf_importer::import_gltf()` creates them. Therefore re-importing the file produces
+/// the same tree below the scene node.
+///Textures are only referenced by the path they were loaded from, textures created from data
+/// (for instance embedded in a .glb) are not exported. Skins and animations are not exported.
+pub fn export_gltf(
+    scene: &tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>,
+    path: &str,
+) -> Result<(), AssetError>{
+    let path = Path::new(path);
+    let is_binary = match path.extension(){
+        Some(ext) => ext == "glb",
+        None => false,
+    };
+
+    let mut writer = GltfWriter{
+        nodes: Vec::new(),
+        meshes: Vec::new(),
+        materials: Vec::new(),
+        textures: Vec::new(),
+        images: Vec::new(),
+        accessors: Vec::new(),
+        buffer_views: Vec::new(),
+        cameras: Vec::new(),
+        lights: Vec::new(),
+        buffer: Vec::new(),
+
+        primitives: BTreeMap::new(),
+        material_indices: BTreeMap::new(),
+        texture_indices: BTreeMap::new(),
+        base: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+    };
+
+    let root_transform = scene.root_node.get_attrib().transform;
+    let mut scene_nodes = Vec::new();
+    for (_, child) in scene.root_node.get_children().iter(){
+        scene_nodes.push(writer.add_node(child, &root_transform));
+    }
+
+    //The binary data is either the second glb chunk or a .bin file
+    let bin_name = match path.file_stem(){
+        Some(stem) => stem.to_string_lossy().into_owned() + ".bin",
+        None => String::from("scene.bin"),
+    };
+
+    let mut root = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": "jakar-engine gltf exporter",
+        },
+        "scene": 0,
+        "scenes": [{
+            "name": scene.name.clone(),
+            "nodes": scene_nodes,
+        }],
+        "nodes": writer.nodes,
+    });
+
+    {
+        let root_obj = root.as_object_mut().expect("gltf root is no object");
+        if !writer.meshes.is_empty(){
+            root_obj.insert(String::from("meshes"), Value::Array(writer.meshes));
+        }
+        if !writer.materials.is_empty(){
+            root_obj.insert(String::from("materials"), Value::Array(writer.materials));
+        }
+        if !writer.textures.is_empty(){
+            root_obj.insert(String::from("textures"), Value::Array(writer.textures));
+            root_obj.insert(String::from("images"), Value::Array(writer.images));
+        }
+        if !writer.cameras.is_empty(){
+            root_obj.insert(String::from("cameras"), Value::Array(writer.cameras));
+        }
+        if !writer.lights.is_empty(){
+            root_obj.insert(String::from("extensionsUsed"), json!(["KHR_lights_punctual"]));
+            root_obj.insert(String::from("extensions"), json!({
+                "KHR_lights_punctual": {
+                    "lights": writer.lights,
+                }
+            }));
+        }
+        if !writer.buffer.is_empty(){
+            root_obj.insert(String::from("accessors"), Value::Array(writer.accessors));
+            root_obj.insert(String::from("bufferViews"), Value::Array(writer.buffer_views));
+            let buffer = if is_binary{
+                json!({"byteLength": writer.buffer.len()})
+            }else{
+                json!({"byteLength": writer.buffer.len(), "uri": bin_name.clone()})
+            };
+            root_obj.insert(String::from("buffers"), json!([buffer]));
+        }
+    }
+
+    let json_string = match serde_json::to_string_pretty(&root){
+        Ok(string) => string,
+        Err(er) => return Err(AssetError::decode(&path.to_string_lossy(), &format!("failed to serialize gltf: {}", er))),
+    };
+
+    if is_binary{
+        write_glb(path, json_string.into_bytes(), writer.buffer)
+    }else{
+        write_file(path, json_string.as_bytes())?;
+        if !writer.buffer.is_empty(){
+            write_file(&writer.base.join(&bin_name), &writer.buffer)?;
+        }
+        Ok(())
+    }
+}
+
+impl GltfWriter{
+    ///Adds `node` and all its children, returns the index of the gltf node.
+    fn add_node(
+        &mut self,
+        node: &Node<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>,
+        parent_transform: &NodeTransform,
+    ) -> usize{
+        let world_transform = get_gltf_transform(node);
+        let local = world_transform.relative_to(parent_transform);
+
+        let mut gltf_node = json!({
+            "name": node.get_name().clone(),
+            "translation": [local.disp.x, local.disp.y, local.disp.z],
+            //cgmath is w,x,y,z gltf x,y,z,w
+            "rotation": [local.rot.v.x, local.rot.v.y, local.rot.v.z, local.rot.s],
+            "scale": [local.scale.x, local.scale.y, local.scale.z],
+        });
+
+        let mut primitives = Vec::new();
+        let mut camera = None;
+        let mut light = None;
+        let mut children = Vec::new();
+
+        self.add_content(node.get_value(), &mut primitives, &mut camera, &mut light);
+
+        //Reserve the index before the children are added, otherwise the order gets mixed
+        let own_index = self.nodes.len();
+        self.nodes.push(Value::Null);
+
+        let is_empty = match node.get_value(){
+            &content::ContentType::Empty(_) => true,
+            _ => false,
+        };
+
+        for (_, child) in node.get_children().iter(){
+            if is_empty && is_merge_able(child, &world_transform, camera.is_some(), light.is_some()){
+                self.add_content(child.get_value(), &mut primitives, &mut camera, &mut light);
+            }else{
+                children.push(self.add_node(child, &world_transform));
+            }
+        }
+
+        {
+            let node_obj = gltf_node.as_object_mut().expect("gltf node is no object");
+            if !primitives.is_empty(){
+                let mesh_index = self.meshes.len();
+                self.meshes.push(json!({
+                    "name": node.get_name().clone(),
+                    "primitives": primitives,
+                }));
+                node_obj.insert(String::from("mesh"), json!(mesh_index));
+            }
+            if let Some(camera_index) = camera{
+                node_obj.insert(String::from("camera"), json!(camera_index));
+            }
+            if let Some(light_index) = light{
+                node_obj.insert(String::from("extensions"), json!({
+                    "KHR_lights_punctual": {"light": light_index}
+                }));
+            }
+            if !children.is_empty(){
+                node_obj.insert(String::from("children"), json!(children));
+            }
+        }
+
+        self.nodes[own_index] = gltf_node;
+        own_index
+    }
+
+    ///Writes the content of a node into the primitives, camera or light of a gltf node
+    fn add_content(
+        &mut self,
+        value: &content::ContentType,
+        primitives: &mut Vec<Value>,
+        camera: &mut Option<usize>,
+        light: &mut Option<usize>,
+    ){
+        match value{
+            &content::ContentType::Mesh(ref mesh) => {
+                if let Some(primitive) = self.add_primitive(mesh){
+                    primitives.push(primitive);
+                }
+            },
+            &content::ContentType::Camera(ref cam) => {
+                let near_far = cam.get_near_far();
+                *camera = Some(self.cameras.len());
+                self.cameras.push(json!({
+                    "type": "perspective",
+                    "perspective": {
+                        "yfov": Rad::from(Deg(cam.get_fov())).0,
+                        "znear": near_far.near_plane,
+                        "zfar": near_far.far_plane,
+                    }
+                }));
+            },
+            &content::ContentType::PointLight(ref point) => {
+                let mut point = point.clone();
+                let color = *point.get_color();
+                *light = Some(self.lights.len());
+                self.lights.push(json!({
+                    "name": point.name.clone(),
+                    "type": "point",
+                    "color": [color.x, color.y, color.z],
+                    "intensity": *point.get_intensity() * LUMINOUS_EFFICACY,
+                    "range": *point.get_radius(),
+                }));
+            },
+            &content::ContentType::SpotLight(ref spot) => {
+                let mut spot = spot.clone();
+                let color = *spot.get_color();
+                *light = Some(self.lights.len());
+                self.lights.push(json!({
+                    "name": spot.name.clone(),
+                    "type": "spot",
+                    "color": [color.x, color.y, color.z],
+                    "intensity": *spot.get_intensity() * LUMINOUS_EFFICACY,
+                    "range": *spot.get_radius(),
+                    "spot": {
+                        "innerConeAngle": spot.get_inner_radius().to_radians(),
+                        "outerConeAngle": spot.get_outer_radius().to_radians(),
+                    }
+                }));
+            },
+            &content::ContentType::DirectionalLight(ref directional) => {
+                let mut directional = directional.clone();
+                let color = *directional.get_color();
+                *light = Some(self.lights.len());
+                self.lights.push(json!({
+                    "name": directional.name.clone(),
+                    "type": "directional",
+                    "color": [color.x, color.y, color.z],
+                    "intensity": *directional.get_intensity() * LUMINOUS_EFFICACY,
+                }));
+            },
+            &content::ContentType::Renderable(_) => {
+                println!("WARNING: GLTF_EXPORTER: renderables can't be exported, writing an empty node");
+            },
+            &content::ContentType::Prefab(ref prefab) => {
+                println!(
+                    "WARNING: GLTF_EXPORTER: prefab {} of scene {} can't be exported, writing an empty node",
+                    prefab.name, prefab.get_scene_name()
+                );
+            },
+            &content::ContentType::ReflectionProbe(ref probe) => {
+                println!(
+                    "WARNING: GLTF_EXPORTER: reflection probe {} can't be exported, writing an empty node",
+                    probe.name
+                );
+            },
+            &content::ContentType::Empty(_) => {},
+        }
+    }
+
+    ///Writes the mesh data to the buffer and returns the primitive. Returns `None` for meshes
+    /// without vertices or indices, gltf doesn't allow empty accessors.
+    fn add_primitive(&mut self, mesh: &Arc<Mutex<mesh::Mesh>>) -> Option<Value>{
+        let mesh_lck = mesh.lock().expect("failed to lock mesh while exporting");
+
+        if let Some(primitive) = self.primitives.get(&mesh_lck.name){
+            return Some(primitive.clone());
+        }
+
+        let positions = mesh_lck.get_all_positions();
+        let indices = mesh_lck.get_indices();
+        if positions.is_empty() || indices.is_empty(){
+            println!("WARNING: GLTF_EXPORTER: mesh {} has no vertices or indices, skipping it", mesh_lck.name);
+            return None;
+        }
+
+        let (min, max) = positions.iter().fold(
+            ([::std::f32::MAX; 3], [::std::f32::MIN; 3]),
+            |(mut min, mut max), pos| {
+                for i in 0..3{
+                    min[i] = min[i].min(pos[i]);
+                    max[i] = max[i].max(pos[i]);
+                }
+                (min, max)
+            }
+        );
+
+        let position_acc = self.add_float_accessor(
+            &positions.iter().flat_map(|v| v.iter().cloned()).collect::<Vec<f32>>(),
+            positions.len(), "VEC3", Some((min.to_vec(), max.to_vec()))
+        );
+        let uvs = mesh_lck.get_all_uvs();
+        let uv_acc = self.add_float_accessor(
+            &uvs.iter().flat_map(|v| v.iter().cloned()).collect::<Vec<f32>>(),
+            uvs.len(), "VEC2", None
+        );
+        let normals = mesh_lck.get_all_normals();
+        let normal_acc = self.add_float_accessor(
+            &normals.iter().flat_map(|v| v.iter().cloned()).collect::<Vec<f32>>(),
+            normals.len(), "VEC3", None
+        );
+        let tangents = mesh_lck.get_all_tangents();
+        let tangent_acc = self.add_float_accessor(
+            &tangents.iter().flat_map(|v| v.iter().cloned()).collect::<Vec<f32>>(),
+            tangents.len(), "VEC4", None
+        );
+        let colors = mesh_lck.get_all_colors();
+        let color_acc = self.add_float_accessor(
+            &colors.iter().flat_map(|v| v.iter().cloned()).collect::<Vec<f32>>(),
+            colors.len(), "VEC4", None
+        );
+        let index_acc = self.add_index_accessor(&indices);
+
+        let mut primitive = json!({
+            "attributes": {
+                "POSITION": position_acc,
+                "NORMAL": normal_acc,
+                "TANGENT": tangent_acc,
+                "TEXCOORD_0": uv_acc,
+                "COLOR_0": color_acc,
+            },
+            "indices": index_acc,
+        });
+
+        if mesh_lck.has_material(){
+            let material_index = self.add_material(&mesh_lck.get_material());
+            primitive.as_object_mut().expect("primitive is no object")
+            .insert(String::from("material"), json!(material_index));
+        }
+
+        self.primitives.insert(mesh_lck.name.clone(), primitive.clone());
+        Some(primitive)
+    }
+
+    ///Adds the material if it is not known yet, returns its index
+    fn add_material(&mut self, material: &Arc<Mutex<material::Material>>) -> usize{
+        let material_lck = material.lock().expect("failed to lock material while exporting");
+        let name = material_lck.get_name();
+        if let Some(index) = self.material_indices.get(&name){
+            return *index;
+        }
+
+        let flags = material_lck.get_texture_usage_info();
+        let factors = material_lck.get_material_factor_info();
+        let pipeline_config = material_lck.get_pipeline().pipeline_config.clone();
+
+        let mut pbr = json!({
+            "baseColorFactor": factors.get_factor_albedo(),
+            "metallicFactor": factors.get_factor_metal(),
+            "roughnessFactor": factors.get_factor_roughness(),
+        });
+        let mut gltf_material = json!({
+            "name": name.clone(),
+            "emissiveFactor": factors.get_factor_emissive(),
+            "doubleSided": pipeline_config.cull_mode == pipeline_builder::CullMode::Disabled,
+        });
+
+        {
+            let pbr_obj = pbr.as_object_mut().expect("pbr is no object");
+            if flags.albedo{
+                if let Some(index) = self.add_texture(&material_lck.get_albedo_texture()){
+                    pbr_obj.insert(String::from("baseColorTexture"), json!({"index": index}));
+                }
+            }
+            if flags.metal || flags.roughness{
+                if let Some(index) = self.add_texture(&material_lck.get_metallic_roughness_texture()){
+                    pbr_obj.insert(String::from("metallicRoughnessTexture"), json!({"index": index}));
+                }
+            }
+        }
+
+        {
+            let mat_obj = gltf_material.as_object_mut().expect("material is no object");
+            mat_obj.insert(String::from("pbrMetallicRoughness"), pbr);
+
+            if flags.normal{
+                if let Some(index) = self.add_texture(&material_lck.get_normal_texture()){
+                    mat_obj.insert(String::from("normalTexture"), json!({
+                        "index": index, "scale": factors.get_factor_normal()
+                    }));
+                }
+            }
+            if flags.occlusion{
+                if let Some(index) = self.add_texture(&material_lck.get_occlusion_texture()){
+                    mat_obj.insert(String::from("occlusionTexture"), json!({
+                        "index": index, "strength": factors.get_factor_occlusion()
+                    }));
+                }
+            }
+            if flags.emissive{
+                if let Some(index) = self.add_texture(&material_lck.get_emissive_texture()){
+                    mat_obj.insert(String::from("emissiveTexture"), json!({"index": index}));
+                }
+            }
+            if flags.height{
+                println!("WARNING: GLTF_EXPORTER: gltf has no height maps, the height map of {} is not exported", name);
+            }
+
+            if flags.is_masked{
+                mat_obj.insert(String::from("alphaMode"), json!("MASK"));
+                mat_obj.insert(String::from("alphaCutoff"), json!(factors.get_alpha_cutoff()));
+            }else{
+                match pipeline_config.blending_operation{
+                    pipeline_builder::BlendTypes::BlendAlphaBlending => {
+                        mat_obj.insert(String::from("alphaMode"), json!("BLEND"));
+                    },
+                    _ => {
+                        mat_obj.insert(String::from("alphaMode"), json!("OPAQUE"));
+                    }
+                }
+            }
+        }
+
+        let index = self.materials.len();
+        self.materials.push(gltf_material);
+        self.material_indices.insert(name, index);
+        index
+    }
+
+    ///Adds an image and texture which references the path of `texture`, returns `None` if the
+    /// texture was not loaded from a file.
+    fn add_texture(&mut self, texture: &Arc<texture::Texture>) -> Option<usize>{
+        let path = texture.get_original_path();
+        if path == "None"{
+            println!(
+                "WARNING: GLTF_EXPORTER: texture {} was not loaded from a file and is not exported",
+                texture.name
+            );
+            return None;
+        }
+
+        if let Some(index) = self.texture_indices.get(&path){
+            return Some(*index);
+        }
+
+        //use a path relative to the exported file if possible
+        let uri = match Path::new(&path).strip_prefix(&self.base){
+            Ok(relative) => relative.to_string_lossy().into_owned(),
+            Err(_) => path.clone(),
+        };
+
+        let index = self.textures.len();
+        self.textures.push(json!({"source": self.images.len()}));
+        self.images.push(json!({"uri": uri}));
+        self.texture_indices.insert(path, index);
+        Some(index)
+    }
+
+    ///Adds a buffer view and accessor for `data`, `count` is the number of elements
+    fn add_float_accessor(
+        &mut self,
+        data: &[f32],
+        count: usize,
+        element_type: &str,
+        min_max: Option<(Vec<f32>, Vec<f32>)>
+    ) -> usize{
+        let view = self.add_buffer_view(data.iter().map(|f| f.to_bits()), ARRAY_BUFFER);
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": count,
+            "type": element_type,
+        });
+        if let Some((min, max)) = min_max{
+            let acc_obj = accessor.as_object_mut().expect("accessor is no object");
+            acc_obj.insert(String::from("min"), json!(min));
+            acc_obj.insert(String::from("max"), json!(max));
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    ///Adds a buffer view and accessor for the indices
+    fn add_index_accessor(&mut self, indices: &[u32]) -> usize{
+        let view = self.add_buffer_view(indices.iter().cloned(), ELEMENT_ARRAY_BUFFER);
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+
+    ///Writes the 4 byte values little endian into the buffer, returns the index of the view.
+    fn add_buffer_view<I>(&mut self, values: I, target: u32) -> usize where I: Iterator<Item = u32>{
+        let offset = self.buffer.len();
+        for value in values{
+            push_u32(&mut self.buffer, value);
+        }
+
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": self.buffer.len() - offset,
+            "target": target,
+        }));
+        self.buffer_views.len() - 1
+    }
+}
+
+///Returns the world transform the gltf node of `node` has. Cameras and lights get rotated by
+/// the importer since they look along another axis, this is reverted here.
+fn get_gltf_transform(node: &Node<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>) -> NodeTransform{
+    let mut transform = node.get_attrib().transform;
+    match node.get_value(){
+        &content::ContentType::Camera(_) => {
+            transform.rot = transform.rot * Quaternion::from_angle_y(Deg(-180.0));
+        },
+        &content::ContentType::PointLight(_) |
+        &content::ContentType::SpotLight(_) |
+        &content::ContentType::DirectionalLight(_) => {
+            transform.rot = transform.rot * Quaternion::from_angle_y(Deg(-90.0));
+        },
+        _ => {},
+    }
+    transform
+}
+
+///Returns true if `child` can be written into the gltf node of its parent. This is the case for
+/// childless meshes, cameras and lights at the same location as the parent.
+fn is_merge_able(
+    child: &Node<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>,
+    parent_transform: &NodeTransform,
+    has_camera: bool,
+    has_light: bool,
+) -> bool{
+    if child.get_children().len() > 0{
+        return false;
+    }
+
+    let content_fits = match child.get_value(){
+        &content::ContentType::Mesh(_) => true,
+        &content::ContentType::Camera(_) => !has_camera,
+        &content::ContentType::PointLight(_) |
+        &content::ContentType::SpotLight(_) |
+        &content::ContentType::DirectionalLight(_) => !has_light,
+        _ => false,
+    };
+
+    if !content_fits{
+        return false;
+    }
+
+    let child_transform = get_gltf_transform(child);
+    let epsilon = 0.0001;
+    (child_transform.disp - parent_transform.disp).magnitude() < epsilon
+    && (child_transform.scale - parent_transform.scale).magnitude() < epsilon
+    //q and -q are the same rotation
+    && child_transform.rot.dot(parent_transform.rot).abs() > 1.0 - epsilon
+}
+
+///Writes `bytes` to a new file at `path`
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), AssetError>{
+    let mut file = match File::create(path){
+        Ok(file) => file,
+        Err(er) => return Err(AssetError::io(&path.to_string_lossy(), er)),
+    };
+    match file.write_all(bytes){
+        Ok(_) => Ok(()),
+        Err(er) => Err(AssetError::io(&path.to_string_lossy(), er)),
+    }
+}
+
+///Writes the json and binary chunk as a .glb file
+fn write_glb(path: &Path, mut json: Vec<u8>, mut bin: Vec<u8>) -> Result<(), AssetError>{
+    //chunks have to be 4 byte aligned, json is padded with spaces, the binary data with zeros
+    while json.len() % 4 != 0{
+        json.push(b' ');
+    }
+    while bin.len() % 4 != 0{
+        bin.push(0);
+    }
+
+    let mut total_length = 12 + 8 + json.len();
+    if !bin.is_empty(){
+        total_length += 8 + bin.len();
+    }
+
+    let mut glb = Vec::with_capacity(total_length);
+    glb.extend_from_slice(b"glTF");
+    push_u32(&mut glb, 2);
+    push_u32(&mut glb, total_length as u32);
+
+    push_u32(&mut glb, json.len() as u32);
+    glb.extend_from_slice(b"JSON");
+    glb.append(&mut json);
+
+    if !bin.is_empty(){
+        push_u32(&mut glb, bin.len() as u32);
+        glb.extend_from_slice(b"BIN\0");
+        glb.append(&mut bin);
+    }
+
+    write_file(path, &glb)
+}
+
+///Pushes `value` little endian
+fn push_u32(bytes: &mut Vec<u8>, value: u32){
+    bytes.push((value & 0xff) as u8);
+    bytes.push((value >> 8 & 0xff) as u8);
+    bytes.push((value >> 16 & 0xff) as u8);
+    bytes.push((value >> 24 & 0xff) as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::resource_management::{ManagerAndRenderInfo, mesh_manager, scene_manager, animation_manager};
+    use core::resources::{empty, light};
+    use core::ReturnBoundInfo;
+    use core::resources::camera::DefaultCamera;
+    use core::engine_settings::EngineSettings;
+    use core::event_bus::EventBus;
+    use input::keymap::KeyMap;
+    use tools::gltf_importer;
+
+    fn settings_and_key_map() -> (Arc<Mutex<EngineSettings>>, Arc<Mutex<KeyMap>>){
+        (Arc::new(Mutex::new(EngineSettings::default())), Arc::new(Mutex::new(KeyMap::new())))
+    }
+
+    fn headless_managers() -> Arc<Mutex<ManagerAndRenderInfo>>{
+        let (settings, key_map) = settings_and_key_map();
+        let mesh_manager = Arc::new(Mutex::new(mesh_manager::MeshManager::new()));
+        let scene_manager = Arc::new(Mutex::new(scene_manager::SceneManager::new(
+            mesh_manager.clone(), None, settings, key_map, EventBus::new()
+        )));
+        Arc::new(Mutex::new(ManagerAndRenderInfo{
+            pipeline_manager: None,
+            uniform_manager: None,
+            device: None,
+            queue: None,
+            texture_manager: None,
+            material_manager: None,
+            mesh_manager: mesh_manager,
+            scene_manager: scene_manager,
+            animation_manager: Arc::new(Mutex::new(animation_manager::AnimationManager::new())),
+        }))
+    }
+
+    fn quad() -> Arc<Mutex<mesh::Mesh>>{
+        let mut quad = mesh::Mesh::new_cpu_only("quad");
+        let vertex = |x: f32, y: f32| mesh::Vertex::new([x, y, 0.0], [0.0; 2], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0, 1.0], [1.0; 4]);
+        quad.set_cpu_vertices_and_indices(
+            vec![vertex(-1.0, -1.0), vertex(1.0, -1.0), vertex(1.0, 1.0), vertex(-1.0, 1.0)],
+            vec![0, 1, 2, 0, 2, 3]
+        );
+        quad.set_bound(Point3::new(-1.0, -1.0, 0.0), Point3::new(1.0, 1.0, 0.0));
+        Arc::new(Mutex::new(quad))
+    }
+
+    ///Adds an empty at `transform` with `value` as its child, like the importer creates them.
+    /// `turn` is the rotation the importer adds to cameras and lights.
+    fn add_holder(
+        tree: &mut tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>,
+        name: &str,
+        value: content::ContentType,
+        transform: NodeTransform,
+        turn: Quaternion<f32>,
+    ){
+        let mut holder_attrib = attributes::NodeAttributes::default();
+        holder_attrib.transform = transform;
+        let holder = tree.add_at_root(content::ContentType::Empty(empty::Empty::new(name)), Some(holder_attrib))
+        .expect("failed to add holder");
+
+        let mut value_attrib = attributes::NodeAttributes::default();
+        value_attrib.transform = transform;
+        value_attrib.transform.rot = transform.rot * turn;
+        tree.add(value, holder, Some(value_attrib)).expect("failed to add value");
+    }
+
+    ///A scene with a mesh, a camera and one light of each type, each in its own empty
+    fn test_scene() -> tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>{
+        let (settings, key_map) = settings_and_key_map();
+        let mut tree = tree::Tree::new(
+            content::ContentType::Empty(empty::Empty::new("export_test")),
+            attributes::NodeAttributes::default()
+        );
+        let no_turn = Quaternion::from_angle_y(Deg(0.0));
+        let light_turn = Quaternion::from_angle_y(Deg(90.0));
+
+        add_holder(&mut tree, "mesh", content::ContentType::Mesh(quad()), NodeTransform::new(
+            Vector3::new(1.0, 2.0, 3.0), Quaternion::from_angle_x(Deg(30.0)), Vector3::new(1.0, 2.0, 1.0)
+        ), no_turn);
+        add_holder(&mut tree, "camera", content::ContentType::Camera(DefaultCamera::new(settings, key_map)), NodeTransform::new(
+            Vector3::new(0.0, 1.0, -5.0), Quaternion::from_angle_y(Deg(20.0)), Vector3::new(1.0, 1.0, 1.0)
+        ), Quaternion::from_angle_y(Deg(180.0)));
+        add_holder(&mut tree, "point", content::ContentType::PointLight(light::LightPoint::new("point")), NodeTransform::new(
+            Vector3::new(-2.0, 3.0, 0.0), Quaternion::from_angle_z(Deg(45.0)), Vector3::new(1.0, 1.0, 1.0)
+        ), light_turn);
+        add_holder(&mut tree, "spot", content::ContentType::SpotLight(light::LightSpot::new("spot")), NodeTransform::new(
+            Vector3::new(4.0, 0.0, 1.0), Quaternion::from_angle_x(Deg(-60.0)), Vector3::new(1.0, 1.0, 1.0)
+        ), light_turn);
+        add_holder(&mut tree, "sun", content::ContentType::DirectionalLight(light::LightDirectional::new("sun")), NodeTransform::new(
+            Vector3::new(0.0, 10.0, 0.0), Quaternion::from_angle_x(Deg(-45.0)), Vector3::new(1.0, 1.0, 1.0)
+        ), light_turn);
+
+        tree.rebuild_bounds();
+        tree
+    }
+
+    fn only_transform(
+        tree: &tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>,
+        value_type: ValueTypeBool,
+    ) -> NodeTransform{
+        let nodes = tree.copy_all_nodes(&Some(SceneComparer::new().with_value_type(value_type)));
+        assert_eq!(nodes.len(), 1);
+        nodes[0].get_attrib().transform
+    }
+
+    fn assert_close(a: &NodeTransform, b: &NodeTransform){
+        assert!((a.disp - b.disp).magnitude() < 1.0e-4, "{:?} != {:?}", a, b);
+        assert!((a.scale - b.scale).magnitude() < 1.0e-4, "{:?} != {:?}", a, b);
+        assert!(a.rot.dot(b.rot).abs() > 0.9999, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn export_and_import_keep_nodes_and_transforms(){
+        let scene = test_scene();
+        let path = ::std::env::temp_dir().join("jakar_gltf_export_round_trip.gltf");
+        let path = path.to_string_lossy().into_owned();
+        export_gltf(&scene, &path).expect("failed to export scene");
+
+        let loaded = gltf_importer::load_gltf(&path, "round_trip", headless_managers(), None)
+        .expect("failed to load exported scene");
+
+        //the importer adds a node per gltf scene below the root
+        assert_eq!(
+            loaded.get_all_names(&None).len(),
+            scene.get_all_names(&None).len() + 1
+        );
+
+        let value_types = vec![
+            ValueTypeBool::none().with_mesh(),
+            ValueTypeBool::none().with_camera(),
+            ValueTypeBool::none().with_point_light(),
+            ValueTypeBool::none().with_spot_light(),
+            ValueTypeBool::none().with_directional_light(),
+        ];
+        for value_type in value_types{
+            assert_close(&only_transform(&loaded, value_type.clone()), &only_transform(&scene, value_type));
+        }
+    }
+}
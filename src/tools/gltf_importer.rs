@@ -1,4 +1,4 @@
-use core::resources::{texture, material, empty, mesh};
+use core::resources::{texture, material, empty, mesh, light};
 //use core::simple_scene_system::node;
 use jakar_tree::*;
 use core::next_tree::*;
@@ -6,6 +6,8 @@ use jakar_tree::node::{Attribute};
 
 use core;
 use core::ReturnBoundInfo;
+use core::asset_error::AssetError;
+use core::resource_management::load_handle;
 use render::pipeline_builder;
 use render::pipeline_manager;
 use render::render_passes::{RenderPassConf, ObjectPassSubPasses};
@@ -16,9 +18,15 @@ use cgmath::*;
 
 use gltf;
 use gltf_importer;
+use gltf_utils;
+use serde_json;
 
 use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::f32::consts;
 
 
 ///Imports a gltf texture
@@ -28,40 +36,79 @@ pub fn load_gltf_texture(
     buffers: &gltf_importer::Buffers,
     base: &Path,
     managers: &Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>,
-) -> Arc<texture::Texture>
+) -> Result<Arc<texture::Texture>, AssetError>
+{
+    let texture_manager = {
+        let managers_lck = managers.lock().expect("failed to lock managers struct");
+        (*managers_lck).texture_manager.clone().expect("textures can't be loaded without a gpu")
+    };
+    //lock the texture manager once to get some data, the lock is released before the image
+    // is read, so a broken image can't poison the manager
+    let (device, queue) = {
+        let texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+        ((*texture_manager_lck).get_device(), (*texture_manager_lck).get_queue())
+    };
+
+    let new_texture = build_gltf_texture(texture, &name, buffers, base, device, queue)?;
+    //now add a copy to the manager and return the other one
+    {
+        let mut texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+        let tex_error = (*texture_manager_lck).add_texture(new_texture.clone());
+        match tex_error{
+            Ok(_) => {}, //everything allright while adding
+            Err(r) => println!("WARNING: GLTF_IMPORTER: failed to add texture to manager: {}", r),
+        }
+    }
+    //finally return the new texture
+    Ok(new_texture)
+}
+
+///Decodes a gltf texture with its sampler settings, without adding it to the texture manager
+fn build_gltf_texture(
+    texture: &gltf::Texture,
+    name: &str,
+    buffers: &gltf_importer::Buffers,
+    base: &Path,
+    device: Arc<vulkano::device::Device>,
+    queue: Arc<vulkano::device::Queue>,
+) -> Result<Arc<texture::Texture>, AssetError>
 {
     //The texture can be a buffer or an external file, depending on the case we load the texture
-    //wrap it into an Arc<Texture>, then add it to the manager once and return the other one
     //first create a texture builder and configure it with the right sampler from the provided texture
     let mut texture_builder = {
-        let texture_manager = {
-            let managers_lck = managers.lock().expect("failed to lock managers struct");
-            (*managers_lck).texture_manager.clone()
-        };
-        //lock the texture manager once to get some data
-        let texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
         //No create the textuer builder based on the type of data
         match texture.source().data(){
 
             gltf::image::Data::View{view, mime_type} => {
                 //found a data buffer for the image
                 println!("Image is Data", );
-                let data = buffers.view(&view).expect("failed to load image data from gltf buffer");
+                let data = match buffers.view(&view){
+                    Some(data) => data,
+                    None => return Err(AssetError::decode(
+                        name, "the buffer view of the image is missing"
+                    )),
+                };
                 //we got the data, lets provide it to a TextureBuilder
                 texture::TextureBuilder::from_data(
                     data.to_vec(),
-                    (*texture_manager_lck).get_device(),
-                    (*texture_manager_lck).get_queue(),
+                    device,
+                    queue,
                 )
             },
             gltf::image::Data::Uri{uri, mime_type} =>{
                 println!("Image is file at: {}", uri);
                 //prepare the path
                 let path = base.join(uri);
+                let path_str = match path.to_str(){
+                    Some(path_str) => path_str,
+                    None => return Err(AssetError::decode(
+                        name, &format!("the image path {:?} is not valid unicode", path)
+                    )),
+                };
                 texture::TextureBuilder::from_image(
-                    path.to_str().expect("failed to create string from path"),
-                    (*texture_manager_lck).get_device(),
-                    (*texture_manager_lck).get_queue(),
+                    path_str,
+                    device,
+                    queue,
                 )
             }
         }
@@ -137,22 +184,46 @@ pub fn load_gltf_texture(
 
 
     //finally build the texture
-    let new_texture = texture_builder.build_with_name(&name);
-    //now add a copy to the manager and return the other one
-    {
-        let texture_manager = {
-            let managers_lck = managers.lock().expect("failed to lock managers struct");
-            (*managers_lck).texture_manager.clone()
-        };
-        let mut texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
-        let tex_error = (*texture_manager_lck).add_texture(new_texture.clone());
-        match tex_error{
-            Ok(_) => {}, //everything allright while adding
-            Err(r) => println!("failed to add texture to manager while loading gltf: {}", r),
+    texture_builder.build_with_name(name)
+}
+
+///Loads the texture of a material slot, a texture which can't be loaded is logged and left out,
+/// the material uses the fallback texture for it then.
+fn load_gltf_slot_texture(
+    texture: &gltf::Texture,
+    name: String,
+    buffers: &gltf_importer::Buffers,
+    base: &Path,
+    managers: &Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>,
+) -> Option<Arc<texture::Texture>>{
+    match load_gltf_texture(texture, name, buffers, base, managers){
+        Ok(texture) => Some(texture),
+        Err(er) => {
+            println!("WARNING: GLTF_IMPORTER: {}, using the fallback texture", er);
+            None
         }
     }
-    //finally return the new texture
-    new_texture
+}
+
+///Reads the factors of `mat`, the emission is allowed to be brighter if the material has an
+/// emissive texture or factor.
+fn get_gltf_material_factors(mat: &gltf::Material, has_emissive_texture: bool) -> material::MaterialFactors{
+    let pbr = mat.pbr_metallic_roughness();
+    //if we have a emmision map and and/or a factor we'll also crank that up too max 100
+    let mut max_factor = 1.0;
+    if has_emissive_texture || mat.emissive_factor()[0] > 0.0 || mat.emissive_factor()[1] > 0.0 || mat.emissive_factor()[2] > 0.0{
+        max_factor = 5.0;
+    }
+
+    material::MaterialFactors::new()
+    .with_factor_albedo(pbr.base_color_factor())
+    .with_factor_normal(mat.normal_texture().map_or(1.0, |t| t.scale()))
+    .with_factor_metal(pbr.metallic_factor())
+    .with_factor_roughness(pbr.roughness_factor())
+    .with_factor_occlusion(mat.occlusion_texture().map_or(1.0, |t| t.strength()))
+    .with_factor_emissive(mat.emissive_factor())
+    .with_max_emmision(max_factor)
+    .with_alpha_cutoff(mat.alpha_cutoff())
 }
 
 ///Imports a gltf material, returns the loaded material from the manager
@@ -170,10 +241,8 @@ pub fn load_gltf_material(
     let albedo = {
         match pbr.base_color_texture(){
             Some(t) => {
-                Some(
-                    load_gltf_texture(
+                load_gltf_slot_texture(
                     &t.texture(), material_name.clone() + "_albedo", buffers, base, managers
-                    )
                 )
             },
             None => None,
@@ -183,10 +252,8 @@ pub fn load_gltf_material(
     let normal = {
         match mat.normal_texture(){
             Some(t) => {
-                Some(
-                    load_gltf_texture(
+                load_gltf_slot_texture(
                     &t.texture(), material_name.clone() + "_normal", buffers, base, managers
-                    )
                 )
             },
             None => None,
@@ -196,10 +263,8 @@ pub fn load_gltf_material(
     let metallic_roughness = {
         match pbr.metallic_roughness_texture(){
             Some(t) => {
-                Some(
-                    load_gltf_texture(
+                load_gltf_slot_texture(
                     &t.texture(), material_name.clone() + "_met_rough", buffers, base, managers
-                    )
                 )
             },
             None => None,
@@ -209,10 +274,8 @@ pub fn load_gltf_material(
     let occlusion = {
         match mat.occlusion_texture(){
             Some(t) => {
-                Some(
-                    load_gltf_texture(
+                load_gltf_slot_texture(
                     &t.texture(), material_name.clone() + "_occlu", buffers, base, managers
-                    )
                 )
             },
             None => None,
@@ -222,10 +285,8 @@ pub fn load_gltf_material(
     let emissive = {
         match mat.emissive_texture(){
             Some(t) => {
-                Some(
-                    load_gltf_texture(
+                load_gltf_slot_texture(
                     &t.texture(), material_name.clone() + "_emissive", buffers, base, managers
-                    )
                 )
             },
             None => None,
@@ -233,28 +294,11 @@ pub fn load_gltf_material(
     };
 
     //We also need the texture factors
-
-    //if we have a emmision map and and/or a factor we'll also crank that up too max 100
-    let mut max_factor = 1.0;
-    if emissive.is_some() || mat.emissive_factor()[0] > 0.0 || mat.emissive_factor()[1] > 0.0 || mat.emissive_factor()[2] > 0.0{
-        max_factor = 5.0;
-    }
-
-    let texture_factors = {
-        material::MaterialFactors::new()
-        .with_factor_albedo(pbr.base_color_factor())
-        .with_factor_normal(mat.normal_texture().map_or(1.0, |t| t.scale()))
-        .with_factor_metal(pbr.metallic_factor())
-        .with_factor_roughness(pbr.roughness_factor())
-        .with_factor_occlusion(mat.occlusion_texture().map_or(1.0, |t| t.strength()))
-        .with_factor_emissive(mat.emissive_factor())
-        .with_max_emmision(max_factor)
-        .with_alpha_cutoff(mat.alpha_cutoff())
-    };
+    let texture_factors = get_gltf_material_factors(mat, emissive.is_some());
     //get the manager
     let texture_manager = {
         let managers_lck = managers.lock().expect("failed to lock managers struct");
-        (*managers_lck).texture_manager.clone()
+        (*managers_lck).texture_manager.clone().expect("textures can't be loaded without a gpu")
     };
 
     let fallback_texture = {
@@ -326,13 +370,13 @@ pub fn load_gltf_material(
         //get the device we are on
         let device = {
             let managers_lck = managers.lock().expect("failed to lock managers struct");
-            (*managers_lck).device.clone()
+            (*managers_lck).device.clone().expect("materials can't be loaded without a gpu")
         };
 
         //get the manager
         let pipeline_manager = {
             let managers_lck = managers.lock().expect("failed to lock managers struct");
-            (*managers_lck).pipeline_manager.clone()
+            (*managers_lck).pipeline_manager.clone().expect("materials can't be loaded without a gpu")
         };
 
         //Get the pipeline based on the needs of this material
@@ -345,7 +389,7 @@ pub fn load_gltf_material(
 
         let uniform_manager = {
             let managers_lck = managers.lock().expect("failed to lock managers struct");
-            (*managers_lck).uniform_manager.clone()
+            (*managers_lck).uniform_manager.clone().expect("materials can't be loaded without a gpu")
         };
 
         (pipeline, uniform_manager, device)
@@ -355,11 +399,18 @@ pub fn load_gltf_material(
     let final_material = material_builder.build(&material_name, pipeline, uniform_manager, device);
     let material_manager = {
         let managers_lck = managers.lock().expect("failed to lock managers struct");
-        (*managers_lck).material_manager.clone()
+        (*managers_lck).material_manager.clone().expect("materials can't be loaded without a gpu")
     };
 
     //now add a copy to the manager and return the name
     let mut material_manager_lck = material_manager.lock().expect("failed to lock material manager");
+    let final_material = match final_material{
+        Ok(material) => material,
+        Err(er) => {
+            println!("WARNING: GLTF_IMPORTER: {}, using the fallback material", er);
+            return (*material_manager_lck).get_default_material();
+        }
+    };
     //Add it and return its
     //println!("Finished loading material with name: {}", material_name);
     let name = (*material_manager_lck).add_material(final_material);
@@ -368,12 +419,183 @@ pub fn load_gltf_material(
     (*material_manager_lck).get_material(&name)
 }
 
+///The vertex data of a gltf primitive
+struct GltfPrimitiveData {
+    vertices: Vec<mesh::Vertex>,
+    indices: Vec<u32>,
+    min: Point3<f32>,
+    max: Point3<f32>,
+    //The joints and weights per vertex, empty if the primitive has none
+    skin: Vec<mesh::SkinVertex>,
+}
+
+///Reads the vertices, indices, bounds and skin of `primitive`
+fn read_gltf_primitive(primitive: &gltf::Primitive, buffers: &gltf_importer::Buffers) -> Result<GltfPrimitiveData, AssetError>{
+    use gltf_utils::PrimitiveIterators; //from the three crate
+
+    //Check for indices, if there are some, use them
+    let mut indices: Vec<u32> = {
+        let ins = PrimitiveIterators::indices(primitive, buffers);
+        match ins{
+            Some(indices) => {
+                indices.into_u32().map(|x| x.into()).collect()
+            },
+            None => Vec::new()
+        }
+    };
+
+    //position
+    let mut positions: Vec<[f32; 3]> = match primitive.positions(buffers){
+        Some(iter) => iter.map(|x| x.into()).collect(),
+        None => return Err(AssetError::decode(
+            &format!("primitive {}", primitive.index()), "the primitive has no positions"
+        )),
+    };
+    //normal
+    let mut normals: Vec<[f32; 3]> = if let Some(iter) = primitive.normals(buffers) {
+        iter.map(|x| x.into()).collect()
+    } else {
+        Vec::new()
+    };
+    //tangents
+    let mut tangents: Vec<[f32; 4]> = if let Some(iter) = primitive.tangents(buffers) {
+        iter.map(|x| x.into()).collect()
+    } else {
+        Vec::new()
+    };
+
+    //TexCoord
+    let mut tex_coords: Vec<[f32; 2]> = {
+        match primitive.tex_coords(0, buffers){
+            Some(coords) => {
+                coords.into_f32().map(|x| x.into()).collect()
+            },
+            None => Vec::new()
+        }
+    };
+
+    //verte color
+    let mut vertex_colors: Vec<[f32; 4]> = {
+        match primitive.colors(0, buffers){
+            Some(colors) => {
+                colors.into_rgba_f32().map(|x| x.into()).collect()
+            },
+            None => Vec::new()
+        }
+    };
+
+    //joints and weights, only used if the node has a skin
+    let joints: Vec<[u32; 4]> = {
+        match primitive.joints(0, buffers){
+            Some(joints) => {
+                joints.into_u16().map(|j| [j[0] as u32, j[1] as u32, j[2] as u32, j[3] as u32]).collect()
+            },
+            None => Vec::new()
+        }
+    };
+
+    let weights: Vec<[f32; 4]> = {
+        match primitive.weights(0, buffers){
+            Some(weights) => {
+                weights.into_f32().collect()
+            },
+            None => Vec::new()
+        }
+    };
+
+    let mut vertices = Vec::new();
+
+    //Have to update vectors to be as long as the positions
+    if positions.len() != tex_coords.len(){
+        tex_coords = vec![[0.0, 0.0]; positions.len()];
+    }
+    if positions.len() != normals.len(){
+        normals = vec![[0.0, 0.0, 0.0]; positions.len()];
+    }
+    if positions.len() != tangents.len(){
+        tangents = vec![[0.0, 0.0, 0.0, 0.0]; positions.len()];
+    }
+    if positions.len() != vertex_colors.len(){
+        vertex_colors = vec![[0.0, 0.0, 0.0, 1.0]; positions.len()];
+    }
+
+    //after getting all the mesh informations, we have to find the mins and maxs of this mesh
+    //to construct a static bound for it.
+    let (mins, maxs) = {
+        let mut min: [f32; 3] = [0.0; 3];
+        let mut max: [f32; 3] = [0.0; 3];
+
+        for position in positions.iter(){
+            //X val
+            //min
+            if position[0] < min[0]{
+                min[0] = position[0];
+            }
+            //max
+            if position[0] > max[0]{
+                max[0] = position[0];
+            }
+
+
+            //Y val
+            //min
+            if position[1] < min[1]{
+                min[1] = position[1];
+            }
+            //max
+            if position[1] > max[1]{
+                max[1] = position[1];
+            }
+            //Z val
+            //min
+            if position[2] < min[2]{
+                min[2] = position[2];
+            }
+            //max
+            if position[2] > max[2]{
+                max[2] = position[2];
+            }
+        }
+
+        (min,max)
+    };
+
+    for i in 0..positions.len(){
+        let vertex = mesh::Vertex::new(
+            positions[i],
+            tex_coords[i],
+            normals[i],
+            tangents[i],
+            vertex_colors[i],
+        );
+        vertices.push(vertex);
+    }
+
+    let skin = if joints.len() == positions.len() && weights.len() == positions.len(){
+        joints.iter().zip(weights.iter())
+        .map(|(j, w)| mesh::SkinVertex::new(*j, *w))
+        .collect()
+    }else{
+        Vec::new()
+    };
+
+    Ok(GltfPrimitiveData{
+        vertices: vertices,
+        indices: indices,
+        min: Point3::new(mins[0], mins[1], mins[2]),
+        max: Point3::new(maxs[0], maxs[1], maxs[2]),
+        skin: skin,
+    })
+}
+
 ///Loads gltf primitves in an Vec<mesh::Mesh> and adds them to the managers as well as their textures
 pub fn load_gltf_mesh(
     scene_name: String,
     mesh: &gltf::Mesh,
+    skeleton_name: Option<&String>, //the skeleton of the skin used by the node of this mesh
     buffers: &gltf_importer::Buffers,
     base: &Path,
+    source: &Path, //the gltf file, remembered by the meshes to reload them
     managers: &Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>,
 ) -> Vec<Arc<Mutex<mesh::Mesh>>>{
 
@@ -383,55 +605,13 @@ pub fn load_gltf_mesh(
     let mut primitive_index = 0;
     //now cycle through all primitives, load the mesh and maybe the material
     for primitive in mesh.primitives(){
-        use gltf_utils::PrimitiveIterators; //from the three crate
-
-        //Check for indices, if there are some, use them
-        let mut indices: Vec<u32> = {
-            let ins = PrimitiveIterators::indices(&primitive, buffers);
-            match ins{
-                Some(indices) => {
-                    indices.into_u32().map(|x| x.into()).collect()
-                },
-                None => Vec::new()
-            }
-        };
-
-        //position
-        let mut positions: Vec<[f32; 3]> = primitive
-            .positions(buffers)
-            .unwrap()
-            .map(|x| x.into())
-            .collect();
-        //normal
-        let mut normals: Vec<[f32; 3]> = if let Some(iter) = primitive.normals(buffers) {
-            iter.map(|x| x.into()).collect()
-        } else {
-            Vec::new()
-        };
-        //tangents
-        let mut tangents: Vec<[f32; 4]> = if let Some(iter) = primitive.tangents(buffers) {
-            iter.map(|x| x.into()).collect()
-        } else {
-            Vec::new()
-        };
-
-        //TexCoord
-        let mut tex_coords: Vec<[f32; 2]> = {
-            match primitive.tex_coords(0, buffers){
-                Some(coords) => {
-                    coords.into_f32().map(|x| x.into()).collect()
-                },
-                None => Vec::new()
-            }
-        };
-
-        //verte color
-        let mut vertex_colors: Vec<[f32; 4]> = {
-            match primitive.colors(0, buffers){
-                Some(colors) => {
-                    colors.into_rgba_f32().map(|x| x.into()).collect()
-                },
-                None => Vec::new()
+        //A broken primitive is left out, the index still counts it to keep the names and sources right
+        let primitive_data = match read_gltf_primitive(&primitive, buffers){
+            Ok(data) => data,
+            Err(er) => {
+                println!("WARNING: GLTF_IMPORTER: skipping a primitive of {}: {}", scene_name, er);
+                primitive_index += 1;
+                continue;
             }
         };
 
@@ -447,98 +627,57 @@ pub fn load_gltf_mesh(
             managers_lck.queue.clone()
         };
 
-        //get the fallback material for the mesh creation, if there is another materail set for
-        // this mesh it will be created further down and be set.
-        let fallback_material = {
-            let material_manager = {
-                let manager_lck = managers.lock().expect("failed to lock managers");
-                (*manager_lck).material_manager.clone()
-            };
+        //If there is no device we are headless and only keep the mesh on the cpu
+        let mut add_mesh = match device{
+            Some(ref device) => {
+                //get the fallback material for the mesh creation, if there is another materail set for
+                // this mesh it will be created further down and be set.
+                let fallback_material = {
+                    let material_manager = {
+                        let manager_lck = managers.lock().expect("failed to lock managers");
+                        (*manager_lck).material_manager.clone().expect("materials can't be loaded without a gpu")
+                    };
 
-            let mut material_manager_lck = material_manager.lock().expect("failed to lock material manager");
-            (*material_manager_lck).get_default_material()
-        };
+                    let mut material_manager_lck = material_manager.lock().expect("failed to lock material manager");
+                    (*material_manager_lck).get_default_material()
+                };
 
-        let mut add_mesh = mesh::Mesh::new(
-            &mesh_name,
-            device.clone(),
-            fallback_material
-        );
+                mesh::Mesh::new(
+                    &mesh_name,
+                    device.clone(),
+                    fallback_material
+                )
+            },
+            None => mesh::Mesh::new_cpu_only(&mesh_name),
+        };
         //create a dummy and fill it
-        let mut vertices = Vec::new();
-
-        //Have to update vectors to be as long as the positions
-        if positions.len() != tex_coords.len(){
-            tex_coords = vec![[0.0, 0.0]; positions.len()];
-        }
-        if positions.len() != normals.len(){
-            normals = vec![[0.0, 0.0, 0.0]; positions.len()];
-        }
-        if positions.len() != tangents.len(){
-            tangents = vec![[0.0, 0.0, 0.0, 0.0]; positions.len()];
-        }
-        if positions.len() != vertex_colors.len(){
-            vertex_colors = vec![[0.0, 0.0, 0.0, 1.0]; positions.len()];
-        }
-
-        //after getting all the mesh informations, we have to find the mins and maxs of this mesh
-        //to construct a static bound for it.
-        let (mins, maxs) = {
-            let mut min: [f32; 3] = [0.0; 3];
-            let mut max: [f32; 3] = [0.0; 3];
-
-            for position in positions.iter(){
-                //X val
-                //min
-                if position[0] < min[0]{
-                    min[0] = position[0];
-                }
-                //max
-                if position[0] > max[0]{
-                    max[0] = position[0];
-                }
-
-
-                //Y val
-                //min
-                if position[1] < min[1]{
-                    min[1] = position[1];
-                }
-                //max
-                if position[1] > max[1]{
-                    max[1] = position[1];
-                }
-                //Z val
-                //min
-                if position[2] < min[2]{
-                    min[2] = position[2];
-                }
-                //max
-                if position[2] > max[2]{
-                    max[2] = position[2];
+        //write new vertices as well as indices to mesh
+        match queue{
+            Some(queue) => {
+                if let Err(er) = add_mesh.set_vertices_and_indices(primitive_data.vertices, primitive_data.indices, queue){
+                    println!("WARNING: GLTF_IMPORTER: skipping {}: {}", mesh_name, er);
+                    primitive_index += 1;
+                    continue;
                 }
-            }
-
-            (min,max)
-        };
-
-        for i in 0..positions.len(){
-            let vertex = mesh::Vertex::new(
-                positions[i],
-                tex_coords[i],
-                normals[i],
-                tangents[i],
-                vertex_colors[i],
-            );
-            vertices.push(vertex);
+            },
+            None => add_mesh.set_cpu_vertices_and_indices(primitive_data.vertices, primitive_data.indices),
         }
-        //write new vertices as well as indices to mesh
-        add_mesh.set_vertices_and_indices(vertices, indices, queue);
-        //TODO SETUP BOUNDS
-        add_mesh.set_bound(
-            Point3::new(mins[0], mins[1], mins[2]),
-            Point3::new(maxs[0], maxs[1], maxs[2])
-        );
+        add_mesh.set_bound(primitive_data.min, primitive_data.max);
+        //remember the primitive to reload it if the file changes
+        add_mesh.set_source(mesh::MeshSource{
+            path: source.to_string_lossy().into_owned(),
+            mesh_index: mesh.index(),
+            primitive_index: primitive_index,
+        });
+
+        //Setup the skin if the node has a skeleton and the primitive has joints and weights
+        let is_skinned = match skeleton_name{
+            Some(skeleton) if !primitive_data.skin.is_empty() => {
+                add_mesh.set_skin(primitive_data.skin, skeleton);
+                true
+            },
+            _ => false,
+        };
 
 
         //look for materials
@@ -546,6 +685,8 @@ pub fn load_gltf_mesh(
         //test if its the default material if not, test if this material si alread in the scene
         println!("SORTING MATERIAL: ", );
         match mesh_material.index(){
+            //Cpu only meshes have no material at all
+            _ if !add_mesh.has_material() => {},
             None => {
                 //is the default material, we can leave the mesh material like it is
                 //println!("\tIs using default material ... ", );
@@ -560,7 +701,7 @@ pub fn load_gltf_mesh(
                     //first check if there is already a material with this name, if not create one
                     let material_manager = {
                         let managers_lck = managers.lock().expect("failed to lock managers struct");
-                        (*managers_lck).material_manager.clone()
+                        (*managers_lck).material_manager.clone().expect("materials can't be loaded without a gpu")
                     };
 
                     //It has a material, check if its alread in the material manager by name
@@ -576,7 +717,7 @@ pub fn load_gltf_mesh(
                     //lock the material manager
                     let material_manager = {
                         let managers_lck = managers.lock().expect("failed to lock managers struct");
-                        (*managers_lck).material_manager.clone()
+                        (*managers_lck).material_manager.clone().expect("materials can't be loaded without a gpu")
                     };
 
                     let mut material_manager_lck = material_manager
@@ -607,6 +748,15 @@ pub fn load_gltf_mesh(
 
         let mut mesh_manager_lck = mesh_manager.lock().expect("failed to lock mesh manager in gltf loader");
         (*mesh_manager_lck).add_arc_mesh(arc_mesh.clone());
+
+        if is_skinned{
+            let animation_manager = {
+                let managers_lck = managers.lock().expect("failed to lock managers struct");
+                (*managers_lck).animation_manager.clone()
+            };
+            let mut animation_manager_lck = animation_manager.lock().expect("failed to lock animation manager");
+            animation_manager_lck.add_skinned_mesh(arc_mesh.clone());
+        }
         //pushing to the return vector, continueing with the other meshes
         return_vec.push(arc_mesh);
         //adding one to the index for naming the new mesh
@@ -620,12 +770,14 @@ pub fn load_gltf_mesh(
 pub fn load_gltf_node(
     gltf_node: &gltf::Node, //used to reference gltf stuff
     scene_name: &String, //used to generate a short, but unique name from the node id
-    parent_transform: Option<Decomposed<Vector3<f32>, Quaternion<f32>>>, //used to construct the initial location of self
+    parent_transform: Option<transform::NodeTransform>, //used to construct the initial location of self
     parent_node_name: &String, //used to add the node in the tree
     tree: &mut tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>, //the actual tree
     buffers: &gltf_importer::Buffers, //the buffers to read the gltf info from
     base: &Path, //the base path from the node
+    source: &Path, //the loaded gltf file
     managers: &Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>, //teh manager to add textures etc to.
+    lights: &PunctualLights, //the lights of the KHR_lights_punctual extension
 )
 {
     //creates the new name, based on the indice
@@ -635,19 +787,11 @@ pub fn load_gltf_node(
     //println!("Created node: {}", new_name.clone());
     //get the transform of this node
     let node_transform = {
-        let mut new_transform: Decomposed<Vector3<f32>, Quaternion<f32>> = Decomposed::one();
-
-        //if we got a parent transform we can add it to the current transform, if not we have to leave it
+        //if we got a parent transform we have to apply ours in its space, if not we are at the root
         let offset_transform = {
             match parent_transform {
                 Some(trans) => trans,
-                //if there is no extra transform make all of them 0.0
-                None => Decomposed{
-                    scale: 1.0,
-                    rot: Quaternion::zero(),
-                    disp: Vector3::new(0.0, 0.0, 0.0),
-                }
-
+                None => transform::NodeTransform::one(),
             }
         };
 
@@ -656,38 +800,34 @@ pub fn load_gltf_node(
         //According to the gltf crate the decomposed is (translation, rotation, scale).
         //translation is the 0th field of decomposed with 3 elements
         let translation = Vector3::new(
-            node_transform.0[0] + offset_transform.disp.x,
-            node_transform.0[1] + offset_transform.disp.y,
-            node_transform.0[2] + offset_transform.disp.z
+            node_transform.0[0],
+            node_transform.0[1],
+            node_transform.0[2]
         );
         //The 1th element is rotation and rotation is in the format of [w,x,y,z]
         //the rotation in gltf is saved as x,y,z,w while in cgmath its w,x,y,z, therefore we need to change
-        let rotation = {
-            let tmp_rot = Quaternion::new(
-                node_transform.1[3],
-                node_transform.1[0],
-                node_transform.1[1],
-                node_transform.1[2]
-            );
-            tmp_rot //+ offset_transform.rot
-        };
+        let rotation = Quaternion::new(
+            node_transform.1[3],
+            node_transform.1[0],
+            node_transform.1[1],
+            node_transform.1[2]
+        );
 
-        //NOTE: Scale is currently only linear in one direction, this might be changed in future to
-        //be comformant to the gltf2.0 rules
-        let scale = {
-            node_transform.2[0]  * offset_transform.scale //is currently only the x value
-        };
+        //The scale is saved per axis
+        let scale = Vector3::new(
+            node_transform.2[0],
+            node_transform.2[1],
+            node_transform.2[2]
+        );
         /*
         println!("Node Transfrom:", );
         println!("\t Translation: {}, {}, {}", translation.x, translation.y, translation.z);
         println!("\t Rotation   : {}, {}, {}, {}", rotation.v.x, rotation.v.y, rotation.v.z, rotation.s);
-        println!("\t Scale      : {}", scale);
+        println!("\t Scale      : {:?}", scale);
         */
-        //update the transform
-        new_transform.scale = scale;
-        new_transform.disp = translation;
-        new_transform.rot = rotation;
-        new_transform
+        let local_transform = transform::NodeTransform::new(translation, rotation, scale);
+        //the tree stores world space transforms
+        offset_transform.concat(&local_transform)
     };
 
     //now create the node as an empty, this empty will be the parent to:
@@ -705,11 +845,14 @@ pub fn load_gltf_node(
         Some(mesh) =>{
             //println!("Found mesh in node: {}", new_name.clone());
             //load the primitves as an Vec<mesh::Mesh>
+            let skeleton_name = gltf_node.skin().map(|skin| get_skeleton_name(scene_name, &skin));
             let primitives = load_gltf_mesh(
                 scene_name.clone(),
                 &mesh,
+                skeleton_name.as_ref(),
                 &buffers,
                 base,
+                source,
                 managers,
             );
             //println!("Finished loading mesh from gltf, adding to node...", );
@@ -734,7 +877,9 @@ pub fn load_gltf_node(
                     // that the bound won't change till the next rebuild.
                     let mesh_lck = prim.lock().expect("failed to lock the mesh while importing");
 
-                    let transparent = {
+                    let transparent = if !mesh_lck.has_material(){
+                        false
+                    }else{
 
                         let material = mesh_lck
                         .get_material();
@@ -771,10 +916,30 @@ pub fn load_gltf_node(
     }
 
     //check for Camera
-    //TODO
+    match gltf_node.camera(){
+        Some(camera) => {
+            let camera_value = load_gltf_camera(&camera, managers);
+            let mut camera_attrib = attributes::NodeAttributes::default();
+            //gltf cameras look along -z, ours along +z
+            camera_attrib.transform = node_transform;
+            camera_attrib.transform.rot = node_transform.rot * Quaternion::from_angle_y(Deg(180.0));
+            let _ = tree.add(camera_value, new_name.clone(), Some(camera_attrib));
+        },
+        None => {},
+    }
 
-    //cycle to children based on own root node as parent
-    //TODO
+    //check for a light of the KHR_lights_punctual extension
+    match lights.get_node_light(gltf_node.index()){
+        Some(gltf_light) => {
+            let light_value = gltf_light.to_content(&(new_name.clone() + "_light"));
+            let mut light_attrib = attributes::NodeAttributes::default();
+            //gltf lights shine along -z, ours along +x
+            light_attrib.transform = node_transform;
+            light_attrib.transform.rot = node_transform.rot * Quaternion::from_angle_y(Deg(90.0));
+            let _ = tree.add(light_value, new_name.clone(), Some(light_attrib));
+        },
+        None => {},
+    }
 
     //after adding everything to the current node, have a look for children, if there are any,
     //iterate through them, always create a node, load it and add it to the current parent
@@ -788,34 +953,531 @@ pub fn load_gltf_node(
                 tree,
                 buffers,
                 base,
+                source,
                 managers,
+                lights,
             );
         }
     }
 }
 
-///Imports a scene from the file at `path`
+///Creates a camera from the gltf camera. Only the field of view is used, the near and far plane
+/// are global engine settings. Orthographic cameras are not supported and imported as perspective
+/// cameras.
+pub fn load_gltf_camera(
+    gltf_camera: &gltf::Camera,
+    managers: &Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>,
+) -> content::ContentType{
+    let mut camera = {
+        let scene_manager = {
+            let managers_lck = managers.lock().expect("failed to lock managers struct");
+            (*managers_lck).scene_manager.clone()
+        };
+        let scene_manager_lck = scene_manager.lock().expect("failed to lock scene manager");
+        scene_manager_lck.new_camera()
+    };
+
+    match gltf_camera.projection(){
+        gltf::camera::Projection::Perspective(perspective) => {
+            //gltf saves the vertical fov in radians
+            camera.set_fov(Deg::from(Rad(perspective.yfov())).0);
+        },
+        _ => {
+            println!("WARNING: GLTF_IMPORTER: orthographic cameras are not supported, using a perspective camera");
+        }
+    }
+
+    content::ContentType::Camera(camera)
+}
+
+///glTF saves light intensities photometric: point and spot lights in candela (lm/sr),
+/// directional lights in lux (lm/m²). Our lights are radiometric (W/sr and W/m²), so we divide by
+/// the luminous efficacy of 683 lm/W.
+pub const LUMINOUS_EFFICACY: f32 = 683.0;
+///Lights without a range end where their inverse square intensity falls under this value.
+const MIN_LIGHT_CONTRIBUTION: f32 = 0.001;
+
+///The kind of a KHR_lights_punctual light
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PunctualLightType {
+    Point,
+    Spot{
+        ///Angle in radians from the direction where the falloff starts
+        inner_cone: f32,
+        ///Angle in radians from the direction where the light ends
+        outer_cone: f32,
+    },
+    Directional,
+}
+
+///A light of the KHR_lights_punctual extension, the values are already converted to the engines
+/// units.
+#[derive(Clone, Debug)]
+pub struct PunctualLight {
+    pub light_type: PunctualLightType,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    pub radius: f32,
+}
+
+impl PunctualLight{
+    fn from_json(json: &serde_json::Value) -> Option<Self>{
+        let color = match json["color"].as_array(){
+            Some(color) if color.len() == 3 => Vector3::new(
+                color[0].as_f64().unwrap_or(1.0) as f32,
+                color[1].as_f64().unwrap_or(1.0) as f32,
+                color[2].as_f64().unwrap_or(1.0) as f32,
+            ),
+            _ => Vector3::new(1.0, 1.0, 1.0),
+        };
+        let intensity = json["intensity"].as_f64().unwrap_or(1.0) as f32 / LUMINOUS_EFFICACY;
+
+        let light_type = match json["type"].as_str(){
+            Some("point") => PunctualLightType::Point,
+            Some("spot") => PunctualLightType::Spot{
+                inner_cone: json["spot"]["innerConeAngle"].as_f64().unwrap_or(0.0) as f32,
+                outer_cone: json["spot"]["outerConeAngle"].as_f64().unwrap_or(consts::PI as f64 / 4.0) as f32,
+            },
+            Some("directional") => PunctualLightType::Directional,
+            _ => {
+                println!("WARNING: GLTF_IMPORTER: unknown light type: {}", json["type"]);
+                return None;
+            }
+        };
+
+        let radius = match json["range"].as_f64(){
+            Some(range) => range as f32,
+            None => (intensity / MIN_LIGHT_CONTRIBUTION).sqrt(),
+        };
+
+        Some(PunctualLight{
+            light_type: light_type,
+            color: color,
+            intensity: intensity,
+            radius: radius,
+        })
+    }
+
+    ///Creates the engine light with `name`
+    pub fn to_content(&self, name: &str) -> content::ContentType{
+        match self.light_type{
+            PunctualLightType::Point => {
+                let mut light = light::LightPoint::new(name);
+                light.set_color(self.color);
+                light.set_intensity(self.intensity);
+                light.set_radius(self.radius);
+                content::ContentType::PointLight(light)
+            },
+            PunctualLightType::Spot{inner_cone, outer_cone} => {
+                let mut light = light::LightSpot::new(name);
+                light.set_color(self.color);
+                light.set_intensity(self.intensity);
+                light.set_radius(self.radius);
+                //our spot lights take the angles in degree
+                light.set_outer_radius(outer_cone.to_degrees());
+                light.set_inner_radius(inner_cone.to_degrees());
+                content::ContentType::SpotLight(light)
+            },
+            PunctualLightType::Directional => {
+                let mut light = light::LightDirectional::new(name);
+                light.set_color(self.color);
+                light.set_intensity(self.intensity);
+                content::ContentType::DirectionalLight(light)
+            },
+        }
+    }
+}
+
+///All lights of the KHR_lights_punctual extension in a gltf file. The gltf crate doesn't read
+/// this extension yet, therefore we read it from the json ourself.
+#[derive(Clone, Debug)]
+pub struct PunctualLights {
+    lights: Vec<PunctualLight>,
+    ///maps gltf node indices to the light they use
+    node_lights: BTreeMap<usize, usize>,
+}
+
+impl PunctualLights{
+    ///Reads the lights of the .gltf or .glb file at `path`. Returns no lights if the file
+    /// doesn't use the extension.
+    pub fn from_file(path: &Path) -> Self{
+        let mut punctual_lights = PunctualLights{
+            lights: Vec::new(),
+            node_lights: BTreeMap::new(),
+        };
+
+        let json = match read_gltf_json(path){
+            Some(json) => json,
+            None => return punctual_lights,
+        };
+
+        if let Some(lights) = json["extensions"]["KHR_lights_punctual"]["lights"].as_array(){
+            for light_json in lights.iter(){
+                //keep the indices intact, unknown lights are replaced by a white point light
+                let light = PunctualLight::from_json(light_json).unwrap_or(PunctualLight{
+                    light_type: PunctualLightType::Point,
+                    color: Vector3::new(1.0, 1.0, 1.0),
+                    intensity: 0.0,
+                    radius: 0.0,
+                });
+                punctual_lights.lights.push(light);
+            }
+        }
+
+        if let Some(nodes) = json["nodes"].as_array(){
+            for (node_index, node) in nodes.iter().enumerate(){
+                if let Some(light_index) = node["extensions"]["KHR_lights_punctual"]["light"].as_u64(){
+                    punctual_lights.node_lights.insert(node_index, light_index as usize);
+                }
+            }
+        }
+
+        punctual_lights
+    }
+
+    ///Returns the light the gltf node with `node_index` has, if there is one.
+    pub fn get_node_light(&self, node_index: usize) -> Option<&PunctualLight>{
+        match self.node_lights.get(&node_index){
+            Some(light_index) => self.lights.get(*light_index),
+            None => None,
+        }
+    }
+}
+
+///Reads the json part of a .gltf or .glb file
+fn read_gltf_json(path: &Path) -> Option<serde_json::Value>{
+    let mut bytes = Vec::new();
+    match File::open(path){
+        Ok(mut file) => {
+            if file.read_to_end(&mut bytes).is_err(){
+                return None;
+            }
+        },
+        Err(_) => return None,
+    }
+
+    //A .glb starts with a 12 byte header, followed by the json chunk (length, type, data)
+    let json_bytes = if bytes.starts_with(b"glTF"){
+        if bytes.len() < 20{
+            return None;
+        }
+        let length = (bytes[12] as usize)
+            | (bytes[13] as usize) << 8
+            | (bytes[14] as usize) << 16
+            | (bytes[15] as usize) << 24;
+
+        if bytes.len() < 20 + length{
+            return None;
+        }
+        &bytes[20..20 + length]
+    }else{
+        &bytes[..]
+    };
+
+    match serde_json::from_slice(json_bytes){
+        Ok(json) => Some(json),
+        Err(er) => {
+            println!("WARNING: GLTF_IMPORTER: failed to read the json of {:?}: {}", path, er);
+            None
+        }
+    }
+}
+
+///Returns the name the skeleton of `skin` has in the animation manager
+pub fn get_skeleton_name(scene_name: &str, skin: &gltf::Skin) -> String{
+    String::from(scene_name) + "_skin_" + &skin.index().to_string()
+}
+
+///Converts the local transform of a gltf node
+fn get_gltf_local_transform(node: &gltf::Node) -> transform::NodeTransform{
+    let (translation, rotation, scale) = node.transform().decomposed();
+    transform::NodeTransform::new(
+        Vector3::from(translation),
+        //gltf is x,y,z,w, cgmath w,x,y,z
+        Quaternion::new(rotation[3], rotation[0], rotation[1], rotation[2]),
+        Vector3::from(scale),
+    )
+}
+
+///Loads all skins of the gltf file as skeletons into the animation manager. The joint transforms
+/// are relative to the skeleton root, so the skinned mesh should be in the same space as the
+/// root joint's parent.
+pub fn load_gltf_skins(
+    gltf: &gltf::Gltf,
+    scene_name: &str,
+    buffers: &gltf_importer::Buffers,
+    managers: &Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>,
+){
+    use core::resources::skeleton::{Skeleton, Joint};
+
+    for skin in gltf.skins(){
+        let mut skeleton = Skeleton::new(&get_skeleton_name(scene_name, &skin));
+
+        let inverse_bind_matrices: Vec<Matrix4<f32>> = match skin.inverse_bind_matrices(){
+            Some(accessor) => {
+                gltf_utils::AccessorIter::<[[f32; 4]; 4]>::new(accessor, buffers)
+                .map(|m| Matrix4::from(m))
+                .collect()
+            },
+            None => Vec::new(),
+        };
+
+        //maps the gltf node index to the joint index
+        let joint_nodes: Vec<gltf::Node> = skin.joints().collect();
+        let joint_indices: Vec<usize> = joint_nodes.iter().map(|node| node.index()).collect();
+
+        for (index, node) in joint_nodes.iter().enumerate(){
+            let joint_name = match node.name(){
+                Some(name) => String::from(name),
+                None => String::from(scene_name) + "_joint_" + &node.index().to_string(),
+            };
+
+            let mut joint = Joint::new(&joint_name)
+            .with_rest_transform(get_gltf_local_transform(node));
+            if let Some(matrix) = inverse_bind_matrices.get(index){
+                joint = joint.with_inverse_bind_matrix(*matrix);
+            }
+            skeleton.add_joint(joint);
+        }
+
+        //Now find the parents, gltf only stores the children
+        for (parent_index, node) in joint_nodes.iter().enumerate(){
+            for child in node.children(){
+                if let Some(child_index) = joint_indices.iter().position(|idx| *idx == child.index()){
+                    skeleton.get_joints_mut()[child_index].parent = Some(parent_index);
+                }
+            }
+        }
+
+        let animation_manager = {
+            let managers_lck = managers.lock().expect("failed to lock managers struct");
+            (*managers_lck).animation_manager.clone()
+        };
+        let mut animation_manager_lck = animation_manager.lock().expect("failed to lock animation manager");
+        animation_manager_lck.add_skeleton(skeleton);
+    }
+}
+
+///Reads the keyframes of a gltf animation channel. Returns `None` for morph target channels,
+/// they are not supported.
+fn load_gltf_channel(
+    channel: &gltf::animation::Channel,
+    buffers: &gltf_importer::Buffers,
+) -> Option<(Vec<f32>, core::resources::animation::ChannelValues, core::resources::animation::Interpolation)>{
+    use core::resources::animation::{ChannelValues, Interpolation};
+
+    let sampler = channel.sampler();
+    let times: Vec<f32> = gltf_utils::AccessorIter::<f32>::new(sampler.input(), buffers).collect();
+
+    //Cubic splines store an in tangent, the value and an out tangent per keyframe,
+    // we only use the value and interpolate linear.
+    let (interpolation, stride, offset) = match sampler.interpolation(){
+        gltf::animation::Interpolation::Step => (Interpolation::Step, 1, 0),
+        gltf::animation::Interpolation::CubicSpline => (Interpolation::Linear, 3, 1),
+        _ => (Interpolation::Linear, 1, 0),
+    };
+
+    let values = match channel.target().path(){
+        gltf::animation::Property::Translation => {
+            ChannelValues::Translation(
+                gltf_utils::AccessorIter::<[f32; 3]>::new(sampler.output(), buffers)
+                .skip(offset).step_by(stride).map(|v| Vector3::from(v)).collect()
+            )
+        },
+        gltf::animation::Property::Rotation => {
+            ChannelValues::Rotation(
+                gltf_utils::AccessorIter::<[f32; 4]>::new(sampler.output(), buffers)
+                .skip(offset).step_by(stride).map(|r| Quaternion::new(r[3], r[0], r[1], r[2])).collect()
+            )
+        },
+        gltf::animation::Property::Scale => {
+            ChannelValues::Scale(
+                gltf_utils::AccessorIter::<[f32; 3]>::new(sampler.output(), buffers)
+                .skip(offset).step_by(stride).map(|v| Vector3::from(v)).collect()
+            )
+        },
+        //morph targets are not supported
+        _ => return None,
+    };
+
+    Some((times, values, interpolation))
+}
+
+///Loads all animations of the gltf file which animate the joints of a skin. One clip is created
+/// per animation and skin, it is called `<skeleton name>_animation_<animation index>`.
+/// Channels which animate other nodes are returned as tracks per gltf node index, see
+/// `add_gltf_node_animations()`. Nodes which are animated by several animations only get the
+/// tracks of the first one.
+pub fn load_gltf_animations(
+    gltf: &gltf::Gltf,
+    scene_name: &str,
+    buffers: &gltf_importer::Buffers,
+    managers: &Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>,
+) -> BTreeMap<usize, Vec<node_controller::animation_controller::NodeTrack>>{
+    use core::resources::animation::{AnimationClip, Channel};
+    use core::next_tree::node_controller::animation_controller::NodeTrack;
+
+    let animation_manager = {
+        let managers_lck = managers.lock().expect("failed to lock managers struct");
+        (*managers_lck).animation_manager.clone()
+    };
+
+    for animation in gltf.animations(){
+        for skin in gltf.skins(){
+            let joint_indices: Vec<usize> = skin.joints().map(|node| node.index()).collect();
+            let mut channels = Vec::new();
+
+            for channel in animation.channels(){
+                let joint = match joint_indices.iter().position(|idx| *idx == channel.target().node().index()){
+                    Some(joint) => joint,
+                    None => continue,
+                };
+
+                if let Some((times, values, interpolation)) = load_gltf_channel(&channel, buffers){
+                    channels.push(Channel{
+                        joint: joint,
+                        times: times,
+                        values: values,
+                        interpolation: interpolation,
+                    });
+                }
+            }
+
+            if channels.is_empty(){
+                continue;
+            }
+
+            let skeleton_name = get_skeleton_name(scene_name, &skin);
+            let clip_name = skeleton_name.clone() + "_animation_" + &animation.index().to_string();
+            let clip = AnimationClip::new(&clip_name, &skeleton_name, channels);
+
+            let mut animation_manager_lck = animation_manager.lock().expect("failed to lock animation manager");
+            animation_manager_lck.add_clip(clip);
+        }
+    }
+
+    //Now collect the channels of the nodes which are no joints
+    let all_joints: Vec<usize> = gltf.skins().flat_map(|skin| skin.joints().map(|node| node.index())).collect();
+    let mut node_tracks: BTreeMap<usize, Vec<NodeTrack>> = BTreeMap::new();
+
+    for animation in gltf.animations(){
+        let mut animation_tracks: BTreeMap<usize, Vec<NodeTrack>> = BTreeMap::new();
+        for channel in animation.channels(){
+            let node_index = channel.target().node().index();
+            if all_joints.contains(&node_index){
+                continue;
+            }
+
+            if let Some((times, values, interpolation)) = load_gltf_channel(&channel, buffers){
+                animation_tracks.entry(node_index).or_insert(Vec::new()).push(NodeTrack{
+                    times: times,
+                    values: values,
+                    interpolation: interpolation,
+                });
+            }
+        }
+
+        for (node_index, tracks) in animation_tracks.into_iter(){
+            if node_tracks.contains_key(&node_index){
+                println!(
+                    "WARNING: GLTF_IMPORTER: node {} is animated by several animations, animation {} is ignored",
+                    node_index, animation.index()
+                );
+                continue;
+            }
+            node_tracks.insert(node_index, tracks);
+        }
+    }
+
+    node_tracks
+}
+
+///Attaches an `AnimationController` which loops the tracks to every node of `tree` which is in
+/// `node_tracks`. The nodes are found by the names `load_gltf_node()` gives them.
+pub fn add_gltf_node_animations(
+    gltf: &gltf::Gltf,
+    scene_name: &str,
+    node_tracks: BTreeMap<usize, Vec<node_controller::animation_controller::NodeTrack>>,
+    tree: &mut tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>,
+){
+    use core::next_tree::node_controller::animation_controller::AnimationController;
+
+    for (node_index, tracks) in node_tracks.into_iter(){
+        let gltf_node = match gltf.nodes().nth(node_index){
+            Some(node) => node,
+            None => continue,
+        };
+
+        let node_name = String::from(scene_name) + "_node_" + &node_index.to_string();
+        match tree.get_node(&node_name){
+            Some(node) => {
+                let controller = AnimationController::new(tracks)
+                .with_local_transform(get_gltf_local_transform(&gltf_node));
+                node.set_controller(controller);
+            },
+            None => println!("WARNING: GLTF_IMPORTER: animated node {} is in no scene", node_name),
+        }
+    }
+}
+
+///Imports a scene from the file at `path` and adds it to the scene manager. An
+/// `AssetLoadedEvent` is published when the import finished or failed.
 pub fn import_gltf(
     path: &str, name: &str,
     managers: Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>
-){
+) -> Result<(), AssetError>{
+    let result = load_gltf(path, name, managers.clone(), None);
+
+    //Done with loading gltf
+    let manager_lck = managers.lock().expect("failed to lock managers");
+    let scene_manager = (*manager_lck).scene_manager.clone();
+    let mut scene_manager_inst = scene_manager.lock().expect("failed to lock scene manager");
+
+    (*scene_manager_inst).add_import_result(name, path, result)?;
+    Ok(())
+}
+
+///Loads the scene from the file at `path` and returns it. The meshes, materials etc. are added to
+/// their managers while loading. If there is a `handle` the progress is reported to it.
+pub fn load_gltf(
+    path: &str, name: &str,
+    managers: Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>,
+    handle: Option<&load_handle::LoadHandle>,
+) -> Result<tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>, AssetError>{
+    let report = |progress: f32| if let Some(handle) = handle { handle.set_progress(progress) };
+    report(0.0);
+
     //load the gltf model into a gltf object
     let path = Path::new(path);
     //a default path if `path` doesn't exist, should load a default object in future
     let default = Path::new("");
     //go to the parent directory and load every gltf in this directory
     let base = path.parent().unwrap_or(default);
-    //TODO don't panic, load a debug object
-    let (gltf, buffers) = gltf_importer::import(path).expect("invalid model for gltf 2.0 loader");
+    let (gltf, buffers) = match gltf_importer::import(path){
+        Ok(gltf) => gltf,
+        Err(er) => return Err(AssetError::decode(&path.to_string_lossy(), &format!("{:?}", er))),
+    };
+    report(0.2);
 
+    //The skeletons have to be known before the meshes which use them are loaded
+    load_gltf_skins(&gltf, name, &buffers, &managers);
+    let node_tracks = load_gltf_animations(&gltf, name, &buffers, &managers);
 
-    //build an empty root node
+
+    let lights = PunctualLights::from_file(path);
+    report(0.3);
+
+    //build an empty root node, gltf has no transform above the scenes, so the root stays at the
+    // origin and every root node of a scene gets its own transform in `load_gltf_node()`
     let empty_object = empty::Empty::new(name);
     let empty_node = content::ContentType::Empty(empty_object);
     //create a tree from it
-    //TODO we might have to read the roots node location, rotation and scale
     let mut scene_tree = tree::Tree::new(empty_node, attributes::NodeAttributes::default());
 
+    //the rest of the progress is split between the root nodes, they load the meshes and textures
+    let root_node_count = gltf.scenes().fold(0, |count, scene| count + scene.nodes().count()).max(1);
+    let mut loaded_root_nodes = 0;
+
     for scene in gltf.scenes(){
         //create an empty scene node with the correct name
         let scene_name = String::from(name) + "_scene_" + &scene.index().to_string();
@@ -823,7 +1485,7 @@ pub fn import_gltf(
         let empty_scene_object = empty::Empty::new(&scene_name);
         let scene_node = content::ContentType::Empty(empty_scene_object);
         //now add the node to the tree
-        let _ = scene_tree.add_at_root(scene_node, None); //TODO might need to read scene location/rotation/scale
+        let _ = scene_tree.add_at_root(scene_node, None);
         //now cycle through its nodes and add the correct meshes, lights whatever to it
         for node in scene.nodes(){
 
@@ -831,21 +1493,203 @@ pub fn import_gltf(
             load_gltf_node(
                 &node,
                 &String::from(name), //This is the name of this gltf file used to reference global gltf file specific data like textures and materials
-                None,       //Scenes dont have a transform
+                None,       //Scenes dont have a transform, the root nodes are in world space
                 &scene_name,
                 &mut scene_tree,
                 &buffers,
                 base,
+                path,
                 &managers,
+                &lights,
             );
+
+            loaded_root_nodes += 1;
+            report(0.3 + 0.7 * (loaded_root_nodes as f32 / root_node_count as f32));
         }
         //Now we added all nodes to the scene tree and can return
     }
 
-    //Done with loading gltf
-    let manager_lck = managers.lock().expect("failed to lock managers");
-    let scene_manager = (*manager_lck).scene_manager.clone();
-    let mut scene_manager_inst = scene_manager.lock().expect("failed to lock scene manager");
+    //the animated nodes exist now
+    add_gltf_node_animations(&gltf, name, node_tracks, &mut scene_tree);
+
+    Ok(scene_tree)
+}
+
+///Reads the gltf file at `path` again and updates `meshes` in place with the vertices of the
+/// primitives they were imported from. The materials of the meshes get the new factors and
+/// textures, so scene nodes which use them show the changes directly. Without a gpu the meshes
+/// are only updated on the cpu.
+///NOTE: A different material or alpha mode of a primitive is ignored till the scene is imported
+/// again.
+pub fn reload_gltf_meshes(
+    path: &str,
+    meshes: &[Arc<Mutex<mesh::Mesh>>],
+    managers: &core::resource_management::ManagerAndRenderInfo,
+) -> Result<(), AssetError>{
+    let (gltf, buffers) = match gltf_importer::import(Path::new(path)){
+        Ok(gltf) => gltf,
+        Err(er) => return Err(AssetError::decode(path, &format!("{:?}", er))),
+    };
+
+    //the materials of the reloaded meshes by their gltf index, each is reloaded once
+    let mut materials = BTreeMap::new();
+
+    for arc_mesh in meshes.iter(){
+        let mut mesh_lck = arc_mesh.lock().expect("failed to lock mesh for reloading");
+        let source = match mesh_lck.get_source(){
+            Some(source) => source,
+            None => continue,
+        };
+
+        let primitive = match gltf.meshes().nth(source.mesh_index)
+        .and_then(|gltf_mesh| gltf_mesh.primitives().nth(source.primitive_index)){
+            Some(primitive) => primitive,
+            None => {
+                println!(
+                    "WARNING: GLTF_IMPORTER: {} has no primitive {} in mesh {} anymore, keeping {}",
+                    path, source.primitive_index, source.mesh_index, mesh_lck.name
+                );
+                continue;
+            }
+        };
+
+        //A half saved file fails here, the caller tries again later
+        let primitive_data = read_gltf_primitive(&primitive, &buffers)?;
+        match managers.queue{
+            Some(ref queue) => mesh_lck.set_vertices_and_indices(
+                primitive_data.vertices, primitive_data.indices, queue.clone()
+            )?,
+            None => mesh_lck.set_cpu_vertices_and_indices(primitive_data.vertices, primitive_data.indices),
+        }
+        mesh_lck.set_bound(primitive_data.min, primitive_data.max);
+
+        //The new vertices become the bind pose of a skinned mesh
+        if let Some(skeleton) = mesh_lck.get_skeleton_name(){
+            if primitive_data.skin.is_empty(){
+                println!("WARNING: GLTF_IMPORTER: {} lost its skin while reloading", mesh_lck.name);
+                mesh_lck.clear_skin();
+            }else{
+                mesh_lck.set_skin(primitive_data.skin, &skeleton);
+            }
+        }
+
+        //Meshes which use the fallback or another material than imported keep it untouched
+        if let Some(material_index) = primitive.material().index(){
+            if mesh_lck.has_material()
+            && mesh_lck.get_material_name().ends_with(&format!("_material_{}", material_index)){
+                materials.insert(material_index, mesh_lck.get_material());
+            }
+        }
+    }
+
+    let base = Path::new(path).parent().unwrap_or(Path::new(""));
+    for (material_index, material) in materials.into_iter(){
+        if let Some(gltf_material) = gltf.materials().nth(material_index){
+            reload_gltf_material(&gltf_material, &material, &buffers, base, managers)?;
+        }
+    }
+
+    Ok(())
+}
+
+///Reads the factors and textures of `mat` again and applies them to `material`, which was
+/// imported from it. The textures are decoded before any manager or the material is locked.
+fn reload_gltf_material(
+    mat: &gltf::Material,
+    material: &Arc<Mutex<material::Material>>,
+    buffers: &gltf_importer::Buffers,
+    base: &Path,
+    managers: &core::resource_management::ManagerAndRenderInfo,
+) -> Result<(), AssetError>{
+    let (texture_manager, material_manager) = match (&managers.texture_manager, &managers.material_manager){
+        (&Some(ref texture_manager), &Some(ref material_manager)) => (texture_manager, material_manager),
+        _ => return Ok(()), //headless meshes have no material
+    };
+
+    let (device, queue, fallback_texture) = {
+        let texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+        (texture_manager_lck.get_device(), texture_manager_lck.get_queue(), texture_manager_lck.get_none())
+    };
+    let material_name = material.lock().expect("failed to lock material for reloading").get_name();
+
+    //the slots in the order albedo, normal, metallic roughness, occlusion, emissive, named like
+    // in `load_gltf_material()`
+    let pbr = mat.pbr_metallic_roughness();
+    let slots = [
+        (pbr.base_color_texture().map(|t| t.texture()), "_albedo"),
+        (mat.normal_texture().map(|t| t.texture()), "_normal"),
+        (pbr.metallic_roughness_texture().map(|t| t.texture()), "_met_rough"),
+        (mat.occlusion_texture().map(|t| t.texture()), "_occlu"),
+        (mat.emissive_texture().map(|t| t.texture()), "_emissive"),
+    ];
+
+    //A half saved image fails here, the caller tries again later
+    let mut textures = Vec::new();
+    for &(ref texture, suffix) in slots.iter(){
+        let loaded = match *texture{
+            Some(ref texture) => Some(build_gltf_texture(
+                texture, &(material_name.clone() + suffix), buffers, base, device.clone(), queue.clone()
+            )?),
+            None => None,
+        };
+        textures.push(loaded);
+    }
+
+    //Swap the textures in the manager and in every other material which shares them
+    for texture in textures.iter().filter_map(|texture| texture.as_ref()){
+        let old_texture = {
+            let mut texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+            match texture_manager_lck.swap_texture(texture.clone()){
+                Ok(old_texture) => Some(old_texture),
+                Err(_) => {
+                    //the slot had no texture before
+                    if let Err(er) = texture_manager_lck.add_texture(texture.clone()){
+                        println!("WARNING: GLTF_IMPORTER: failed to add texture to manager: {}", er);
+                    }
+                    None
+                }
+            }
+        };
+
+        if let Some(old_texture) = old_texture{
+            let mut material_manager_lck = material_manager.lock().expect("failed to lock material manager");
+            material_manager_lck.replace_texture(&old_texture, texture);
+        }
+    }
+
+    let slot = |index: usize| match textures[index]{
+        Some(ref texture) => texture.clone(),
+        None => fallback_texture.clone(),
+    };
+
+    let mut material_lck = material.lock().expect("failed to lock material for reloading");
+    material_lck.set_albedo_texture(slot(0));
+    material_lck.set_normal_texture(slot(1));
+    material_lck.set_metallic_roughness_texture(slot(2));
+    material_lck.set_occlusion_texture(slot(3));
+    material_lck.set_emissive_texture(slot(4));
+
+    //the height map is no gltf slot and keeps its texture, flag and factors
+    let mut usage_flags = material_lck.get_texture_usage_info();
+    usage_flags.albedo = textures[0].is_some();
+    usage_flags.normal = textures[1].is_some();
+    usage_flags.metal = textures[2].is_some();
+    usage_flags.roughness = textures[2].is_some();
+    usage_flags.occlusion = textures[3].is_some();
+    usage_flags.emissive = textures[4].is_some();
+    usage_flags.is_masked = match mat.alpha_mode(){
+        gltf::material::AlphaMode::Mask => true,
+        _ => false,
+    };
+    material_lck.set_texture_usage_info(usage_flags);
+
+    let old_factors = material_lck.get_material_factor_info();
+    material_lck.set_material_factor_info(
+        get_gltf_material_factors(mat, textures[4].is_some())
+        .with_height_scale(old_factors.get_height_scale())
+        .with_height_steps(old_factors.get_height_steps())
+    );
 
-    (*scene_manager_inst).add_scene(scene_tree);
+    material_lck.recreate_static_sets();
+    Ok(())
 }
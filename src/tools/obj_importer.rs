@@ -0,0 +1,807 @@
+use core::resources::{texture, material, empty, mesh};
+use core::next_tree::*;
+use core::ReturnBoundInfo;
+use core::asset_error::AssetError;
+use core;
+use core::resource_management::load_handle;
+use jakar_tree::*;
+use render::pipeline_builder;
+use render::pipeline_manager;
+use render::render_passes::{RenderPassConf, ObjectPassSubPasses};
+use tools::Importer;
+
+use vulkano;
+use cgmath::*;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+///Imports Wavefront .obj files and their .mtl materials.
+///Every object (`o`), group (`g`) and material change (`usemtl`) starts a new mesh. Faces with
+/// more than three vertices are triangulated as fans, missing normals are smoothed from the faces
+/// and tangents are always generated from the uvs.
+///Only an .obj file which can't be read is an error. Everything in it which can't be used (invalid
+/// faces or numbers, missing .mtl files or materials, meshes which can't be uploaded) is skipped
+/// with a warning and the rest of the file is loaded.
+pub struct ObjImporter {
+    ///Needed to create the materials and textures, without them the meshes keep the default
+    /// material (or none if there is no gpu)
+    managers: Option<Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>>,
+}
+
+impl ObjImporter{
+    ///Sets the managers which are used to create the textures and materials of the .mtl files.
+    pub fn with_managers(mut self, managers: Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>) -> Self{
+        self.managers = Some(managers);
+        self
+    }
+
+    ///Loads the meshes, `device` and `queue` are `None` if the engine runs headless.
+    /// If there is a `handle` the progress is reported to it.
+    fn load(
+        &mut self,
+        path: &str,
+        name: &str,
+        device: Option<Arc<vulkano::device::Device>>,
+        queue: Option<Arc<vulkano::device::Queue>>,
+        handle: Option<&load_handle::LoadHandle>,
+    ) -> Result<Vec<mesh::Mesh>, AssetError>{
+        let report = |progress: f32| if let Some(handle) = handle { handle.set_progress(progress) };
+        report(0.0);
+
+        let obj_data = ObjData::from_file(Path::new(path))?;
+        report(0.2);
+
+        let base = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut obj_materials = BTreeMap::new();
+        for lib in obj_data.material_libs.iter(){
+            match load_mtl(&base.join(lib)){
+                Ok(mut materials) => obj_materials.append(&mut materials),
+                Err(er) => println!("WARNING: OBJ_IMPORTER: {}", er),
+            }
+        }
+
+        let mut meshes = Vec::new();
+        for (group_index, group) in obj_data.groups.iter().enumerate(){
+            let (mut vertices, indices) = obj_data.build_vertices(group);
+            if indices.is_empty(){
+                continue;
+            }
+            mesh::generate_tangents(&mut vertices, &indices);
+
+            let mesh_name = String::from(name) + "_" + &group.name + "_mesh_" + &group_index.to_string();
+
+            let material = match (&self.managers, &group.material){
+                (&Some(ref managers), &Some(ref material_name)) if device.is_some() => {
+                    match obj_materials.get(material_name){
+                        Some(obj_material) => Some(
+                            create_material(obj_material, &(String::from(name) + "_material_" + material_name), base, managers)
+                        ),
+                        None => {
+                            println!("WARNING: OBJ_IMPORTER: material {} is not defined, using the default one", material_name);
+                            None
+                        }
+                    }
+                },
+                _ => None,
+            };
+
+            let mut new_mesh = match device{
+                Some(ref device) => {
+                    let material = match material{
+                        Some(material) => material,
+                        None => match self.managers{
+                            Some(ref managers) => {
+                                let material_manager = {
+                                    let managers_lck = managers.lock().expect("failed to lock managers struct");
+                                    (*managers_lck).material_manager.clone().expect("materials can't be loaded without a gpu")
+                                };
+                                let mut material_manager_lck = material_manager.lock().expect("failed to lock material manager");
+                                (*material_manager_lck).get_default_material()
+                            },
+                            None => {
+                                println!("WARNING: OBJ_IMPORTER: can't create {} on the gpu without managers", mesh_name);
+                                match mesh_from_vertices(mesh::Mesh::new_cpu_only(&mesh_name), vertices, indices, None){
+                                    Ok(new_mesh) => meshes.push(new_mesh),
+                                    Err(er) => println!("WARNING: OBJ_IMPORTER: skipping {}: {}", mesh_name, er),
+                                }
+                                continue;
+                            }
+                        },
+                    };
+                    mesh::Mesh::new(&mesh_name, device.clone(), material)
+                },
+                None => mesh::Mesh::new_cpu_only(&mesh_name),
+            };
+
+            match mesh_from_vertices(new_mesh, vertices, indices, queue.clone()){
+                Ok(new_mesh) => meshes.push(new_mesh),
+                Err(er) => println!("WARNING: OBJ_IMPORTER: skipping {}: {}", mesh_name, er),
+            }
+            report(0.2 + 0.8 * ((group_index + 1) as f32 / obj_data.groups.len() as f32));
+        }
+
+        Ok(meshes)
+    }
+}
+
+impl Importer for ObjImporter{
+    fn new() -> Self{
+        ObjImporter{
+            managers: None,
+        }
+    }
+
+    ///Imports the meshes of the .obj at `path`. The materials are only created if the importer
+    /// was created `with_managers()`.
+    fn import(&mut self, path: &str, name: &str, device: Arc<vulkano::device::Device>,
+        queue: Arc<vulkano::device::Queue>)
+        -> Vec<mesh::Mesh>
+    {
+        match self.load(path, name, Some(device), Some(queue), None){
+            Ok(meshes) => meshes,
+            Err(er) => {
+                println!("WARNING: OBJ_IMPORTER: {}", er);
+                Vec::new()
+            }
+        }
+    }
+}
+
+///Imports the .obj at `path` into a new scene called `name` and adds it to the scene manager.
+/// The meshes are added to the mesh manager, the materials and textures to their managers.
+/// An `AssetLoadedEvent` is published when the import finished or failed.
+pub fn import_obj(
+    path: &str, name: &str,
+    managers: Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>
+) -> Result<(), AssetError>{
+    let result = load_obj(path, name, managers.clone(), None);
+
+    let scene_manager = {
+        let managers_lck = managers.lock().expect("failed to lock managers");
+        (*managers_lck).scene_manager.clone()
+    };
+    let mut scene_manager_lck = scene_manager.lock().expect("failed to lock scene manager");
+    (*scene_manager_lck).add_import_result(name, path, result)?;
+    Ok(())
+}
+
+///Loads the .obj at `path` as scene called `name` and returns it. The meshes, materials and
+/// textures are added to their managers. If there is a `handle` the progress is reported to it.
+/// Fails only if the .obj file can't be read, broken parts of it are skipped with a warning.
+pub fn load_obj(
+    path: &str, name: &str,
+    managers: Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>,
+    handle: Option<&load_handle::LoadHandle>,
+) -> Result<tree::Tree<content::ContentType, jobs::SceneJobs, attributes::NodeAttributes>, AssetError>{
+    let (device, queue) = {
+        let managers_lck = managers.lock().expect("failed to lock managers struct");
+        (managers_lck.device.clone(), managers_lck.queue.clone())
+    };
+
+    let meshes = ObjImporter::new()
+    .with_managers(managers.clone())
+    .load(path, name, device, queue, handle)?;
+
+    let mut scene_tree = tree::Tree::new(
+        content::ContentType::Empty(empty::Empty::new(name)),
+        attributes::NodeAttributes::default()
+    );
+
+    let mesh_manager = {
+        let managers_lck = managers.lock().expect("failed to lock managers struct");
+        (*managers_lck).mesh_manager.clone()
+    };
+
+    for new_mesh in meshes{
+        let mut attrib = attributes::NodeAttributes::default();
+        attrib.bound = new_mesh.get_bound();
+        attrib.value_bound = new_mesh.get_bound();
+        attrib.is_transparent = is_transparent(&new_mesh);
+
+        let arc_mesh = Arc::new(Mutex::new(new_mesh));
+        {
+            let mut mesh_manager_lck = mesh_manager.lock().expect("failed to lock mesh manager in obj loader");
+            (*mesh_manager_lck).add_arc_mesh(arc_mesh.clone());
+        }
+        let _ = scene_tree.add_at_root(content::ContentType::Mesh(arc_mesh), Some(attrib));
+    }
+
+    scene_tree.rebuild_bounds();
+    Ok(scene_tree)
+}
+
+///Returns true if the material of `mesh` uses alpha blending
+fn is_transparent(mesh: &mesh::Mesh) -> bool{
+    if !mesh.has_material(){
+        return false;
+    }
+    let material = mesh.get_material();
+    let material_lck = material.lock().expect("failed to lock mesh material while importing");
+    match material_lck.get_pipeline().pipeline_config.blending_operation {
+        pipeline_builder::BlendTypes::BlendAlphaBlending => true,
+        _ => false,
+    }
+}
+
+///Sets the vertices, indices and the bound of `new_mesh`, fails if the buffers can't be uploaded
+fn mesh_from_vertices(
+    mut new_mesh: mesh::Mesh,
+    vertices: Vec<mesh::Vertex>,
+    indices: Vec<u32>,
+    queue: Option<Arc<vulkano::device::Queue>>,
+) -> Result<mesh::Mesh, AssetError>{
+    match queue{
+        Some(queue) => new_mesh.set_vertices_and_indices(vertices, indices, queue)?,
+        None => new_mesh.set_cpu_vertices_and_indices(vertices, indices),
+    }
+
+    let positions = new_mesh.get_all_positions();
+    let mut min = [::std::f32::MAX; 3];
+    let mut max = [::std::f32::MIN; 3];
+    for position in positions.iter(){
+        for i in 0..3{
+            min[i] = min[i].min(position[i]);
+            max[i] = max[i].max(position[i]);
+        }
+    }
+    if !positions.is_empty(){
+        new_mesh.set_bound(Point3::from(min), Point3::from(max));
+    }
+
+    Ok(new_mesh)
+}
+
+///One vertex of a face as (position, tex coord, normal) indices into the obj lists
+type FaceVertex = (usize, Option<usize>, Option<usize>);
+
+///A part of the obj which becomes one mesh
+struct ObjGroup {
+    name: String,
+    material: Option<String>,
+    ///triangles
+    faces: Vec<[FaceVertex; 3]>,
+}
+
+///The parsed content of an obj file
+struct ObjData {
+    positions: Vec<[f32; 3]>,
+    tex_coords: Vec<[f32; 2]>,
+    normals: Vec<[f32; 3]>,
+    groups: Vec<ObjGroup>,
+    material_libs: Vec<String>,
+}
+
+impl ObjData{
+    fn from_file(path: &Path) -> Result<Self, AssetError>{
+        let content = read_to_string(path)?;
+
+        let mut data = ObjData{
+            positions: Vec::new(),
+            tex_coords: Vec::new(),
+            normals: Vec::new(),
+            groups: vec![ObjGroup{name: String::from("default"), material: None, faces: Vec::new()}],
+            material_libs: Vec::new(),
+        };
+
+        for (line_number, line) in content.lines().enumerate(){
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next(){
+                Some(keyword) => keyword,
+                None => continue,
+            };
+
+            match keyword{
+                "v" => data.positions.push(parse_floats(tokens, [0.0; 3], path, line_number)),
+                "vt" => {
+                    let uv = parse_floats(tokens, [0.0; 2], path, line_number);
+                    //obj has its uv origin at the bottom left, vulkan at the top left
+                    data.tex_coords.push([uv[0], 1.0 - uv[1]]);
+                },
+                "vn" => data.normals.push(parse_floats(tokens, [0.0; 3], path, line_number)),
+                "f" => {
+                    let mut face = Vec::new();
+                    let mut invalid = None;
+                    for token in tokens{
+                        match data.parse_face_vertex(token){
+                            Some(vertex) => face.push(vertex),
+                            None => invalid = Some(token),
+                        }
+                    }
+                    //a face with a broken vertex would be a different shape, so it is left out
+                    if let Some(token) = invalid{
+                        println!(
+                            "WARNING: OBJ_IMPORTER: skipping face with invalid vertex \"{}\" in {:?}:{}", token, path, line_number + 1
+                        );
+                        continue;
+                    }
+                    if face.len() < 3{
+                        println!(
+                            "WARNING: OBJ_IMPORTER: skipping face with {} vertices in {:?}:{}", face.len(), path, line_number + 1
+                        );
+                        continue;
+                    }
+                    let group = data.groups.last_mut().expect("obj has no group");
+                    group.faces.append(&mut triangulate_fan(&face));
+                },
+                "o" | "g" => {
+                    let name = tokens.collect::<Vec<_>>().join("_");
+                    let material = data.groups.last().and_then(|group| group.material.clone());
+                    data.start_group(if name.is_empty() { String::from("default") } else { name }, material);
+                },
+                "usemtl" => {
+                    let material = tokens.collect::<Vec<_>>().join(" ");
+                    let name = data.groups.last().map(|group| group.name.clone()).unwrap_or(String::from("default"));
+                    data.start_group(name, Some(material));
+                },
+                "mtllib" => {
+                    for lib in tokens{
+                        data.material_libs.push(String::from(lib));
+                    }
+                },
+                //smoothing groups, lines, points etc. are not supported
+                _ => {},
+            }
+        }
+
+        Ok(data)
+    }
+
+    ///Starts a new group, or changes the current one if it has no faces yet
+    fn start_group(&mut self, name: String, material: Option<String>){
+        match self.groups.last_mut(){
+            Some(ref mut group) if group.faces.is_empty() => {
+                group.name = name;
+                group.material = material;
+                return;
+            },
+            _ => {},
+        }
+        self.groups.push(ObjGroup{name: name, material: material, faces: Vec::new()});
+    }
+
+    ///Parses "v", "v/vt", "v//vn" or "v/vt/vn", negative indices are relative to the end.
+    fn parse_face_vertex(&self, token: &str) -> Option<FaceVertex>{
+        let mut parts = token.split('/');
+        let position = resolve_index(parts.next(), self.positions.len())?;
+        let tex_coord = resolve_index(parts.next(), self.tex_coords.len());
+        let normal = resolve_index(parts.next(), self.normals.len());
+        Some((position, tex_coord, normal))
+    }
+
+    ///Creates the vertices and indices of `group`. Vertices which share position, uv and normal
+    /// are only created once.
+    fn build_vertices(&self, group: &ObjGroup) -> (Vec<mesh::Vertex>, Vec<u32>){
+        let mut vertex_indices: BTreeMap<FaceVertex, u32> = BTreeMap::new();
+        let mut positions = Vec::new();
+        let mut tex_coords = Vec::new();
+        let mut normals = Vec::new();
+        let mut has_normal = Vec::new();
+        let mut indices = Vec::new();
+
+        for face in group.faces.iter(){
+            for face_vertex in face.iter(){
+                let index = match vertex_indices.get(face_vertex){
+                    Some(index) => *index,
+                    None => {
+                        let (pos, uv, normal) = *face_vertex;
+                        positions.push(self.positions[pos]);
+                        tex_coords.push(uv.map(|uv| self.tex_coords[uv]).unwrap_or([0.0; 2]));
+                        normals.push(normal.map(|n| Vector3::from(self.normals[n])).unwrap_or(Vector3::new(0.0, 0.0, 0.0)));
+                        has_normal.push(normal.is_some());
+                        let index = positions.len() as u32 - 1;
+                        vertex_indices.insert(*face_vertex, index);
+                        index
+                    }
+                };
+                indices.push(index);
+            }
+        }
+
+        //smooth the missing normals from the faces, since those vertices share their position
+        // indices, neighbouring faces add to the same vertex
+        if has_normal.iter().any(|has| !has){
+            for triangle in indices.chunks(3){
+                let (i0, i1, i2) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+                let p0 = Vector3::from(positions[i0]);
+                let face_normal = (Vector3::from(positions[i1]) - p0).cross(Vector3::from(positions[i2]) - p0);
+                for idx in [i0, i1, i2].iter(){
+                    if !has_normal[*idx]{
+                        normals[*idx] += face_normal;
+                    }
+                }
+            }
+        }
+
+        let vertices = positions.iter().enumerate().map(|(idx, position)|{
+            let normal = if normals[idx].magnitude2() > 0.0{
+                normals[idx].normalize()
+            }else{
+                Vector3::new(0.0, 1.0, 0.0)
+            };
+            mesh::Vertex::new(
+                *position,
+                tex_coords[idx],
+                normal.into(),
+                [0.0; 4],
+                [1.0; 4],
+            )
+        }).collect();
+
+        (vertices, indices)
+    }
+}
+
+///Splits a convex face into triangles which all share the first vertex
+fn triangulate_fan(face: &[FaceVertex]) -> Vec<[FaceVertex; 3]>{
+    let mut triangles = Vec::new();
+    for i in 1..face.len().max(2) - 1{
+        triangles.push([face[0], face[i], face[i + 1]]);
+    }
+    triangles
+}
+
+///Converts an obj index (starting at 1, negative from the end) to an index into a list of `len`
+fn resolve_index(token: Option<&str>, len: usize) -> Option<usize>{
+    let index: i64 = match token{
+        Some(token) if !token.is_empty() => match token.parse(){
+            Ok(index) => index,
+            Err(_) => return None,
+        },
+        _ => return None,
+    };
+
+    let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+    if resolved >= 0 && (resolved as usize) < len{
+        Some(resolved as usize)
+    }else{
+        None
+    }
+}
+
+///Parses up to N floats, missing or invalid ones keep the value of `default`. Invalid ones are
+/// reported with the `line_number` in `path`, the value is still used to keep the indices of the
+/// following values.
+fn parse_floats<'a, I, T>(tokens: I, mut default: T, path: &Path, line_number: usize) -> T
+where I: Iterator<Item = &'a str>, T: AsMut<[f32]>{
+    {
+        let values = default.as_mut();
+        for (idx, token) in tokens.enumerate(){
+            if idx >= values.len(){
+                break;
+            }
+            match token.parse(){
+                Ok(value) => values[idx] = value,
+                Err(_) => println!(
+                    "WARNING: OBJ_IMPORTER: invalid number \"{}\" in {:?}:{}, using {}", token, path, line_number + 1, values[idx]
+                ),
+            }
+        }
+    }
+    default
+}
+
+fn read_to_string(path: &Path) -> Result<String, AssetError>{
+    let mut file = match File::open(path){
+        Ok(file) => file,
+        Err(er) => return Err(AssetError::io(&path.to_string_lossy(), er)),
+    };
+    let mut content = String::new();
+    match file.read_to_string(&mut content){
+        Ok(_) => Ok(content),
+        Err(er) => Err(AssetError::io(&path.to_string_lossy(), er)),
+    }
+}
+
+///A material of a .mtl file
+#[derive(Clone, Debug)]
+struct ObjMaterial {
+    ///Kd
+    diffuse: [f32; 3],
+    ///Ks
+    specular: [f32; 3],
+    ///Ns
+    shininess: f32,
+    ///d, or 1 - Tr
+    dissolve: f32,
+    ///Ke
+    emissive: [f32; 3],
+    ///map_Kd
+    diffuse_map: Option<String>,
+    ///norm and the -bm factor
+    normal_map: Option<(String, f32)>,
+    ///map_Bump or bump and the -bm factor, it is used as height map for parallax occlusion mapping
+    height_map: Option<(String, f32)>,
+}
+
+impl ObjMaterial{
+    fn new() -> Self{
+        ObjMaterial{
+            diffuse: [0.8; 3],
+            specular: [0.0; 3],
+            shininess: 0.0,
+            dissolve: 1.0,
+            emissive: [0.0; 3],
+            diffuse_map: None,
+            normal_map: None,
+            height_map: None,
+        }
+    }
+
+    ///Converts the Phong parameters to our metallic roughness factors.
+    /// * albedo = Kd and d
+    /// * roughness = sqrt(2 / (Ns + 2)), the usual Blinn-Phong exponent to GGX conversion
+    /// * metal = how far the brightest Ks channel is above the 4% reflectance of dielectrics
+    /// * emissive = Ke
+    fn to_factors(&self) -> material::MaterialFactors{
+        let roughness = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+        let max_specular = self.specular[0].max(self.specular[1]).max(self.specular[2]);
+        let metal = ((max_specular - 0.04) / (1.0 - 0.04)).max(0.0).min(1.0);
+        let has_emission = self.emissive.iter().any(|e| *e > 0.0);
+
+        let mut factors = material::MaterialFactors::new()
+        .with_factor_albedo([self.diffuse[0], self.diffuse[1], self.diffuse[2], self.dissolve])
+        .with_factor_metal(metal)
+        .with_factor_roughness(roughness)
+        .with_factor_emissive(self.emissive);
+
+        if let Some((_, bump_factor)) = self.normal_map{
+            factors = factors.with_factor_normal(bump_factor);
+        }
+        if let Some((_, bump_factor)) = self.height_map{
+            let height_scale = factors.get_height_scale() * bump_factor;
+            factors = factors.with_height_scale(height_scale);
+        }
+        //same as the gltf importer, emissive materials may glow brighter
+        if has_emission{
+            factors = factors.with_max_emmision(5.0);
+        }
+        factors
+    }
+}
+
+///Loads all materials of the .mtl file at `path`
+fn load_mtl(path: &Path) -> Result<BTreeMap<String, ObjMaterial>, AssetError>{
+    let content = read_to_string(path)?;
+    let mut materials = BTreeMap::new();
+    let mut current: Option<(String, ObjMaterial)> = None;
+
+    for (line_number, line) in content.lines().enumerate(){
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty(){
+            continue;
+        }
+
+        if tokens[0] == "newmtl"{
+            if let Some((name, material)) = current.take(){
+                materials.insert(name, material);
+            }
+            current = Some((tokens[1..].join(" "), ObjMaterial::new()));
+            continue;
+        }
+
+        let material = match current{
+            Some((_, ref mut material)) => material,
+            None => continue,
+        };
+
+        match tokens[0]{
+            "Kd" => material.diffuse = parse_floats(tokens[1..].iter().cloned(), material.diffuse, path, line_number),
+            "Ks" => material.specular = parse_floats(tokens[1..].iter().cloned(), material.specular, path, line_number),
+            "Ke" => material.emissive = parse_floats(tokens[1..].iter().cloned(), material.emissive, path, line_number),
+            "Ns" => material.shininess = parse_floats(tokens[1..].iter().cloned(), [material.shininess], path, line_number)[0],
+            "d" => material.dissolve = parse_floats(tokens[1..].iter().cloned(), [material.dissolve], path, line_number)[0],
+            "Tr" => material.dissolve = 1.0 - parse_floats(tokens[1..].iter().cloned(), [1.0 - material.dissolve], path, line_number)[0],
+            "map_Kd" => material.diffuse_map = tokens.last().map(|t| String::from(*t)),
+            //a bump map stores heights, only "norm" is a tangent space normal map
+            "map_Bump" | "map_bump" | "bump" => material.height_map = parse_texture_map(&tokens[1..]),
+            "norm" => material.normal_map = parse_texture_map(&tokens[1..]),
+            _ => {},
+        }
+    }
+
+    if let Some((name, material)) = current.take(){
+        materials.insert(name, material);
+    }
+
+    Ok(materials)
+}
+
+///Returns the file and the -bm factor of a texture map statement. The path is the last token,
+/// options like "-bm 0.5" come before it.
+fn parse_texture_map(tokens: &[&str]) -> Option<(String, f32)>{
+    let factor = tokens.iter().position(|t| *t == "-bm")
+    .and_then(|idx| tokens.get(idx + 1))
+    .and_then(|t| t.parse().ok())
+    .unwrap_or(1.0);
+    tokens.last().map(|t| (String::from(*t), factor))
+}
+
+///Creates the material `name` from `obj_material` or returns it, if it exists already.
+fn create_material(
+    obj_material: &ObjMaterial,
+    name: &str,
+    base: &Path,
+    managers: &Arc<Mutex<core::resource_management::ManagerAndRenderInfo>>,
+) -> Arc<Mutex<material::Material>>{
+    let (texture_manager, material_manager, pipeline_manager, uniform_manager, device) = {
+        let managers_lck = managers.lock().expect("failed to lock managers struct");
+        (
+            (*managers_lck).texture_manager.clone().expect("textures can't be loaded without a gpu"),
+            (*managers_lck).material_manager.clone().expect("materials can't be loaded without a gpu"),
+            (*managers_lck).pipeline_manager.clone().expect("materials can't be loaded without a gpu"),
+            (*managers_lck).uniform_manager.clone().expect("materials can't be loaded without a gpu"),
+            (*managers_lck).device.clone().expect("materials can't be loaded without a gpu"),
+        )
+    };
+
+    {
+        let mut material_manager_lck = material_manager.lock().expect("failed to lock material manager");
+        if (*material_manager_lck).is_available(name){
+            return (*material_manager_lck).get_material(name);
+        }
+    }
+
+    //a texture which can't be loaded is left out, the material uses the fallback texture then.
+    // The texture manager is not locked while the image is read, so a broken image can't poison it.
+    let load_texture = |file: &str, texture_name: String| -> Option<Arc<texture::Texture>>{
+        let (texture_device, texture_queue) = {
+            let texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+            ((*texture_manager_lck).get_device(), (*texture_manager_lck).get_queue())
+        };
+        let texture_path = base.join(file);
+        let new_texture = match texture::TextureBuilder::from_image(
+            &texture_path.to_string_lossy(),
+            texture_device,
+            texture_queue,
+        ).build_with_name(&texture_name){
+            Ok(texture) => texture,
+            Err(er) => {
+                println!("WARNING: OBJ_IMPORTER: {}, using the fallback texture", er);
+                return None;
+            }
+        };
+
+        let mut texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+        match (*texture_manager_lck).add_texture(new_texture.clone()){
+            Ok(_) => {},
+            Err(r) => println!("WARNING: OBJ_IMPORTER: failed to add texture to manager: {}", r),
+        }
+        Some(new_texture)
+    };
+
+    let albedo = obj_material.diffuse_map.as_ref().and_then(|file| load_texture(file, String::from(name) + "_albedo"));
+    let normal = obj_material.normal_map.as_ref().and_then(|&(ref file, _)| load_texture(file, String::from(name) + "_normal"));
+    let height = obj_material.height_map.as_ref().and_then(|&(ref file, _)| load_texture(file, String::from(name) + "_height"));
+
+    let fallback_texture = {
+        let texture_manager_lck = texture_manager.lock().expect("failed to lock texture manager");
+        (*texture_manager_lck).get_none()
+    };
+
+    let mut material_builder = material::MaterialBuilder::new(
+        albedo,
+        normal,
+        None,
+        None,
+        None,
+        fallback_texture,
+    )
+    .with_factors(obj_material.to_factors());
+
+    if let Some(height) = height{
+        material_builder = material_builder.with_height_texture(height);
+    }
+
+    let blending_mode = if obj_material.dissolve < 1.0{
+        pipeline_builder::BlendTypes::BlendAlphaBlending
+    }else{
+        pipeline_builder::BlendTypes::BlendPassThrough
+    };
+
+    let requirements = pipeline_manager::PipelineRequirements{
+        blend_type: blending_mode,
+        culling: pipeline_builder::CullMode::Back,
+        render_pass: RenderPassConf::ObjectPass(ObjectPassSubPasses::ForwardRenderingPass),
+        shader_set: "Pbr".to_string(),
+    };
+
+    let pipeline = {
+        let mut pipeline_manager_lck = pipeline_manager.lock().expect("failed to lock pipe manager");
+        (*pipeline_manager_lck).get_pipeline_by_requirements(requirements)
+    };
+
+    let final_material = material_builder.build(name, pipeline, uniform_manager, device);
+
+    let mut material_manager_lck = material_manager.lock().expect("failed to lock material manager");
+    let final_material = match final_material{
+        Ok(material) => material,
+        Err(er) => {
+            println!("WARNING: OBJ_IMPORTER: {}, using the fallback material", er);
+            return (*material_manager_lck).get_default_material();
+        }
+    };
+    let name = (*material_manager_lck).add_material(final_material);
+    (*material_manager_lck).get_material(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn resolve_index_counts_from_one_and_from_the_end(){
+        assert_eq!(resolve_index(Some("1"), 3), Some(0));
+        assert_eq!(resolve_index(Some("3"), 3), Some(2));
+        assert_eq!(resolve_index(Some("-1"), 3), Some(2));
+        assert_eq!(resolve_index(Some("-3"), 3), Some(0));
+    }
+
+    #[test]
+    fn resolve_index_rejects_invalid_indices(){
+        assert_eq!(resolve_index(Some("0"), 3), None);
+        assert_eq!(resolve_index(Some("4"), 3), None);
+        assert_eq!(resolve_index(Some("-4"), 3), None);
+        assert_eq!(resolve_index(Some("x"), 3), None);
+        //"v//vn" has an empty tex coord index
+        assert_eq!(resolve_index(Some(""), 3), None);
+        assert_eq!(resolve_index(None, 3), None);
+    }
+
+    #[test]
+    fn fans_share_the_first_vertex(){
+        let vertex = |i: usize| -> FaceVertex { (i, None, None) };
+        let quad = [vertex(0), vertex(1), vertex(2), vertex(3)];
+        assert_eq!(
+            triangulate_fan(&quad),
+            vec![[vertex(0), vertex(1), vertex(2)], [vertex(0), vertex(2), vertex(3)]]
+        );
+
+        let pentagon: Vec<FaceVertex> = (0..5).map(vertex).collect();
+        let triangles = triangulate_fan(&pentagon);
+        assert_eq!(triangles.len(), 3);
+        assert!(triangles.iter().all(|triangle| triangle[0] == vertex(0)));
+        assert_eq!(triangles[2], [vertex(0), vertex(3), vertex(4)]);
+
+        assert!(triangulate_fan(&quad[..2]).is_empty());
+    }
+
+    #[test]
+    fn parse_floats_keeps_defaults_for_missing_and_invalid_values(){
+        let path = Path::new("test.obj");
+        assert_eq!(parse_floats("1 2 3".split_whitespace(), [0.0; 3], path, 0), [1.0, 2.0, 3.0]);
+        assert_eq!(parse_floats("1".split_whitespace(), [0.0, 5.0, 6.0], path, 0), [1.0, 5.0, 6.0]);
+        //an invalid value keeps the position of the following ones
+        assert_eq!(parse_floats("1 x 3".split_whitespace(), [0.0, 7.0, 0.0], path, 0), [1.0, 7.0, 3.0]);
+        //extra values, like the w of a position, are ignored
+        assert_eq!(parse_floats("1 2 3 4".split_whitespace(), [0.0; 3], path, 0), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn bump_maps_are_height_maps_and_norm_is_the_normal_map(){
+        let path = ::std::env::temp_dir().join("jakar_obj_importer_bump.mtl");
+        {
+            let mut file = File::create(&path).expect("failed to create test mtl");
+            file.write_all(b"newmtl bumpy\nmap_Bump -bm 0.5 bumpy_height.png\nnewmtl normal\nnorm -bm 2 normal_map.png\n")
+            .expect("failed to write test mtl");
+        }
+
+        let materials = load_mtl(&path).expect("failed to load test mtl");
+        let _ = ::std::fs::remove_file(&path);
+
+        let bumpy = &materials["bumpy"];
+        assert_eq!(bumpy.height_map, Some((String::from("bumpy_height.png"), 0.5)));
+        assert!(bumpy.normal_map.is_none());
+        let default_scale = material::MaterialFactors::new().get_height_scale();
+        assert_eq!(bumpy.to_factors().get_height_scale(), default_scale * 0.5);
+
+        let normal = &materials["normal"];
+        assert_eq!(normal.normal_map, Some((String::from("normal_map.png"), 2.0)));
+        assert!(normal.height_map.is_none());
+        assert_eq!(normal.to_factors().get_factor_normal(), 2.0);
+    }
+}